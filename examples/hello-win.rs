@@ -6,15 +6,15 @@ use winapi::shared::minwindef::{HINSTANCE, LPARAM, LRESULT, UINT, WPARAM};
 use winapi::shared::windef::HWND;
 use winapi::um::wingdi::CreateSolidBrush;
 use winapi::um::winuser::{
-    LoadCursorW, LoadIconW, PostQuitMessage, ShowWindow, IDC_ARROW, IDI_APPLICATION, SW_SHOWNORMAL,
-    WM_CHAR, WM_DESTROY, WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP, WM_SYSCHAR, WM_SYSKEYDOWN,
-    WM_SYSKEYUP, WS_OVERLAPPEDWINDOW,
+    LoadCursorW, LoadIconW, PostQuitMessage, IDC_ARROW, IDI_APPLICATION, WM_CHAR, WM_DESTROY,
+    WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_NCDESTROY, WM_SYSCHAR,
+    WM_SYSKEYDOWN, WM_SYSKEYUP, WS_OVERLAPPEDWINDOW,
 };
 
 #[cfg(feature = "kb")]
 use win_win::KeyboardState;
 
-use win_win::{WindowBuilder, WindowClass, WindowProc};
+use win_win::{ShowCommand, WindowBuilder, WindowClass, WindowProc};
 
 struct MyWindowProc {
     #[cfg(feature = "kb")]
@@ -35,7 +35,7 @@ impl WindowProc for MyWindowProc {
                 PostQuitMessage(0);
             },
             WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP | WM_CHAR | WM_SYSCHAR
-            | WM_INPUTLANGCHANGE => {
+            | WM_INPUTLANGCHANGE | WM_KILLFOCUS | WM_NCDESTROY => {
                 #[cfg(feature = "kb")]
                 if let Some(event) = unsafe {
                     self.kb_state
@@ -67,11 +67,12 @@ fn main() {
             #[cfg(feature = "kb")]
             kb_state: RefCell::new(KeyboardState::new()),
         };
-        let hwnd = WindowBuilder::new(window_proc, &win_class)
+        WindowBuilder::new(window_proc, &win_class)
             .name("win-win example")
             .style(WS_OVERLAPPEDWINDOW)
-            .build();
-        ShowWindow(hwnd, SW_SHOWNORMAL);
+            .show_command(ShowCommand::Normal)
+            .build()
+            .unwrap();
         win_win::runloop(null_mut());
     }
 }
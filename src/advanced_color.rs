@@ -0,0 +1,188 @@
+//! HDR and advanced color information for monitors.
+//!
+//! Whether a monitor is in HDR ("advanced color") mode, and the brightness of SDR content on
+//! it, come from the [DisplayConfig] API, which describes display paths rather than monitors;
+//! [`Monitor::advanced_color`] finds the path for a monitor by its GDI device name. An
+//! application rendering to a swapchain uses this to choose a format and to tone-map: with
+//! HDR enabled, SDR content should be scaled to the SDR white level.
+//!
+//! [DisplayConfig]: https://docs.microsoft.com/en-us/windows-hardware/drivers/display/ccd-apis
+//! [`Monitor::advanced_color`]: struct.Monitor.html#method.advanced_color
+
+use std::ffi::OsString;
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::winerror::{ERROR_INSUFFICIENT_BUFFER, ERROR_SUCCESS};
+use winapi::um::wingdi::{
+    DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO, DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+    DISPLAYCONFIG_DEVICE_INFO_HEADER, DISPLAYCONFIG_DEVICE_INFO_TYPE,
+    DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO, DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO,
+    DISPLAYCONFIG_SOURCE_DEVICE_NAME, QDC_ONLY_ACTIVE_PATHS,
+};
+use winapi::um::winnt::{LONG, LUID};
+
+use wio::wide::FromWide;
+
+use crate::monitor::Monitor;
+
+/// `DISPLAYCONFIG_DEVICE_INFO_GET_SDR_WHITE_LEVEL`, which winapi lacks.
+const GET_SDR_WHITE_LEVEL: DISPLAYCONFIG_DEVICE_INFO_TYPE = 11;
+
+/// `DISPLAYCONFIG_SDR_WHITE_LEVEL`, which winapi lacks.
+#[repr(C)]
+struct SdrWhiteLevel {
+    header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    /// The SDR white level, where 1000 is 80 nits.
+    level: ULONG,
+}
+
+/// The advanced color state of a monitor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdvancedColor {
+    /// Whether the monitor supports advanced color (HDR or wide color gamut).
+    pub supported: bool,
+    /// Whether advanced color is enabled, which for HDR monitors means HDR output.
+    pub enabled: bool,
+    /// The number of bits per color channel sent to the monitor.
+    pub bits_per_channel: u32,
+    /// The brightness of SDR white, in nits.
+    ///
+    /// This is `None` before Windows 10 1709, which can't report it. Without HDR, SDR content
+    /// isn't scaled, and the value is the default of 80.
+    pub sdr_white_level: Option<f32>,
+}
+
+impl Monitor {
+    /// The advanced color (HDR) state of the monitor.
+    ///
+    /// Returns `None` if the DisplayConfig API isn't available (before Windows 7) or the
+    /// monitor has no active display path. This queries the display configuration on each
+    /// call, so cache the result, refreshing it on `WM_DISPLAYCHANGE` or with
+    /// [`EventProc::advanced_color_events`].
+    ///
+    /// [`EventProc::advanced_color_events`]: struct.EventProc.html#method.advanced_color_events
+    pub fn advanced_color(&self) -> Option<AdvancedColor> {
+        let device_name = self.device_name()?;
+        unsafe {
+            let path = active_paths()?.into_iter().find(|path| {
+                source_name(path.sourceInfo.adapterId, path.sourceInfo.id)
+                    .is_some_and(|name| name == device_name)
+            })?;
+            let (adapter_id, id) = (path.targetInfo.adapterId, path.targetInfo.id);
+            let mut info: DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO = mem::zeroed();
+            if !device_info(
+                &mut info as *mut DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO
+                    as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER,
+                DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO,
+                mem::size_of::<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>(),
+                adapter_id,
+                id,
+            ) {
+                return None;
+            }
+            let mut white: SdrWhiteLevel = mem::zeroed();
+            let sdr_white_level = if device_info(
+                &mut white as *mut SdrWhiteLevel as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER,
+                GET_SDR_WHITE_LEVEL,
+                mem::size_of::<SdrWhiteLevel>(),
+                adapter_id,
+                id,
+            ) {
+                Some(white.level as f32 / 1000.0 * 80.0)
+            } else {
+                None
+            };
+            Some(AdvancedColor {
+                supported: info.advancedColorSupported() != 0,
+                enabled: info.advancedColorEnabled() != 0,
+                bits_per_channel: info.bitsPerColorChannel,
+                sdr_white_level,
+            })
+        }
+    }
+}
+
+/// The active display paths, from `QueryDisplayConfig`.
+unsafe fn active_paths() -> Option<Vec<DISPLAYCONFIG_PATH_INFO>> {
+    let get_sizes = dynamic_fn!(
+        "user32.dll",
+        GetDisplayConfigBufferSizes: fn(u32, *mut u32, *mut u32) -> LONG
+    )?;
+    let query = dynamic_fn!(
+        "user32.dll",
+        QueryDisplayConfig: fn(
+            u32,
+            *mut u32,
+            *mut DISPLAYCONFIG_PATH_INFO,
+            *mut u32,
+            *mut DISPLAYCONFIG_MODE_INFO,
+            *mut u32
+        ) -> LONG
+    )?;
+    // The configuration can change between the calls, in which case the buffers are too small.
+    loop {
+        let (mut n_paths, mut n_modes) = (0, 0);
+        if get_sizes(QDC_ONLY_ACTIVE_PATHS, &mut n_paths, &mut n_modes) != ERROR_SUCCESS as LONG {
+            return None;
+        }
+        let mut paths = vec![mem::zeroed(); n_paths as usize];
+        let mut modes = vec![mem::zeroed(); n_modes as usize];
+        let status = query(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut n_paths,
+            paths.as_mut_ptr(),
+            &mut n_modes,
+            modes.as_mut_ptr(),
+            null_mut(),
+        );
+        match status as u32 {
+            ERROR_SUCCESS => {
+                paths.truncate(n_paths as usize);
+                return Some(paths);
+            }
+            ERROR_INSUFFICIENT_BUFFER => continue,
+            _ => return None,
+        }
+    }
+}
+
+/// The GDI device name of a display source, as in `MONITORINFOEX`.
+unsafe fn source_name(adapter_id: LUID, id: u32) -> Option<OsString> {
+    let mut name: DISPLAYCONFIG_SOURCE_DEVICE_NAME = mem::zeroed();
+    if !device_info(
+        &mut name as *mut DISPLAYCONFIG_SOURCE_DEVICE_NAME as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER,
+        DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+        mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>(),
+        adapter_id,
+        id,
+    ) {
+        return None;
+    }
+    Some(OsString::from_wide_null(&name.viewGdiDeviceName))
+}
+
+/// Fill in a request header and call `DisplayConfigGetDeviceInfo`, returning success.
+///
+/// The header must be the first field of a request structure of the given size.
+unsafe fn device_info(
+    header: *mut DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    kind: DISPLAYCONFIG_DEVICE_INFO_TYPE,
+    size: usize,
+    adapter_id: LUID,
+    id: u32,
+) -> bool {
+    let get_info = match dynamic_fn!(
+        "user32.dll",
+        DisplayConfigGetDeviceInfo: fn(*mut DISPLAYCONFIG_DEVICE_INFO_HEADER) -> LONG
+    ) {
+        Some(f) => f,
+        None => return false,
+    };
+    (*header)._type = kind;
+    (*header).size = size as u32;
+    (*header).adapterId = adapter_id;
+    (*header).id = id;
+    get_info(header) == ERROR_SUCCESS as LONG
+}
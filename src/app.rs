@@ -0,0 +1,206 @@
+//! A simple application runner.
+//!
+//! [`run`] registers a window class, creates a single top-level window, and runs the message
+//! loop, delivering [`Event`]s to a closure. It trades flexibility for brevity; anything it
+//! doesn't cover can be done with the lower-level builders, [`EventProc`], and [`Runloop`].
+//!
+//! [`run`]: fn.run.html
+//! [`Event`]: enum.Event.html
+//! [`EventProc`]: struct.EventProc.html
+//! [`Runloop`]: struct.Runloop.html
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    DestroyWindow, DispatchMessageW, GetMessageW, InvalidateRect, LoadCursorW, PeekMessageW,
+    PostQuitMessage, TranslateMessage, IDC_ARROW, PM_REMOVE, WM_QUIT, WS_OVERLAPPEDWINDOW,
+};
+
+use crate::error::Error;
+use crate::events::{Event, EventHandler, EventProc};
+#[cfg(feature = "gamepad")]
+use crate::gamepad::{GamepadState, Gamepads};
+use crate::ui_thread::UiThreadToken;
+use crate::window::{ShowCommand, WindowBuilder, WindowClass};
+
+pub(crate) const CLASS_NAME: &str = "win-win.run";
+
+/// What the runner should do after an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlFlow {
+    /// Block until the next message.
+    Wait,
+    /// Don't block; deliver [`Event::Idle`](enum.Event.html#variant.Idle) whenever the queue
+    /// is empty. This is for continuous animation. With the `gamepad` feature, game controllers
+    /// are also polled, delivering any changes as `Event::Gamepad` before each `Idle`.
+    Poll,
+    /// Destroy the window and return from [`run`](fn.run.html).
+    Exit,
+}
+
+/// The application context, passed to the setup and event closures.
+pub struct Context {
+    hwnd: Cell<HWND>,
+    control_flow: Cell<ControlFlow>,
+    #[cfg(feature = "gamepad")]
+    gamepads: RefCell<Gamepads>,
+}
+
+impl Context {
+    /// The application window.
+    ///
+    /// This is null after the window is destroyed.
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd.get()
+    }
+
+    /// A token for the UI thread, which runs the closures.
+    pub fn ui_thread(&self) -> UiThreadToken {
+        UiThreadToken::assume()
+    }
+
+    /// The current control flow.
+    pub fn control_flow(&self) -> ControlFlow {
+        self.control_flow.get()
+    }
+
+    /// Set the control flow, as if returned from the event closure.
+    ///
+    /// An exit can't be cancelled.
+    pub fn set_control_flow(&self, flow: ControlFlow) {
+        if self.control_flow.get() != ControlFlow::Exit {
+            self.control_flow.set(flow);
+        }
+    }
+
+    /// The state of a game controller as of the last poll, or `None` if not connected.
+    ///
+    /// Controllers are only polled with `ControlFlow::Poll`.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad(&self, index: u32) -> Option<GamepadState> {
+        self.gamepads.borrow().state(index)
+    }
+
+    /// Invalidate the whole window, so it is repainted.
+    pub fn request_redraw(&self) {
+        let hwnd = self.hwnd.get();
+        if !hwnd.is_null() {
+            unsafe {
+                InvalidateRect(hwnd, null_mut(), 0);
+            }
+        }
+    }
+}
+
+type Handler = Rc<RefCell<dyn FnMut(Event, &Context) -> ControlFlow>>;
+
+struct RunHandler {
+    ctx: Rc<Context>,
+    handler: Handler,
+}
+
+impl RunHandler {
+    fn dispatch(&self, event: Event) {
+        let flow = (self.handler.borrow_mut())(event, &self.ctx);
+        self.ctx.set_control_flow(flow);
+    }
+}
+
+impl EventHandler for RunHandler {
+    fn event(&mut self, hwnd: HWND, event: Event) -> bool {
+        let destroyed = event == Event::Destroyed;
+        // This is set before creation returns, so `Created` sees it.
+        self.ctx.hwnd.set(hwnd);
+        // A reentrant event (for example, from a message box shown by the handler) can't
+        // reach the closure; it just gets default processing.
+        if let Ok(mut handler) = self.handler.try_borrow_mut() {
+            let flow = handler(event, &self.ctx);
+            self.ctx.set_control_flow(flow);
+        }
+        if destroyed {
+            self.ctx.hwnd.set(null_mut());
+            unsafe {
+                PostQuitMessage(0);
+            }
+        }
+        // Default processing is always done, so closing the window destroys it.
+        false
+    }
+}
+
+/// Create a window and run the message loop, delivering events to a closure.
+///
+/// The window class is registered on first use. The `setup` closure is called once the window
+/// is created and shown. The `handler` closure is called for each event, and its return value
+/// sets the [`ControlFlow`]. Events always get default processing; for more control, use
+/// [`EventProc`] directly.
+///
+/// This returns when the window is destroyed, or on `ControlFlow::Exit`.
+///
+/// [`ControlFlow`]: enum.ControlFlow.html
+/// [`EventProc`]: struct.EventProc.html
+pub fn run(
+    setup: impl FnOnce(&Context),
+    handler: impl FnMut(Event, &Context) -> ControlFlow + 'static,
+) -> Result<(), Error> {
+    let ctx = Rc::new(Context {
+        hwnd: Cell::new(null_mut()),
+        control_flow: Cell::new(ControlFlow::Wait),
+        #[cfg(feature = "gamepad")]
+        gamepads: RefCell::new(Gamepads::new()),
+    });
+    let run_handler = RunHandler {
+        ctx: ctx.clone(),
+        handler: Rc::new(RefCell::new(handler)),
+    };
+    let idle_handler = RunHandler {
+        ctx: ctx.clone(),
+        handler: run_handler.handler.clone(),
+    };
+    unsafe {
+        let class = match WindowClass::builder(CLASS_NAME)
+            .cursor(LoadCursorW(null_mut(), IDC_ARROW))
+            .build()
+        {
+            Ok(class) => class,
+            // Already registered by a previous call.
+            Err(_) => WindowClass::from_name(CLASS_NAME),
+        };
+        WindowBuilder::new(EventProc::new(run_handler), &class)
+            .style(WS_OVERLAPPEDWINDOW)
+            .show_command(ShowCommand::Normal)
+            .build()?;
+        setup(&ctx);
+        let mut msg = mem::zeroed();
+        loop {
+            let hwnd = ctx.hwnd();
+            if ctx.control_flow() == ControlFlow::Exit && !hwnd.is_null() {
+                DestroyWindow(hwnd);
+            }
+            if ctx.control_flow() == ControlFlow::Poll {
+                if PeekMessageW(&mut msg, null_mut(), 0, 0, PM_REMOVE) == 0 {
+                    #[cfg(feature = "gamepad")]
+                    {
+                        let events = ctx.gamepads.borrow_mut().poll();
+                        for event in events {
+                            idle_handler.dispatch(Event::Gamepad(event));
+                        }
+                    }
+                    idle_handler.dispatch(Event::Idle);
+                    continue;
+                }
+                if msg.message == WM_QUIT {
+                    return Ok(());
+                }
+            } else if GetMessageW(&mut msg, null_mut(), 0, 0) <= 0 {
+                return Ok(());
+            }
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Media and browser keys, via `WM_APPCOMMAND`.
+//!
+//! Keys such as play/pause, volume, and browser back/forward, as well as the back/forward
+//! buttons on many mice, don't generally arrive as `WM_KEYDOWN` with a useful interpretation.
+//! Instead, `DefWindowProc` turns them into [`WM_APPCOMMAND`] messages, which are sent to the
+//! window with focus and bubble up to parent windows (and finally to a shell hook) until one of
+//! them handles it.
+//!
+//! To handle a command, return [`APPCOMMAND_HANDLED`] from the window procedure. To let it
+//! propagate to the parent, return `None` so `DefWindowProc` forwards it.
+//!
+//! [`WM_APPCOMMAND`]: https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-appcommand
+//! [`APPCOMMAND_HANDLED`]: constant.APPCOMMAND_HANDLED.html
+
+use winapi::shared::minwindef::{LOWORD, LPARAM, LRESULT, UINT, WORD, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    APPCOMMAND_BROWSER_BACKWARD, APPCOMMAND_BROWSER_FAVORITES, APPCOMMAND_BROWSER_FORWARD,
+    APPCOMMAND_BROWSER_HOME, APPCOMMAND_BROWSER_REFRESH, APPCOMMAND_BROWSER_SEARCH,
+    APPCOMMAND_BROWSER_STOP, APPCOMMAND_CLOSE, APPCOMMAND_COPY, APPCOMMAND_CUT, APPCOMMAND_DELETE,
+    APPCOMMAND_FIND, APPCOMMAND_HELP, APPCOMMAND_LAUNCH_APP1, APPCOMMAND_LAUNCH_APP2,
+    APPCOMMAND_LAUNCH_MAIL, APPCOMMAND_LAUNCH_MEDIA_SELECT, APPCOMMAND_MEDIA_CHANNEL_DOWN,
+    APPCOMMAND_MEDIA_CHANNEL_UP, APPCOMMAND_MEDIA_FAST_FORWARD, APPCOMMAND_MEDIA_NEXTTRACK,
+    APPCOMMAND_MEDIA_PAUSE, APPCOMMAND_MEDIA_PLAY, APPCOMMAND_MEDIA_PLAY_PAUSE,
+    APPCOMMAND_MEDIA_PREVIOUSTRACK, APPCOMMAND_MEDIA_RECORD, APPCOMMAND_MEDIA_REWIND,
+    APPCOMMAND_MEDIA_STOP, APPCOMMAND_MICROPHONE_VOLUME_DOWN, APPCOMMAND_MICROPHONE_VOLUME_MUTE,
+    APPCOMMAND_MICROPHONE_VOLUME_UP, APPCOMMAND_NEW, APPCOMMAND_OPEN, APPCOMMAND_PASTE,
+    APPCOMMAND_PRINT, APPCOMMAND_REDO, APPCOMMAND_SAVE, APPCOMMAND_UNDO, APPCOMMAND_VOLUME_DOWN,
+    APPCOMMAND_VOLUME_MUTE, APPCOMMAND_VOLUME_UP, FAPPCOMMAND_KEY, FAPPCOMMAND_MOUSE,
+    GET_APPCOMMAND_LPARAM, GET_DEVICE_LPARAM, WM_APPCOMMAND,
+};
+
+/// The value to return from the window procedure when a `WM_APPCOMMAND` is handled.
+pub const APPCOMMAND_HANDLED: LRESULT = 1;
+
+/// A command from a `WM_APPCOMMAND` message.
+///
+/// This covers the commands commonly produced by keyboards and mice. Others are reported as
+/// `Other`, with the raw `APPCOMMAND_` value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppCommand {
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNextTrack,
+    MediaPreviousTrack,
+    MediaStop,
+    MediaPlayPause,
+    MediaPlay,
+    MediaPause,
+    MediaRecord,
+    MediaFastForward,
+    MediaRewind,
+    MediaChannelUp,
+    MediaChannelDown,
+    LaunchMail,
+    LaunchMediaSelect,
+    LaunchApp1,
+    LaunchApp2,
+    MicrophoneVolumeMute,
+    MicrophoneVolumeDown,
+    MicrophoneVolumeUp,
+    Help,
+    Find,
+    New,
+    Open,
+    Close,
+    Save,
+    Print,
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    Delete,
+    Other(i16),
+}
+
+/// The kind of device that generated an app command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppCommandDevice {
+    Key,
+    Mouse,
+    Oem,
+}
+
+/// A decoded `WM_APPCOMMAND` message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppCommandEvent {
+    pub command: AppCommand,
+    pub device: AppCommandDevice,
+    /// The state of the virtual keys, as a combination of `MK_` flags.
+    pub keys: WORD,
+    /// The window where the user clicked or pressed the key.
+    ///
+    /// This can be a child of the window receiving the message, as the message bubbles up.
+    pub source: HWND,
+}
+
+impl AppCommandEvent {
+    /// Decode a `WM_APPCOMMAND` message.
+    ///
+    /// Returns `None` for other messages.
+    pub fn decode(msg: UINT, wparam: WPARAM, lparam: LPARAM) -> Option<AppCommandEvent> {
+        if msg != WM_APPCOMMAND {
+            return None;
+        }
+        let device = match GET_DEVICE_LPARAM(lparam) {
+            FAPPCOMMAND_MOUSE => AppCommandDevice::Mouse,
+            FAPPCOMMAND_KEY => AppCommandDevice::Key,
+            _ => AppCommandDevice::Oem,
+        };
+        Some(AppCommandEvent {
+            command: AppCommand::from_raw(GET_APPCOMMAND_LPARAM(lparam)),
+            device,
+            keys: LOWORD(lparam as u32),
+            source: wparam as HWND,
+        })
+    }
+}
+
+impl AppCommand {
+    /// Convert from a raw `APPCOMMAND_` value.
+    pub fn from_raw(cmd: i16) -> AppCommand {
+        use AppCommand::*;
+        match cmd {
+            APPCOMMAND_BROWSER_BACKWARD => BrowserBack,
+            APPCOMMAND_BROWSER_FORWARD => BrowserForward,
+            APPCOMMAND_BROWSER_REFRESH => BrowserRefresh,
+            APPCOMMAND_BROWSER_STOP => BrowserStop,
+            APPCOMMAND_BROWSER_SEARCH => BrowserSearch,
+            APPCOMMAND_BROWSER_FAVORITES => BrowserFavorites,
+            APPCOMMAND_BROWSER_HOME => BrowserHome,
+            APPCOMMAND_VOLUME_MUTE => VolumeMute,
+            APPCOMMAND_VOLUME_DOWN => VolumeDown,
+            APPCOMMAND_VOLUME_UP => VolumeUp,
+            APPCOMMAND_MEDIA_NEXTTRACK => MediaNextTrack,
+            APPCOMMAND_MEDIA_PREVIOUSTRACK => MediaPreviousTrack,
+            APPCOMMAND_MEDIA_STOP => MediaStop,
+            APPCOMMAND_MEDIA_PLAY_PAUSE => MediaPlayPause,
+            APPCOMMAND_MEDIA_PLAY => MediaPlay,
+            APPCOMMAND_MEDIA_PAUSE => MediaPause,
+            APPCOMMAND_MEDIA_RECORD => MediaRecord,
+            APPCOMMAND_MEDIA_FAST_FORWARD => MediaFastForward,
+            APPCOMMAND_MEDIA_REWIND => MediaRewind,
+            APPCOMMAND_MEDIA_CHANNEL_UP => MediaChannelUp,
+            APPCOMMAND_MEDIA_CHANNEL_DOWN => MediaChannelDown,
+            APPCOMMAND_LAUNCH_MAIL => LaunchMail,
+            APPCOMMAND_LAUNCH_MEDIA_SELECT => LaunchMediaSelect,
+            APPCOMMAND_LAUNCH_APP1 => LaunchApp1,
+            APPCOMMAND_LAUNCH_APP2 => LaunchApp2,
+            APPCOMMAND_MICROPHONE_VOLUME_MUTE => MicrophoneVolumeMute,
+            APPCOMMAND_MICROPHONE_VOLUME_DOWN => MicrophoneVolumeDown,
+            APPCOMMAND_MICROPHONE_VOLUME_UP => MicrophoneVolumeUp,
+            APPCOMMAND_HELP => Help,
+            APPCOMMAND_FIND => Find,
+            APPCOMMAND_NEW => New,
+            APPCOMMAND_OPEN => Open,
+            APPCOMMAND_CLOSE => Close,
+            APPCOMMAND_SAVE => Save,
+            APPCOMMAND_PRINT => Print,
+            APPCOMMAND_UNDO => Undo,
+            APPCOMMAND_REDO => Redo,
+            APPCOMMAND_COPY => Copy,
+            APPCOMMAND_CUT => Cut,
+            APPCOMMAND_PASTE => Paste,
+            APPCOMMAND_DELETE => Delete,
+            _ => Other(cmd),
+        }
+    }
+}
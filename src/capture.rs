@@ -0,0 +1,111 @@
+//! Capturing window contents.
+
+use std::mem;
+use std::ptr::null_mut;
+use std::slice;
+
+use winapi::shared::windef::{HWND, RECT};
+use winapi::um::wingdi::{
+    CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, SelectObject, BITMAPINFO,
+    BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+};
+use winapi::um::winuser::{
+    GetClientRect, GetWindowRect, PrintWindow, PW_CLIENTONLY, PW_RENDERFULLCONTENT,
+};
+
+use crate::error::Error;
+
+/// An image in 8-bit RGBA format, with rows stored top to bottom.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// The pixel data, `4 * width * height` bytes.
+    pub data: Vec<u8>,
+}
+
+/// Capture the contents of a window.
+///
+/// This uses [`PrintWindow`] with the `PW_RENDERFULLCONTENT` flag, which also captures content
+/// rendered with DirectX or DirectComposition, and works for windows that are occluded (though
+/// not minimized). When `client_only` is set, only the client area is captured; otherwise the
+/// result includes the non-client frame.
+///
+/// The alpha channel of the result is always opaque, as GDI doesn't reliably preserve it.
+///
+/// This is suitable for thumbnails and "attach a screenshot to the bug report" features. It is
+/// not fast enough for video capture.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`PrintWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-printwindow
+pub unsafe fn capture_window(hwnd: HWND, client_only: bool) -> Result<Image, Error> {
+    let mut rect: RECT = mem::zeroed();
+    let ok = if client_only {
+        GetClientRect(hwnd, &mut rect)
+    } else {
+        GetWindowRect(hwnd, &mut rect)
+    };
+    if ok == 0 {
+        return Err(Error::last_error());
+    }
+    let width = (rect.right - rect.left).max(0) as u32;
+    let height = (rect.bottom - rect.top).max(0) as u32;
+    if width == 0 || height == 0 {
+        return Ok(Image {
+            width,
+            height,
+            data: Vec::new(),
+        });
+    }
+
+    let hdc = CreateCompatibleDC(null_mut());
+    if hdc.is_null() {
+        return Err(Error::last_error());
+    }
+    let mut bmi: BITMAPINFO = mem::zeroed();
+    bmi.bmiHeader = BITMAPINFOHEADER {
+        biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: width as i32,
+        // Negative height means a top-down DIB.
+        biHeight: -(height as i32),
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB,
+        ..mem::zeroed()
+    };
+    let mut bits = null_mut();
+    let bitmap = CreateDIBSection(hdc, &bmi, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+    if bitmap.is_null() {
+        let err = Error::last_error();
+        DeleteDC(hdc);
+        return Err(err);
+    }
+    let old_bitmap = SelectObject(hdc, bitmap as _);
+    let mut flags = PW_RENDERFULLCONTENT;
+    if client_only {
+        flags |= PW_CLIENTONLY;
+    }
+    let result = if PrintWindow(hwnd, hdc, flags) == 0 {
+        Err(Error::last_error())
+    } else {
+        // The DIB is BGRA; swizzle to RGBA and force opaque alpha.
+        let len = 4 * width as usize * height as usize;
+        let mut data = slice::from_raw_parts(bits as *const u8, len).to_vec();
+        for pixel in data.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+            pixel[3] = 0xff;
+        }
+        Ok(Image {
+            width,
+            height,
+            data,
+        })
+    };
+    SelectObject(hdc, old_bitmap);
+    DeleteObject(bitmap as _);
+    DeleteDC(hdc);
+    result
+}
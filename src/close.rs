@@ -0,0 +1,114 @@
+//! Asking the application whether a window may close.
+//!
+//! `WM_CLOSE` is delivered as [`Event::CloseRequested`], carrying a [`CloseRequest`]. The
+//! handler can let the window close by leaving the event unhandled, veto the close by
+//! handling it, or [`defer`] the decision, as when a "save changes?" dialog runs
+//! asynchronously. A deferred decision is made later with the [`CloseToken`]; until then,
+//! further close requests for the window are ignored, so the window stays open.
+//!
+//! [`Event::CloseRequested`]: enum.Event.html#variant.CloseRequested
+//! [`CloseRequest`]: struct.CloseRequest.html
+//! [`defer`]: struct.CloseRequest.html#method.defer
+//! [`CloseToken`]: struct.CloseToken.html
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::DestroyWindow;
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CloseState {
+    Open,
+    /// A decision was deferred, and hasn't been made yet.
+    Pending,
+    /// The window has been destroyed.
+    Destroyed,
+}
+
+/// The close state of a window, shared with its requests and tokens.
+pub(crate) type SharedCloseState = Rc<Cell<CloseState>>;
+
+/// A request to close a window, from `WM_CLOSE`.
+#[derive(Clone)]
+pub struct CloseRequest {
+    hwnd: HWND,
+    state: SharedCloseState,
+}
+
+impl CloseRequest {
+    pub(crate) fn new(hwnd: HWND, state: SharedCloseState) -> CloseRequest {
+        CloseRequest { hwnd, state }
+    }
+
+    /// Defer the decision, keeping the window open until it is made with the returned token.
+    ///
+    /// The event should be handled (the handler returning `true`), so that the window isn't
+    /// closed straight away.
+    pub fn defer(&self) -> CloseToken {
+        self.state.set(CloseState::Pending);
+        CloseToken {
+            hwnd: self.hwnd,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for CloseRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CloseRequest")
+            .field("hwnd", &self.hwnd)
+            .finish()
+    }
+}
+
+impl PartialEq for CloseRequest {
+    fn eq(&self, other: &CloseRequest) -> bool {
+        self.hwnd == other.hwnd && Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+/// A deferred decision on closing a window.
+///
+/// Dropping the token without calling [`allow`](#method.allow) vetoes the close. The token
+/// isn't `Send`, as the window must be destroyed on its own thread; it can be held by a
+/// future on a single-threaded executor running there.
+pub struct CloseToken {
+    hwnd: HWND,
+    state: SharedCloseState,
+}
+
+impl CloseToken {
+    /// Close the window, by destroying it.
+    ///
+    /// Returns `Error::WindowDestroyed` if the window was destroyed in the meantime.
+    pub fn allow(self) -> Result<(), Error> {
+        if self.state.get() == CloseState::Destroyed {
+            return Err(Error::WindowDestroyed);
+        }
+        self.state.set(CloseState::Open);
+        if unsafe { DestroyWindow(self.hwnd) } == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(())
+    }
+
+    /// Keep the window open. Later close requests are delivered again.
+    pub fn veto(self) {}
+
+    /// Whether the window still exists.
+    pub fn is_window_open(&self) -> bool {
+        self.state.get() != CloseState::Destroyed
+    }
+}
+
+impl Drop for CloseToken {
+    fn drop(&mut self) {
+        if self.state.get() == CloseState::Pending {
+            self.state.set(CloseState::Open);
+        }
+    }
+}
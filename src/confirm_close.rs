@@ -0,0 +1,201 @@
+//! Confirming the close of a window with unsaved changes.
+//!
+//! Document applications should ask before discarding changes, both when the user closes
+//! the window and when the session ends (logoff, shutdown, or restart for an update).
+//! [`confirm_close`] asks with the standard Save / Don't Save / Cancel dialog, and
+//! [`CloseConfirmation`] applies it to `WM_CLOSE` and `WM_QUERYENDSESSION`.
+//!
+//! [`confirm_close`]: fn.confirm_close.html
+//! [`CloseConfirmation`]: struct.CloseConfirmation.html
+
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::ctypes::c_int;
+use winapi::shared::minwindef::{BOOL, FALSE, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::commctrl::{
+    TASKDIALOGCONFIG, TASKDIALOG_BUTTON, TDCBF_CANCEL_BUTTON, TDF_ALLOW_DIALOG_CANCELLATION,
+    TDF_POSITION_RELATIVE_TO_WINDOW, TD_WARNING_ICON,
+};
+use winapi::um::winnt::HRESULT;
+use winapi::um::winuser::{
+    GetWindowTextLengthW, GetWindowTextW, MessageBoxW, ShutdownBlockReasonCreate,
+    ShutdownBlockReasonDestroy, IDNO, IDYES, MB_ICONWARNING, MB_YESNOCANCEL, WM_CLOSE,
+    WM_QUERYENDSESSION,
+};
+
+use wio::wide::ToWide;
+
+/// Whether a document has changes that would be lost on closing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocumentState {
+    /// There are no unsaved changes.
+    Saved,
+    /// There are unsaved changes to the named document.
+    Unsaved { name: String },
+}
+
+/// The user's answer to [`confirm_close`](fn.confirm_close.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseDecision {
+    /// Save the changes, then close.
+    Save,
+    /// Close without saving.
+    DontSave,
+    /// Don't close.
+    Cancel,
+}
+
+/// Ask whether to save changes before closing.
+///
+/// For a saved document, this returns `DontSave` without asking. Otherwise it shows a
+/// [task dialog] owned by the window, titled with the window's title, or a message box before
+/// Windows Vista or without version 6 of the common controls.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [task dialog]: https://docs.microsoft.com/en-us/windows/win32/controls/task-dialogs-overview
+pub unsafe fn confirm_close(hwnd: HWND, state: &DocumentState) -> CloseDecision {
+    let name = match state {
+        DocumentState::Saved => return CloseDecision::DontSave,
+        DocumentState::Unsaved { name } => name,
+    };
+    let title = window_title(hwnd);
+    let instruction = format!("Do you want to save changes to {}?", name).to_wide_null();
+    let answer = match task_dialog(hwnd, &title, &instruction) {
+        Some(answer) => answer,
+        None => MessageBoxW(
+            hwnd,
+            instruction.as_ptr(),
+            title.as_ptr(),
+            MB_YESNOCANCEL | MB_ICONWARNING,
+        ),
+    };
+    match answer {
+        IDYES => CloseDecision::Save,
+        IDNO => CloseDecision::DontSave,
+        _ => CloseDecision::Cancel,
+    }
+}
+
+/// The window's title, null-terminated.
+unsafe fn window_title(hwnd: HWND) -> Vec<u16> {
+    let mut buf = vec![0u16; GetWindowTextLengthW(hwnd) as usize + 1];
+    let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as c_int);
+    buf.truncate(len as usize);
+    buf.push(0);
+    buf
+}
+
+/// Show the Save / Don't Save / Cancel task dialog, returning the button id.
+///
+/// `TaskDialogIndirect` is loaded at runtime, as it only exists in version 6 of the common
+/// controls, which needs an application manifest.
+unsafe fn task_dialog(hwnd: HWND, title: &[u16], instruction: &[u16]) -> Option<c_int> {
+    let task_dialog_indirect = dynamic_fn!(
+        "comctl32.dll",
+        TaskDialogIndirect: fn(*const TASKDIALOGCONFIG, *mut c_int, *mut c_int, *mut BOOL) -> HRESULT
+    )?;
+    let save = "&Save".to_wide_null();
+    let dont_save = "Do&n't Save".to_wide_null();
+    let buttons = [
+        TASKDIALOG_BUTTON {
+            nButtonID: IDYES,
+            pszButtonText: save.as_ptr(),
+        },
+        TASKDIALOG_BUTTON {
+            nButtonID: IDNO,
+            pszButtonText: dont_save.as_ptr(),
+        },
+    ];
+    let mut config: TASKDIALOGCONFIG = mem::zeroed();
+    config.cbSize = mem::size_of::<TASKDIALOGCONFIG>() as UINT;
+    config.hwndParent = hwnd;
+    config.dwFlags = TDF_ALLOW_DIALOG_CANCELLATION | TDF_POSITION_RELATIVE_TO_WINDOW;
+    config.dwCommonButtons = TDCBF_CANCEL_BUTTON;
+    config.pszWindowTitle = title.as_ptr();
+    *config.u1.pszMainIcon_mut() = TD_WARNING_ICON;
+    config.pszMainInstruction = instruction.as_ptr();
+    config.cButtons = buttons.len() as UINT;
+    config.pButtons = buttons.as_ptr();
+    config.nDefaultButton = IDYES;
+    let mut button = 0;
+    if task_dialog_indirect(&config, &mut button, null_mut(), null_mut()) < 0 {
+        return None;
+    }
+    Some(button)
+}
+
+/// Confirms closing a window with unsaved changes, on close and at the end of the session.
+///
+/// Call [`process_message`] from the window procedure, or from `EventHandler::raw` with
+/// [`EventProc`]. The save callback is called when the user chooses to save, and returns
+/// whether the save succeeded; if it didn't, the window stays open.
+///
+/// [`process_message`]: #method.process_message
+/// [`EventProc`]: struct.EventProc.html
+pub struct CloseConfirmation {
+    save: Box<dyn FnMut(HWND) -> bool>,
+}
+
+impl CloseConfirmation {
+    pub fn new(save: impl FnMut(HWND) -> bool + 'static) -> CloseConfirmation {
+        CloseConfirmation {
+            save: Box::new(save),
+        }
+    }
+
+    /// Handle `WM_CLOSE` and `WM_QUERYENDSESSION`, given the current state of the document.
+    ///
+    /// A `None` result means the message should get its usual processing, which for
+    /// `WM_CLOSE` destroys the window. During `WM_QUERYENDSESSION`, the unsaved changes are
+    /// given as the reason the session can't end yet, with
+    /// [`ShutdownBlockReasonCreate`], while the dialog is shown.
+    ///
+    /// # Safety
+    ///
+    /// The `hwnd` argument must be a valid HWND reference.
+    ///
+    /// [`ShutdownBlockReasonCreate`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-shutdownblockreasoncreate
+    pub unsafe fn process_message(
+        &mut self,
+        hwnd: HWND,
+        msg: UINT,
+        _wparam: WPARAM,
+        _lparam: LPARAM,
+        state: &DocumentState,
+    ) -> Option<LRESULT> {
+        match msg {
+            WM_CLOSE => {
+                if self.decide(hwnd, state) {
+                    None
+                } else {
+                    Some(0)
+                }
+            }
+            WM_QUERYENDSESSION => {
+                if *state == DocumentState::Saved {
+                    return Some(TRUE as LRESULT);
+                }
+                let reason = "This document has unsaved changes.".to_wide_null();
+                ShutdownBlockReasonCreate(hwnd, reason.as_ptr());
+                let may_end = self.decide(hwnd, state);
+                ShutdownBlockReasonDestroy(hwnd);
+                Some(if may_end { TRUE } else { FALSE } as LRESULT)
+            }
+            _ => None,
+        }
+    }
+
+    /// Ask the user, saving if asked to, and return whether the window may close.
+    unsafe fn decide(&mut self, hwnd: HWND, state: &DocumentState) -> bool {
+        match confirm_close(hwnd, state) {
+            CloseDecision::Save => (self.save)(hwnd),
+            CloseDecision::DontSave => true,
+            CloseDecision::Cancel => false,
+        }
+    }
+}
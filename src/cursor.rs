@@ -0,0 +1,457 @@
+//! Setting the mouse cursor.
+//!
+//! There are two ways for a window to choose its cursor: the class cursor, which
+//! `DefWindowProc` applies in response to [`WM_SETCURSOR`], and calling [`SetCursor`]
+//! explicitly, usually on `WM_MOUSEMOVE`. Mixing the two causes flashing, as each mouse move
+//! sets the cursor twice. An application that wants different cursors in different parts of its
+//! client area should leave the class cursor unset, and either call [`set_cursor`] from both
+//! `WM_SETCURSOR` (returning `TRUE`) and `WM_MOUSEMOVE`, or install a [`CursorMap`], which
+//! this crate consults when handling `WM_SETCURSOR` for the client area.
+//!
+//! [`WM_SETCURSOR`]: https://docs.microsoft.com/en-us/windows/win32/menurc/wm-setcursor
+//! [`SetCursor`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setcursor
+//! [`set_cursor`]: fn.set_cursor.html
+//! [`CursorMap`]: struct.CursorMap.html
+
+use std::mem;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use winapi::shared::basetsd::{DWORD_PTR, UINT_PTR};
+use winapi::shared::minwindef::{LPARAM, LRESULT, MAKELONG, TRUE, UINT, WPARAM};
+use winapi::shared::windef::{HCURSOR, HWND};
+use winapi::um::commctrl::{
+    DefSubclassProc, GetWindowSubclass, RemoveWindowSubclass, SetWindowSubclass,
+};
+use winapi::um::winnt::LPCWSTR;
+use winapi::um::winuser::{
+    ClientToScreen, DestroyCursor, DispatchMessageW, GetCursorPos, IsChild, LoadCursorW,
+    PeekMessageW, ScreenToClient, SendMessageW, SetCursor, SetCursorPos, WindowFromPoint,
+    IDC_APPSTARTING, IDC_ARROW, IDC_CROSS, IDC_HAND, IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL,
+    IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, IDC_WAIT, PM_QS_PAINT, PM_REMOVE,
+    WM_MOUSEMOVE, WM_NCDESTROY, WM_NCHITTEST, WM_SETCURSOR,
+};
+
+use crate::error::Error;
+use crate::geometry::{Point, Rect};
+use crate::icon::create_icon;
+use crate::ui_thread::UiThreadToken;
+use crate::window::window_state;
+use crate::window_data::{remove_window_data, set_window_data};
+
+/// The window data key for the cursor set by `set_window_cursor`.
+const CURSOR_KEY: &str = "cursor";
+
+/// A cursor shape.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorIcon {
+    Arrow,
+    /// The pointing hand, for links.
+    Hand,
+    /// The text selection cursor.
+    IBeam,
+    Crosshair,
+    /// The hourglass or spinner.
+    Wait,
+    /// The arrow with a spinner, for work happening in the background.
+    Progress,
+    NotAllowed,
+    Help,
+    Move,
+    ResizeNorthSouth,
+    ResizeEastWest,
+    ResizeNorthwestSoutheast,
+    ResizeNortheastSouthwest,
+    /// A cursor loaded by the application.
+    Custom(HCURSOR),
+}
+
+impl CursorIcon {
+    /// The cursor handle.
+    ///
+    /// System cursors are shared, so this doesn't need to be cached or destroyed.
+    pub fn handle(self) -> HCURSOR {
+        let id = match self {
+            CursorIcon::Custom(cursor) => return cursor,
+            CursorIcon::Arrow => IDC_ARROW,
+            CursorIcon::Hand => IDC_HAND,
+            CursorIcon::IBeam => IDC_IBEAM,
+            CursorIcon::Crosshair => IDC_CROSS,
+            CursorIcon::Wait => IDC_WAIT,
+            CursorIcon::Progress => IDC_APPSTARTING,
+            CursorIcon::NotAllowed => IDC_NO,
+            CursorIcon::Help => IDC_HELP,
+            CursorIcon::Move => IDC_SIZEALL,
+            CursorIcon::ResizeNorthSouth => IDC_SIZENS,
+            CursorIcon::ResizeEastWest => IDC_SIZEWE,
+            CursorIcon::ResizeNorthwestSoutheast => IDC_SIZENWSE,
+            CursorIcon::ResizeNortheastSouthwest => IDC_SIZENESW,
+        };
+        load_system_cursor(id)
+    }
+}
+
+fn load_system_cursor(id: LPCWSTR) -> HCURSOR {
+    unsafe { LoadCursorW(null_mut(), id) }
+}
+
+/// A cursor, either a shared system cursor or one created by the application.
+///
+/// Cursors created by the application are destroyed when dropped.
+#[derive(Debug)]
+pub struct Cursor {
+    handle: HCURSOR,
+    /// Whether the cursor is destroyed on drop; system cursors are shared.
+    owned: bool,
+}
+
+impl Cursor {
+    /// A system cursor.
+    ///
+    /// For `CursorIcon::Custom`, the handle is borrowed, and isn't destroyed on drop.
+    pub fn system(cursor: CursorIcon) -> Cursor {
+        Cursor {
+            handle: cursor.handle(),
+            owned: false,
+        }
+    }
+
+    /// Create a cursor from 8-bit RGBA pixels, with rows stored top to bottom.
+    ///
+    /// The hotspot is the position of the pointer within the image, in pixels. The alpha
+    /// channel is straight (not premultiplied). Returns a `Win32` error of
+    /// `ERROR_INVALID_PARAMETER` if `rgba` isn't `4 * width * height` bytes.
+    ///
+    /// The cursor isn't scaled for DPI; create it at the size for the window's DPI, from
+    /// [`Dpi::cursor_size`](struct.Dpi.html#method.cursor_size).
+    pub fn from_rgba(
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+        hotspot: (u32, u32),
+    ) -> Result<Cursor, Error> {
+        let handle = unsafe { create_icon(width, height, rgba, Some(hotspot))? };
+        Ok(Cursor {
+            handle,
+            owned: true,
+        })
+    }
+
+    /// The cursor handle.
+    pub fn handle(&self) -> HCURSOR {
+        self.handle
+    }
+
+    /// The cursor as a [`CursorIcon`](enum.CursorIcon.html), for use with [`set_cursor`] and
+    /// [`CursorMap`].
+    ///
+    /// The result refers to this cursor, so must not be used after it is dropped.
+    ///
+    /// [`set_cursor`]: fn.set_cursor.html
+    /// [`CursorMap`]: struct.CursorMap.html
+    pub fn icon(&self) -> CursorIcon {
+        CursorIcon::Custom(self.handle)
+    }
+
+    /// Give up ownership of the handle, which is no longer destroyed.
+    pub fn into_raw(self) -> HCURSOR {
+        let handle = self.handle;
+        mem::forget(self);
+        handle
+    }
+}
+
+impl From<CursorIcon> for Cursor {
+    fn from(cursor: CursorIcon) -> Cursor {
+        Cursor::system(cursor)
+    }
+}
+
+impl Drop for Cursor {
+    fn drop(&mut self) {
+        if self.owned {
+            unsafe {
+                DestroyCursor(self.handle);
+            }
+        }
+    }
+}
+
+/// Set the cursor.
+///
+/// This is intended to be called when handling `WM_SETCURSOR` or `WM_MOUSEMOVE`. When
+/// called from `WM_SETCURSOR`, the window procedure should return `TRUE` to prevent further
+/// processing.
+///
+/// The cursor is that of the calling thread, shown while the mouse is over its windows, so
+/// this takes a [`UiThreadToken`](struct.UiThreadToken.html).
+pub fn set_cursor(_ui: UiThreadToken, cursor: CursorIcon) {
+    unsafe {
+        SetCursor(cursor.handle());
+    }
+}
+
+/// The cursor position, in screen coordinates.
+///
+/// This is the position now. When handling a message, the position at the time the message
+/// was posted, from [`message_pos`](fn.message_pos.html), is usually more appropriate.
+///
+/// This fails when the desktop is not the input desktop, for example while the screen is
+/// locked.
+pub fn cursor_pos() -> Result<Point, Error> {
+    unsafe {
+        let mut pt = mem::zeroed();
+        if GetCursorPos(&mut pt) == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(pt.into())
+    }
+}
+
+/// Move the cursor, in screen coordinates.
+///
+/// This generates a `WM_MOUSEMOVE`, as if the user had moved the mouse, which should be
+/// accounted for when recentering the cursor for relative motion.
+pub fn set_cursor_pos(pt: Point) -> Result<(), Error> {
+    unsafe {
+        if SetCursorPos(pt.x, pt.y) == 0 {
+            return Err(Error::last_error());
+        }
+    }
+    Ok(())
+}
+
+/// The cursor position, in client coordinates of the window.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn client_cursor_pos(hwnd: HWND) -> Result<Point, Error> {
+    let mut pt = cursor_pos()?.into();
+    if ScreenToClient(hwnd, &mut pt) == 0 {
+        return Err(Error::last_error());
+    }
+    Ok(pt.into())
+}
+
+/// Move the cursor, in client coordinates of the window.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn set_client_cursor_pos(hwnd: HWND, pt: Point) -> Result<(), Error> {
+    let mut pt = pt.into();
+    if ClientToScreen(hwnd, &mut pt) == 0 {
+        return Err(Error::last_error());
+    }
+    set_cursor_pos(pt.into())
+}
+
+/// A map from areas of the client area to cursors.
+///
+/// Areas added later take precedence over earlier ones where they overlap.
+#[derive(Clone, Debug)]
+pub struct CursorMap {
+    default: CursorIcon,
+    areas: Vec<(Rect, CursorIcon)>,
+}
+
+impl CursorMap {
+    /// Create a map with the cursor used outside all areas.
+    pub fn new(default: CursorIcon) -> CursorMap {
+        CursorMap {
+            default,
+            areas: Vec::new(),
+        }
+    }
+
+    /// Add an area, in client coordinates.
+    pub fn area(mut self, rect: Rect, cursor: CursorIcon) -> Self {
+        self.areas.push((rect, cursor));
+        self
+    }
+
+    /// The cursor at a point, in client coordinates.
+    pub fn cursor_at(&self, pt: Point) -> CursorIcon {
+        self.areas
+            .iter()
+            .rev()
+            .find(|(rect, _)| rect.contains(pt))
+            .map(|(_, cursor)| *cursor)
+            .unwrap_or(self.default)
+    }
+}
+
+/// Set or clear the cursor map of a window.
+///
+/// While a map is installed, `WM_SETCURSOR` messages for the client area that the window
+/// procedure doesn't handle are answered by setting the cursor from the map. The window
+/// class should have no cursor, to avoid flashing.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_cursor_map(hwnd: HWND, map: Option<CursorMap>) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    *state.cursor_map.borrow_mut() = map;
+    Ok(())
+}
+
+/// Set or clear the cursor of a window's client area.
+///
+/// This installs a [`CursorMap`] with just the given cursor, and keeps the cursor until it is
+/// replaced or the window is destroyed. As with `set_cursor_map`, the window class should
+/// have no cursor.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`CursorMap`]: struct.CursorMap.html
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_window_cursor(hwnd: HWND, cursor: Option<Cursor>) -> Result<(), Error> {
+    set_cursor_map(
+        hwnd,
+        cursor.as_ref().map(|cursor| CursorMap::new(cursor.icon())),
+    )?;
+    match cursor {
+        // This drops the previous cursor, which the map no longer refers to.
+        Some(cursor) => set_window_data(hwnd, CURSOR_KEY, Rc::new(cursor))?,
+        None => {
+            remove_window_data(hwnd, CURSOR_KEY);
+        }
+    }
+    Ok(())
+}
+
+/// The subclass id for busy cursors; any value unique to this pair with `busy_subclass_proc`
+/// will do. The reference data is the number of active guards.
+const BUSY_SUBCLASS_ID: UINT_PTR = 0x7769_6e62;
+
+/// Shows the wait cursor over a window while a short blocking task runs.
+///
+/// While the guard is alive, the cursor is the wait cursor, and `WM_SETCURSOR` messages for
+/// the window and its children are answered with it, so it stays if messages are processed
+/// (for example, by [`pump_paint`], or by a modal dialog). When the last guard for the window
+/// is dropped, the cursor is set again as if the mouse had moved.
+///
+/// This is for tasks of a second or two; longer work should move to another thread, keeping
+/// the window responsive.
+///
+/// [`pump_paint`]: #method.pump_paint
+pub struct BusyCursor {
+    hwnd: HWND,
+}
+
+impl BusyCursor {
+    /// Show the wait cursor over the window until the guard is dropped.
+    ///
+    /// Guards can be nested.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, to a window on the calling thread, which
+    /// must outlive the guard.
+    pub unsafe fn begin(hwnd: HWND) -> Result<BusyCursor, Error> {
+        let mut count = 0;
+        GetWindowSubclass(hwnd, Some(busy_subclass_proc), BUSY_SUBCLASS_ID, &mut count);
+        if SetWindowSubclass(hwnd, Some(busy_subclass_proc), BUSY_SUBCLASS_ID, count + 1) == 0 {
+            return Err(Error::last_error());
+        }
+        set_cursor(UiThreadToken::assume(), CursorIcon::Wait);
+        Ok(BusyCursor { hwnd })
+    }
+
+    /// Process pending paint messages, so the window can be repainted during the task.
+    ///
+    /// Other messages, including input, stay in the queue.
+    pub fn pump_paint(&self) {
+        unsafe {
+            let mut msg = mem::zeroed();
+            while PeekMessageW(&mut msg, null_mut(), 0, 0, PM_REMOVE | PM_QS_PAINT) != 0 {
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+}
+
+impl Drop for BusyCursor {
+    fn drop(&mut self) {
+        unsafe {
+            let mut count = 0;
+            if GetWindowSubclass(
+                self.hwnd,
+                Some(busy_subclass_proc),
+                BUSY_SUBCLASS_ID,
+                &mut count,
+            ) == 0
+            {
+                // The window was destroyed.
+                return;
+            }
+            if count > 1 {
+                SetWindowSubclass(
+                    self.hwnd,
+                    Some(busy_subclass_proc),
+                    BUSY_SUBCLASS_ID,
+                    count - 1,
+                );
+                return;
+            }
+            RemoveWindowSubclass(self.hwnd, Some(busy_subclass_proc), BUSY_SUBCLASS_ID);
+            refresh_cursor(self.hwnd);
+        }
+    }
+}
+
+/// Have the window under the cursor set it again, if that is the window or a child of it.
+unsafe fn refresh_cursor(hwnd: HWND) {
+    let mut pt = mem::zeroed();
+    if GetCursorPos(&mut pt) == 0 {
+        return;
+    }
+    let target = WindowFromPoint(pt);
+    if target != hwnd && IsChild(hwnd, target) == 0 {
+        return;
+    }
+    let lparam = MAKELONG(pt.x as u16, pt.y as u16) as LPARAM;
+    let hit = SendMessageW(target, WM_NCHITTEST, 0, lparam);
+    SendMessageW(
+        target,
+        WM_SETCURSOR,
+        target as WPARAM,
+        MAKELONG(hit as u16, WM_MOUSEMOVE as u16) as LPARAM,
+    );
+}
+
+unsafe extern "system" fn busy_subclass_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    id: UINT_PTR,
+    _ref_data: DWORD_PTR,
+) -> LRESULT {
+    match msg {
+        WM_SETCURSOR => {
+            // Subclass procedures run on the window's thread.
+            set_cursor(UiThreadToken::assume(), CursorIcon::Wait);
+            return TRUE as LRESULT;
+        }
+        WM_NCDESTROY => {
+            RemoveWindowSubclass(hwnd, Some(busy_subclass_proc), id);
+        }
+        _ => (),
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
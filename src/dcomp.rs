@@ -0,0 +1,53 @@
+//! DirectComposition targets for windows.
+
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::BOOL;
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::SUCCEEDED;
+use winapi::um::dcomp::{IDCompositionDesktopDevice, IDCompositionDevice, IDCompositionTarget};
+use winapi::um::unknwnbase::IUnknown;
+
+use wio::com::ComPtr;
+
+use crate::error::Error;
+
+/// Create a DirectComposition target for a window.
+///
+/// The device may be an `IDCompositionDevice` (from `DCompositionCreateDevice`) or an
+/// `IDCompositionDesktopDevice` (from `DCompositionCreateDevice2` or 3). A topmost target is
+/// drawn above the window's children. Set the root visual of the target, and commit the
+/// device, to show content.
+///
+/// The window should be created with [`WindowBuilder::for_composition`], or at least
+/// `WS_EX_NOREDIRECTIONBITMAP`. A window can only have one target of each kind (topmost or
+/// not); creating a second returns an error.
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid HWND reference, and `device` must be a valid COM
+/// object.
+///
+/// [`WindowBuilder::for_composition`]: struct.WindowBuilder.html#method.for_composition
+pub unsafe fn create_composition_target(
+    hwnd: HWND,
+    device: *mut IUnknown,
+    topmost: bool,
+) -> Result<ComPtr<IDCompositionTarget>, Error> {
+    (*device).AddRef();
+    let device = ComPtr::from_raw(device);
+    let mut target = null_mut();
+    let hr = if let Ok(desktop) = device.cast::<IDCompositionDesktopDevice>() {
+        desktop.CreateTargetForHwnd(hwnd, topmost as BOOL, &mut target)
+    } else {
+        let device = device
+            .cast::<IDCompositionDevice>()
+            .map_err(Error::Hresult)?;
+        device.CreateTargetForHwnd(hwnd, topmost as BOOL, &mut target)
+    };
+    if SUCCEEDED(hr) {
+        Ok(ComPtr::from_raw(target))
+    } else {
+        Err(Error::Hresult(hr))
+    }
+}
@@ -0,0 +1,511 @@
+//! Input device enumeration.
+//!
+//! Applications can adapt to the hardware present: offering a touch-friendly layout when a
+//! touch screen is attached, or pressure-sensitive tools when there is a pen. [`input_devices`]
+//! lists mice and keyboards (from [raw input]) and pen and touch digitizers (from
+//! [`GetPointerDevices`], on Windows 8 and later), and [`register_device_notifications`]
+//! requests messages when devices are attached or removed.
+//!
+//! Those messages come from two notification systems: raw input (`WM_INPUT_DEVICE_CHANGE`)
+//! and device interface notifications (`WM_DEVICECHANGE`). A [`DeviceTracker`] decodes both
+//! into a single [`DeviceEvent`], reporting each arrival or removal once.
+//!
+//! [`input_devices`]: fn.input_devices.html
+//! [`register_device_notifications`]: fn.register_device_notifications.html
+//! [`DeviceTracker`]: struct.DeviceTracker.html
+//! [`DeviceEvent`]: enum.DeviceEvent.html
+//! [raw input]: https://docs.microsoft.com/en-us/windows/win32/inputdev/raw-input
+//! [`GetPointerDevices`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getpointerdevices
+
+use std::collections::HashMap;
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::shared::hidclass::GUID_DEVINTERFACE_HID;
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, LPVOID, UINT, ULONG, USHORT, WPARAM};
+use winapi::shared::windef::{HMONITOR, HWND};
+use winapi::um::dbt::{
+    DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_DEVICEINTERFACE,
+    DEV_BROADCAST_DEVICEINTERFACE_W, DEV_BROADCAST_HDR,
+};
+use winapi::um::winnt::{HANDLE, WCHAR};
+use winapi::um::winuser::{
+    GetRawInputDeviceInfoW, GetRawInputDeviceList, RegisterDeviceNotificationW,
+    RegisterRawInputDevices, UnregisterDeviceNotification, DEVICE_NOTIFY_WINDOW_HANDLE,
+    GIDC_ARRIVAL, GIDC_REMOVAL, HDEVNOTIFY, RAWINPUTDEVICE, RAWINPUTDEVICELIST, RIDEV_DEVNOTIFY,
+    RIDI_DEVICEINFO, RIDI_DEVICENAME, RID_DEVICE_INFO, RIM_TYPEHID, RIM_TYPEKEYBOARD,
+    RIM_TYPEMOUSE, WM_DEVICECHANGE, WM_INPUT_DEVICE_CHANGE,
+};
+
+use crate::error::Error;
+
+/// The kind of an input device.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    Mouse,
+    Keyboard,
+    /// A pen digitizer, either integrated with a display or external (a graphics tablet).
+    Pen,
+    /// A touch screen.
+    Touch,
+    /// A precision touchpad.
+    Touchpad,
+    /// A game controller or joystick.
+    Gamepad,
+    /// Any other HID device.
+    Other,
+}
+
+/// An attached input device.
+#[derive(Clone, Debug)]
+pub struct InputDevice {
+    pub kind: DeviceKind,
+    /// The device handle. For mice and keyboards, this is the raw input handle; for pen and
+    /// touch devices, the pointer device handle.
+    pub handle: HANDLE,
+    /// The device name: the device interface path for mice and keyboards, the product
+    /// string for pen and touch devices.
+    pub name: String,
+    /// The maximum number of simultaneous contacts, for pen and touch devices.
+    pub max_contacts: Option<u32>,
+    /// The logical range of tip pressure values, for devices that report it.
+    pub pressure_range: Option<(i32, i32)>,
+}
+
+const POINTER_DEVICE_PRODUCT_STRING_MAX: usize = 520;
+const POINTER_DEVICE_TYPE_INTEGRATED_PEN: DWORD = 1;
+const POINTER_DEVICE_TYPE_EXTERNAL_PEN: DWORD = 2;
+const POINTER_DEVICE_TYPE_TOUCH: DWORD = 3;
+const POINTER_DEVICE_TYPE_TOUCH_PAD: DWORD = 5;
+
+/// HID usage pages and usages.
+const HID_USAGE_PAGE_GENERIC: USHORT = 0x01;
+const HID_USAGE_GENERIC_MOUSE: USHORT = 0x02;
+const HID_USAGE_GENERIC_JOYSTICK: USHORT = 0x04;
+const HID_USAGE_GENERIC_GAMEPAD: USHORT = 0x05;
+const HID_USAGE_GENERIC_KEYBOARD: USHORT = 0x06;
+const HID_USAGE_PAGE_DIGITIZER: USHORT = 0x0d;
+const HID_USAGE_DIGITIZER_PEN: USHORT = 0x02;
+const HID_USAGE_DIGITIZER_TOUCH_SCREEN: USHORT = 0x04;
+const HID_USAGE_DIGITIZER_TOUCH_PAD: USHORT = 0x05;
+const HID_USAGE_DIGITIZER_TIP_PRESSURE: USHORT = 0x30;
+
+// These are not declared by winapi.
+#[allow(non_snake_case)]
+#[repr(C)]
+struct POINTER_DEVICE_INFO {
+    displayOrientation: DWORD,
+    device: HANDLE,
+    pointerDeviceType: DWORD,
+    monitor: HMONITOR,
+    startingCursorId: ULONG,
+    maxActiveContacts: USHORT,
+    productString: [WCHAR; POINTER_DEVICE_PRODUCT_STRING_MAX],
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+struct POINTER_DEVICE_PROPERTY {
+    logicalMin: i32,
+    logicalMax: i32,
+    physicalMin: i32,
+    physicalMax: i32,
+    unit: u32,
+    unitExponent: u32,
+    usagePageId: USHORT,
+    usageId: USHORT,
+}
+
+/// List the attached mice, keyboards, game controllers, and pen and touch digitizers.
+///
+/// Pen and touch devices are only listed on Windows 8 and later.
+pub fn input_devices() -> Result<Vec<InputDevice>, Error> {
+    let mut devices = raw_input_devices()?;
+    unsafe {
+        devices.extend(pointer_devices());
+    }
+    Ok(devices)
+}
+
+/// The handles of the attached raw input devices.
+fn raw_input_handles() -> Result<Vec<HANDLE>, Error> {
+    unsafe {
+        let size = mem::size_of::<RAWINPUTDEVICELIST>() as UINT;
+        let mut count = 0;
+        if GetRawInputDeviceList(null_mut(), &mut count, size) == !0 {
+            return Err(Error::last_error());
+        }
+        let mut list: Vec<RAWINPUTDEVICELIST> = vec![mem::zeroed(); count as usize];
+        let n = GetRawInputDeviceList(list.as_mut_ptr(), &mut count, size);
+        if n == !0 {
+            return Err(Error::last_error());
+        }
+        list.truncate(n as usize);
+        Ok(list.iter().map(|item| item.hDevice).collect())
+    }
+}
+
+fn raw_input_devices() -> Result<Vec<InputDevice>, Error> {
+    Ok(raw_input_handles()?
+        .into_iter()
+        .filter_map(|handle| unsafe {
+            let kind = raw_input_kind(handle)?;
+            match kind {
+                DeviceKind::Mouse | DeviceKind::Keyboard | DeviceKind::Gamepad => (),
+                // Digitizers are listed from the pointer devices.
+                _ => return None,
+            }
+            Some(InputDevice {
+                kind,
+                handle,
+                name: raw_input_device_name(handle).unwrap_or_default(),
+                max_contacts: None,
+                pressure_range: None,
+            })
+        })
+        .collect())
+}
+
+/// The kind of a raw input device, from its type and top-level HID usage.
+unsafe fn raw_input_kind(device: HANDLE) -> Option<DeviceKind> {
+    let mut info: RID_DEVICE_INFO = mem::zeroed();
+    info.cbSize = mem::size_of::<RID_DEVICE_INFO>() as DWORD;
+    let mut size = info.cbSize;
+    if GetRawInputDeviceInfoW(
+        device,
+        RIDI_DEVICEINFO,
+        &mut info as *mut _ as LPVOID,
+        &mut size,
+    ) == !0
+    {
+        return None;
+    }
+    let kind = match info.dwType {
+        RIM_TYPEMOUSE => DeviceKind::Mouse,
+        RIM_TYPEKEYBOARD => DeviceKind::Keyboard,
+        RIM_TYPEHID => {
+            let hid = info.u.hid();
+            match (hid.usUsagePage, hid.usUsage) {
+                (HID_USAGE_PAGE_GENERIC, HID_USAGE_GENERIC_JOYSTICK)
+                | (HID_USAGE_PAGE_GENERIC, HID_USAGE_GENERIC_GAMEPAD) => DeviceKind::Gamepad,
+                (HID_USAGE_PAGE_DIGITIZER, HID_USAGE_DIGITIZER_PEN) => DeviceKind::Pen,
+                (HID_USAGE_PAGE_DIGITIZER, HID_USAGE_DIGITIZER_TOUCH_SCREEN) => DeviceKind::Touch,
+                (HID_USAGE_PAGE_DIGITIZER, HID_USAGE_DIGITIZER_TOUCH_PAD) => DeviceKind::Touchpad,
+                _ => DeviceKind::Other,
+            }
+        }
+        _ => return None,
+    };
+    Some(kind)
+}
+
+/// The device interface name of a raw input device.
+unsafe fn raw_input_device_name(device: HANDLE) -> Option<String> {
+    let mut len = 0;
+    GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, null_mut(), &mut len);
+    if len == 0 {
+        return None;
+    }
+    let mut buf = vec![0u16; len as usize];
+    let n = GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, buf.as_mut_ptr() as _, &mut len);
+    if n == !0 {
+        return None;
+    }
+    let end = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    Some(String::from_utf16_lossy(&buf[..end]))
+}
+
+unsafe fn pointer_devices() -> Vec<InputDevice> {
+    let get_devices = match dynamic_fn!(
+        "user32.dll",
+        GetPointerDevices: fn(*mut u32, *mut POINTER_DEVICE_INFO) -> BOOL
+    ) {
+        Some(f) => f,
+        None => return Vec::new(),
+    };
+    let mut count = 0;
+    if get_devices(&mut count, null_mut()) == 0 {
+        return Vec::new();
+    }
+    let mut infos: Vec<POINTER_DEVICE_INFO> = (0..count).map(|_| mem::zeroed()).collect();
+    if get_devices(&mut count, infos.as_mut_ptr()) == 0 {
+        return Vec::new();
+    }
+    infos.truncate(count as usize);
+    infos
+        .iter()
+        .filter_map(|info| {
+            let kind = match info.pointerDeviceType {
+                POINTER_DEVICE_TYPE_INTEGRATED_PEN | POINTER_DEVICE_TYPE_EXTERNAL_PEN => {
+                    DeviceKind::Pen
+                }
+                POINTER_DEVICE_TYPE_TOUCH => DeviceKind::Touch,
+                POINTER_DEVICE_TYPE_TOUCH_PAD => DeviceKind::Touchpad,
+                _ => return None,
+            };
+            let name = &info.productString;
+            let end = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            Some(InputDevice {
+                kind,
+                handle: info.device,
+                name: String::from_utf16_lossy(&name[..end]),
+                max_contacts: Some(info.maxActiveContacts as u32),
+                pressure_range: pressure_range(info.device),
+            })
+        })
+        .collect()
+}
+
+unsafe fn pressure_range(device: HANDLE) -> Option<(i32, i32)> {
+    let get_properties = dynamic_fn!(
+        "user32.dll",
+        GetPointerDeviceProperties: fn(HANDLE, *mut u32, *mut POINTER_DEVICE_PROPERTY) -> BOOL
+    )?;
+    let mut count = 0;
+    if get_properties(device, &mut count, null_mut()) == 0 {
+        return None;
+    }
+    let mut properties: Vec<POINTER_DEVICE_PROPERTY> = (0..count).map(|_| mem::zeroed()).collect();
+    if get_properties(device, &mut count, properties.as_mut_ptr()) == 0 {
+        return None;
+    }
+    properties.truncate(count as usize);
+    properties
+        .iter()
+        .find(|p| {
+            p.usagePageId == HID_USAGE_PAGE_DIGITIZER
+                && p.usageId == HID_USAGE_DIGITIZER_TIP_PRESSURE
+        })
+        .map(|p| (p.logicalMin, p.logicalMax))
+}
+
+/// Request notifications when input devices are attached or removed.
+///
+/// Mouse, keyboard, and game controller changes arrive as [`WM_INPUT_DEVICE_CHANGE`], with
+/// `wparam` either `GIDC_ARRIVAL` or `GIDC_REMOVAL`, and the raw input device handle in
+/// `lparam`. Pen and touch changes arrive as [`WM_POINTERDEVICECHANGE`], on Windows 8 and
+/// later.
+///
+/// This registers the window for raw mouse, keyboard, and game controller input, so it will
+/// also receive `WM_INPUT` messages, which can be passed to `DefWindowProc`. It replaces any
+/// previous raw input registration for these devices by the process.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WM_INPUT_DEVICE_CHANGE`]: https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-input-device-change
+/// [`WM_POINTERDEVICECHANGE`]: https://docs.microsoft.com/en-us/previous-versions/windows/desktop/inputmsg/wm-pointerdevicechange
+pub unsafe fn register_device_notifications(hwnd: HWND) -> Result<(), Error> {
+    let devices = [
+        HID_USAGE_GENERIC_MOUSE,
+        HID_USAGE_GENERIC_KEYBOARD,
+        HID_USAGE_GENERIC_JOYSTICK,
+        HID_USAGE_GENERIC_GAMEPAD,
+    ]
+    .map(|usage| RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: usage,
+        dwFlags: RIDEV_DEVNOTIFY,
+        hwndTarget: hwnd,
+    });
+    if RegisterRawInputDevices(
+        devices.as_ptr(),
+        devices.len() as UINT,
+        mem::size_of::<RAWINPUTDEVICE>() as UINT,
+    ) == 0
+    {
+        return Err(Error::last_error());
+    }
+    if let Some(register_pointer) = dynamic_fn!(
+        "user32.dll",
+        RegisterPointerDeviceNotifications: fn(HWND, BOOL) -> BOOL
+    ) {
+        if register_pointer(hwnd, 0) == 0 {
+            return Err(Error::last_error());
+        }
+    }
+    Ok(())
+}
+
+/// A stable identifier for an input device.
+///
+/// This is the device instance path, which is the same whether the device was reported by raw
+/// input or by a device interface notification, so that it can be used to match arrivals with
+/// removals. It can also be compared with [`InputDevice::name`] for mice, keyboards, and game
+/// controllers.
+///
+/// [`InputDevice::name`]: struct.InputDevice.html#structfield.name
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceId(String);
+
+impl DeviceId {
+    /// Make an id from a device interface path.
+    ///
+    /// Paths are case-insensitive, and the same device has an interface for each of its
+    /// classes, distinguished by a trailing class GUID; both are normalized away.
+    pub fn from_path(path: &str) -> DeviceId {
+        let mut path = path.to_lowercase();
+        if path.ends_with('}') {
+            if let Some(i) = path.rfind("#{") {
+                path.truncate(i);
+            }
+        }
+        DeviceId(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// An input device was attached or removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Added { kind: DeviceKind, id: DeviceId },
+    Removed { kind: DeviceKind, id: DeviceId },
+}
+
+/// Decodes device notifications into [`DeviceEvent`]s.
+///
+/// Raw input reports mice, keyboards, and game controllers as `WM_INPUT_DEVICE_CHANGE`, but
+/// the device handle in a removal can no longer be queried; device interface notifications
+/// (`WM_DEVICECHANGE`) report every HID device, but without saying what it is. The tracker
+/// registers for both, remembers what it has seen, and reports each arrival and removal once,
+/// whichever notification arrives first.
+///
+/// Devices already attached are reported as added shortly after the tracker is created.
+///
+/// [`DeviceEvent`]: enum.DeviceEvent.html
+pub struct DeviceTracker {
+    notify: HDEVNOTIFY,
+    /// Raw input handles of attached devices, since they can't be queried on removal.
+    handles: HashMap<HANDLE, DeviceId>,
+    attached: HashMap<DeviceId, DeviceKind>,
+}
+
+impl DeviceTracker {
+    /// Register the window for device notifications.
+    ///
+    /// This calls [`register_device_notifications`], with its effect on raw input, and also
+    /// registers for HID device interface notifications, until the tracker is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`register_device_notifications`]: fn.register_device_notifications.html
+    pub unsafe fn new(hwnd: HWND) -> Result<DeviceTracker, Error> {
+        register_device_notifications(hwnd)?;
+        let mut filter: DEV_BROADCAST_DEVICEINTERFACE_W = mem::zeroed();
+        filter.dbcc_size = mem::size_of::<DEV_BROADCAST_DEVICEINTERFACE_W>() as DWORD;
+        filter.dbcc_devicetype = DBT_DEVTYP_DEVICEINTERFACE;
+        filter.dbcc_classguid = GUID_DEVINTERFACE_HID;
+        let notify = RegisterDeviceNotificationW(
+            hwnd as HANDLE,
+            &mut filter as *mut _ as LPVOID,
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        );
+        if notify.is_null() {
+            return Err(Error::last_error());
+        }
+        Ok(DeviceTracker {
+            notify,
+            handles: HashMap::new(),
+            attached: HashMap::new(),
+        })
+    }
+
+    /// Process a message, returning a device event.
+    ///
+    /// Call this for `WM_INPUT_DEVICE_CHANGE` and `WM_DEVICECHANGE` (other messages are
+    /// ignored). The messages should still be passed on to `DefWindowProc`.
+    ///
+    /// # Safety
+    ///
+    /// The `lparam` must be valid for the message, as it is when received by a window
+    /// procedure.
+    pub unsafe fn process_message(
+        &mut self,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<DeviceEvent> {
+        match msg {
+            WM_INPUT_DEVICE_CHANGE => {
+                let handle = lparam as HANDLE;
+                match wparam as DWORD {
+                    GIDC_ARRIVAL => {
+                        let kind = raw_input_kind(handle)?;
+                        let id = DeviceId::from_path(&raw_input_device_name(handle)?);
+                        self.handles.insert(handle, id.clone());
+                        self.added(kind, id)
+                    }
+                    GIDC_REMOVAL => {
+                        let id = self.handles.remove(&handle)?;
+                        self.removed(id)
+                    }
+                    _ => None,
+                }
+            }
+            WM_DEVICECHANGE if lparam != 0 => {
+                let header = &*(lparam as *const DEV_BROADCAST_HDR);
+                if header.dbch_devicetype != DBT_DEVTYP_DEVICEINTERFACE {
+                    return None;
+                }
+                let info = lparam as *const DEV_BROADCAST_DEVICEINTERFACE_W;
+                let name = (*info).dbcc_name.as_ptr();
+                let len = (0..).take_while(|&i| *name.add(i) != 0).count();
+                let path = String::from_utf16_lossy(std::slice::from_raw_parts(name, len));
+                let id = DeviceId::from_path(&path);
+                match wparam {
+                    DBT_DEVICEARRIVAL => {
+                        if self.attached.contains_key(&id) {
+                            return None;
+                        }
+                        let kind = kind_of_device(&id).unwrap_or(DeviceKind::Other);
+                        self.added(kind, id)
+                    }
+                    DBT_DEVICEREMOVECOMPLETE => {
+                        self.handles.retain(|_, handle_id| *handle_id != id);
+                        self.removed(id)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn added(&mut self, kind: DeviceKind, id: DeviceId) -> Option<DeviceEvent> {
+        if self.attached.insert(id.clone(), kind).is_some() {
+            return None;
+        }
+        Some(DeviceEvent::Added { kind, id })
+    }
+
+    fn removed(&mut self, id: DeviceId) -> Option<DeviceEvent> {
+        let kind = self.attached.remove(&id)?;
+        Some(DeviceEvent::Removed { kind, id })
+    }
+}
+
+impl Drop for DeviceTracker {
+    fn drop(&mut self) {
+        unsafe {
+            UnregisterDeviceNotification(self.notify);
+        }
+    }
+}
+
+/// The kind of a device, if raw input knows about it.
+unsafe fn kind_of_device(id: &DeviceId) -> Option<DeviceKind> {
+    raw_input_handles().ok()?.into_iter().find_map(|handle| {
+        let name = raw_input_device_name(handle)?;
+        if DeviceId::from_path(&name) == *id {
+            raw_input_kind(handle)
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,222 @@
+//! Leak detection for windows and window classes.
+//!
+//! This module is enabled by the `diagnostics` feature. It tracks windows created by
+//! [`WindowBuilder`] and classes registered by [`WindowClassBuilder`]. A window that is never
+//! destroyed (because `DestroyWindow` is never called) leaks its window procedure, which can
+//! hold arbitrary resources; a class that is registered but never used is usually a sign of
+//! a typo or dead code.
+//!
+//! It also exposes the reference count and call depth of a window's procedure, so tests can
+//! assert invariants about its lifetime.
+//!
+//! [`WindowBuilder`]: struct.WindowBuilder.html
+//! [`WindowClassBuilder`]: struct.WindowClassBuilder.html
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use winapi::shared::minwindef::ATOM;
+use winapi::shared::windef::HWND;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+
+use crate::window::window_state;
+
+#[derive(Default)]
+struct Registry {
+    /// Live windows, keyed by the address of their state.
+    windows: HashMap<usize, LiveWindow>,
+    /// Destroyed windows whose state was still referenced.
+    retained: Vec<LiveWindow>,
+    classes: HashMap<ATOM, ClassRecord>,
+}
+
+struct ClassRecord {
+    name: String,
+    used: bool,
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn with_registry<R>(f: impl FnOnce(&mut Registry) -> R) -> R {
+    let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    f(registry.get_or_insert_with(Default::default))
+}
+
+/// A window that has been created and not yet destroyed.
+#[derive(Clone, Debug)]
+pub struct LiveWindow {
+    /// The window handle, as an integer (window handles are not `Send`).
+    pub hwnd: usize,
+    /// The window's class atom.
+    pub class: ATOM,
+    /// The id of the thread that created the window.
+    pub thread_id: u32,
+}
+
+/// A snapshot of live windows and unused classes.
+#[derive(Clone, Debug, Default)]
+pub struct LeakReport {
+    pub live_windows: Vec<LiveWindow>,
+    /// Windows whose state was still referenced (other than by calls in progress) when they
+    /// were destroyed, so was not dropped on `WM_NCDESTROY`.
+    pub retained_windows: Vec<LiveWindow>,
+    /// The names of classes that were registered but never used to create a window.
+    pub unused_classes: Vec<String>,
+}
+
+impl LeakReport {
+    /// Take a snapshot of the current state.
+    pub fn current() -> LeakReport {
+        with_registry(|registry| LeakReport {
+            live_windows: registry.windows.values().cloned().collect(),
+            retained_windows: registry.retained.clone(),
+            unused_classes: registry
+                .classes
+                .values()
+                .filter(|class| !class.used)
+                .map(|class| class.name.clone())
+                .collect(),
+        })
+    }
+
+    /// Whether nothing was found.
+    pub fn is_empty(&self) -> bool {
+        self.live_windows.is_empty()
+            && self.retained_windows.is_empty()
+            && self.unused_classes.is_empty()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for window in &self.live_windows {
+            writeln!(
+                f,
+                "window 0x{:x} (class atom {}, thread {}) was never destroyed",
+                window.hwnd, window.class, window.thread_id
+            )?;
+        }
+        for window in &self.retained_windows {
+            writeln!(
+                f,
+                "window 0x{:x} (class atom {}, thread {}) state outlived WM_NCDESTROY",
+                window.hwnd, window.class, window.thread_id
+            )?;
+        }
+        for class in &self.unused_classes {
+            writeln!(f, "class {:?} was registered but never used", class)?;
+        }
+        Ok(())
+    }
+}
+
+/// A guard that reports leaks to stderr when dropped.
+///
+/// Create one at the start of `main`; when it goes out of scope at the end of the program,
+/// any windows not yet destroyed and classes never used are reported.
+pub struct LeakCheck {
+    _private: (),
+}
+
+impl LeakCheck {
+    pub fn new() -> LeakCheck {
+        LeakCheck { _private: () }
+    }
+}
+
+impl Default for LeakCheck {
+    fn default() -> Self {
+        LeakCheck::new()
+    }
+}
+
+impl Drop for LeakCheck {
+    fn drop(&mut self) {
+        let report = LeakReport::current();
+        if !report.is_empty() {
+            eprint!("win-win leak check:\n{}", report);
+        }
+    }
+}
+
+pub(crate) fn class_registered(atom: ATOM, name: String) {
+    with_registry(|registry| {
+        registry
+            .classes
+            .insert(atom, ClassRecord { name, used: false });
+    });
+}
+
+pub(crate) fn class_unregistered(atom: ATOM) {
+    with_registry(|registry| {
+        registry.classes.remove(&atom);
+    });
+}
+
+pub(crate) fn window_created(state: usize, hwnd: HWND, class: ATOM) {
+    let thread_id = unsafe { GetCurrentThreadId() };
+    with_registry(|registry| {
+        if let Some(record) = registry.classes.get_mut(&class) {
+            record.used = true;
+        }
+        registry.windows.insert(
+            state,
+            LiveWindow {
+                hwnd: hwnd as usize,
+                class,
+                thread_id,
+            },
+        );
+    });
+}
+
+/// Record that a window was destroyed, with `retained` unexpected references to its state.
+pub(crate) fn window_destroyed(state: usize, retained: usize) {
+    with_registry(|registry| {
+        if let Some(window) = registry.windows.remove(&state) {
+            if retained > 0 {
+                registry.retained.push(window);
+            }
+        }
+    });
+}
+
+/// The strong reference count of a window's procedure state.
+///
+/// This is 1 (held by the window) plus one for each call to the window procedure in progress,
+/// plus any other references; the crate only holds others briefly. Returns `None` if the
+/// window was not created by this crate or belongs to another thread.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn proc_strong_count(hwnd: HWND) -> Option<usize> {
+    // Don't count the reference taken here.
+    window_state(hwnd).map(|state| Rc::strong_count(&state) - 1)
+}
+
+/// The number of calls to a window's procedure in progress.
+///
+/// This is 1 within a window procedure, unless the call is reentrant. Calls for [hot messages]
+/// are not counted. Returns `None` if the window was not created by this crate or belongs to
+/// another thread.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [hot messages]: trait.WindowProc.html#method.hot_messages
+pub unsafe fn proc_depth(hwnd: HWND) -> Option<u32> {
+    window_state(hwnd).map(|state| state.depth.get())
+}
+
+/// Whether a window's procedure is currently in a reentrant call.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn is_proc_reentrant(hwnd: HWND) -> bool {
+    proc_depth(hwnd).is_some_and(|depth| depth > 1)
+}
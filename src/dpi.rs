@@ -0,0 +1,355 @@
+//! DPI awareness, DPI queries, and DPI-scaled cursors and icons.
+//!
+//! A process that doesn't declare itself DPI aware is scaled by the system as a bitmap, and
+//! looks blurry on high-DPI monitors. Call [`set_process_dpi_awareness`] before creating any
+//! windows (or declare awareness in the application manifest) to render at full resolution.
+//!
+//! Custom cursors and icons loaded with `LoadCursor` and `LoadIcon` always come out at the
+//! system DPI size, so on a high-DPI monitor (with per-monitor DPI awareness) they look tiny.
+//! The types here load the resource at the size appropriate for the window's current DPI, and
+//! reload it when the DPI changes, typically in response to [`WM_DPICHANGED`].
+//!
+//! [`WM_DPICHANGED`]: https://docs.microsoft.com/en-us/windows/win32/hidpi/wm-dpichanged
+//! [`set_process_dpi_awareness`]: fn.set_process_dpi_awareness.html
+
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{BOOL, HINSTANCE, LOWORD, LPARAM, UINT, WPARAM};
+use winapi::shared::windef::{
+    DPI_AWARENESS_CONTEXT, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, DPI_AWARENESS_CONTEXT_SYSTEM_AWARE,
+    DPI_AWARENESS_CONTEXT_UNAWARE, HCURSOR, HICON, HWND,
+};
+use winapi::shared::winerror::ERROR_INVALID_PARAMETER;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::wingdi::{GetDeviceCaps, LOGPIXELSX};
+use winapi::um::winnt::HRESULT;
+use winapi::um::winuser::{
+    DestroyCursor, DestroyIcon, GetDC, GetSystemMetrics, LoadImageW, ReleaseDC, SendMessageW,
+    SetProcessDPIAware, ICON_BIG, ICON_SMALL, IMAGE_CURSOR, IMAGE_ICON, LR_DEFAULTCOLOR,
+    MAKEINTRESOURCEW, SM_CXCURSOR, SM_CXICON, SM_CXSMICON, SM_CYCURSOR, SM_CYICON, SM_CYSMICON,
+    WM_SETICON,
+};
+
+use crate::error::Error;
+
+/// A DPI value, in dots per inch.
+///
+/// 96 DPI corresponds to 100% scaling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Dpi(pub u32);
+
+impl Dpi {
+    /// The DPI at 100% scaling.
+    pub const DEFAULT: Dpi = Dpi(96);
+
+    /// The DPI of the window.
+    ///
+    /// This uses [`GetDpiForWindow`] where available (Windows 10 1607 and later), and falls
+    /// back to the system DPI otherwise.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`GetDpiForWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getdpiforwindow
+    pub unsafe fn for_window(hwnd: HWND) -> Dpi {
+        if let Some(get_dpi_for_window) =
+            dynamic_fn!("user32.dll", GetDpiForWindow: fn(HWND) -> UINT)
+        {
+            let dpi = get_dpi_for_window(hwnd);
+            if dpi != 0 {
+                return Dpi(dpi);
+            }
+        }
+        Dpi::system()
+    }
+
+    /// The system DPI.
+    pub fn system() -> Dpi {
+        unsafe {
+            let hdc = GetDC(null_mut());
+            if hdc.is_null() {
+                return Dpi::DEFAULT;
+            }
+            let dpi = GetDeviceCaps(hdc, LOGPIXELSX);
+            ReleaseDC(null_mut(), hdc);
+            Dpi(dpi as u32)
+        }
+    }
+
+    /// The new DPI from a [`WM_DPICHANGED`] message.
+    ///
+    /// [`WM_DPICHANGED`]: https://docs.microsoft.com/en-us/windows/win32/hidpi/wm-dpichanged
+    pub fn from_wparam(wparam: WPARAM) -> Dpi {
+        Dpi(LOWORD(wparam as u32) as u32)
+    }
+
+    /// The scale factor relative to 96 DPI.
+    pub fn scale_factor(self) -> f64 {
+        self.0 as f64 / 96.0
+    }
+
+    /// Scale a value in 96-DPI units to this DPI.
+    pub fn scale(self, value: i32) -> i32 {
+        (value as i64 * self.0 as i64 / 96) as i32
+    }
+
+    /// A system metric, as it would be at this DPI.
+    ///
+    /// This uses [`GetSystemMetricsForDpi`] where available, and otherwise scales the value
+    /// from the system DPI.
+    ///
+    /// [`GetSystemMetricsForDpi`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsystemmetricsfordpi
+    pub fn system_metric(self, index: i32) -> i32 {
+        unsafe {
+            if let Some(get_system_metrics_for_dpi) =
+                dynamic_fn!("user32.dll", GetSystemMetricsForDpi: fn(i32, UINT) -> i32)
+            {
+                return get_system_metrics_for_dpi(index, self.0);
+            }
+            let system = Dpi::system().0.max(1);
+            (GetSystemMetrics(index) as i64 * self.0 as i64 / system as i64) as i32
+        }
+    }
+
+    /// The standard cursor size at this DPI.
+    pub fn cursor_size(self) -> (i32, i32) {
+        (
+            self.system_metric(SM_CXCURSOR),
+            self.system_metric(SM_CYCURSOR),
+        )
+    }
+
+    /// The standard (large) icon size at this DPI.
+    pub fn icon_size(self) -> (i32, i32) {
+        (self.system_metric(SM_CXICON), self.system_metric(SM_CYICON))
+    }
+
+    /// The small icon size at this DPI, as used in the title bar.
+    pub fn small_icon_size(self) -> (i32, i32) {
+        (
+            self.system_metric(SM_CXSMICON),
+            self.system_metric(SM_CYSMICON),
+        )
+    }
+}
+
+/// A process DPI awareness mode.
+///
+/// See [DPI awareness modes] for how these differ.
+///
+/// [DPI awareness modes]: https://docs.microsoft.com/en-us/windows/win32/hidpi/high-dpi-desktop-application-development-on-windows#dpi-awareness-modes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DpiAwareness {
+    /// The system scales the window as a bitmap.
+    Unaware,
+    /// Windows are rendered for the DPI of the primary monitor at login, and scaled as bitmaps
+    /// on other monitors.
+    System,
+    /// Windows are rendered for the DPI of their monitor, and receive `WM_DPICHANGED` when
+    /// it changes. The non-client area is not scaled.
+    PerMonitor,
+    /// As `PerMonitor`, with the non-client area, dialogs, and common controls scaled by the
+    /// system too. This is the best choice for new applications.
+    PerMonitorV2,
+}
+
+/// Set the DPI awareness of the process, returning the mode actually set.
+///
+/// This must be called before any windows are created. Where the requested mode isn't
+/// supported, the closest lower one is used: per-monitor v2 needs Windows 10 1703,
+/// per-monitor awareness Windows 8.1, and before that only system awareness is available.
+///
+/// This uses [`SetProcessDpiAwarenessContext`], falling back to `SetProcessDpiAwareness`
+/// and then `SetProcessDPIAware`. It fails with `ERROR_ACCESS_DENIED` (or the corresponding
+/// `HRESULT`) if the awareness has already been set, by an earlier call or by the
+/// application manifest.
+///
+/// [`SetProcessDpiAwarenessContext`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setprocessdpiawarenesscontext
+pub fn set_process_dpi_awareness(awareness: DpiAwareness) -> Result<DpiAwareness, Error> {
+    unsafe {
+        if let Some(set_context) = dynamic_fn!(
+            "user32.dll",
+            SetProcessDpiAwarenessContext: fn(DPI_AWARENESS_CONTEXT) -> BOOL
+        ) {
+            let mut mode = awareness;
+            loop {
+                let context = match mode {
+                    DpiAwareness::Unaware => DPI_AWARENESS_CONTEXT_UNAWARE,
+                    DpiAwareness::System => DPI_AWARENESS_CONTEXT_SYSTEM_AWARE,
+                    DpiAwareness::PerMonitor => DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE,
+                    DpiAwareness::PerMonitorV2 => DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+                };
+                if set_context(context) != 0 {
+                    return Ok(mode);
+                }
+                // Before 1703, the v2 context is rejected as an invalid parameter.
+                if mode != DpiAwareness::PerMonitorV2 || GetLastError() != ERROR_INVALID_PARAMETER {
+                    return Err(Error::last_error());
+                }
+                mode = DpiAwareness::PerMonitor;
+            }
+        }
+        let mode = awareness.min(DpiAwareness::PerMonitor);
+        if let Some(set_awareness) =
+            dynamic_fn!("shcore.dll", SetProcessDpiAwareness: fn(u32) -> HRESULT)
+        {
+            let value = match mode {
+                DpiAwareness::Unaware => 0,
+                DpiAwareness::System => 1,
+                _ => 2,
+            };
+            let hr = set_awareness(value);
+            if hr < 0 {
+                return Err(Error::Hresult(hr));
+            }
+            return Ok(mode);
+        }
+        // Unawareness is the default, so there is nothing to do.
+        if mode == DpiAwareness::Unaware {
+            return Ok(mode);
+        }
+        if SetProcessDPIAware() == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(DpiAwareness::System)
+    }
+}
+
+unsafe fn load_image(
+    hinstance: HINSTANCE,
+    id: u16,
+    image_type: UINT,
+    size: (i32, i32),
+) -> Result<HICON, Error> {
+    let handle = LoadImageW(
+        hinstance,
+        MAKEINTRESOURCEW(id),
+        image_type,
+        size.0,
+        size.1,
+        LR_DEFAULTCOLOR,
+    );
+    if handle.is_null() {
+        Err(Error::last_error())
+    } else {
+        Ok(handle as HICON)
+    }
+}
+
+/// A cursor resource, loaded at the size for a given DPI.
+///
+/// Call [`get`] when handling `WM_SETCURSOR`; the cursor is reloaded only when the DPI
+/// differs from the last call.
+///
+/// [`get`]: #method.get
+pub struct ScaledCursor {
+    hinstance: HINSTANCE,
+    id: u16,
+    current: Option<(Dpi, HCURSOR)>,
+}
+
+impl ScaledCursor {
+    /// Create a cursor from a resource in the given module.
+    ///
+    /// The resource is not loaded until first use.
+    pub fn new(hinstance: HINSTANCE, id: u16) -> ScaledCursor {
+        ScaledCursor {
+            hinstance,
+            id,
+            current: None,
+        }
+    }
+
+    /// The cursor handle at the given DPI.
+    pub fn get(&mut self, dpi: Dpi) -> Result<HCURSOR, Error> {
+        if let Some((current_dpi, cursor)) = self.current {
+            if current_dpi == dpi {
+                return Ok(cursor);
+            }
+        }
+        unsafe {
+            let cursor = load_image(self.hinstance, self.id, IMAGE_CURSOR, dpi.cursor_size())?;
+            if let Some((_, old)) = self.current.replace((dpi, cursor)) {
+                DestroyCursor(old);
+            }
+            Ok(cursor)
+        }
+    }
+}
+
+impl Drop for ScaledCursor {
+    fn drop(&mut self) {
+        if let Some((_, cursor)) = self.current {
+            unsafe {
+                DestroyCursor(cursor);
+            }
+        }
+    }
+}
+
+/// A window's title bar and taskbar icons, loaded at the size for the window's DPI.
+///
+/// Call [`apply`] after creating the window, and again on `WM_DPICHANGED`.
+///
+/// [`apply`]: #method.apply
+pub struct WindowIcons {
+    hinstance: HINSTANCE,
+    id: u16,
+    current: Option<(Dpi, HICON, HICON)>,
+}
+
+impl WindowIcons {
+    /// Create icons from a resource in the given module.
+    ///
+    /// The resource should contain images at several sizes; the best match is chosen.
+    pub fn new(hinstance: HINSTANCE, id: u16) -> WindowIcons {
+        WindowIcons {
+            hinstance,
+            id,
+            current: None,
+        }
+    }
+
+    /// Set the window's icons, reloading them if the window's DPI has changed.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference. The icons must outlive their use by the
+    /// window, so this should not be dropped before the window is destroyed.
+    pub unsafe fn apply(&mut self, hwnd: HWND) -> Result<(), Error> {
+        let dpi = Dpi::for_window(hwnd);
+        if let Some((current_dpi, _, _)) = self.current {
+            if current_dpi == dpi {
+                return Ok(());
+            }
+        }
+        let big = load_image(self.hinstance, self.id, IMAGE_ICON, dpi.icon_size())?;
+        let small = match load_image(self.hinstance, self.id, IMAGE_ICON, dpi.small_icon_size()) {
+            Ok(small) => small,
+            Err(e) => {
+                DestroyIcon(big);
+                return Err(e);
+            }
+        };
+        SendMessageW(hwnd, WM_SETICON, ICON_BIG as WPARAM, big as LPARAM);
+        SendMessageW(hwnd, WM_SETICON, ICON_SMALL as WPARAM, small as LPARAM);
+        if let Some((_, old_big, old_small)) = self.current.replace((dpi, big, small)) {
+            DestroyIcon(old_big);
+            DestroyIcon(old_small);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WindowIcons {
+    fn drop(&mut self) {
+        if let Some((_, big, small)) = self.current {
+            unsafe {
+                DestroyIcon(big);
+                DestroyIcon(small);
+            }
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Desktop Window Manager attributes.
+//!
+//! The DWM composes the desktop, and exposes per-window settings through
+//! [`DwmSetWindowAttribute`] and [`DwmGetWindowAttribute`].
+//!
+//! [`DwmSetWindowAttribute`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmsetwindowattribute
+//! [`DwmGetWindowAttribute`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmgetwindowattribute
+
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{BOOL, DWORD, LPCVOID, LPVOID};
+use winapi::shared::windef::{HWND, RECT};
+use winapi::shared::winerror::{ERROR_NOT_SUPPORTED, ERROR_SUCCESS};
+use winapi::um::dwmapi::{
+    DwmExtendFrameIntoClientArea, DwmGetWindowAttribute, DwmSetWindowAttribute, DWMNCRP_ENABLED,
+    DWMNCRP_USEWINDOWSTYLE, DWMWA_CLOAK, DWMWA_CLOAKED, DWMWA_EXTENDED_FRAME_BOUNDS,
+    DWMWA_NCRENDERING_POLICY, DWM_CLOAKED_APP, DWM_CLOAKED_INHERITED, DWM_CLOAKED_SHELL,
+};
+use winapi::um::uxtheme::MARGINS;
+use winapi::um::winreg::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD};
+use winapi::um::winuser::{
+    GetClassLongPtrW, GetWindowLongW, GetWindowRect, SetClassLongPtrW, CS_DROPSHADOW, GCL_STYLE,
+    GWL_EXSTYLE, GWL_STYLE, WS_CAPTION, WS_CHILD, WS_EX_TOOLWINDOW,
+};
+
+use wio::wide::ToWide;
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::version::{is_build_at_least, is_windows11};
+
+unsafe fn set_attribute<T>(hwnd: HWND, attribute: DWORD, value: &T) -> Result<(), Error> {
+    let hr = DwmSetWindowAttribute(
+        hwnd,
+        attribute,
+        value as *const T as LPCVOID,
+        mem::size_of::<T>() as DWORD,
+    );
+    if hr < 0 {
+        Err(Error::Hresult(hr))
+    } else {
+        Ok(())
+    }
+}
+
+unsafe fn get_attribute<T>(hwnd: HWND, attribute: DWORD) -> Result<T, Error> {
+    let mut value: T = mem::zeroed();
+    let hr = DwmGetWindowAttribute(
+        hwnd,
+        attribute,
+        &mut value as *mut T as LPVOID,
+        mem::size_of::<T>() as DWORD,
+    );
+    if hr < 0 {
+        Err(Error::Hresult(hr))
+    } else {
+        Ok(value)
+    }
+}
+
+/// The bounds of a window as drawn, in screen coordinates.
+///
+/// Since Windows 10, the window rectangle includes an invisible resize border around the
+/// visible frame; this is the visible frame, falling back to the window rectangle if the DWM
+/// can't provide it.
+pub(crate) unsafe fn frame_bounds(hwnd: HWND) -> Rect {
+    if let Ok(rect) = get_attribute::<RECT>(hwnd, DWMWA_EXTENDED_FRAME_BOUNDS) {
+        return rect.into();
+    }
+    let mut rect = mem::zeroed();
+    GetWindowRect(hwnd, &mut rect);
+    rect.into()
+}
+
+/// Why a window is cloaked.
+///
+/// A cloaked window is invisible, but otherwise behaves as if visible: it keeps its place in
+/// the z-order, and is still rendered by the application. See [`cloaked_state`].
+///
+/// [`cloaked_state`]: fn.cloaked_state.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cloaked {
+    /// Cloaked by the application, with [`set_cloaked`](fn.set_cloaked.html).
+    pub app: bool,
+    /// Cloaked by the shell; for example, the window is on another virtual desktop.
+    pub shell: bool,
+    /// Cloaked because its owner is cloaked.
+    pub inherited: bool,
+}
+
+impl Cloaked {
+    /// Whether the window is cloaked for any reason.
+    pub fn is_cloaked(&self) -> bool {
+        self.app || self.shell || self.inherited
+    }
+}
+
+/// Cloak or uncloak a window.
+///
+/// Cloaking hides a window without the minimize or hide animation, while it continues to be
+/// composed. A window created cloaked and shown can render its first frame before being
+/// uncloaked, avoiding the flash of an empty window at startup; cloaking is also how a
+/// torn-off tab can be prepared before it appears.
+///
+/// This sets [`DWMWA_CLOAK`], which requires Windows 8.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`DWMWA_CLOAK`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/ne-dwmapi-dwmwindowattribute
+pub unsafe fn set_cloaked(hwnd: HWND, cloaked: bool) -> Result<(), Error> {
+    set_attribute::<BOOL>(hwnd, DWMWA_CLOAK, &(cloaked as BOOL))
+}
+
+/// Query whether a window is cloaked, and why.
+///
+/// This queries [`DWMWA_CLOAKED`], which requires Windows 8.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`DWMWA_CLOAKED`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/ne-dwmapi-dwmwindowattribute
+pub unsafe fn cloaked_state(hwnd: HWND) -> Result<Cloaked, Error> {
+    let flags = get_attribute::<DWORD>(hwnd, DWMWA_CLOAKED)?;
+    Ok(Cloaked {
+        app: flags & DWM_CLOAKED_APP != 0,
+        shell: flags & DWM_CLOAKED_SHELL != 0,
+        inherited: flags & DWM_CLOAKED_INHERITED != 0,
+    })
+}
+
+/// Turn the drop shadow of a window on or off.
+///
+/// For a popup, such as one configured with [`WindowBuilder::popup`] (a window with
+/// `WS_EX_TOOLWINDOW` and no caption), this sets `CS_DROPSHADOW` in the class style, the
+/// shadow used by menus and tooltips. The class style applies to all windows of the class,
+/// and takes effect the next time each is shown.
+///
+/// For other top-level windows, the DWM draws the shadow: the non-client rendering policy is
+/// enabled, and the frame is extended one pixel into the client area with
+/// [`DwmExtendFrameIntoClientArea`]. This gives a borderless window the shadow of a normal
+/// window. The extended frame is covered by the window's own rendering, as long as that is
+/// opaque. This path requires desktop composition, which is always on from Windows 8.
+///
+/// Child windows have no shadow, and `Error::InvalidStyle` is returned for them.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder::popup`]: struct.WindowBuilder.html#method.popup
+/// [`DwmExtendFrameIntoClientArea`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmextendframeintoclientarea
+pub unsafe fn set_drop_shadow(hwnd: HWND, shadow: bool) -> Result<(), Error> {
+    let style = GetWindowLongW(hwnd, GWL_STYLE) as DWORD;
+    let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as DWORD;
+    if style & WS_CHILD != 0 {
+        return Err(Error::InvalidStyle("child windows have no drop shadow"));
+    }
+    if ex_style & WS_EX_TOOLWINDOW != 0 && style & WS_CAPTION == 0 {
+        let class_style = GetClassLongPtrW(hwnd, GCL_STYLE) as DWORD;
+        let new_style = if shadow {
+            class_style | CS_DROPSHADOW
+        } else {
+            class_style & !CS_DROPSHADOW
+        };
+        if new_style != class_style {
+            SetClassLongPtrW(hwnd, GCL_STYLE, new_style as _);
+        }
+        return Ok(());
+    }
+    let (policy, inset) = if shadow {
+        (DWMNCRP_ENABLED, 1)
+    } else {
+        (DWMNCRP_USEWINDOWSTYLE, 0)
+    };
+    set_attribute::<DWORD>(hwnd, DWMWA_NCRENDERING_POLICY, &policy)?;
+    let margins = MARGINS {
+        cxLeftWidth: inset,
+        cxRightWidth: inset,
+        cyTopHeight: inset,
+        cyBottomHeight: inset,
+    };
+    let hr = DwmExtendFrameIntoClientArea(hwnd, &margins);
+    if hr < 0 {
+        return Err(Error::Hresult(hr));
+    }
+    Ok(())
+}
+
+/// The attribute for dark mode title bars, from Windows 10 build 18985 (documented as
+/// `DWMWA_USE_IMMERSIVE_DARK_MODE`).
+const DWMWA_USE_IMMERSIVE_DARK_MODE: DWORD = 20;
+
+/// The undocumented attribute for dark mode title bars in earlier builds, from 17763.
+const DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1: DWORD = 19;
+
+/// Give a window a dark or light title bar.
+///
+/// The DWM draws the title bar light by default, whatever the system theme; an application
+/// with a dark theme should call this with `true` (for example, if [`AppTheme::current`] is
+/// dark), and again on [`Event::ThemeChanged`] if it follows the system theme. To set it
+/// before the window is shown, use [`WindowBuilder::dark_mode_titlebar`].
+///
+/// This sets [`DWMWA_USE_IMMERSIVE_DARK_MODE`], using the attribute value for the running
+/// build. Before Windows 10 version 1809 (build 17763), title bars are always light, and this
+/// does nothing.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`AppTheme::current`]: enum.AppTheme.html#method.current
+/// [`Event::ThemeChanged`]: enum.Event.html#variant.ThemeChanged
+/// [`WindowBuilder::dark_mode_titlebar`]: struct.WindowBuilder.html#method.dark_mode_titlebar
+/// [`DWMWA_USE_IMMERSIVE_DARK_MODE`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/ne-dwmapi-dwmwindowattribute
+pub unsafe fn set_dark_mode_titlebar(hwnd: HWND, dark: bool) -> Result<(), Error> {
+    let attribute = if is_build_at_least(18985) {
+        DWMWA_USE_IMMERSIVE_DARK_MODE
+    } else if is_build_at_least(17763) {
+        DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1
+    } else {
+        return Ok(());
+    };
+    set_attribute::<BOOL>(hwnd, attribute, &(dark as BOOL))
+}
+
+/// The attribute for the system backdrop, from Windows 11 build 22621.
+const DWMWA_SYSTEMBACKDROP_TYPE: DWORD = 38;
+
+/// The undocumented attribute enabling Mica in earlier builds of Windows 11.
+const DWMWA_MICA_EFFECT: DWORD = 1029;
+
+/// A material drawn by the system behind a window's client area.
+///
+/// See [`set_backdrop`](fn.set_backdrop.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backdrop {
+    /// No backdrop; the client area is drawn by the window alone.
+    None,
+    /// Mica, tinted with the desktop wallpaper, for long-lived windows.
+    Mica,
+    /// A variant of Mica with a stronger tint, for windows with tabs in the title bar.
+    MicaAlt,
+    /// Acrylic, a blur of what is behind the window, for transient windows such as popups.
+    Acrylic,
+}
+
+impl Backdrop {
+    /// The `DWM_SYSTEMBACKDROP_TYPE` value.
+    fn backdrop_type(self) -> DWORD {
+        match self {
+            Backdrop::None => 1,
+            Backdrop::Mica => 2,
+            Backdrop::Acrylic => 3,
+            Backdrop::MicaAlt => 4,
+        }
+    }
+}
+
+/// Set the system backdrop of a window.
+///
+/// The backdrop shows through wherever the window draws transparent pixels, so this also
+/// extends the frame into the whole client area with [`DwmExtendFrameIntoClientArea`] (or,
+/// for `Backdrop::None`, retracts it, undoing any [`set_drop_shadow`]). The window must then
+/// draw its background transparent: with a swapchain or DirectComposition using premultiplied
+/// alpha, or by painting black with GDI. Following the [system theme](enum.AppTheme.html) also
+/// needs [`set_dark_mode_titlebar`], which switches the backdrop to its dark variant.
+///
+/// This sets [`DWMWA_SYSTEMBACKDROP_TYPE`], from Windows 11 build 22621. Earlier builds of
+/// Windows 11 only have Mica, set with an undocumented attribute, and the other backdrops
+/// fall back to it. Before Windows 11, this returns a `Win32` error of `ERROR_NOT_SUPPORTED`.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`DwmExtendFrameIntoClientArea`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmextendframeintoclientarea
+/// [`set_drop_shadow`]: fn.set_drop_shadow.html
+/// [`set_dark_mode_titlebar`]: fn.set_dark_mode_titlebar.html
+/// [`DWMWA_SYSTEMBACKDROP_TYPE`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/ne-dwmapi-dwmwindowattribute
+pub unsafe fn set_backdrop(hwnd: HWND, backdrop: Backdrop) -> Result<(), Error> {
+    if is_build_at_least(22621) {
+        set_attribute::<DWORD>(hwnd, DWMWA_SYSTEMBACKDROP_TYPE, &backdrop.backdrop_type())?;
+    } else if is_windows11() {
+        let mica = backdrop != Backdrop::None;
+        set_attribute::<BOOL>(hwnd, DWMWA_MICA_EFFECT, &(mica as BOOL))?;
+    } else {
+        return Err(Error::Win32(ERROR_NOT_SUPPORTED));
+    }
+    // Negative margins extend the frame over the whole client area.
+    let inset = if backdrop == Backdrop::None { 0 } else { -1 };
+    let margins = MARGINS {
+        cxLeftWidth: inset,
+        cxRightWidth: inset,
+        cyTopHeight: inset,
+        cyBottomHeight: inset,
+    };
+    let hr = DwmExtendFrameIntoClientArea(hwnd, &margins);
+    if hr < 0 {
+        return Err(Error::Hresult(hr));
+    }
+    Ok(())
+}
+
+/// The attribute for the corner rounding of a window, from Windows 11.
+const DWMWA_WINDOW_CORNER_PREFERENCE: DWORD = 33;
+
+/// How the corners of a window are rounded.
+///
+/// See [`set_corner_preference`](fn.set_corner_preference.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CornerPreference {
+    /// Let the system decide, which rounds top-level windows, but not, for example, popups
+    /// without a frame.
+    Default,
+    /// Never round the corners.
+    DoNotRound,
+    /// Round the corners, if appropriate.
+    Round,
+    /// Round the corners with a small radius, if appropriate, as for menus and tooltips.
+    RoundSmall,
+}
+
+impl CornerPreference {
+    /// The `DWM_WINDOW_CORNER_PREFERENCE` value.
+    fn value(self) -> DWORD {
+        match self {
+            CornerPreference::Default => 0,
+            CornerPreference::DoNotRound => 1,
+            CornerPreference::Round => 2,
+            CornerPreference::RoundSmall => 3,
+        }
+    }
+}
+
+/// Set how the corners of a window are rounded.
+///
+/// Windows 11 rounds the corners of most top-level windows, and chooses by the window's
+/// styles; a borderless window can ask for rounding to match the rest of the system, and a
+/// window that draws its own edges (such as a docking panel) can opt out. To set it before
+/// the window is shown, use [`WindowBuilder::corner_preference`].
+///
+/// This sets [`DWMWA_WINDOW_CORNER_PREFERENCE`]. Before Windows 11, corners are always
+/// square, and this does nothing.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder::corner_preference`]: struct.WindowBuilder.html#method.corner_preference
+/// [`DWMWA_WINDOW_CORNER_PREFERENCE`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/ne-dwmapi-dwmwindowattribute
+pub unsafe fn set_corner_preference(hwnd: HWND, preference: CornerPreference) -> Result<(), Error> {
+    if !is_windows11() {
+        return Ok(());
+    }
+    set_attribute::<DWORD>(hwnd, DWMWA_WINDOW_CORNER_PREFERENCE, &preference.value())
+}
+
+/// The light or dark theme chosen for applications in the system settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppTheme {
+    Light,
+    Dark,
+}
+
+impl AppTheme {
+    /// The current app theme.
+    ///
+    /// This reads the `AppsUseLightTheme` value from the registry, as there is no API for it.
+    /// Before Windows 10 version 1809, and if the value is missing, this is `Light`. A change
+    /// is signalled by [`Event::ThemeChanged`](enum.Event.html#variant.ThemeChanged).
+    pub fn current() -> AppTheme {
+        let key =
+            "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize".to_wide_null();
+        let name = "AppsUseLightTheme".to_wide_null();
+        let mut value: DWORD = 1;
+        let mut size = mem::size_of::<DWORD>() as DWORD;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_CURRENT_USER,
+                key.as_ptr(),
+                name.as_ptr(),
+                RRF_RT_REG_DWORD,
+                null_mut(),
+                &mut value as *mut DWORD as LPVOID,
+                &mut size,
+            )
+        };
+        if status == ERROR_SUCCESS as i32 && value == 0 {
+            AppTheme::Dark
+        } else {
+            AppTheme::Light
+        }
+    }
+}
@@ -1,4 +1,11 @@
 use std::fmt;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::DWORD;
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::winbase::{
+    FormatMessageW, FORMAT_MESSAGE_FROM_SYSTEM, FORMAT_MESSAGE_IGNORE_INSERTS,
+};
 use winapi::um::winnt::HRESULT;
 
 /// A wrapper for winapi errors.
@@ -6,6 +13,27 @@ use winapi::um::winnt::HRESULT;
 pub enum Error {
     RegisterClassFailed,
     Hresult(HRESULT),
+    /// A Win32 error code, as returned by [`GetLastError`].
+    ///
+    /// [`GetLastError`]: https://docs.microsoft.com/en-us/windows/win32/api/errhandlingapi/nf-errhandlingapi-getlasterror
+    Win32(DWORD),
+    /// An invalid combination of window or class styles, with an explanation.
+    InvalidStyle(&'static str),
+    /// The window was not created by this crate, or belongs to another thread.
+    ForeignWindow,
+    /// The window has been destroyed.
+    WindowDestroyed,
+    /// `CreateWindowEx` failed.
+    ///
+    /// The code is from [`GetLastError`], and the message is its system description. A code
+    /// of 0 usually means the window procedure failed the creation, by returning `FALSE`
+    /// from `WM_NCCREATE` or -1 from `WM_CREATE`.
+    ///
+    /// [`GetLastError`]: https://docs.microsoft.com/en-us/windows/win32/api/errhandlingapi/nf-errhandlingapi-getlasterror
+    CreateWindowFailed {
+        code: DWORD,
+        message: String,
+    },
 }
 
 impl fmt::Display for Error {
@@ -13,8 +41,59 @@ impl fmt::Display for Error {
         match self {
             Error::RegisterClassFailed => write!(f, "RegisterClass failed"),
             Error::Hresult(hr) => write!(f, "HRESULT 0x{:x}", hr),
+            Error::Win32(code) => write!(f, "Win32 error {}", code),
+            Error::InvalidStyle(reason) => write!(f, "Invalid style: {}", reason),
+            Error::ForeignWindow => write!(f, "Window not owned by this thread"),
+            Error::WindowDestroyed => write!(f, "Window has been destroyed"),
+            Error::CreateWindowFailed { code: 0, .. } => {
+                write!(
+                    f,
+                    "CreateWindowEx failed: creation refused by the window procedure"
+                )
+            }
+            Error::CreateWindowFailed { code, message } => {
+                write!(f, "CreateWindowEx failed (error {}): {}", code, message)
+            }
         }
     }
 }
 
 impl std::error::Error for Error {}
+
+impl Error {
+    /// An error from the calling thread's last-error code.
+    pub(crate) fn last_error() -> Error {
+        Error::Win32(unsafe { GetLastError() })
+    }
+
+    /// A `CreateWindowFailed` error from the calling thread's last-error code.
+    pub(crate) fn create_window_failed() -> Error {
+        let code = unsafe { GetLastError() };
+        Error::CreateWindowFailed {
+            code,
+            message: format_message(code),
+        }
+    }
+}
+
+/// The system description of a Win32 error code, from [`FormatMessageW`].
+///
+/// [`FormatMessageW`]: https://docs.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-formatmessagew
+fn format_message(code: DWORD) -> String {
+    let mut buf = [0u16; 512];
+    let len = unsafe {
+        FormatMessageW(
+            FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
+            null_mut(),
+            code,
+            0,
+            buf.as_mut_ptr(),
+            buf.len() as DWORD,
+            null_mut(),
+        )
+    };
+    // The message ends with a line break.
+    String::from_utf16_lossy(&buf[..len as usize])
+        .trim_end()
+        .to_string()
+}
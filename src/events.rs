@@ -0,0 +1,636 @@
+//! A typed event layer over window messages.
+//!
+//! Most of this crate deals in raw window messages, which is flexible but demands familiarity
+//! with the quirks of each message. This module decodes the commonly handled messages into a
+//! single [`Event`] enum, delivered to an [`EventHandler`]. Wrap the handler in an
+//! [`EventProc`] to use it as a window procedure.
+//!
+//! Messages are first offered to [`EventHandler::raw`], so the handler can still take over
+//! any message, including those with an event.
+//!
+//! [`Event`]: enum.Event.html
+//! [`EventHandler`]: trait.EventHandler.html
+//! [`EventHandler::raw`]: trait.EventHandler.html#method.raw
+//! [`EventProc`]: struct.EventProc.html
+
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use winapi::shared::minwindef::{HIWORD, LOWORD, LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::{HWND, RECT};
+use winapi::um::winuser::{
+    IsIconic, IsWindowVisible, SetWindowPos, SIZE_MAXIMIZED, SIZE_MINIMIZED, SWP_NOACTIVATE,
+    SWP_NOZORDER, SW_OTHERUNZOOM, SW_OTHERZOOM, SW_PARENTCLOSING, SW_PARENTOPENING, WA_INACTIVE,
+    WM_ACTIVATE, WM_CHAR, WM_CLOSE, WM_CREATE, WM_DESTROY, WM_DISPLAYCHANGE, WM_DPICHANGED,
+    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK,
+    WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSELEAVE, WM_MOUSEMOVE, WM_MOVE, WM_PAINT, WM_RBUTTONDBLCLK,
+    WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETTINGCHANGE, WM_SHOWWINDOW, WM_SIZE, WM_SYSCHAR,
+    WM_SYSKEYDOWN, WM_SYSKEYUP, WM_THEMECHANGED,
+};
+
+use crate::advanced_color::AdvancedColor;
+use crate::appcommand::{AppCommandEvent, APPCOMMAND_HANDLED};
+use crate::close::{CloseRequest, CloseState, SharedCloseState};
+use crate::dpi::Dpi;
+use crate::fullscreen::refit_fullscreen;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::GamepadEvent;
+use crate::geometry::{Point, Rect};
+use crate::message::message_time;
+use crate::monitor::Monitor;
+use crate::mouse::{lparam_to_point, ButtonState, MouseEvent, WheelEvent, XButton};
+use crate::pointer::PointerEvent;
+use crate::window::WindowProc;
+
+/// The state of the window after a resize.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeState {
+    Restored,
+    Minimized,
+    Maximized,
+}
+
+/// Why a window was shown or hidden.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisibilityReason {
+    /// A call to `ShowWindow` (or `ShowWindowAsync`) for this window.
+    ShowWindow,
+    /// The owner window was minimized, hiding the windows it owns.
+    OwnerMinimized,
+    /// The owner window was restored, showing the windows it owns again.
+    OwnerRestored,
+    /// Another window was maximized, covering this one.
+    OtherMaximized,
+    /// Another window was restored from maximized, uncovering this one.
+    OtherRestored,
+}
+
+/// A mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+/// A key press or release, without text interpretation.
+///
+/// Text input arrives separately as [`Event::Char`](enum.Event.html#variant.Char). For full
+/// keyboard handling (keys and codes according to the W3C model), use `KeyboardState` (with
+/// the `kb` feature) from [`EventHandler::raw`](trait.EventHandler.html#method.raw).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The virtual key code.
+    pub vk: i32,
+    /// The scan code, with 0x100 set for extended keys.
+    pub scan_code: u32,
+    pub state: ButtonState,
+    /// Whether this is an auto-repeat.
+    pub repeat: bool,
+    /// Whether this is a system key (with Alt held, or F10).
+    pub system: bool,
+    /// The message time, in milliseconds.
+    pub time: u32,
+}
+
+/// A mouse button event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseButtonEvent {
+    pub button: MouseButton,
+    pub state: ButtonState,
+    /// The cursor position, in client coordinates.
+    pub pos: Point,
+    /// The state of the virtual keys, as a combination of `MK_` flags.
+    pub keys: u16,
+    /// The message time, in milliseconds.
+    pub time: u32,
+}
+
+/// The new display mode, from [`WM_DISPLAYCHANGE`].
+///
+/// [`WM_DISPLAYCHANGE`]: https://docs.microsoft.com/en-us/windows/win32/gdi/wm-displaychange
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayMode {
+    /// The horizontal resolution of the screen, in pixels.
+    pub width: u32,
+    /// The vertical resolution of the screen, in pixels.
+    pub height: u32,
+    /// The color depth, in bits per pixel.
+    pub bits_per_pixel: u32,
+}
+
+/// A window event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// The window has been created (`WM_CREATE`).
+    Created,
+    /// The user asked to close the window (`WM_CLOSE`).
+    ///
+    /// If unhandled, the window is destroyed. If handled, it stays open; to decide later,
+    /// handle the event and call [`CloseRequest::defer`], and close requests are ignored
+    /// until the decision is made.
+    ///
+    /// [`CloseRequest::defer`]: struct.CloseRequest.html#method.defer
+    CloseRequested(CloseRequest),
+    /// The window is being destroyed (`WM_DESTROY`).
+    Destroyed,
+    /// The client area was resized, in pixels.
+    Resized {
+        width: u32,
+        height: u32,
+        state: SizeState,
+    },
+    /// The window moved; the position is of the client area, in screen coordinates.
+    Moved(Point),
+    /// The window is about to be shown or hidden (`WM_SHOWWINDOW`).
+    ///
+    /// This is sent before the change, and isn't sent for windows created with `WS_VISIBLE`,
+    /// or when a window is minimized or maximized, which is reported by `Resized`.
+    VisibilityChanged {
+        visible: bool,
+        reason: VisibilityReason,
+    },
+    /// The window was activated or deactivated.
+    Focused(bool),
+    /// The window needs painting.
+    ///
+    /// The handler should paint with [`Paint`](struct.Paint.html). If unhandled, the
+    /// window is validated without painting.
+    Paint,
+    Key(KeyEvent),
+    /// A character of text input.
+    ///
+    /// Characters outside the basic multilingual plane arrive as two events, each with a
+    /// UTF-16 surrogate; these are combined into one event.
+    Char(char),
+    MouseMove {
+        /// The cursor position, in client coordinates.
+        pos: Point,
+    },
+    /// The cursor left the client area (requires `TrackMouseEvent`).
+    MouseLeave,
+    MouseButton(MouseButtonEvent),
+    Wheel(WheelEvent),
+    Pointer(PointerEvent),
+    AppCommand(AppCommandEvent),
+    /// The DPI changed, usually because the window moved to another monitor.
+    ///
+    /// The suggested rectangle is in screen coordinates; if this event is unhandled, the
+    /// window is moved and resized to it, as recommended.
+    DpiChanged {
+        dpi: Dpi,
+        suggested: Rect,
+    },
+    /// The system theme (light or dark mode, high contrast, or visual styles) changed.
+    ThemeChanged,
+    /// The window moved to another monitor.
+    ///
+    /// This is checked on `WM_MOVE`, `WM_DPICHANGED`, and `WM_DISPLAYCHANGE`, and delivered
+    /// before the event for that message. The monitor is the one with the largest part of
+    /// the window, as reported by `MonitorFromWindow`. Applications can use it to adapt frame
+    /// pacing to the new refresh rate, or switch color profiles.
+    MonitorChanged {
+        old: Monitor,
+        new: Monitor,
+    },
+    /// The color profile for the window changed, usually because it moved to another monitor.
+    ///
+    /// This is only delivered when enabled with
+    /// [`EventProc::color_profile_events`](struct.EventProc.html#method.color_profile_events),
+    /// after any `MonitorChanged`. The path is that of the new profile, as from
+    /// [`Monitor::icc_profile_path`](struct.Monitor.html#method.icc_profile_path).
+    ColorProfileChanged(Option<PathBuf>),
+    /// The advanced color (HDR) state for the window changed, because it moved to another
+    /// monitor or the display settings changed.
+    ///
+    /// This is only delivered when enabled with
+    /// [`EventProc::advanced_color_events`](struct.EventProc.html#method.advanced_color_events),
+    /// after any `MonitorChanged`.
+    AdvancedColorChanged(Option<AdvancedColor>),
+    /// The display settings changed (`WM_DISPLAYCHANGE`): the resolution, the color depth, a
+    /// monitor was added or removed, or HDR was turned on or off.
+    ///
+    /// This is delivered after any `MonitorChanged`, `ColorProfileChanged`,
+    /// `AdvancedColorChanged`, and `RefreshRateChanged`.
+    DisplayChanged(DisplayMode),
+    /// The refresh rate of the window's monitor changed, because it moved to another monitor
+    /// or the display mode changed.
+    ///
+    /// This is only delivered when enabled with
+    /// [`EventProc::refresh_rate_events`](struct.EventProc.html#method.refresh_rate_events),
+    /// after any `MonitorChanged`. The rate is that of
+    /// [`Monitor::refresh_rate`](struct.Monitor.html#method.refresh_rate).
+    RefreshRateChanged(Option<u32>),
+    /// The message queue is empty.
+    ///
+    /// This is only produced by [`run`](fn.run.html) with `ControlFlow::Poll`.
+    Idle,
+    /// A game controller changed state.
+    ///
+    /// Like `Idle`, this is only produced by [`run`](fn.run.html) with `ControlFlow::Poll`,
+    /// which polls the controllers before each `Idle`.
+    #[cfg(feature = "gamepad")]
+    Gamepad(GamepadEvent),
+}
+
+/// A handler for [`Event`](enum.Event.html)s.
+pub trait EventHandler {
+    /// Handle an event, returning `true` if it was handled.
+    ///
+    /// Unhandled events get default processing.
+    fn event(&mut self, hwnd: HWND, event: Event) -> bool;
+
+    /// Handle a raw message, before it is decoded.
+    ///
+    /// Returning `Some` bypasses event decoding and default processing.
+    fn raw(&mut self, hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> Option<LRESULT> {
+        let _ = (hwnd, msg, wparam, lparam);
+        None
+    }
+}
+
+/// A window procedure that delivers events to an [`EventHandler`].
+///
+/// Messages delivered reentrantly, while the handler is running, get default processing.
+///
+/// [`EventHandler`]: trait.EventHandler.html
+pub struct EventProc<H> {
+    handler: RefCell<H>,
+    high_surrogate: Cell<Option<u16>>,
+    monitor: Cell<Option<Monitor>>,
+    /// The current color profile, when tracked.
+    color_profile: Option<RefCell<Option<PathBuf>>>,
+    /// The current advanced color state, when tracked.
+    advanced_color: Option<Cell<Option<AdvancedColor>>>,
+    /// The current refresh rate, when tracked.
+    refresh_rate: Option<Cell<Option<u32>>>,
+    refit_fullscreen: bool,
+    close_state: SharedCloseState,
+}
+
+impl<H: EventHandler> EventProc<H> {
+    pub fn new(handler: H) -> EventProc<H> {
+        EventProc {
+            handler: RefCell::new(handler),
+            high_surrogate: Default::default(),
+            monitor: Default::default(),
+            color_profile: None,
+            advanced_color: None,
+            refresh_rate: None,
+            refit_fullscreen: false,
+            close_state: Rc::new(Cell::new(CloseState::Open)),
+        }
+    }
+
+    /// Deliver [`Event::ColorProfileChanged`] when the window's color profile changes.
+    ///
+    /// The profile is checked when the window moves to another monitor, and on
+    /// `WM_DISPLAYCHANGE`. The system sends no message when the profile of a monitor is changed
+    /// in the color management settings, so an application that needs to notice that should
+    /// also check [`window_icc_profile_path`] when it is activated.
+    ///
+    /// [`Event::ColorProfileChanged`]: enum.Event.html#variant.ColorProfileChanged
+    /// [`window_icc_profile_path`]: fn.window_icc_profile_path.html
+    pub fn color_profile_events(mut self) -> Self {
+        self.color_profile = Some(Default::default());
+        self
+    }
+
+    /// Deliver [`Event::AdvancedColorChanged`] when the window's advanced color state changes.
+    ///
+    /// The state is checked when the window moves to another monitor, and on
+    /// `WM_DISPLAYCHANGE`, which is sent when HDR is turned on or off. Changing the SDR
+    /// content brightness in the display settings sends no message, so the SDR white level
+    /// may be stale until the next check.
+    ///
+    /// [`Event::AdvancedColorChanged`]: enum.Event.html#variant.AdvancedColorChanged
+    pub fn advanced_color_events(mut self) -> Self {
+        self.advanced_color = Some(Default::default());
+        self
+    }
+
+    /// Deliver [`Event::RefreshRateChanged`] when the refresh rate of the window's monitor
+    /// changes.
+    ///
+    /// The rate is checked when the window moves to another monitor, and on
+    /// `WM_DISPLAYCHANGE`. Applications pacing frames to the display should re-query their
+    /// timing (such as [`FrameTiming`](struct.FrameTiming.html)) on this event.
+    ///
+    /// [`Event::RefreshRateChanged`]: enum.Event.html#variant.RefreshRateChanged
+    pub fn refresh_rate_events(mut self) -> Self {
+        self.refresh_rate = Some(Default::default());
+        self
+    }
+
+    /// Keep a [fullscreen](fn.set_fullscreen.html) window covering its area when the display
+    /// configuration changes.
+    ///
+    /// On `WM_DISPLAYCHANGE`, before any events are delivered, the window is moved and resized
+    /// with [`refit_fullscreen`](fn.refit_fullscreen.html).
+    pub fn refit_fullscreen(mut self) -> Self {
+        self.refit_fullscreen = true;
+        self
+    }
+
+    /// Check whether the refresh rate changed, returning the new rate.
+    unsafe fn refresh_rate_change(&self, hwnd: HWND, msg: UINT) -> Option<Option<u32>> {
+        let current = self.refresh_rate.as_ref()?;
+        let new = Monitor::from_window(hwnd).refresh_rate();
+        let old = current.replace(new);
+        if msg != WM_CREATE && old != new {
+            Some(new)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the window's advanced color state changed, returning the new state.
+    unsafe fn advanced_color_change(&self, hwnd: HWND, msg: UINT) -> Option<Option<AdvancedColor>> {
+        let current = self.advanced_color.as_ref()?;
+        let new = Monitor::from_window(hwnd).advanced_color();
+        let old = current.replace(new);
+        if msg != WM_CREATE && old != new {
+            Some(new)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the window's color profile changed, returning the new profile.
+    unsafe fn color_profile_change(&self, hwnd: HWND, msg: UINT) -> Option<Option<PathBuf>> {
+        let current = self.color_profile.as_ref()?;
+        let new = window_icc_profile_path(hwnd);
+        let old = current.replace(new.clone());
+        if msg != WM_CREATE && old != new {
+            Some(new)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the window's monitor changed, returning the change.
+    unsafe fn monitor_change(&self, hwnd: HWND) -> Option<(Monitor, Monitor)> {
+        let new = Monitor::from_window(hwnd);
+        match self.monitor.replace(Some(new)) {
+            Some(old) if old != new => Some((old, new)),
+            _ => None,
+        }
+    }
+
+    fn decode(&self, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> Option<(Event, LRESULT)> {
+        let event = match msg {
+            WM_CREATE => Event::Created,
+            WM_DESTROY => Event::Destroyed,
+            WM_SIZE => {
+                let state = match wparam {
+                    SIZE_MINIMIZED => SizeState::Minimized,
+                    SIZE_MAXIMIZED => SizeState::Maximized,
+                    _ => SizeState::Restored,
+                };
+                Event::Resized {
+                    width: LOWORD(lparam as u32) as u32,
+                    height: HIWORD(lparam as u32) as u32,
+                    state,
+                }
+            }
+            WM_MOVE => Event::Moved(lparam_to_point(lparam)),
+            WM_DISPLAYCHANGE => Event::DisplayChanged(DisplayMode {
+                width: LOWORD(lparam as u32) as u32,
+                height: HIWORD(lparam as u32) as u32,
+                bits_per_pixel: wparam as u32,
+            }),
+            WM_SHOWWINDOW => Event::VisibilityChanged {
+                visible: wparam != 0,
+                reason: match lparam {
+                    SW_PARENTCLOSING => VisibilityReason::OwnerMinimized,
+                    SW_PARENTOPENING => VisibilityReason::OwnerRestored,
+                    SW_OTHERZOOM => VisibilityReason::OtherMaximized,
+                    SW_OTHERUNZOOM => VisibilityReason::OtherRestored,
+                    _ => VisibilityReason::ShowWindow,
+                },
+            },
+            WM_ACTIVATE => Event::Focused(LOWORD(wparam as u32) != WA_INACTIVE),
+            WM_PAINT => Event::Paint,
+            WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => Event::Key(KeyEvent {
+                vk: wparam as i32,
+                scan_code: ((lparam >> 16) & 0x1ff) as u32,
+                state: if msg == WM_KEYDOWN || msg == WM_SYSKEYDOWN {
+                    ButtonState::Down
+                } else {
+                    ButtonState::Up
+                },
+                repeat: msg != WM_KEYUP && msg != WM_SYSKEYUP && lparam & 0x4000_0000 != 0,
+                system: msg == WM_SYSKEYDOWN || msg == WM_SYSKEYUP,
+                time: message_time(),
+            }),
+            WM_CHAR | WM_SYSCHAR => {
+                let unit = wparam as u16;
+                let c = if (0xd800..0xdc00).contains(&unit) {
+                    self.high_surrogate.set(Some(unit));
+                    return None;
+                } else if let Some(high) = self.high_surrogate.take() {
+                    std::char::decode_utf16([high, unit].iter().cloned())
+                        .next()?
+                        .ok()?
+                } else {
+                    std::char::from_u32(unit as u32)?
+                };
+                Event::Char(c)
+            }
+            WM_MOUSEMOVE => Event::MouseMove {
+                pos: lparam_to_point(lparam),
+            },
+            WM_MOUSELEAVE => Event::MouseLeave,
+            WM_LBUTTONDOWN | WM_LBUTTONUP | WM_LBUTTONDBLCLK | WM_RBUTTONDOWN | WM_RBUTTONUP
+            | WM_RBUTTONDBLCLK | WM_MBUTTONDOWN | WM_MBUTTONUP | WM_MBUTTONDBLCLK => {
+                let (button, state) = match msg {
+                    WM_LBUTTONDOWN => (MouseButton::Left, ButtonState::Down),
+                    WM_LBUTTONUP => (MouseButton::Left, ButtonState::Up),
+                    WM_LBUTTONDBLCLK => (MouseButton::Left, ButtonState::DoubleClick),
+                    WM_RBUTTONDOWN => (MouseButton::Right, ButtonState::Down),
+                    WM_RBUTTONUP => (MouseButton::Right, ButtonState::Up),
+                    WM_RBUTTONDBLCLK => (MouseButton::Right, ButtonState::DoubleClick),
+                    WM_MBUTTONDOWN => (MouseButton::Middle, ButtonState::Down),
+                    WM_MBUTTONUP => (MouseButton::Middle, ButtonState::Up),
+                    _ => (MouseButton::Middle, ButtonState::DoubleClick),
+                };
+                Event::MouseButton(MouseButtonEvent {
+                    button,
+                    state,
+                    pos: lparam_to_point(lparam),
+                    keys: LOWORD(wparam as u32),
+                    time: message_time(),
+                })
+            }
+            WM_DPICHANGED => {
+                let rect = unsafe { *(lparam as *const RECT) };
+                Event::DpiChanged {
+                    dpi: Dpi::from_wparam(wparam),
+                    suggested: rect.into(),
+                }
+            }
+            WM_THEMECHANGED | WM_SETTINGCHANGE => {
+                if msg == WM_SETTINGCHANGE && !is_color_set_change(lparam) {
+                    return None;
+                }
+                Event::ThemeChanged
+            }
+            _ => {
+                if let Some(event) = MouseEvent::decode(msg, wparam, lparam) {
+                    let result = event.handled_result();
+                    let event = match event {
+                        MouseEvent::Wheel(wheel) => Event::Wheel(wheel),
+                        MouseEvent::XButton(x) => Event::MouseButton(MouseButtonEvent {
+                            button: match x.button {
+                                XButton::X1 => MouseButton::X1,
+                                XButton::X2 => MouseButton::X2,
+                            },
+                            state: x.state,
+                            pos: x.pos,
+                            keys: x.keys,
+                            time: x.time,
+                        }),
+                    };
+                    return Some((event, result));
+                } else if let Some(event) = AppCommandEvent::decode(msg, wparam, lparam) {
+                    return Some((Event::AppCommand(event), APPCOMMAND_HANDLED));
+                } else if let Some(event) = PointerEvent::decode(msg, wparam, lparam) {
+                    Event::Pointer(event)
+                } else {
+                    return None;
+                }
+            }
+        };
+        Some((event, 0))
+    }
+}
+
+/// Whether the window is visible: it and its ancestors have the `WS_VISIBLE` style.
+///
+/// A visible window may still be covered, minimized, or [cloaked]; a render loop can skip
+/// frames for a window that is hidden or [minimized](fn.is_minimized.html). See
+/// [`IsWindowVisible`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [cloaked]: fn.cloaked_state.html
+/// [`IsWindowVisible`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-iswindowvisible
+pub unsafe fn is_visible(hwnd: HWND) -> bool {
+    IsWindowVisible(hwnd) != 0
+}
+
+/// Whether the window is minimized. See [`IsIconic`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`IsIconic`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-isiconic
+pub unsafe fn is_minimized(hwnd: HWND) -> bool {
+    IsIconic(hwnd) != 0
+}
+
+/// The path of the color (ICC) profile for the monitor the window is on.
+///
+/// See [`Monitor::icc_profile_path`](struct.Monitor.html#method.icc_profile_path).
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn window_icc_profile_path(hwnd: HWND) -> Option<PathBuf> {
+    Monitor::from_window(hwnd).icc_profile_path()
+}
+
+/// Whether a `WM_SETTINGCHANGE` is for the "ImmersiveColorSet", sent on light/dark changes.
+fn is_color_set_change(lparam: LPARAM) -> bool {
+    if lparam == 0 {
+        return false;
+    }
+    let expected = "ImmersiveColorSet".encode_utf16().chain(Some(0));
+    let ptr = lparam as *const u16;
+    expected
+        .enumerate()
+        .all(|(i, c)| unsafe { *ptr.add(i) } == c)
+}
+
+unsafe fn apply_suggested_rect(hwnd: HWND, rect: Rect) {
+    SetWindowPos(
+        hwnd,
+        null_mut(),
+        rect.left,
+        rect.top,
+        rect.width(),
+        rect.height(),
+        SWP_NOZORDER | SWP_NOACTIVATE,
+    );
+}
+
+impl<H: EventHandler> WindowProc for EventProc<H> {
+    // The hwnd is that of the window receiving the message, so it is valid.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn window_proc(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<LRESULT> {
+        let mut handler = self.handler.try_borrow_mut().ok()?;
+        if let Some(result) = handler.raw(hwnd, msg, wparam, lparam) {
+            return Some(result);
+        }
+        if msg == WM_DISPLAYCHANGE && self.refit_fullscreen {
+            let _ = unsafe { refit_fullscreen(hwnd) };
+        }
+        if let WM_CREATE | WM_MOVE | WM_DPICHANGED | WM_DISPLAYCHANGE = msg {
+            // On creation, this just records the initial monitor.
+            let monitor_change = unsafe { self.monitor_change(hwnd) };
+            if let Some((old, new)) = monitor_change {
+                handler.event(hwnd, Event::MonitorChanged { old, new });
+            }
+            if monitor_change.is_some() || msg == WM_CREATE || msg == WM_DISPLAYCHANGE {
+                if let Some(profile) = unsafe { self.color_profile_change(hwnd, msg) } {
+                    handler.event(hwnd, Event::ColorProfileChanged(profile));
+                }
+                if let Some(color) = unsafe { self.advanced_color_change(hwnd, msg) } {
+                    handler.event(hwnd, Event::AdvancedColorChanged(color));
+                }
+                if let Some(rate) = unsafe { self.refresh_rate_change(hwnd, msg) } {
+                    handler.event(hwnd, Event::RefreshRateChanged(rate));
+                }
+            }
+        }
+        match msg {
+            WM_CLOSE => {
+                if self.close_state.get() == CloseState::Pending {
+                    return Some(0);
+                }
+                let request = CloseRequest::new(hwnd, self.close_state.clone());
+                return handler
+                    .event(hwnd, Event::CloseRequested(request))
+                    .then_some(0);
+            }
+            WM_DESTROY => self.close_state.set(CloseState::Destroyed),
+            _ => (),
+        }
+        let (event, result) = self.decode(msg, wparam, lparam)?;
+        let suggested = match event {
+            Event::DpiChanged { suggested, .. } => Some(suggested),
+            _ => None,
+        };
+        if handler.event(hwnd, event) {
+            Some(result)
+        } else if let Some(rect) = suggested {
+            unsafe { apply_suggested_rect(hwnd, rect) };
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
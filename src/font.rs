@@ -0,0 +1,83 @@
+//! The UI font, for standard controls.
+//!
+//! Controls created with `CreateWindowEx` draw their text in the System font, a legacy bitmap
+//! font, until they are sent [`WM_SETFONT`]. Dialogs do this automatically; a window that
+//! hosts controls itself should create the message font with [`system_message_font`] and
+//! give it to its controls with [`set_font_for_children`].
+//!
+//! [`WM_SETFONT`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-setfont
+//! [`system_message_font`]: fn.system_message_font.html
+//! [`set_font_for_children`]: fn.set_font_for_children.html
+
+use winapi::shared::minwindef::{BOOL, LPARAM, TRUE, WPARAM};
+use winapi::shared::windef::{HFONT, HWND};
+use winapi::um::wingdi::{CreateFontIndirectW, DeleteObject, LOGFONTW};
+use winapi::um::winuser::{EnumChildWindows, SendMessageW, WM_SETFONT};
+
+use crate::dpi::Dpi;
+use crate::error::Error;
+use crate::metrics::message_font;
+
+/// A GDI font, deleted when dropped.
+///
+/// A font given to a window with `WM_SETFONT` is not copied, so it must outlive the window;
+/// usually it is stored in the window's state, or with
+/// [`set_window_data`](fn.set_window_data.html).
+#[derive(Debug)]
+pub struct Font {
+    hfont: HFONT,
+}
+
+impl Font {
+    /// Create a font from a description.
+    pub fn from_logfont(logfont: &LOGFONTW) -> Result<Font, Error> {
+        let hfont = unsafe { CreateFontIndirectW(logfont) };
+        if hfont.is_null() {
+            return Err(Error::last_error());
+        }
+        Ok(Font { hfont })
+    }
+
+    /// The font handle.
+    pub fn handle(&self) -> HFONT {
+        self.hfont
+    }
+}
+
+impl Drop for Font {
+    fn drop(&mut self) {
+        unsafe {
+            DeleteObject(self.hfont as _);
+        }
+    }
+}
+
+/// The font used in message boxes, which is the standard font for controls, at the given DPI.
+///
+/// See [`metrics::message_font`](metrics/fn.message_font.html) for the description. On
+/// `WM_DPICHANGED`, create the font again for the new DPI.
+pub fn system_message_font(dpi: Dpi) -> Result<Font, Error> {
+    Font::from_logfont(&message_font(dpi)?)
+}
+
+/// Send `WM_SETFONT` with the font to all the descendants of a window.
+///
+/// If `redraw` is true, the controls repaint themselves with the new font. The font must
+/// outlive the controls, or be replaced before it is dropped.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn set_font_for_children(hwnd: HWND, font: &Font, redraw: bool) {
+    unsafe extern "system" fn enum_proc(child: HWND, lparam: LPARAM) -> BOOL {
+        let (hfont, redraw) = *(lparam as *const (HFONT, bool));
+        SendMessageW(child, WM_SETFONT, hfont as WPARAM, redraw as LPARAM);
+        TRUE
+    }
+    let data = (font.hfont, redraw);
+    EnumChildWindows(
+        hwnd,
+        Some(enum_proc),
+        &data as *const (HFONT, bool) as LPARAM,
+    );
+}
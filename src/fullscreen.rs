@@ -0,0 +1,230 @@
+//! Borderless fullscreen, on one or several monitors.
+//!
+//! Fullscreen windows on Windows are ordinary windows without a frame, sized to cover the
+//! monitor; the shell notices and hides the taskbar while such a window is active.
+//! [`set_fullscreen`] does this, saving the window's style and placement so that leaving
+//! fullscreen restores them exactly, and can also span several monitors, as for video walls
+//! and presentation tools. [`toggle_fullscreen`] suits the usual F11 binding.
+//!
+//! [`set_fullscreen`]: fn.set_fullscreen.html
+//! [`toggle_fullscreen`]: fn.toggle_fullscreen.html
+
+use std::cell::RefCell;
+use std::mem;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    GetSystemMetrics, GetWindowLongW, GetWindowPlacement, SetWindowLongW, SetWindowPlacement,
+    SetWindowPos, GWL_EXSTYLE, GWL_STYLE, HWND_TOP, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE,
+    SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER, WINDOWPLACEMENT, WS_EX_CLIENTEDGE,
+    WS_EX_DLGMODALFRAME, WS_EX_STATICEDGE, WS_EX_WINDOWEDGE, WS_OVERLAPPEDWINDOW,
+};
+
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::monitor::Monitor;
+use crate::window_data::{get_window_data, remove_window_data, set_window_data};
+
+/// The window data key for the saved state.
+const DATA_KEY: &str = "fullscreen";
+
+/// Which area a fullscreen window covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fullscreen {
+    /// The monitor the window is on.
+    Current,
+    /// A particular monitor.
+    Monitor(Monitor),
+    /// The bounding rectangle of a set of monitors.
+    ///
+    /// For monitors of different sizes, or not arranged in a rectangle, parts of the window
+    /// are off screen.
+    Monitors(Vec<Monitor>),
+    /// The whole virtual screen, spanning all monitors.
+    VirtualScreen,
+}
+
+impl Fullscreen {
+    /// The area covered, in virtual screen coordinates.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn rect(&self, hwnd: HWND) -> Rect {
+        match self {
+            Fullscreen::Current => Monitor::from_window(hwnd).rect(),
+            Fullscreen::Monitor(monitor) => monitor.rect(),
+            Fullscreen::Monitors(monitors) => monitors
+                .iter()
+                .map(Monitor::rect)
+                .reduce(|a, b| {
+                    Rect::new(
+                        a.left.min(b.left),
+                        a.top.min(b.top),
+                        a.right.max(b.right),
+                        a.bottom.max(b.bottom),
+                    )
+                })
+                .unwrap_or_else(|| Monitor::from_window(hwnd).rect()),
+            Fullscreen::VirtualScreen => {
+                let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+                let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+                Rect::new(
+                    left,
+                    top,
+                    left + GetSystemMetrics(SM_CXVIRTUALSCREEN),
+                    top + GetSystemMetrics(SM_CYVIRTUALSCREEN),
+                )
+            }
+        }
+    }
+}
+
+/// The state restored on leaving fullscreen.
+struct Saved {
+    style: i32,
+    ex_style: i32,
+    placement: WINDOWPLACEMENT,
+    /// The area covered, to refit after display changes.
+    target: RefCell<Fullscreen>,
+}
+
+/// The styles removed while fullscreen.
+///
+/// This includes the system menu and the minimize and maximize boxes, as well as the frame,
+/// so that snapping and maximizing can't resize the window while it is fullscreen.
+const FRAME_STYLE: u32 = WS_OVERLAPPEDWINDOW;
+const FRAME_EX_STYLE: u32 =
+    WS_EX_DLGMODALFRAME | WS_EX_WINDOWEDGE | WS_EX_CLIENTEDGE | WS_EX_STATICEDGE;
+
+/// Enter or leave fullscreen.
+///
+/// With `Some`, the window's frame is removed and it is moved to cover the given area; if it
+/// is already fullscreen, it is just moved. With `None`, the frame, position, size, and
+/// maximized state from before entering fullscreen are restored.
+///
+/// The area is computed once; if the display configuration changes (see `WM_DISPLAYCHANGE`),
+/// call this again, or [`refit_fullscreen`](fn.refit_fullscreen.html).
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference, to a top-level window on the calling thread.
+pub unsafe fn set_fullscreen(hwnd: HWND, fullscreen: Option<Fullscreen>) -> Result<(), Error> {
+    match fullscreen {
+        Some(target) => {
+            let rect = target.rect(hwnd);
+            if let Some(saved) = get_window_data::<Saved>(hwnd, DATA_KEY) {
+                *saved.target.borrow_mut() = target;
+            } else {
+                let mut placement: WINDOWPLACEMENT = mem::zeroed();
+                placement.length = mem::size_of::<WINDOWPLACEMENT>() as u32;
+                if GetWindowPlacement(hwnd, &mut placement) == 0 {
+                    return Err(Error::last_error());
+                }
+                let saved = Saved {
+                    style: GetWindowLongW(hwnd, GWL_STYLE),
+                    ex_style: GetWindowLongW(hwnd, GWL_EXSTYLE),
+                    placement,
+                    target: RefCell::new(target),
+                };
+                let (style, ex_style) = (saved.style, saved.ex_style);
+                set_window_data(hwnd, DATA_KEY, Rc::new(saved))?;
+                SetWindowLongW(hwnd, GWL_STYLE, style & !(FRAME_STYLE as i32));
+                SetWindowLongW(hwnd, GWL_EXSTYLE, ex_style & !(FRAME_EX_STYLE as i32));
+            }
+            if SetWindowPos(
+                hwnd,
+                HWND_TOP,
+                rect.left,
+                rect.top,
+                rect.width(),
+                rect.height(),
+                SWP_FRAMECHANGED | SWP_NOOWNERZORDER,
+            ) == 0
+            {
+                return Err(Error::last_error());
+            }
+        }
+        None => {
+            let saved = remove_window_data(hwnd, DATA_KEY).and_then(|data| data.downcast().ok());
+            let saved: Rc<Saved> = match saved {
+                Some(saved) => saved,
+                None => return Ok(()),
+            };
+            SetWindowLongW(hwnd, GWL_STYLE, saved.style);
+            SetWindowLongW(hwnd, GWL_EXSTYLE, saved.ex_style);
+            SetWindowPos(
+                hwnd,
+                null_mut(),
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE
+                    | SWP_NOSIZE
+                    | SWP_NOZORDER
+                    | SWP_NOOWNERZORDER
+                    | SWP_NOACTIVATE
+                    | SWP_FRAMECHANGED,
+            );
+            if SetWindowPlacement(hwnd, &saved.placement) == 0 {
+                return Err(Error::last_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the window is fullscreen, by way of [`set_fullscreen`](fn.set_fullscreen.html).
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn is_fullscreen(hwnd: HWND) -> bool {
+    get_window_data::<Saved>(hwnd, DATA_KEY).is_some()
+}
+
+/// Enter fullscreen on the window's current monitor, or leave it if already fullscreen.
+///
+/// Returns whether the window is now fullscreen. See [`set_fullscreen`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference, to a top-level window on the calling thread.
+///
+/// [`set_fullscreen`]: fn.set_fullscreen.html
+pub unsafe fn toggle_fullscreen(hwnd: HWND) -> Result<bool, Error> {
+    let enter = !is_fullscreen(hwnd);
+    set_fullscreen(
+        hwnd,
+        if enter {
+            Some(Fullscreen::Current)
+        } else {
+            None
+        },
+    )?;
+    Ok(enter)
+}
+
+/// Move a fullscreen window to cover its area again, after a change in the display
+/// configuration.
+///
+/// Does nothing if the window isn't fullscreen. [`EventProc`] can do this on
+/// `WM_DISPLAYCHANGE`; see [`EventProc::refit_fullscreen`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference, to a top-level window on the calling thread.
+///
+/// [`EventProc`]: struct.EventProc.html
+/// [`EventProc::refit_fullscreen`]: struct.EventProc.html#method.refit_fullscreen
+pub unsafe fn refit_fullscreen(hwnd: HWND) -> Result<(), Error> {
+    let target = match get_window_data::<Saved>(hwnd, DATA_KEY) {
+        Some(saved) => saved.target.borrow().clone(),
+        None => return Ok(()),
+    };
+    set_fullscreen(hwnd, Some(target))
+}
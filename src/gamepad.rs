@@ -0,0 +1,328 @@
+//! Game controller polling with XInput.
+//!
+//! [XInput] reports the state of up to four Xbox-compatible controllers, but only when asked:
+//! there are no messages. [`Gamepads`] polls the controllers and turns changes of state into
+//! [`GamepadEvent`]s, including connection and disconnection. With [`run`] and
+//! `ControlFlow::Poll`, this is done before each `Event::Idle`, and the events are delivered
+//! as `Event::Gamepad`.
+//!
+//! [XInput]: https://docs.microsoft.com/en-us/windows/win32/xinput/getting-started-with-xinput
+//! [`Gamepads`]: struct.Gamepads.html
+//! [`GamepadEvent`]: enum.GamepadEvent.html
+//! [`run`]: fn.run.html
+
+use std::time::{Duration, Instant};
+
+use winapi::shared::minwindef::{DWORD, WORD};
+use winapi::shared::winerror::ERROR_SUCCESS;
+use winapi::um::xinput::{
+    XINPUT_GAMEPAD, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_BACK,
+    XINPUT_GAMEPAD_DPAD_DOWN, XINPUT_GAMEPAD_DPAD_LEFT, XINPUT_GAMEPAD_DPAD_RIGHT,
+    XINPUT_GAMEPAD_DPAD_UP, XINPUT_GAMEPAD_LEFT_SHOULDER, XINPUT_GAMEPAD_LEFT_THUMB,
+    XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE, XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_GAMEPAD_RIGHT_THUMB,
+    XINPUT_GAMEPAD_RIGHT_THUMB_DEADZONE, XINPUT_GAMEPAD_START, XINPUT_GAMEPAD_TRIGGER_THRESHOLD,
+    XINPUT_GAMEPAD_X, XINPUT_GAMEPAD_Y, XINPUT_STATE, XUSER_MAX_COUNT,
+};
+
+use crate::mouse::ButtonState;
+
+/// How often to look for newly connected controllers.
+///
+/// Querying an empty slot is slow (it enumerates devices), so it isn't done on every poll.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A game controller button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftShoulder,
+    RightShoulder,
+    Back,
+    Start,
+    /// Pressing the left stick.
+    LeftThumb,
+    /// Pressing the right stick.
+    RightThumb,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+const BUTTONS: [(GamepadButton, WORD); 14] = [
+    (GamepadButton::A, XINPUT_GAMEPAD_A),
+    (GamepadButton::B, XINPUT_GAMEPAD_B),
+    (GamepadButton::X, XINPUT_GAMEPAD_X),
+    (GamepadButton::Y, XINPUT_GAMEPAD_Y),
+    (GamepadButton::LeftShoulder, XINPUT_GAMEPAD_LEFT_SHOULDER),
+    (GamepadButton::RightShoulder, XINPUT_GAMEPAD_RIGHT_SHOULDER),
+    (GamepadButton::Back, XINPUT_GAMEPAD_BACK),
+    (GamepadButton::Start, XINPUT_GAMEPAD_START),
+    (GamepadButton::LeftThumb, XINPUT_GAMEPAD_LEFT_THUMB),
+    (GamepadButton::RightThumb, XINPUT_GAMEPAD_RIGHT_THUMB),
+    (GamepadButton::DpadUp, XINPUT_GAMEPAD_DPAD_UP),
+    (GamepadButton::DpadDown, XINPUT_GAMEPAD_DPAD_DOWN),
+    (GamepadButton::DpadLeft, XINPUT_GAMEPAD_DPAD_LEFT),
+    (GamepadButton::DpadRight, XINPUT_GAMEPAD_DPAD_RIGHT),
+];
+
+impl GamepadButton {
+    fn mask(self) -> WORD {
+        BUTTONS.iter().find(|(b, _)| *b == self).unwrap().1
+    }
+}
+
+/// A game controller axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// The state of a game controller.
+///
+/// Stick positions range from -1.0 to 1.0, with positive y up; triggers range from 0.0 to
+/// 1.0. The recommended dead zones are applied, so a controller at rest reads as zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GamepadState {
+    buttons: WORD,
+    pub left_stick: (f32, f32),
+    pub right_stick: (f32, f32),
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+}
+
+impl GamepadState {
+    fn from_xinput(gamepad: &XINPUT_GAMEPAD) -> GamepadState {
+        GamepadState {
+            buttons: gamepad.wButtons,
+            left_stick: stick(
+                gamepad.sThumbLX,
+                gamepad.sThumbLY,
+                XINPUT_GAMEPAD_LEFT_THUMB_DEADZONE,
+            ),
+            right_stick: stick(
+                gamepad.sThumbRX,
+                gamepad.sThumbRY,
+                XINPUT_GAMEPAD_RIGHT_THUMB_DEADZONE,
+            ),
+            left_trigger: trigger(gamepad.bLeftTrigger),
+            right_trigger: trigger(gamepad.bRightTrigger),
+        }
+    }
+
+    /// Whether a button is pressed.
+    pub fn is_pressed(&self, button: GamepadButton) -> bool {
+        self.buttons & button.mask() != 0
+    }
+
+    /// The value of an axis.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        match axis {
+            GamepadAxis::LeftX => self.left_stick.0,
+            GamepadAxis::LeftY => self.left_stick.1,
+            GamepadAxis::RightX => self.right_stick.0,
+            GamepadAxis::RightY => self.right_stick.1,
+            GamepadAxis::LeftTrigger => self.left_trigger,
+            GamepadAxis::RightTrigger => self.right_trigger,
+        }
+    }
+}
+
+/// Normalize a stick position, with a radial dead zone.
+fn stick(x: i16, y: i16, dead_zone: i16) -> (f32, f32) {
+    let (x, y) = (x as f32, y as f32);
+    let magnitude = (x * x + y * y).sqrt();
+    let dead_zone = dead_zone as f32;
+    if magnitude <= dead_zone {
+        return (0.0, 0.0);
+    }
+    let scaled = ((magnitude - dead_zone) / (32767.0 - dead_zone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+/// Normalize a trigger position, with a dead zone.
+fn trigger(value: u8) -> f32 {
+    let threshold = XINPUT_GAMEPAD_TRIGGER_THRESHOLD;
+    if value <= threshold {
+        0.0
+    } else {
+        (value - threshold) as f32 / (255 - threshold) as f32
+    }
+}
+
+const AXES: [GamepadAxis; 6] = [
+    GamepadAxis::LeftX,
+    GamepadAxis::LeftY,
+    GamepadAxis::RightX,
+    GamepadAxis::RightY,
+    GamepadAxis::LeftTrigger,
+    GamepadAxis::RightTrigger,
+];
+
+/// A change in the state of a game controller.
+///
+/// The `index` is the XInput user index, from 0 to 3.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GamepadEvent {
+    Connected {
+        index: u32,
+    },
+    /// The controller was disconnected. Pressed buttons are not released first.
+    Disconnected {
+        index: u32,
+    },
+    Button {
+        index: u32,
+        button: GamepadButton,
+        state: ButtonState,
+    },
+    Axis {
+        index: u32,
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+#[derive(Clone, Copy)]
+struct Pad {
+    packet: DWORD,
+    state: GamepadState,
+}
+
+/// Polls XInput game controllers.
+///
+/// XInput is loaded at runtime, so on a system without it, no controllers are reported.
+pub struct Gamepads {
+    pads: [Option<Pad>; XUSER_MAX_COUNT as usize],
+    last_scan: Option<Instant>,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Gamepads::new()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        Gamepads {
+            pads: [None; XUSER_MAX_COUNT as usize],
+            last_scan: None,
+        }
+    }
+
+    /// Query the controllers, returning the changes since the last poll.
+    ///
+    /// Connected controllers are queried on each call; empty slots are checked for new
+    /// controllers at most once a second, or on the next call after [`rescan`].
+    ///
+    /// [`rescan`]: #method.rescan
+    pub fn poll(&mut self) -> Vec<GamepadEvent> {
+        let now = Instant::now();
+        let scan = self
+            .last_scan
+            .is_none_or(|last| now.duration_since(last) >= RESCAN_INTERVAL);
+        if scan {
+            self.last_scan = Some(now);
+        }
+        let mut events = Vec::new();
+        for index in 0..XUSER_MAX_COUNT {
+            let slot = &mut self.pads[index as usize];
+            if slot.is_none() && !scan {
+                continue;
+            }
+            match get_state(index) {
+                Some(xstate) => {
+                    let state = GamepadState::from_xinput(&xstate.Gamepad);
+                    let old = match slot {
+                        Some(pad) if pad.packet == xstate.dwPacketNumber => continue,
+                        Some(pad) => pad.state,
+                        None => {
+                            events.push(GamepadEvent::Connected { index });
+                            GamepadState::default()
+                        }
+                    };
+                    *slot = Some(Pad {
+                        packet: xstate.dwPacketNumber,
+                        state,
+                    });
+                    diff(index, &old, &state, &mut events);
+                }
+                None => {
+                    if slot.take().is_some() {
+                        events.push(GamepadEvent::Disconnected { index });
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Check empty slots for new controllers on the next poll.
+    ///
+    /// Call this on a device arrival, such as `DeviceEvent::Added` with `DeviceKind::Gamepad`,
+    /// to report a new controller without waiting.
+    pub fn rescan(&mut self) {
+        self.last_scan = None;
+    }
+
+    /// The state of a controller as of the last poll, or `None` if not connected.
+    pub fn state(&self, index: u32) -> Option<GamepadState> {
+        self.pads.get(index as usize)?.map(|pad| pad.state)
+    }
+}
+
+fn diff(index: u32, old: &GamepadState, new: &GamepadState, events: &mut Vec<GamepadEvent>) {
+    for &(button, _) in &BUTTONS {
+        let pressed = new.is_pressed(button);
+        if old.is_pressed(button) != pressed {
+            let state = if pressed {
+                ButtonState::Down
+            } else {
+                ButtonState::Up
+            };
+            events.push(GamepadEvent::Button {
+                index,
+                button,
+                state,
+            });
+        }
+    }
+    for &axis in &AXES {
+        let value = new.axis(axis);
+        if old.axis(axis) != value {
+            events.push(GamepadEvent::Axis { index, axis, value });
+        }
+    }
+}
+
+fn get_state(index: DWORD) -> Option<XINPUT_STATE> {
+    unsafe {
+        let get = match dynamic_fn!(
+            "xinput1_4.dll",
+            XInputGetState: fn(DWORD, *mut XINPUT_STATE) -> DWORD
+        ) {
+            Some(f) => f,
+            // Windows 7 has only this older version.
+            None => dynamic_fn!(
+                "xinput9_1_0.dll",
+                XInputGetState: fn(DWORD, *mut XINPUT_STATE) -> DWORD
+            )?,
+        };
+        let mut state = std::mem::zeroed();
+        // Any error, usually ERROR_DEVICE_NOT_CONNECTED, means there's no usable controller.
+        if get(index, &mut state) == ERROR_SUCCESS {
+            Some(state)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Basic geometry types.
+//!
+//! These are thin, `Copy` counterparts of the winapi [`POINT`] and [`RECT`] structs, with
+//! conversions in both directions. All values are in raw pixels.
+//!
+//! [`POINT`]: https://docs.microsoft.com/en-us/windows/win32/api/windef/ns-windef-point
+//! [`RECT`]: https://docs.microsoft.com/en-us/windows/win32/api/windef/ns-windef-rect
+
+use winapi::shared::windef::{POINT, RECT};
+
+/// A point, in raw pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A rectangle, in raw pixels.
+///
+/// As with the winapi `RECT`, the `right` and `bottom` edges are exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Point {
+    /// Create a new point.
+    pub const fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+}
+
+impl Rect {
+    /// Create a new rectangle from its edges.
+    pub const fn new(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// The width of the rectangle.
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    /// The height of the rectangle.
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    /// Whether the rectangle contains the point.
+    pub fn contains(&self, pt: Point) -> bool {
+        pt.x >= self.left && pt.x < self.right && pt.y >= self.top && pt.y < self.bottom
+    }
+}
+
+impl From<POINT> for Point {
+    fn from(pt: POINT) -> Point {
+        Point::new(pt.x, pt.y)
+    }
+}
+
+impl From<Point> for POINT {
+    fn from(pt: Point) -> POINT {
+        POINT { x: pt.x, y: pt.y }
+    }
+}
+
+impl From<RECT> for Rect {
+    fn from(r: RECT) -> Rect {
+        Rect::new(r.left, r.top, r.right, r.bottom)
+    }
+}
+
+impl From<Rect> for RECT {
+    fn from(r: Rect) -> RECT {
+        RECT {
+            left: r.left,
+            top: r.top,
+            right: r.right,
+            bottom: r.bottom,
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Hit testing for custom-drawn title bars.
+//!
+//! An application drawing its own title bar in the client area needs the system to treat that
+//! area as a caption, so that the window can be dragged (and double-clicked to maximize, and
+//! snapped) as usual. This is done by answering [`WM_NCHITTEST`] with `HTCAPTION`. A
+//! [`DragRegions`] describes which parts of the client area are caption areas, and which
+//! parts within them (buttons, tabs, search boxes) are interactive and should stay client
+//! area.
+//!
+//! A borderless window drawing its whole frame can describe it with a [`TitleBar`]: caption
+//! areas, caption buttons, and a resize border. Reporting the maximize button as such is what
+//! brings up the snap layouts flyout of Windows 11 when it is hovered.
+//!
+//! For anything beyond these, [`set_hit_test`] installs a function that can answer with any
+//! [`HitTest`] result.
+//!
+//! [`WM_NCHITTEST`]: https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-nchittest
+//! [`DragRegions`]: struct.DragRegions.html
+//! [`TitleBar`]: struct.TitleBar.html
+//! [`set_hit_test`]: fn.set_hit_test.html
+//! [`HitTest`]: enum.HitTest.html
+
+use std::cell::Cell;
+use std::mem;
+use std::rc::Rc;
+
+use winapi::shared::minwindef::{DWORD, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    IsZoomed, SendMessageW, TrackMouseEvent, HTBOTTOM, HTBOTTOMLEFT, HTBOTTOMRIGHT, HTCAPTION,
+    HTCLIENT, HTCLOSE, HTHELP, HTLEFT, HTMAXBUTTON, HTMINBUTTON, HTNOWHERE, HTRIGHT, HTSYSMENU,
+    HTTOP, HTTOPLEFT, HTTOPRIGHT, HTTRANSPARENT, SC_CLOSE, SC_MAXIMIZE, SC_MINIMIZE, SC_RESTORE,
+    TME_LEAVE, TME_NONCLIENT, TRACKMOUSEEVENT, WM_NCLBUTTONDBLCLK, WM_NCLBUTTONDOWN,
+    WM_NCLBUTTONUP, WM_NCMOUSELEAVE, WM_NCMOUSEMOVE, WM_SYSCOMMAND,
+};
+
+use crate::error::Error;
+use crate::geometry::{Point, Rect};
+use crate::window::window_state;
+
+/// A result of hit testing: the part of the window at a point.
+///
+/// These are the `HT` values returned from [`WM_NCHITTEST`].
+///
+/// [`WM_NCHITTEST`]: https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-nchittest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HitTest {
+    /// Not part of the window, such as the border between windows.
+    Nowhere,
+    Client,
+    /// The title bar, which moves the window when dragged.
+    Caption,
+    /// The window menu (system menu) button.
+    SysMenu,
+    MinButton,
+    MaxButton,
+    Close,
+    Help,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    /// Covered by another window in the same thread, which receives the mouse input instead.
+    Transparent,
+}
+
+impl HitTest {
+    /// The value to return from `WM_NCHITTEST`.
+    pub fn to_lresult(self) -> LRESULT {
+        let code = match self {
+            HitTest::Nowhere => HTNOWHERE,
+            HitTest::Client => HTCLIENT,
+            HitTest::Caption => HTCAPTION,
+            HitTest::SysMenu => HTSYSMENU,
+            HitTest::MinButton => HTMINBUTTON,
+            HitTest::MaxButton => HTMAXBUTTON,
+            HitTest::Close => HTCLOSE,
+            HitTest::Help => HTHELP,
+            HitTest::Left => HTLEFT,
+            HitTest::Right => HTRIGHT,
+            HitTest::Top => HTTOP,
+            HitTest::Bottom => HTBOTTOM,
+            HitTest::TopLeft => HTTOPLEFT,
+            HitTest::TopRight => HTTOPRIGHT,
+            HitTest::BottomLeft => HTBOTTOMLEFT,
+            HitTest::BottomRight => HTBOTTOMRIGHT,
+            HitTest::Transparent => HTTRANSPARENT,
+        };
+        code as LRESULT
+    }
+
+    /// The hit test result for a standard resize border.
+    ///
+    /// Given the client size and the border thickness, this returns the edge or corner a point
+    /// (in client coordinates) is on, if any. This is for windows without a frame that should
+    /// still be resizable from the edges of the client area.
+    pub fn resize_border(pt: Point, width: i32, height: i32, border: i32) -> Option<HitTest> {
+        let left = pt.x < border;
+        let right = pt.x >= width - border;
+        let top = pt.y < border;
+        let bottom = pt.y >= height - border;
+        match (left, right, top, bottom) {
+            (true, _, true, _) => Some(HitTest::TopLeft),
+            (_, true, true, _) => Some(HitTest::TopRight),
+            (true, _, _, true) => Some(HitTest::BottomLeft),
+            (_, true, _, true) => Some(HitTest::BottomRight),
+            (true, ..) => Some(HitTest::Left),
+            (_, true, ..) => Some(HitTest::Right),
+            (_, _, true, _) => Some(HitTest::Top),
+            (_, _, _, true) => Some(HitTest::Bottom),
+            _ => None,
+        }
+    }
+}
+
+/// A hit test function; see [`set_hit_test`](fn.set_hit_test.html).
+pub(crate) type HitTestFn = Box<dyn Fn(Point) -> Option<HitTest>>;
+
+/// The caption areas of a window's client area.
+///
+/// When installed on a window, `WM_NCHITTEST` messages not handled by the window procedure
+/// are answered with `HTCAPTION` for points in a caption area (and not in an excluded area).
+/// Points near the window edges still resize the window, as they are not in the client area.
+#[derive(Default)]
+pub struct DragRegions {
+    caption: Vec<Rect>,
+    exclude: Vec<Rect>,
+    callback: Option<Box<dyn Fn(Point) -> bool>>,
+}
+
+impl DragRegions {
+    /// An empty set of regions.
+    pub fn new() -> DragRegions {
+        Default::default()
+    }
+
+    /// Regions determined by a callback.
+    ///
+    /// The callback receives a point in client coordinates, and returns whether it is in a
+    /// caption area. It is called on every hit test, so it should be fast.
+    pub fn from_fn(f: impl Fn(Point) -> bool + 'static) -> DragRegions {
+        DragRegions {
+            callback: Some(Box::new(f)),
+            ..Default::default()
+        }
+    }
+
+    /// Add a caption area, in client coordinates.
+    pub fn caption(mut self, rect: Rect) -> Self {
+        self.caption.push(rect);
+        self
+    }
+
+    /// Exclude an area, in client coordinates, from the caption areas.
+    ///
+    /// This is for interactive controls within the title bar.
+    pub fn exclude(mut self, rect: Rect) -> Self {
+        self.exclude.push(rect);
+        self
+    }
+
+    /// Whether a point, in client coordinates, is in a caption area.
+    pub fn is_caption(&self, pt: Point) -> bool {
+        if self.exclude.iter().any(|rect| rect.contains(pt)) {
+            return false;
+        }
+        self.caption.iter().any(|rect| rect.contains(pt))
+            || self.callback.as_ref().map(|f| f(pt)).unwrap_or(false)
+    }
+}
+
+/// Set or clear the drag regions of a window.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_drag_regions(hwnd: HWND, regions: Option<DragRegions>) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    *state.drag_regions.borrow_mut() = regions;
+    Ok(())
+}
+
+/// Set the hit test function of a window.
+///
+/// The function receives a point in client coordinates, and is called for `WM_NCHITTEST`
+/// messages not handled by the window procedure, before `DefWindowProc` and any drag
+/// regions. A `Some` result is returned from the message; with `None`, hit testing continues
+/// as usual. It is called on every mouse move, so it should be fast.
+///
+/// This is a lighter alternative to a custom frame for a borderless window that needs a
+/// draggable area or resizing from its edges (see [`HitTest::resize_border`]).
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`HitTest::resize_border`]: enum.HitTest.html#method.resize_border
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_hit_test(
+    hwnd: HWND,
+    f: impl Fn(Point) -> Option<HitTest> + 'static,
+) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    *state.hit_test.borrow_mut() = Some(Box::new(f));
+    Ok(())
+}
+
+/// Remove the hit test function of a window.
+///
+/// See [`set_hit_test`](fn.set_hit_test.html).
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn clear_hit_test(hwnd: HWND) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    *state.hit_test.borrow_mut() = None;
+    Ok(())
+}
+
+/// A callback for the state of the caption buttons; see
+/// [`TitleBar::on_button_state`](struct.TitleBar.html#method.on_button_state).
+type ButtonStateFn = Rc<dyn Fn(Option<HitTest>, bool)>;
+
+/// A change of the caption button state, with the callback to report it to.
+pub(crate) type ButtonStateChange = (ButtonStateFn, Option<HitTest>, bool);
+
+/// The layout of a custom-drawn window frame: caption areas, caption buttons, and the resize
+/// border.
+///
+/// When installed on a window (with [`set_title_bar`] or [`WindowBuilder::title_bar`]),
+/// `WM_NCHITTEST` messages not handled by the window procedure are answered from the layout,
+/// with points outside it being client area. This is for windows without a frame, whose
+/// client area is the whole window.
+///
+/// Caption buttons are reported to the system as such, so the maximize button shows the snap
+/// layouts flyout on Windows 11, and the system doesn't send client mouse messages for them.
+/// Instead, clicks on the buttons minimize, maximize or restore, and close the window (with
+/// `WM_SYSCOMMAND`), and the window learns which button to draw hovered or pressed through
+/// [`on_button_state`]. For this, the window procedure must leave the `WM_NCMOUSEMOVE`,
+/// `WM_NCMOUSELEAVE`, and `WM_NCLBUTTON*` messages unhandled.
+///
+/// [`set_title_bar`]: fn.set_title_bar.html
+/// [`WindowBuilder::title_bar`]: struct.WindowBuilder.html#method.title_bar
+/// [`on_button_state`]: #method.on_button_state
+#[derive(Default)]
+pub struct TitleBar {
+    caption: Vec<Rect>,
+    exclude: Vec<Rect>,
+    buttons: Vec<(Rect, HitTest)>,
+    border: i32,
+    on_button_state: Option<ButtonStateFn>,
+    hovered: Cell<Option<HitTest>>,
+    pressed: Cell<Option<HitTest>>,
+}
+
+impl TitleBar {
+    /// An empty layout, in which everything is client area.
+    pub fn new() -> TitleBar {
+        Default::default()
+    }
+
+    /// Add a caption area, in client coordinates.
+    pub fn caption(mut self, rect: Rect) -> Self {
+        self.caption.push(rect);
+        self
+    }
+
+    /// Exclude an area, in client coordinates, from the caption areas.
+    ///
+    /// This is for interactive controls within the title bar, such as tabs.
+    pub fn exclude(mut self, rect: Rect) -> Self {
+        self.exclude.push(rect);
+        self
+    }
+
+    /// Set the area of the minimize button, in client coordinates.
+    pub fn min_button(self, rect: Rect) -> Self {
+        self.button(rect, HitTest::MinButton)
+    }
+
+    /// Set the area of the maximize (and restore) button, in client coordinates.
+    pub fn max_button(self, rect: Rect) -> Self {
+        self.button(rect, HitTest::MaxButton)
+    }
+
+    /// Set the area of the close button, in client coordinates.
+    pub fn close_button(self, rect: Rect) -> Self {
+        self.button(rect, HitTest::Close)
+    }
+
+    fn button(mut self, rect: Rect, hit: HitTest) -> Self {
+        self.buttons.retain(|&(_, other)| other != hit);
+        self.buttons.push((rect, hit));
+        self
+    }
+
+    /// Set the thickness of the resize border along the edges of the window, in pixels.
+    ///
+    /// The default is zero, for a window that can't be resized from its edges. The border
+    /// isn't used while the window is maximized.
+    pub fn resize_border(mut self, thickness: i32) -> Self {
+        self.border = thickness;
+        self
+    }
+
+    /// Call a function when the hovered or pressed caption button changes.
+    ///
+    /// The function receives the button under the mouse, if any (one of
+    /// `HitTest::MinButton`, `HitTest::MaxButton`, and `HitTest::Close`), and whether it is
+    /// pressed. It should redraw the buttons.
+    pub fn on_button_state(mut self, f: impl Fn(Option<HitTest>, bool) + 'static) -> Self {
+        self.on_button_state = Some(Rc::new(f));
+        self
+    }
+
+    /// The hit test result for a point in client coordinates, given the client size and
+    /// whether the window is maximized.
+    pub fn hit_test(&self, pt: Point, width: i32, height: i32, maximized: bool) -> HitTest {
+        if !maximized && self.border > 0 {
+            if let Some(hit) = HitTest::resize_border(pt, width, height, self.border) {
+                return hit;
+            }
+        }
+        if let Some(&(_, hit)) = self.buttons.iter().find(|(rect, _)| rect.contains(pt)) {
+            return hit;
+        }
+        if self.exclude.iter().any(|rect| rect.contains(pt)) {
+            return HitTest::Client;
+        }
+        if self.caption.iter().any(|rect| rect.contains(pt)) {
+            HitTest::Caption
+        } else {
+            HitTest::Client
+        }
+    }
+
+    /// Handle the non-client mouse messages of the caption buttons.
+    ///
+    /// Returns the result of the message, if handled, and the callback to call (after the
+    /// title bar is no longer borrowed) if the button state changed.
+    pub(crate) unsafe fn process_message(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+    ) -> (Option<LRESULT>, Option<ButtonStateChange>) {
+        let (hovered, pressed) = (self.hovered.get(), self.pressed.get());
+        let button = button_hit(wparam);
+        let result = match msg {
+            WM_NCMOUSEMOVE => {
+                if button.is_some() && hovered.is_none() {
+                    let mut track = TRACKMOUSEEVENT {
+                        cbSize: mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
+                        dwFlags: TME_LEAVE | TME_NONCLIENT,
+                        hwndTrack: hwnd,
+                        dwHoverTime: 0,
+                    };
+                    TrackMouseEvent(&mut track);
+                }
+                self.hovered.set(button);
+                None
+            }
+            WM_NCMOUSELEAVE => {
+                self.hovered.set(None);
+                self.pressed.set(None);
+                None
+            }
+            // Handling these keeps `DefWindowProc` from drawing its own buttons.
+            WM_NCLBUTTONDOWN | WM_NCLBUTTONDBLCLK if button.is_some() => {
+                self.pressed.set(button);
+                Some(0)
+            }
+            WM_NCLBUTTONUP => {
+                self.pressed.set(None);
+                if button.is_some() && button == pressed {
+                    let command = match button {
+                        Some(HitTest::MinButton) => SC_MINIMIZE,
+                        Some(HitTest::MaxButton) if IsZoomed(hwnd) != 0 => SC_RESTORE,
+                        Some(HitTest::MaxButton) => SC_MAXIMIZE,
+                        _ => SC_CLOSE,
+                    };
+                    SendMessageW(hwnd, WM_SYSCOMMAND, command, 0);
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let state = (self.hovered.get(), self.pressed.get().is_some());
+        let notify = match &self.on_button_state {
+            Some(f) if state != (hovered, pressed.is_some()) => Some((f.clone(), state.0, state.1)),
+            _ => None,
+        };
+        (result, notify)
+    }
+}
+
+/// The caption button of a non-client hit test code.
+fn button_hit(code: WPARAM) -> Option<HitTest> {
+    match code as isize {
+        HTMINBUTTON => Some(HitTest::MinButton),
+        HTMAXBUTTON => Some(HitTest::MaxButton),
+        HTCLOSE => Some(HitTest::Close),
+        _ => None,
+    }
+}
+
+/// Set or clear the title bar layout of a window.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`]. The layout takes precedence over drag regions, but
+/// not over a [hit test function](fn.set_hit_test.html). Call this again with a new layout
+/// when the window is resized, if the buttons move.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_title_bar(hwnd: HWND, title_bar: Option<TitleBar>) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    *state.title_bar.borrow_mut() = title_bar;
+    Ok(())
+}
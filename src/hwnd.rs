@@ -0,0 +1,236 @@
+//! A window handle that knows when the window is gone.
+
+use std::cell::Cell;
+use std::ffi::{OsStr, OsString};
+#[cfg(feature = "raw-window-handle")]
+use std::num::NonZeroIsize;
+use std::ptr::null;
+use std::rc::Rc;
+
+use winapi::ctypes::c_int;
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::windef::HWND;
+#[cfg(feature = "raw-window-handle")]
+use winapi::um::winuser::{GetWindowLongPtrW, GWLP_HINSTANCE};
+use winapi::um::winuser::{InvalidateRect, ShowWindow};
+
+#[cfg(feature = "raw-window-handle")]
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawWindowHandle,
+    Win32WindowHandle, WindowHandle,
+};
+
+use crate::cursor::{set_window_cursor, Cursor};
+use crate::error::Error;
+use crate::events::{is_minimized, is_visible};
+use crate::fullscreen::{is_fullscreen, set_fullscreen, toggle_fullscreen, Fullscreen};
+use crate::icon::{set_icon, Icon};
+use crate::ui_thread::UiThreadToken;
+use crate::window::{set_window_title, window_title};
+use crate::zorder::{bring_to_front, is_always_on_top, set_always_on_top};
+
+/// A handle to a window created by [`WindowBuilder`].
+///
+/// Unlike a bare `HWND`, this tracks the lifetime of the window: once the window has
+/// received `WM_NCDESTROY`, [`is_valid`] is false and the methods return
+/// [`Error::WindowDestroyed`] rather than acting on a handle the system may have reused.
+/// Clones refer to the same window. The handle isn't `Send`, as windows belong to the thread
+/// that created them.
+///
+/// Dropping the handle doesn't destroy the window; call [`destroy`] for that.
+///
+/// With the `raw-window-handle` feature, this implements `HasWindowHandle` and
+/// `HasDisplayHandle`, so the window can be used directly with crates such as wgpu and
+/// softbuffer.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`is_valid`]: #method.is_valid
+/// [`destroy`]: #method.destroy
+/// [`Error::WindowDestroyed`]: enum.Error.html#variant.WindowDestroyed
+#[derive(Clone, Debug)]
+pub struct Hwnd {
+    hwnd: HWND,
+    alive: Rc<Cell<bool>>,
+}
+
+impl Hwnd {
+    pub(crate) fn new(hwnd: HWND, alive: Rc<Cell<bool>>) -> Hwnd {
+        Hwnd { hwnd, alive }
+    }
+
+    /// The raw window handle, for use with winapi.
+    ///
+    /// This is returned even after the window is destroyed, so check [`is_valid`] before
+    /// passing it to functions that rely on it.
+    ///
+    /// [`is_valid`]: #method.is_valid
+    pub fn raw(&self) -> HWND {
+        self.hwnd
+    }
+
+    /// Whether the window still exists, that is, hasn't yet received `WM_NCDESTROY`.
+    pub fn is_valid(&self) -> bool {
+        self.alive.get()
+    }
+
+    /// A token for the window's thread, which is the calling thread.
+    pub fn ui_thread(&self) -> UiThreadToken {
+        UiThreadToken::assume()
+    }
+
+    /// Whether the window exists and is visible; see [`is_visible`](fn.is_visible.html).
+    pub fn is_visible(&self) -> bool {
+        self.is_valid() && unsafe { is_visible(self.hwnd) }
+    }
+
+    /// Whether the window exists and is minimized.
+    pub fn is_minimized(&self) -> bool {
+        self.is_valid() && unsafe { is_minimized(self.hwnd) }
+    }
+
+    /// The raw handle, or an error if the window is gone.
+    fn checked(&self) -> Result<HWND, Error> {
+        if self.is_valid() {
+            Ok(self.hwnd)
+        } else {
+            Err(Error::WindowDestroyed)
+        }
+    }
+
+    /// Set the window's show state, with an `SW_` value such as `SW_SHOWNORMAL`.
+    ///
+    /// The result is whether the window was previously visible. See [`ShowWindow`].
+    ///
+    /// [`ShowWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-showwindow
+    pub fn show(&self, cmd: c_int) -> Result<bool, Error> {
+        let hwnd = self.checked()?;
+        Ok(unsafe { ShowWindow(hwnd, cmd) } != 0)
+    }
+
+    /// Destroy the window.
+    ///
+    /// The window procedure receives `WM_DESTROY` and `WM_NCDESTROY` before this returns, and
+    /// afterwards the handle is no longer valid. See [`UiThreadToken::destroy_window`].
+    ///
+    /// [`UiThreadToken::destroy_window`]: struct.UiThreadToken.html#method.destroy_window
+    pub fn destroy(&self, ui: UiThreadToken) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe { ui.destroy_window(hwnd) }
+    }
+
+    /// Set the window's title.
+    pub fn set_title(&self, title: impl AsRef<OsStr>) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe { set_window_title(hwnd, title) }
+    }
+
+    /// The window's title.
+    pub fn title(&self) -> Result<OsString, Error> {
+        let hwnd = self.checked()?;
+        unsafe { window_title(hwnd) }
+    }
+
+    /// Set the window's icon, which is kept until replaced or the window is destroyed.
+    ///
+    /// See [`set_icon`](fn.set_icon.html).
+    pub fn set_icon(&self, icon: Icon) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe { set_icon(hwnd, icon) }
+    }
+
+    /// Set or clear the cursor of the client area.
+    ///
+    /// See [`set_window_cursor`](fn.set_window_cursor.html).
+    pub fn set_cursor(&self, ui: UiThreadToken, cursor: Option<Cursor>) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe {
+            ui.check(hwnd)?;
+            set_window_cursor(hwnd, cursor)
+        }
+    }
+
+    /// Whether the window exists and is fullscreen.
+    pub fn is_fullscreen(&self) -> bool {
+        self.is_valid() && unsafe { is_fullscreen(self.hwnd) }
+    }
+
+    /// Enter or leave borderless fullscreen.
+    ///
+    /// See [`set_fullscreen`](fn.set_fullscreen.html).
+    pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe { set_fullscreen(hwnd, fullscreen) }
+    }
+
+    /// Toggle fullscreen on the window's current monitor, returning whether it is now
+    /// fullscreen.
+    ///
+    /// See [`toggle_fullscreen`](fn.toggle_fullscreen.html).
+    pub fn toggle_fullscreen(&self) -> Result<bool, Error> {
+        let hwnd = self.checked()?;
+        unsafe { toggle_fullscreen(hwnd) }
+    }
+
+    /// Whether the window exists and is in the topmost band.
+    pub fn is_always_on_top(&self) -> bool {
+        self.is_valid() && unsafe { is_always_on_top(self.hwnd) }
+    }
+
+    /// Move the window into or out of the topmost band.
+    ///
+    /// See [`set_always_on_top`](fn.set_always_on_top.html).
+    pub fn set_always_on_top(&self, on_top: bool) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe { set_always_on_top(hwnd, on_top) }
+    }
+
+    /// Move the window to the top of its band, without activating it.
+    ///
+    /// See [`bring_to_front`](fn.bring_to_front.html).
+    pub fn bring_to_front(&self) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        unsafe { bring_to_front(hwnd) }
+    }
+
+    /// Mark the whole client area as needing to be repainted, without erasing the background.
+    pub fn invalidate(&self) -> Result<(), Error> {
+        let hwnd = self.checked()?;
+        if unsafe { InvalidateRect(hwnd, null(), FALSE) } == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Hwnd {
+    fn eq(&self, other: &Hwnd) -> bool {
+        self.hwnd == other.hwnd && Rc::ptr_eq(&self.alive, &other.alive)
+    }
+}
+
+impl Eq for Hwnd {}
+
+#[cfg(feature = "raw-window-handle")]
+impl HasWindowHandle for Hwnd {
+    /// The Win32 handle, or `HandleError::Unavailable` once the window is destroyed.
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        if !self.is_valid() {
+            return Err(HandleError::Unavailable);
+        }
+        let hwnd = NonZeroIsize::new(self.hwnd as isize).ok_or(HandleError::Unavailable)?;
+        let mut handle = Win32WindowHandle::new(hwnd);
+        // On 32-bit targets, `GetWindowLongPtrW` is `GetWindowLongW`, returning `LONG`.
+        let hinstance = unsafe { GetWindowLongPtrW(self.hwnd, GWLP_HINSTANCE) } as isize;
+        handle.hinstance = NonZeroIsize::new(hinstance);
+        // Windows belong to their thread and `Hwnd` isn't `Send`, so the window can't be
+        // destroyed from elsewhere while the handle is in use.
+        Ok(unsafe { WindowHandle::borrow_raw(RawWindowHandle::Win32(handle)) })
+    }
+}
+
+#[cfg(feature = "raw-window-handle")]
+impl HasDisplayHandle for Hwnd {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Ok(DisplayHandle::windows())
+    }
+}
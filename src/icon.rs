@@ -0,0 +1,181 @@
+//! Icons created from pixel data.
+//!
+//! Icons are usually compiled into the executable as resources, but an application may
+//! prefer to embed a PNG and decode it at startup. [`Icon::from_rgba`] turns the decoded
+//! pixels into an icon with [`CreateIconIndirect`], which can be given to a window class or
+//! set on a window with [`set_icon`].
+//!
+//! [`Icon::from_rgba`]: struct.Icon.html#method.from_rgba
+//! [`set_icon`]: fn.set_icon.html
+//! [`CreateIconIndirect`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createiconindirect
+
+use std::mem;
+use std::ptr::null_mut;
+use std::rc::Rc;
+use std::slice;
+
+use winapi::shared::minwindef::{LPARAM, UINT, WPARAM};
+use winapi::shared::windef::{HICON, HWND};
+use winapi::shared::winerror::ERROR_INVALID_PARAMETER;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::wingdi::{
+    CreateBitmap, CreateDIBSection, DeleteObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+    DIB_RGB_COLORS,
+};
+use winapi::um::winuser::{
+    CreateIconIndirect, DestroyIcon, GetWindowThreadProcessId, SendMessageW, ICONINFO, ICON_BIG,
+    ICON_SMALL, WM_SETICON,
+};
+
+use crate::error::Error;
+use crate::window_data::set_window_data;
+
+/// The window data key for the icon set by `set_icon`.
+const ICON_KEY: &str = "icon";
+
+/// The window data key for the icon set by `set_small_icon`.
+const SMALL_ICON_KEY: &str = "icon.small";
+
+/// An icon, destroyed when dropped.
+#[derive(Debug)]
+pub struct Icon {
+    handle: HICON,
+}
+
+impl Icon {
+    /// Create an icon from 8-bit RGBA pixels, with rows stored top to bottom.
+    ///
+    /// The alpha channel is straight (not premultiplied). Returns a `Win32` error of
+    /// `ERROR_INVALID_PARAMETER` if `rgba` isn't `4 * width * height` bytes.
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Icon, Error> {
+        let handle = unsafe { create_icon(width, height, rgba, None)? };
+        Ok(Icon { handle })
+    }
+
+    /// Take ownership of an icon handle, which will be destroyed when this is dropped.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HICON reference, not a shared icon (such as one loaded
+    /// with `LR_SHARED`), and not destroyed elsewhere.
+    pub unsafe fn from_raw(handle: HICON) -> Icon {
+        Icon { handle }
+    }
+
+    /// The icon handle.
+    pub fn handle(&self) -> HICON {
+        self.handle
+    }
+
+    /// Give up ownership of the handle, which is no longer destroyed.
+    pub fn into_raw(self) -> HICON {
+        let handle = self.handle;
+        mem::forget(self);
+        handle
+    }
+}
+
+impl Drop for Icon {
+    fn drop(&mut self) {
+        unsafe {
+            DestroyIcon(self.handle);
+        }
+    }
+}
+
+/// Create an icon, or a cursor with the given hotspot, from 8-bit RGBA pixels.
+pub(crate) unsafe fn create_icon(
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    hotspot: Option<(u32, u32)>,
+) -> Result<HICON, Error> {
+    if width == 0 || height == 0 || rgba.len() != 4 * width as usize * height as usize {
+        return Err(Error::Win32(ERROR_INVALID_PARAMETER));
+    }
+    let mut bmi: BITMAPINFO = mem::zeroed();
+    bmi.bmiHeader = BITMAPINFOHEADER {
+        biSize: mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: width as i32,
+        // Negative height means a top-down DIB.
+        biHeight: -(height as i32),
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB,
+        ..mem::zeroed()
+    };
+    let mut bits = null_mut();
+    let color = CreateDIBSection(null_mut(), &bmi, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+    if color.is_null() {
+        return Err(Error::last_error());
+    }
+    // The DIB is BGRA.
+    let dst = slice::from_raw_parts_mut(bits as *mut u8, rgba.len());
+    for (dst, src) in dst.chunks_exact_mut(4).zip(rgba.chunks_exact(4)) {
+        dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+    }
+    // With an alpha channel in the color bitmap, the mask is ignored, but must be present.
+    let mask = CreateBitmap(width as i32, height as i32, 1, 1, null_mut());
+    if mask.is_null() {
+        let err = Error::last_error();
+        DeleteObject(color as _);
+        return Err(err);
+    }
+    let (x_hotspot, y_hotspot) = hotspot.unwrap_or((0, 0));
+    let mut info = ICONINFO {
+        fIcon: hotspot.is_none() as _,
+        xHotspot: x_hotspot,
+        yHotspot: y_hotspot,
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    let icon = CreateIconIndirect(&mut info);
+    let result = if icon.is_null() {
+        Err(Error::last_error())
+    } else {
+        Ok(icon)
+    };
+    // The icon has its own copies of the bitmaps.
+    DeleteObject(mask as _);
+    DeleteObject(color as _);
+    result
+}
+
+/// Set a window's icon, shown in the title bar and by Alt+Tab and the taskbar.
+///
+/// The icon is kept until it is replaced or the window is destroyed. Windows scales it to
+/// the sizes it needs; for the best results at small sizes, also call [`set_small_icon`].
+///
+/// Returns [`Error::ForeignWindow`] if the window belongs to another thread.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`set_small_icon`]: fn.set_small_icon.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_icon(hwnd: HWND, icon: Icon) -> Result<(), Error> {
+    set_window_icon(hwnd, icon, ICON_BIG, ICON_KEY)
+}
+
+/// Set a window's small icon, shown in the title bar and the taskbar.
+///
+/// Without a small icon, one is scaled down from the icon set by [`set_icon`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`set_icon`]: fn.set_icon.html
+pub unsafe fn set_small_icon(hwnd: HWND, icon: Icon) -> Result<(), Error> {
+    set_window_icon(hwnd, icon, ICON_SMALL, SMALL_ICON_KEY)
+}
+
+unsafe fn set_window_icon(hwnd: HWND, icon: Icon, kind: UINT, key: &str) -> Result<(), Error> {
+    if GetWindowThreadProcessId(hwnd, null_mut()) != GetCurrentThreadId() {
+        return Err(Error::ForeignWindow);
+    }
+    SendMessageW(hwnd, WM_SETICON, kind as WPARAM, icon.handle as LPARAM);
+    // This drops the previous icon, which the window no longer uses.
+    set_window_data(hwnd, key, Rc::new(icon))
+}
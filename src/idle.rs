@@ -0,0 +1,171 @@
+//! Detecting when the user is idle.
+//!
+//! [`last_input_time`] reports the time of the last input in the session, from
+//! [`GetLastInputInfo`]. [`IdleTracker`] builds on it with a timer, reporting when the user
+//! has been idle for a given time and when they return, for screen savers, auto-lock, and
+//! presence indicators.
+//!
+//! [`last_input_time`]: fn.last_input_time.html
+//! [`IdleTracker`]: struct.IdleTracker.html
+//! [`GetLastInputInfo`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getlastinputinfo
+
+use std::mem;
+use std::time::Duration;
+
+use winapi::shared::basetsd::UINT_PTR;
+use winapi::shared::minwindef::{LPARAM, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::um::winuser::{
+    GetLastInputInfo, KillTimer, SetTimer, LASTINPUTINFO, WM_KEYDOWN, WM_KEYLAST, WM_MOUSEFIRST,
+    WM_MOUSELAST, WM_NCMOUSEMOVE, WM_NCXBUTTONDBLCLK, WM_POINTERDOWN, WM_POINTERUPDATE, WM_TIMER,
+    WM_TOUCH,
+};
+
+use crate::error::Error;
+use crate::message::elapsed_ms;
+
+/// The timer id used by [`IdleTracker`](struct.IdleTracker.html).
+const IDLE_TIMER_ID: UINT_PTR = 0x7769_6e69;
+
+/// The time of the last user input in the session, in milliseconds since system start.
+///
+/// This counts input to any application, not just this one. Like message times, it wraps
+/// around about every 49.7 days; use [`elapsed_ms`](fn.elapsed_ms.html) for differences.
+pub fn last_input_time() -> u32 {
+    unsafe {
+        let mut info: LASTINPUTINFO = mem::zeroed();
+        info.cbSize = mem::size_of::<LASTINPUTINFO>() as UINT;
+        if GetLastInputInfo(&mut info) == 0 {
+            return GetTickCount();
+        }
+        info.dwTime
+    }
+}
+
+/// How long the user has been idle, by [`last_input_time`](fn.last_input_time.html).
+pub fn idle_duration() -> Duration {
+    let now = unsafe { GetTickCount() };
+    Duration::from_millis(elapsed_ms(last_input_time(), now) as u64)
+}
+
+/// Which input counts as activity for an [`IdleTracker`](struct.IdleTracker.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleScope {
+    /// Input to any application in the session.
+    Session,
+    /// Keyboard, mouse, pointer, and touch messages passed to
+    /// [`IdleTracker::process_message`](struct.IdleTracker.html#method.process_message).
+    ///
+    /// Pass the messages of all the windows that should count.
+    Windows,
+}
+
+/// A change in whether the user is idle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleEvent {
+    /// There has been no input for the timeout.
+    Idle,
+    /// There was input after an `Idle` event.
+    Active,
+}
+
+/// Reports when the user becomes idle and when they return.
+///
+/// This runs a timer on the window, and reports changes from [`process_message`], which
+/// should be called with each message the window procedure receives. The timer is stopped
+/// when the tracker is dropped.
+///
+/// [`process_message`]: #method.process_message
+pub struct IdleTracker {
+    hwnd: HWND,
+    timeout: u32,
+    scope: IdleScope,
+    last_input: u32,
+    idle: bool,
+}
+
+impl IdleTracker {
+    /// Start tracking, reporting idleness after `timeout` without input.
+    ///
+    /// # Safety
+    ///
+    /// The `hwnd` argument must be a valid HWND reference, to a window on the calling thread,
+    /// and must outlive the tracker.
+    pub unsafe fn new(
+        hwnd: HWND,
+        timeout: Duration,
+        scope: IdleScope,
+    ) -> Result<IdleTracker, Error> {
+        let timeout = timeout.as_millis().min(u32::MAX as u128) as u32;
+        // Check often enough to notice idleness promptly, and the return from it when input
+        // comes from other applications.
+        let interval = (timeout / 10).clamp(100, 1000);
+        if SetTimer(hwnd, IDLE_TIMER_ID, interval, None) == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(IdleTracker {
+            hwnd,
+            timeout,
+            scope,
+            last_input: GetTickCount(),
+            idle: false,
+        })
+    }
+
+    /// Whether the user is currently considered idle.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// The time of the last input counted, in milliseconds since system start.
+    pub fn last_input_time(&self) -> u32 {
+        match self.scope {
+            IdleScope::Session => last_input_time(),
+            IdleScope::Windows => self.last_input,
+        }
+    }
+
+    /// Process a window message, returning a change in idleness.
+    ///
+    /// The tracker's own `WM_TIMER` messages should not be passed on to `DefWindowProc`;
+    /// other messages are only observed.
+    pub fn process_message(
+        &mut self,
+        msg: UINT,
+        wparam: WPARAM,
+        _lparam: LPARAM,
+    ) -> Option<IdleEvent> {
+        let now = unsafe { GetTickCount() };
+        let is_input = match msg {
+            WM_KEYDOWN..=WM_KEYLAST
+            | WM_MOUSEFIRST..=WM_MOUSELAST
+            | WM_NCMOUSEMOVE..=WM_NCXBUTTONDBLCLK
+            | WM_POINTERUPDATE..=WM_POINTERDOWN
+            | WM_TOUCH => true,
+            WM_TIMER if wparam == IDLE_TIMER_ID => false,
+            _ => return None,
+        };
+        if is_input {
+            self.last_input = now;
+        }
+        let idle = elapsed_ms(self.last_input_time(), now) >= self.timeout;
+        if idle == self.idle {
+            return None;
+        }
+        self.idle = idle;
+        Some(if idle {
+            IdleEvent::Idle
+        } else {
+            IdleEvent::Active
+        })
+    }
+}
+
+impl Drop for IdleTracker {
+    fn drop(&mut self) {
+        unsafe {
+            KillTimer(self.hwnd, IDLE_TIMER_ID);
+        }
+    }
+}
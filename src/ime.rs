@@ -0,0 +1,55 @@
+//! Input method (IME) state.
+//!
+//! While an IME composition is in progress, keystrokes edit the composition: Space picks a
+//! candidate, Enter commits, and Ctrl with a letter may have a meaning to the IME. Those
+//! keystrokes shouldn't also trigger accelerators or application shortcuts. [`Runloop`] skips
+//! its accelerator tables, and `ShortcutMatcher` (with the `kb` feature) matches nothing,
+//! while [`is_composing`] is true for the window receiving the key, unless told otherwise.
+//!
+//! [`Runloop`]: struct.Runloop.html
+//! [`is_composing`]: fn.is_composing.html
+
+use winapi::shared::minwindef::{DWORD, LPVOID, UINT, WPARAM};
+use winapi::shared::ntdef::LONG;
+use winapi::shared::windef::HWND;
+use winapi::um::imm::{ImmGetContext, ImmReleaseContext, HIMC};
+use winapi::um::winuser::{VK_PROCESSKEY, WM_KEYDOWN, WM_SYSKEYDOWN};
+
+// Not declared by winapi.
+#[link(name = "imm32")]
+extern "system" {
+    pub(crate) fn ImmGetCompositionStringW(
+        himc: HIMC,
+        index: DWORD,
+        buf: LPVOID,
+        len: DWORD,
+    ) -> LONG;
+}
+
+const GCS_COMPSTR: DWORD = 0x8;
+
+/// Whether an IME composition is in progress in a window.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn is_composing(hwnd: HWND) -> bool {
+    let himc = ImmGetContext(hwnd);
+    if himc.is_null() {
+        return false;
+    }
+    let len = ImmGetCompositionStringW(himc, GCS_COMPSTR, std::ptr::null_mut(), 0);
+    ImmReleaseContext(hwnd, himc);
+    len > 0
+}
+
+/// Whether a key down message belongs to the IME, and so shouldn't be matched as a shortcut.
+///
+/// This is the case for keys the IME processed (which arrive as `VK_PROCESSKEY`), and any
+/// key during a composition. Other messages are never the IME's.
+pub(crate) unsafe fn is_ime_key(hwnd: HWND, msg: UINT, wparam: WPARAM) -> bool {
+    match msg {
+        WM_KEYDOWN | WM_SYSKEYDOWN => wparam as i32 == VK_PROCESSKEY || is_composing(hwnd),
+        _ => false,
+    }
+}
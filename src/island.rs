@@ -0,0 +1,256 @@
+//! Hosting XAML and composition islands in a window.
+//!
+//! Windows can host modern UI inside a classic window in two ways: a [XAML island], in which a
+//! `DesktopWindowXamlSource` owns a child window showing XAML content, and a composition
+//! island, in which a `DesktopWindowTarget` shows a tree of `Windows.UI.Composition` visuals.
+//! Both are WinRT objects, created with the application's WinRT bindings; this module has the
+//! Win32 half, through their COM interop interfaces: attaching to a window, keeping the island
+//! sized with it, passing it the focus, and giving it keyboard messages first, so that its
+//! tab navigation and accelerators work.
+//!
+//! XAML islands need Windows 10 version 1903, and an application manifest declaring it, as
+//! described in the XAML island documentation. COM (and WinRT) must be initialized as a
+//! single-threaded apartment on the window's thread.
+//!
+//! [XAML island]: https://docs.microsoft.com/en-us/windows/apps/desktop/modernize/xaml-islands
+
+use std::cell::Cell;
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{BOOL, FALSE, LPARAM, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::{E_NOINTERFACE, HRESULT, SUCCEEDED};
+use winapi::um::unknwnbase::IUnknown;
+use winapi::um::winuser::{
+    GetClientRect, SetFocus, SetWindowPos, ShowWindow, MSG, SIZE_MINIMIZED, SWP_NOACTIVATE,
+    SWP_NOZORDER, SWP_SHOWWINDOW, SW_SHOW, WM_SETFOCUS, WM_SIZE,
+};
+
+use wio::com::ComPtr;
+
+use crate::error::Error;
+use crate::geometry::Rect;
+
+use self::interfaces::{
+    ICompositorDesktopInterop, IDesktopWindowXamlSourceNative, IDesktopWindowXamlSourceNative2,
+};
+
+// These interop interfaces are from the Windows SDK, and not defined in winapi.
+#[allow(non_snake_case)]
+mod interfaces {
+    use winapi::shared::minwindef::{BOOL, DWORD};
+    use winapi::shared::windef::HWND;
+    use winapi::shared::winerror::HRESULT;
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::um::winuser::MSG;
+    use winapi::RIDL;
+
+    RIDL! {#[uuid(0x3cbcf1bf, 0x2f76, 0x4e9c, 0x96, 0xab, 0xe8, 0x4b, 0x37, 0x97, 0x25, 0x54)]
+    interface IDesktopWindowXamlSourceNative(IDesktopWindowXamlSourceNativeVtbl):
+        IUnknown(IUnknownVtbl) {
+        fn AttachToWindow(
+            parentWnd: HWND,
+        ) -> HRESULT,
+        fn get_WindowHandle(
+            hWnd: *mut HWND,
+        ) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0xe3dcd8c7, 0x3057, 0x4692, 0x99, 0xc3, 0x7b, 0x77, 0x20, 0xaf, 0xda, 0x31)]
+    interface IDesktopWindowXamlSourceNative2(IDesktopWindowXamlSourceNative2Vtbl):
+        IDesktopWindowXamlSourceNative(IDesktopWindowXamlSourceNativeVtbl) {
+        fn PreTranslateMessage(
+            message: *const MSG,
+            result: *mut BOOL,
+        ) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x29e691fa, 0x4567, 0x4dca, 0xb3, 0x19, 0xd0, 0xf2, 0x07, 0xeb, 0x68, 0x07)]
+    interface ICompositorDesktopInterop(ICompositorDesktopInteropVtbl): IUnknown(IUnknownVtbl) {
+        fn CreateDesktopWindowTarget(
+            hwndTarget: HWND,
+            isTopmost: BOOL,
+            result: *mut *mut IUnknown,
+        ) -> HRESULT,
+        fn EnsureOnThread(
+            threadId: DWORD,
+        ) -> HRESULT,
+    }}
+}
+
+fn check(hr: HRESULT) -> Result<(), Error> {
+    if SUCCEEDED(hr) {
+        Ok(())
+    } else {
+        Err(Error::Hresult(hr))
+    }
+}
+
+/// A XAML island attached to a window.
+///
+/// The island's content is set through the `DesktopWindowXamlSource`, with WinRT bindings;
+/// this keeps the island's window in step with the parent window. The source stays attached
+/// while this (or the application's reference to the source) is alive; close the source to
+/// remove the island.
+pub struct XamlIsland {
+    source: ComPtr<IDesktopWindowXamlSourceNative>,
+    /// The interface for `PreTranslateMessage`, from Windows 10 version 1903.
+    source2: Option<ComPtr<IDesktopWindowXamlSourceNative2>>,
+    parent: HWND,
+    island: HWND,
+    /// The bounds set with `set_bounds`, or `None` to fill the window.
+    bounds: Cell<Option<Rect>>,
+}
+
+impl XamlIsland {
+    /// Attach a `DesktopWindowXamlSource` to a window.
+    ///
+    /// The source is passed as its `IUnknown` (any interface of the WinRT object will do),
+    /// and isn't consumed. The island is shown, filling the window's client area until
+    /// [`set_bounds`](#method.set_bounds) is called.
+    ///
+    /// Returns `Error::Hresult(E_NOINTERFACE)` if the object isn't a `DesktopWindowXamlSource`.
+    ///
+    /// # Safety
+    ///
+    /// The `source` argument must be a valid COM object, and the `parent` argument a valid
+    /// HWND reference, to a window on the calling thread.
+    pub unsafe fn attach(source: *mut IUnknown, parent: HWND) -> Result<XamlIsland, Error> {
+        if source.is_null() {
+            return Err(Error::Hresult(E_NOINTERFACE));
+        }
+        (*source).AddRef();
+        let source = ComPtr::from_raw(source);
+        let native = source
+            .cast::<IDesktopWindowXamlSourceNative>()
+            .map_err(Error::Hresult)?;
+        check(native.AttachToWindow(parent))?;
+        let mut island = null_mut();
+        check(native.get_WindowHandle(&mut island))?;
+        let island = XamlIsland {
+            source2: source.cast::<IDesktopWindowXamlSourceNative2>().ok(),
+            source: native,
+            parent,
+            island,
+            bounds: Cell::new(None),
+        };
+        island.layout();
+        ShowWindow(island.island, SW_SHOW);
+        Ok(island)
+    }
+
+    /// The island's window, a child of the parent window.
+    pub fn hwnd(&self) -> HWND {
+        self.island
+    }
+
+    /// Give the island a chance to handle a message before it is translated and dispatched.
+    ///
+    /// Returns `true` if the island handled the message, in which case it must not be
+    /// dispatched. Without this, keyboard navigation and accelerators in the island don't
+    /// work. Call it from the message loop, for example with [`Runloop::pre_translate`].
+    ///
+    /// Before Windows 10 version 1903, this always returns `false`.
+    ///
+    /// [`Runloop::pre_translate`]: struct.Runloop.html#method.pre_translate
+    pub fn pre_translate_message(&self, msg: &MSG) -> bool {
+        let source2 = match &self.source2 {
+            Some(source2) => source2,
+            None => return false,
+        };
+        let mut handled: BOOL = FALSE;
+        unsafe { SUCCEEDED(source2.PreTranslateMessage(msg, &mut handled)) && handled != 0 }
+    }
+
+    /// Process a message of the parent window, keeping the island in step with it.
+    ///
+    /// On `WM_SIZE`, the island is resized to fill the client area (unless bounds were set),
+    /// and on `WM_SETFOCUS`, the focus is passed to the island. Messages are only observed,
+    /// and should still be handled as usual.
+    pub fn process_message(&self, msg: UINT, wparam: WPARAM, _lparam: LPARAM) {
+        match msg {
+            WM_SIZE if wparam != SIZE_MINIMIZED => self.layout(),
+            WM_SETFOCUS => unsafe {
+                SetFocus(self.island);
+            },
+            _ => (),
+        }
+    }
+
+    /// Set the area of the island, in the parent window's client coordinates.
+    ///
+    /// After this, the island no longer follows the size of the window.
+    pub fn set_bounds(&self, bounds: Rect) {
+        self.bounds.set(Some(bounds));
+        self.layout();
+    }
+
+    /// Make the island fill the window's client area, following its size.
+    ///
+    /// This is the default, until [`set_bounds`](#method.set_bounds) is called.
+    pub fn fill_window(&self) {
+        self.bounds.set(None);
+        self.layout();
+    }
+
+    fn layout(&self) {
+        unsafe {
+            let rect = match self.bounds.get() {
+                Some(bounds) => bounds,
+                None => {
+                    let mut rect = mem::zeroed();
+                    GetClientRect(self.parent, &mut rect);
+                    rect.into()
+                }
+            };
+            SetWindowPos(
+                self.island,
+                null_mut(),
+                rect.left,
+                rect.top,
+                rect.width(),
+                rect.height(),
+                SWP_NOZORDER | SWP_NOACTIVATE | SWP_SHOWWINDOW,
+            );
+        }
+    }
+
+    /// The `IDesktopWindowXamlSourceNative` interface, for APIs not wrapped here.
+    pub fn source(&self) -> *mut IUnknown {
+        self.source.as_raw() as *mut IUnknown
+    }
+}
+
+/// Create a composition island: a `DesktopWindowTarget` showing visuals in a window.
+///
+/// The compositor is a `Windows.UI.Composition.Compositor`, passed as its `IUnknown`; the
+/// result is the `DesktopWindowTarget`, whose `Root` is set to the visual tree with WinRT
+/// bindings. A topmost target is drawn above the window's own content (including its
+/// children), and otherwise below it. The visuals are sized by the application, so resize
+/// them on `WM_SIZE`.
+///
+/// The window should usually be created with `WS_EX_NOREDIRECTIONBITMAP`, so that its
+/// redirection surface doesn't cover a target that isn't topmost.
+///
+/// # Safety
+///
+/// The `compositor` argument must be a valid COM object, created on the calling thread, and
+/// the `hwnd` argument a valid HWND reference, to a window on the calling thread.
+pub unsafe fn create_desktop_window_target(
+    compositor: *mut IUnknown,
+    hwnd: HWND,
+    topmost: bool,
+) -> Result<ComPtr<IUnknown>, Error> {
+    if compositor.is_null() {
+        return Err(Error::Hresult(E_NOINTERFACE));
+    }
+    (*compositor).AddRef();
+    let compositor = ComPtr::from_raw(compositor);
+    let interop = compositor
+        .cast::<ICompositorDesktopInterop>()
+        .map_err(Error::Hresult)?;
+    let mut target = null_mut();
+    check(interop.CreateDesktopWindowTarget(hwnd, topmost as BOOL, &mut target))?;
+    Ok(ComPtr::from_raw(target))
+}
@@ -0,0 +1,183 @@
+//! Tracking a held key, as for push-to-talk.
+//!
+//! A push-to-talk key should keep working when focus moves briefly to another window, and a
+//! voice chat utility may want it to work when the application isn't focused at all. A
+//! low-level keyboard hook can do this, but it sees all keystrokes, slows down input for the
+//! whole session, and is flagged by security software. [`KeyHoldTracker`] instead combines
+//! the window's own key messages with polling [`GetAsyncKeyState`] on a timer.
+//!
+//! [`KeyHoldTracker`]: struct.KeyHoldTracker.html
+//! [`GetAsyncKeyState`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getasynckeystate
+
+use std::time::Duration;
+
+use winapi::shared::basetsd::UINT_PTR;
+use winapi::shared::minwindef::{LPARAM, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::um::winuser::{
+    GetAsyncKeyState, KillTimer, SetTimer, WM_KEYDOWN, WM_KEYUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+    WM_TIMER,
+};
+
+use crate::error::Error;
+use crate::message::elapsed_ms;
+
+/// The timer id used by [`KeyHoldTracker`](struct.KeyHoldTracker.html).
+const KEY_HOLD_TIMER_ID: UINT_PTR = 0x7769_6e6b;
+
+/// When a [`KeyHoldTracker`](struct.KeyHoldTracker.html) notices the key being pressed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyHoldScope {
+    /// Presses are noticed only while the window has keyboard focus.
+    ///
+    /// The release is noticed wherever focus is, so holding the key through a brief focus
+    /// change doesn't end the hold.
+    Focused,
+    /// Presses are noticed whichever application has focus.
+    ///
+    /// They are noticed on the next poll, so may be late by up to the poll interval. Keys
+    /// pressed in an elevated application, or on the secure desktop, aren't seen.
+    Global,
+}
+
+/// A change in the state of the tracked key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyHoldEvent {
+    /// The key was pressed.
+    Pressed,
+    /// The key has been held for the hold delay.
+    Held,
+    /// The key was released, after being down for the given time.
+    Released { duration: Duration },
+}
+
+/// Tracks whether a single key is held down.
+///
+/// Events are reported from [`process_message`], which should be called with each message
+/// the window procedure receives. A timer on the window polls the key state; it is stopped
+/// when the tracker is dropped.
+///
+/// [`process_message`]: #method.process_message
+pub struct KeyHoldTracker {
+    hwnd: HWND,
+    vk: i32,
+    scope: KeyHoldScope,
+    hold_delay: u32,
+    pressed_at: Option<u32>,
+    held: bool,
+}
+
+impl KeyHoldTracker {
+    /// The default interval at which the key state is polled.
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// The default time a key must be down before [`KeyHoldEvent::Held`].
+    ///
+    /// [`KeyHoldEvent::Held`]: enum.KeyHoldEvent.html#variant.Held
+    pub const DEFAULT_HOLD_DELAY: Duration = Duration::from_millis(300);
+
+    /// Start tracking the key with the given virtual key code, such as `VK_CAPITAL` or
+    /// `VK_XBUTTON1`.
+    ///
+    /// Mouse buttons are only seen by polling, so use `KeyHoldScope::Global` for them.
+    ///
+    /// # Safety
+    ///
+    /// The `hwnd` argument must be a valid HWND reference, to a window on the calling thread,
+    /// and must outlive the tracker.
+    pub unsafe fn new(hwnd: HWND, vk: i32, scope: KeyHoldScope) -> Result<KeyHoldTracker, Error> {
+        let mut tracker = KeyHoldTracker {
+            hwnd,
+            vk,
+            scope,
+            hold_delay: KeyHoldTracker::DEFAULT_HOLD_DELAY.as_millis() as u32,
+            pressed_at: None,
+            held: false,
+        };
+        tracker.set_poll_interval(KeyHoldTracker::DEFAULT_POLL_INTERVAL)?;
+        Ok(tracker)
+    }
+
+    /// Set how often the key state is polled.
+    ///
+    /// Shorter intervals notice global presses and releases during focus changes sooner.
+    pub fn set_poll_interval(&mut self, interval: Duration) -> Result<(), Error> {
+        let interval = interval.as_millis().clamp(10, u32::MAX as u128) as u32;
+        unsafe {
+            if SetTimer(self.hwnd, KEY_HOLD_TIMER_ID, interval, None) == 0 {
+                return Err(Error::last_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Set how long the key must be down before [`KeyHoldEvent::Held`].
+    ///
+    /// [`KeyHoldEvent::Held`]: enum.KeyHoldEvent.html#variant.Held
+    pub fn set_hold_delay(&mut self, delay: Duration) {
+        self.hold_delay = delay.as_millis().min(u32::MAX as u128) as u32;
+    }
+
+    /// Whether the key is currently down.
+    pub fn is_down(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+
+    /// Process a window message, returning a change in the key state.
+    ///
+    /// The tracker's own `WM_TIMER` messages should not be passed on to `DefWindowProc`;
+    /// other messages are only observed.
+    pub fn process_message(
+        &mut self,
+        msg: UINT,
+        wparam: WPARAM,
+        _lparam: LPARAM,
+    ) -> Option<KeyHoldEvent> {
+        let now = unsafe { GetTickCount() };
+        match msg {
+            WM_KEYDOWN | WM_SYSKEYDOWN if wparam as i32 == self.vk => self.press(now),
+            WM_KEYUP | WM_SYSKEYUP if wparam as i32 == self.vk => self.release(now),
+            WM_TIMER if wparam == KEY_HOLD_TIMER_ID => self.poll(now),
+            _ => None,
+        }
+    }
+
+    fn poll(&mut self, now: u32) -> Option<KeyHoldEvent> {
+        let down = unsafe { GetAsyncKeyState(self.vk) } < 0;
+        match self.pressed_at {
+            None if down && self.scope == KeyHoldScope::Global => self.press(now),
+            None => None,
+            Some(_) if !down => self.release(now),
+            Some(pressed_at) if !self.held && elapsed_ms(pressed_at, now) >= self.hold_delay => {
+                self.held = true;
+                Some(KeyHoldEvent::Held)
+            }
+            Some(_) => None,
+        }
+    }
+
+    fn press(&mut self, now: u32) -> Option<KeyHoldEvent> {
+        // Auto-repeat sends further key downs while held.
+        if self.pressed_at.is_some() {
+            return None;
+        }
+        self.pressed_at = Some(now);
+        self.held = false;
+        Some(KeyHoldEvent::Pressed)
+    }
+
+    fn release(&mut self, now: u32) -> Option<KeyHoldEvent> {
+        let pressed_at = self.pressed_at.take()?;
+        let duration = Duration::from_millis(elapsed_ms(pressed_at, now) as u64);
+        Some(KeyHoldEvent::Released { duration })
+    }
+}
+
+impl Drop for KeyHoldTracker {
+    fn drop(&mut self) {
+        unsafe {
+            KillTimer(self.hwnd, KEY_HOLD_TIMER_ID);
+        }
+    }
+}
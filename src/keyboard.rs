@@ -8,28 +8,35 @@ use std::ops::RangeInclusive;
 
 use keyboard_types::{Code, Key, KeyState, KeyboardEvent, Location, Modifiers};
 
-use winapi::shared::minwindef::{HKL, INT, LPARAM, UINT, WPARAM};
-use winapi::shared::ntdef::SHORT;
+use winapi::shared::minwindef::{DWORD, HKL, INT, LPARAM, LPVOID, UINT, WORD, WPARAM};
+use winapi::shared::ntdef::{LONG, SHORT};
 use winapi::shared::windef::HWND;
+use winapi::um::imm::{ImmGetContext, ImmReleaseContext};
 use winapi::um::winuser::{
-    GetKeyState, GetKeyboardLayout, MapVirtualKeyExW, PeekMessageW, ToUnicodeEx, VkKeyScanW,
-    MAPVK_VK_TO_CHAR, MAPVK_VSC_TO_VK_EX, PM_NOREMOVE, VK_ACCEPT, VK_ADD, VK_APPS, VK_ATTN,
-    VK_BACK, VK_BROWSER_BACK, VK_BROWSER_FAVORITES, VK_BROWSER_FORWARD, VK_BROWSER_HOME,
-    VK_BROWSER_REFRESH, VK_BROWSER_SEARCH, VK_BROWSER_STOP, VK_CANCEL, VK_CAPITAL, VK_CLEAR,
-    VK_CONTROL, VK_CONVERT, VK_CRSEL, VK_DECIMAL, VK_DELETE, VK_DIVIDE, VK_DOWN, VK_END, VK_EREOF,
-    VK_ESCAPE, VK_EXECUTE, VK_EXSEL, VK_F1, VK_F10, VK_F11, VK_F12, VK_F2, VK_F3, VK_F4, VK_F5,
-    VK_F6, VK_F7, VK_F8, VK_F9, VK_FINAL, VK_HELP, VK_HOME, VK_INSERT, VK_JUNJA, VK_KANA, VK_KANJI,
-    VK_LAUNCH_APP1, VK_LAUNCH_APP2, VK_LAUNCH_MAIL, VK_LAUNCH_MEDIA_SELECT, VK_LCONTROL, VK_LEFT,
-    VK_LMENU, VK_LSHIFT, VK_LWIN, VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK,
+    GetKeyNameTextW, GetKeyState, GetKeyboardLayout, MapVirtualKeyExW, PeekMessageW, SendInput,
+    ToUnicodeEx, VkKeyScanExW, VkKeyScanW, INPUT, INPUT_KEYBOARD, KEYBDINPUT,
+    KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, MAPVK_VK_TO_CHAR, MAPVK_VK_TO_VSC_EX,
+    MAPVK_VSC_TO_VK_EX, PM_NOREMOVE, VK_ACCEPT, VK_ADD, VK_APPS, VK_ATTN, VK_BACK, VK_BROWSER_BACK,
+    VK_BROWSER_FAVORITES, VK_BROWSER_FORWARD, VK_BROWSER_HOME, VK_BROWSER_REFRESH,
+    VK_BROWSER_SEARCH, VK_BROWSER_STOP, VK_CANCEL, VK_CAPITAL, VK_CLEAR, VK_CONTROL, VK_CONVERT,
+    VK_CRSEL, VK_DECIMAL, VK_DELETE, VK_DIVIDE, VK_DOWN, VK_END, VK_EREOF, VK_ESCAPE, VK_EXECUTE,
+    VK_EXSEL, VK_F1, VK_F10, VK_F11, VK_F12, VK_F2, VK_F3, VK_F4, VK_F5, VK_F6, VK_F7, VK_F8,
+    VK_F9, VK_FINAL, VK_HELP, VK_HOME, VK_INSERT, VK_JUNJA, VK_KANA, VK_KANJI, VK_LAUNCH_APP1,
+    VK_LAUNCH_APP2, VK_LAUNCH_MAIL, VK_LAUNCH_MEDIA_SELECT, VK_LCONTROL, VK_LEFT, VK_LMENU,
+    VK_LSHIFT, VK_LWIN, VK_MEDIA_NEXT_TRACK, VK_MEDIA_PLAY_PAUSE, VK_MEDIA_PREV_TRACK,
     VK_MEDIA_STOP, VK_MENU, VK_MODECHANGE, VK_MULTIPLY, VK_NEXT, VK_NONCONVERT, VK_NUMLOCK,
     VK_NUMPAD0, VK_NUMPAD1, VK_NUMPAD2, VK_NUMPAD3, VK_NUMPAD4, VK_NUMPAD5, VK_NUMPAD6, VK_NUMPAD7,
     VK_NUMPAD8, VK_NUMPAD9, VK_OEM_ATTN, VK_OEM_CLEAR, VK_PAUSE, VK_PLAY, VK_PRINT, VK_PRIOR,
     VK_PROCESSKEY, VK_RCONTROL, VK_RETURN, VK_RIGHT, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SCROLL,
     VK_SELECT, VK_SHIFT, VK_SLEEP, VK_SNAPSHOT, VK_SUBTRACT, VK_TAB, VK_UP, VK_VOLUME_DOWN,
-    VK_VOLUME_MUTE, VK_VOLUME_UP, VK_ZOOM, WM_CHAR, WM_INPUTLANGCHANGE, WM_KEYDOWN, WM_KEYUP,
-    WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP,
+    VK_VOLUME_MUTE, VK_VOLUME_UP, VK_ZOOM, WM_CHAR, WM_IME_COMPOSITION, WM_INPUTLANGCHANGE,
+    WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_NCDESTROY, WM_SYSCHAR, WM_SYSKEYDOWN, WM_SYSKEYUP,
 };
 
+use crate::error::Error;
+use crate::ime::{is_ime_key, ImmGetCompositionStringW};
+use crate::message::message_time;
+
 const VK_ABNT_C2: INT = 0xc2;
 
 /// A (non-extended) virtual key code.
@@ -41,15 +48,126 @@ const SHIFT_STATE_SHIFT: ShiftState = 1;
 const SHIFT_STATE_ALTGR: ShiftState = 2;
 const N_SHIFT_STATE: ShiftState = 4;
 
-/// Per-window keyboard state.
+/// Keyboard state, for one window or for all the windows on a thread.
+///
+/// Either model works. The keyboard layout is per thread, so a single state can
+/// be shared (for example, in an `Rc<RefCell<KeyboardState>>`) by all the
+/// windows on the thread, which avoids loading the layout for each one. The
+/// partial state of multi-message sequences is kept per window, and discarded
+/// when the window loses focus or is destroyed, so a sequence interrupted by a
+/// focus change can't leak into another window. Alternatively, create one per
+/// window.
+///
+/// In either case, pass `WM_KILLFOCUS` and `WM_NCDESTROY` to
+/// [`process_message`](#method.process_message) along with the keyboard messages.
 pub struct KeyboardState {
+    layout: LayoutSnapshot,
+    stashes: HashMap<HWND, Stash>,
+    last_event_time: u32,
+}
+
+/// Data from the earlier messages of a multi-message sequence.
+#[derive(Default)]
+struct Stash {
+    vk: Option<VkCode>,
+    utf16: Vec<u16>,
+    time: Option<u32>,
+}
+
+/// Mapping information from a keyboard layout.
+///
+/// This is the layout-dependent part of [`KeyboardState`], for use with
+/// [`translate_key_message`] by code that keeps its own keyboard state. It is
+/// a snapshot: load a new one on `WM_INPUTLANGCHANGE`.
+///
+/// [`KeyboardState`]: struct.KeyboardState.html
+/// [`translate_key_message`]: fn.translate_key_message.html
+pub struct LayoutSnapshot {
     hkl: HKL,
     // A map from (vk, is_shifted) to string val
     key_vals: HashMap<(VkCode, ShiftState), String>,
     dead_keys: HashSet<(VkCode, ShiftState)>,
     has_altgr: bool,
-    stash_vk: Option<VkCode>,
-    stash_utf16: Vec<u16>,
+}
+
+/// A key event translated from a single message, without reference to
+/// other messages.
+///
+/// Unlike the events from [`KeyboardState`], the key is never the text
+/// produced by the key (which arrives in subsequent `WM_CHAR` messages), but
+/// always the key's value in the layout for the current shift state.
+///
+/// [`KeyboardState`]: struct.KeyboardState.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawKeyEvent {
+    pub state: KeyState,
+    /// The virtual key code, distinguishing left and right modifier keys.
+    pub vk: i32,
+    /// The scan code, with 0x100 set for extended keys.
+    pub scan_code: u32,
+    pub code: Code,
+    pub key: Key,
+    pub location: Location,
+    pub modifiers: Modifiers,
+    pub repeat: bool,
+    /// Whether the message was `WM_SYSKEYDOWN` or `WM_SYSKEYUP`.
+    pub system: bool,
+}
+
+impl From<RawKeyEvent> for KeyboardEvent {
+    fn from(event: RawKeyEvent) -> KeyboardEvent {
+        KeyboardEvent {
+            state: event.state,
+            modifiers: event.modifiers,
+            code: event.code,
+            key: event.key,
+            is_composing: false,
+            location: event.location,
+            repeat: event.repeat,
+        }
+    }
+}
+
+/// Committed text, separate from the key events that produced it.
+///
+/// See [`KeyboardState::process_input`](struct.KeyboardState.html#method.process_input).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEvent {
+    pub text: String,
+    /// Whether the text is the result of an IME composition.
+    pub from_ime: bool,
+}
+
+/// Keyboard input, with key events and text separated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyboardInput {
+    Key(KeyboardEvent),
+    Text(TextEvent),
+}
+
+const GCS_RESULTSTR: DWORD = 0x800;
+
+/// Get the result string of an IME composition, if there is one.
+unsafe fn ime_result(hwnd: HWND) -> Option<String> {
+    let himc = ImmGetContext(hwnd);
+    if himc.is_null() {
+        return None;
+    }
+    let len = ImmGetCompositionStringW(himc, GCS_RESULTSTR, std::ptr::null_mut(), 0);
+    let result = if len > 0 {
+        let mut buf = vec![0u16; len as usize / 2];
+        ImmGetCompositionStringW(
+            himc,
+            GCS_RESULTSTR,
+            buf.as_mut_ptr() as LPVOID,
+            len as DWORD,
+        );
+        Some(String::from_utf16_lossy(&buf))
+    } else {
+        None
+    };
+    ImmReleaseContext(hwnd, himc);
+    result
 }
 
 /// Virtual key codes that are considered printable.
@@ -431,6 +549,176 @@ pub fn key_to_vk(key: &Key) -> Option<i32> {
     })
 }
 
+/// A lock key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockKey {
+    CapsLock,
+    NumLock,
+    ScrollLock,
+}
+
+impl LockKey {
+    fn vk(self) -> INT {
+        match self {
+            LockKey::CapsLock => VK_CAPITAL,
+            LockKey::NumLock => VK_NUMLOCK,
+            LockKey::ScrollLock => VK_SCROLL,
+        }
+    }
+
+    /// Whether the lock is on.
+    ///
+    /// This uses [`GetKeyState`], so it reflects the state as of the last message retrieved
+    /// by the calling thread, which is what should be shown in state indicators.
+    ///
+    /// [`GetKeyState`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getkeystate
+    pub fn is_on(self) -> bool {
+        unsafe { GetKeyState(self.vk()) & 1 != 0 }
+    }
+}
+
+/// Whether Caps Lock is on.
+pub fn caps_lock() -> bool {
+    LockKey::CapsLock.is_on()
+}
+
+/// Whether Num Lock is on.
+pub fn num_lock() -> bool {
+    LockKey::NumLock.is_on()
+}
+
+/// Whether Scroll Lock is on.
+pub fn scroll_lock() -> bool {
+    LockKey::ScrollLock.is_on()
+}
+
+/// Turn a lock key on or off.
+///
+/// If the key is not already in the requested state, this synthesizes a press and release
+/// of the key with [`SendInput`]. The new state is seen by [`LockKey::is_on`] only after the
+/// synthesized input has been processed.
+///
+/// Like all synthesized input, this is subject to UIPI, and fails if a window of a higher
+/// integrity level has focus.
+///
+/// [`SendInput`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-sendinput
+/// [`LockKey::is_on`]: enum.LockKey.html#method.is_on
+pub fn set_lock_key(key: LockKey, on: bool) -> Result<(), Error> {
+    if key.is_on() == on {
+        return Ok(());
+    }
+    let mut flags = 0;
+    if key == LockKey::NumLock {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    unsafe {
+        let mut inputs: [INPUT; 2] = mem::zeroed();
+        for (input, up) in inputs.iter_mut().zip(&[false, true]) {
+            input.type_ = INPUT_KEYBOARD;
+            *input.u.ki_mut() = KEYBDINPUT {
+                wVk: key.vk() as WORD,
+                wScan: 0,
+                dwFlags: if *up { flags | KEYEVENTF_KEYUP } else { flags },
+                time: 0,
+                dwExtraInfo: 0,
+            };
+        }
+        let sent = SendInput(
+            inputs.len() as UINT,
+            inputs.as_mut_ptr(),
+            mem::size_of::<INPUT>() as INT,
+        );
+        if sent as usize != inputs.len() {
+            return Err(Error::last_error());
+        }
+    }
+    Ok(())
+}
+
+/// Convert a scan code to the form expected by `MapVirtualKeyEx`.
+///
+/// Scan codes in this module are as reported in bits 16-24 of the `lparam` of key messages:
+/// the low byte is the scan code, and 0x100 is set for extended keys. The `0xE0` prefixed
+/// form is also accepted.
+fn scan_to_mapvk(scan_code: u32) -> u32 {
+    if scan_code & 0xff00 == 0xe000 {
+        scan_code
+    } else if scan_code & 0x100 != 0 {
+        0xe000 | (scan_code & 0xff)
+    } else {
+        scan_code & 0xff
+    }
+}
+
+fn layout_or_current(layout: Option<HKL>) -> HKL {
+    layout.unwrap_or_else(|| unsafe { GetKeyboardLayout(0) })
+}
+
+/// Convert a scan code to a virtual key code.
+///
+/// The scan code is in the format of bits 16-24 of the `lparam` of key messages, with 0x100
+/// set for extended keys. Scan codes identify physical keys and are independent of the
+/// keyboard layout, which makes them the right thing to store for game key bindings; this
+/// maps them through `layout`, or the current thread's layout if `None`.
+///
+/// Left and right variants of modifier keys are distinguished.
+pub fn scancode_to_vk(scan_code: u32, layout: Option<HKL>) -> Option<i32> {
+    let hkl = layout_or_current(layout);
+    let vk = unsafe { MapVirtualKeyExW(scan_to_mapvk(scan_code), MAPVK_VSC_TO_VK_EX, hkl) };
+    if vk == 0 {
+        None
+    } else {
+        Some(vk as i32)
+    }
+}
+
+/// Convert a virtual key code to a scan code.
+///
+/// The result is in the format of bits 16-24 of the `lparam` of key messages, with 0x100 set
+/// for extended keys. See [`scancode_to_vk`](fn.scancode_to_vk.html).
+pub fn vk_to_scancode(vk: i32, layout: Option<HKL>) -> Option<u32> {
+    let hkl = layout_or_current(layout);
+    let scan_code = unsafe { MapVirtualKeyExW(vk as UINT, MAPVK_VK_TO_VSC_EX, hkl) };
+    match scan_code {
+        0 => None,
+        _ if scan_code & 0xff00 != 0 => Some(0x100 | (scan_code & 0xff)),
+        _ => Some(scan_code),
+    }
+}
+
+/// The location of the key with the given scan code.
+///
+/// This distinguishes, for example, the numeric keypad Enter from the main one, and the
+/// left Shift from the right. See [`scancode_to_vk`](fn.scancode_to_vk.html) for the
+/// format of the scan code.
+pub fn scancode_to_key_location(scan_code: u32, layout: Option<HKL>) -> Option<Location> {
+    let vk = scancode_to_vk(scan_code, layout)?;
+    let is_extended = scan_to_mapvk(scan_code) & 0xff00 != 0;
+    Some(vk_to_location(vk as VkCode, is_extended))
+}
+
+/// The display name of the key with the given scan code, in the current layout.
+///
+/// This is the name shown to users, for example in a key binding UI, as returned by
+/// [`GetKeyNameText`]. It is localized to the keyboard layout's language. See
+/// [`scancode_to_vk`](fn.scancode_to_vk.html) for the format of the scan code.
+///
+/// [`GetKeyNameText`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getkeynametextw
+pub fn scancode_to_key_name(scan_code: u32) -> Option<String> {
+    let mapped = scan_to_mapvk(scan_code);
+    let mut lparam = (mapped & 0xff) << 16;
+    if mapped & 0xff00 != 0 {
+        lparam |= 1 << 24;
+    }
+    let mut buf = [0u16; 64];
+    let len = unsafe { GetKeyNameTextW(lparam as LONG, buf.as_mut_ptr(), buf.len() as INT) };
+    if len <= 0 {
+        None
+    } else {
+        Some(String::from_utf16_lossy(&buf[..len as usize]))
+    }
+}
+
 fn code_unit_to_key(code_unit: u32) -> Key {
     match code_unit {
         0x8 | 0x7F => Key::Backspace,
@@ -475,26 +763,13 @@ fn vk_to_location(vk: VkCode, is_extended: bool) -> Location {
 impl KeyboardState {
     /// Create a new keyboard state.
     ///
-    /// There should be one of these per window. It loads the current keyboard
-    /// layout and retains some mapping information from it.
+    /// It loads the current keyboard layout and retains some mapping
+    /// information from it.
     pub fn new() -> KeyboardState {
-        unsafe {
-            let hkl = GetKeyboardLayout(0);
-            let key_vals = HashMap::new();
-            let dead_keys = HashSet::new();
-            let stash_vk = None;
-            let stash_utf16 = Vec::new();
-            let has_altgr = false;
-            let mut result = KeyboardState {
-                hkl,
-                key_vals,
-                dead_keys,
-                has_altgr,
-                stash_vk,
-                stash_utf16,
-            };
-            result.load_keyboard_layout();
-            result
+        KeyboardState {
+            layout: LayoutSnapshot::current(),
+            stashes: HashMap::new(),
+            last_event_time: 0,
         }
     }
 
@@ -503,7 +778,8 @@ impl KeyboardState {
     /// This is the main interface point for generating cooked keyboard events
     /// from raw platform messages. It should be called for each relevant message,
     /// which comprises: `WM_KEYDOWN`, `WM_KEYUP`, `WM_CHAR`, `WM_SYSKEYDOWN`,
-    /// `WM_SYSKEYUP`, `WM_SYSCHAR`, and `WM_INPUTLANGCHANGE`.
+    /// `WM_SYSKEYUP`, `WM_SYSCHAR`, and `WM_INPUTLANGCHANGE`, along with
+    /// `WM_KILLFOCUS` and `WM_NCDESTROY` to keep per-window state consistent.
     ///
     /// As a general theory, many keyboard events generate a sequence of platform
     /// messages. In these cases, we stash information from all messages but the
@@ -540,71 +816,40 @@ impl KeyboardState {
         match msg {
             WM_KEYDOWN | WM_SYSKEYDOWN => {
                 //println!("keydown wparam {:x} lparam {:x}", wparam, lparam);
-                let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
-                let vk = self.refine_vk(wparam as u8, scan_code);
                 if is_last_message(hwnd, msg, lparam) {
-                    let modifiers = self.get_modifiers();
-                    let code = scan_to_code(scan_code);
-                    let key = vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers));
-                    let repeat = (lparam & 0x4000_0000) != 0;
-                    let is_extended = (lparam & 0x100_0000) != 0;
-                    let location = vk_to_location(vk, is_extended);
-                    let state = KeyState::Down;
-                    let event = KeyboardEvent {
-                        state,
-                        modifiers,
-                        code,
-                        key,
-                        is_composing: false,
-                        location,
-                        repeat,
-                    };
-                    Some(event)
+                    self.last_event_time = message_time();
+                    translate_key_message(hwnd, msg, wparam, lparam, &self.layout).map(Into::into)
                 } else {
-                    self.stash_vk = Some(vk);
+                    let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
+                    let stash = self.stashes.entry(hwnd).or_default();
+                    stash.vk = Some(self.layout.refine_vk(wparam as u8, scan_code));
+                    stash.time = Some(message_time());
                     None
                 }
             }
             WM_KEYUP | WM_SYSKEYUP => {
-                let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
-                let vk = self.refine_vk(wparam as u8, scan_code);
-                let modifiers = self.get_modifiers();
-                let code = scan_to_code(scan_code);
-                let key = vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers));
-                let repeat = false;
-                let is_extended = (lparam & 0x100_0000) != 0;
-                let location = vk_to_location(vk, is_extended);
-                let state = KeyState::Up;
-                let event = KeyboardEvent {
-                    state,
-                    modifiers,
-                    code,
-                    key,
-                    is_composing: false,
-                    location,
-                    repeat,
-                };
-                Some(event)
+                self.last_event_time = message_time();
+                translate_key_message(hwnd, msg, wparam, lparam, &self.layout).map(Into::into)
             }
             WM_CHAR | WM_SYSCHAR => {
                 //println!("char wparam {:x} lparam {:x}", wparam, lparam);
                 if is_last_message(hwnd, msg, lparam) {
-                    let stash_vk = self.stash_vk.take();
+                    let mut stash = self.stashes.remove(&hwnd).unwrap_or_default();
+                    self.last_event_time = stash.time.unwrap_or_else(message_time);
                     let modifiers = self.get_modifiers();
                     let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
-                    let vk = self.refine_vk(stash_vk.unwrap_or(0), scan_code);
+                    let vk = self.layout.refine_vk(stash.vk.unwrap_or(0), scan_code);
                     let code = scan_to_code(scan_code);
-                    let key = if self.stash_utf16.is_empty() && wparam < 0x20 {
-                        vk_to_key(vk).unwrap_or_else(|| self.get_base_key(vk, modifiers))
+                    let key = if stash.utf16.is_empty() && wparam < 0x20 {
+                        vk_to_key(vk).unwrap_or_else(|| self.layout.get_base_key(vk, modifiers))
                     } else {
-                        self.stash_utf16.push(wparam as u16);
-                        if let Ok(s) = String::from_utf16(&self.stash_utf16) {
+                        stash.utf16.push(wparam as u16);
+                        if let Ok(s) = String::from_utf16(&stash.utf16) {
                             Key::Character(s)
                         } else {
                             Key::Unidentified
                         }
                     };
-                    self.stash_utf16.clear();
                     let repeat = (lparam & 0x4000_0000) != 0;
                     let is_extended = (lparam & 0x100_0000) != 0;
                     let location = vk_to_location(vk, is_extended);
@@ -620,19 +865,110 @@ impl KeyboardState {
                     };
                     Some(event)
                 } else {
-                    self.stash_utf16.push(wparam as u16);
+                    self.stashes
+                        .entry(hwnd)
+                        .or_default()
+                        .utf16
+                        .push(wparam as u16);
                     None
                 }
             }
             WM_INPUTLANGCHANGE => {
-                self.hkl = lparam as HKL;
-                self.load_keyboard_layout();
+                // With a shared state, each window may report the same change.
+                if lparam as HKL != self.layout.hkl {
+                    self.layout = LayoutSnapshot::new(lparam as HKL);
+                }
+                None
+            }
+            WM_KILLFOCUS | WM_NCDESTROY => {
+                self.forget_window(hwnd);
                 None
             }
             _ => None,
         }
     }
 
+    /// Process one message, keeping key events and text separate.
+    ///
+    /// This is an alternative to [`process_message`](#method.process_message)
+    /// for applications (such as text editors) that handle shortcuts and text
+    /// insertion separately. Key messages produce `KeyboardInput::Key` events
+    /// immediately, with the key's value in the layout (never the composed
+    /// text). Text arrives separately as `KeyboardInput::Text`: from `WM_CHAR`
+    /// (with surrogate pairs combined, and control characters omitted, as they
+    /// are covered by key events), and from the result string of
+    /// `WM_IME_COMPOSITION`. In the latter case, the message must not be passed
+    /// to `DefWindowProc`, which would deliver the same text again as `WM_CHAR`.
+    ///
+    /// It should be called for the same messages as `process_message`, plus
+    /// `WM_IME_COMPOSITION`. `WM_SYSCHAR` (menu mnemonics) produces no text.
+    ///
+    /// # Safety
+    ///
+    /// As for `process_message`.
+    pub unsafe fn process_input(
+        &mut self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<KeyboardInput> {
+        match msg {
+            WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP => {
+                self.last_event_time = message_time();
+                let event = translate_key_message(hwnd, msg, wparam, lparam, &self.layout)?;
+                Some(KeyboardInput::Key(event.into()))
+            }
+            WM_CHAR => {
+                let unit = wparam as u16;
+                if unit < 0x20 || unit == 0x7f {
+                    return None;
+                }
+                self.last_event_time = message_time();
+                let stash = self.stashes.entry(hwnd).or_default();
+                stash.utf16.push(unit);
+                if (0xd800..0xdc00).contains(&unit) {
+                    // A high surrogate; wait for the low one.
+                    return None;
+                }
+                let text = String::from_utf16_lossy(&stash.utf16);
+                stash.utf16.clear();
+                Some(KeyboardInput::Text(TextEvent {
+                    text,
+                    from_ime: false,
+                }))
+            }
+            WM_SYSCHAR => None,
+            WM_IME_COMPOSITION if lparam as DWORD & GCS_RESULTSTR != 0 => {
+                self.last_event_time = message_time();
+                let text = ime_result(hwnd)?;
+                Some(KeyboardInput::Text(TextEvent {
+                    text,
+                    from_ime: true,
+                }))
+            }
+            _ => {
+                self.process_message(hwnd, msg, wparam, lparam);
+                None
+            }
+        }
+    }
+
+    /// Discard any partial state for a window.
+    ///
+    /// This is done by `process_message` on `WM_KILLFOCUS` and `WM_NCDESTROY`.
+    pub fn forget_window(&mut self, hwnd: HWND) {
+        self.stashes.remove(&hwnd);
+    }
+
+    /// The message time of the event most recently returned by `process_message`.
+    ///
+    /// A key down event may be returned while processing a later `WM_CHAR` message; this is
+    /// the time of the original key message. See [`message_time`](fn.message_time.html).
+    pub fn last_event_time(&self) -> u32 {
+        self.last_event_time
+    }
+
     /// Get the modifier state.
     ///
     /// This function is designed to be called from a message handler, and
@@ -645,6 +981,95 @@ impl KeyboardState {
     /// [`GetKeyState`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getkeystate
     /// [AltGr]: https://en.wikipedia.org/wiki/AltGr_key
     pub fn get_modifiers(&self) -> Modifiers {
+        self.layout.modifiers()
+    }
+
+    /// The keyboard layout in use.
+    pub fn layout(&self) -> &LayoutSnapshot {
+        &self.layout
+    }
+}
+
+/// Translate a key message into an event, using only that message.
+///
+/// This handles `WM_KEYDOWN`, `WM_KEYUP`, `WM_SYSKEYDOWN`, and `WM_SYSKEYUP`,
+/// returning `None` for other messages. Unlike [`KeyboardState`], it keeps no
+/// state between messages and doesn't look at the message queue, so character
+/// messages (including dead key sequences) are left to the caller.
+///
+/// The modifiers are read with `GetKeyState`, so reflect the state at the time
+/// of the message when called from the thread that received it.
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid `HWND`.
+///
+/// [`KeyboardState`]: struct.KeyboardState.html
+pub unsafe fn translate_key_message(
+    _hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    layout: &LayoutSnapshot,
+) -> Option<RawKeyEvent> {
+    let (state, system) = match msg {
+        WM_KEYDOWN => (KeyState::Down, false),
+        WM_SYSKEYDOWN => (KeyState::Down, true),
+        WM_KEYUP => (KeyState::Up, false),
+        WM_SYSKEYUP => (KeyState::Up, true),
+        _ => return None,
+    };
+    let scan_code = ((lparam & SCAN_MASK) >> 16) as u32;
+    let vk = layout.refine_vk(wparam as u8, scan_code);
+    let modifiers = layout.modifiers();
+    let is_extended = (lparam & 0x100_0000) != 0;
+    Some(RawKeyEvent {
+        state,
+        vk: vk as i32,
+        scan_code,
+        code: scan_to_code(scan_code),
+        key: vk_to_key(vk).unwrap_or_else(|| layout.get_base_key(vk, modifiers)),
+        location: vk_to_location(vk, is_extended),
+        modifiers,
+        repeat: state == KeyState::Down && (lparam & 0x4000_0000) != 0,
+        system,
+    })
+}
+
+impl LayoutSnapshot {
+    /// Load mapping information from a keyboard layout.
+    pub fn new(hkl: HKL) -> LayoutSnapshot {
+        let mut result = LayoutSnapshot {
+            hkl,
+            key_vals: HashMap::new(),
+            dead_keys: HashSet::new(),
+            has_altgr: false,
+        };
+        result.load_keyboard_layout();
+        result
+    }
+
+    /// Load the keyboard layout of the calling thread.
+    pub fn current() -> LayoutSnapshot {
+        LayoutSnapshot::new(unsafe { GetKeyboardLayout(0) })
+    }
+
+    /// The layout handle.
+    pub fn hkl(&self) -> HKL {
+        self.hkl
+    }
+
+    /// Whether the layout has an [AltGr] key.
+    ///
+    /// [AltGr]: https://en.wikipedia.org/wiki/AltGr_key
+    pub fn has_altgr(&self) -> bool {
+        self.has_altgr
+    }
+
+    /// Get the modifier state.
+    ///
+    /// See [`KeyboardState::get_modifiers`](struct.KeyboardState.html#method.get_modifiers).
+    pub fn modifiers(&self) -> Modifiers {
         unsafe {
             let mut modifiers = Modifiers::empty();
             for &(vk, modifier, mask) in MODIFIER_MAP {
@@ -775,3 +1200,204 @@ impl KeyboardState {
         }
     }
 }
+
+/// The key of a [`Shortcut`](struct.Shortcut.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShortcutKey {
+    /// The key that produces this character in the current layout.
+    ///
+    /// This is what users expect for mnemonic shortcuts such as Ctrl+Z: on a French AZERTY
+    /// layout it is the key labeled Z, not the one in the US Z position. When no key in the
+    /// layout produces the character (for example, a Latin letter in a Cyrillic layout), a
+    /// letter or digit falls back to the key with the corresponding virtual key code, which is
+    /// what other applications do.
+    ///
+    /// ASCII letters match regardless of case: `Char('Z')` is the same shortcut as
+    /// `Char('z')`, and Shift must be added to the modifiers explicitly, as in Ctrl+Shift+Z.
+    /// Other characters that need Shift (such as `'?'` on a US layout) include it.
+    Char(char),
+    /// The physical key, regardless of layout.
+    ///
+    /// This suits shortcuts chosen for their position, such as WASD movement.
+    Code(Code),
+}
+
+/// A keyboard shortcut.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    /// The modifiers, of which only Shift, Control, Alt, AltGraph, and Meta are compared.
+    pub modifiers: Modifiers,
+    pub key: ShortcutKey,
+}
+
+impl Shortcut {
+    pub fn new(modifiers: Modifiers, key: ShortcutKey) -> Shortcut {
+        Shortcut { modifiers, key }
+    }
+}
+
+/// The modifiers that distinguish shortcuts; lock keys are ignored.
+fn shortcut_modifiers(modifiers: Modifiers) -> Modifiers {
+    modifiers
+        & (Modifiers::SHIFT
+            | Modifiers::CONTROL
+            | Modifiers::ALT
+            | Modifiers::ALT_GRAPH
+            | Modifiers::META)
+}
+
+/// A binding, with its key resolved for the current layout.
+struct Binding<T> {
+    shortcut: Shortcut,
+    value: T,
+    /// For character shortcuts, the virtual key and any modifiers needed to produce the
+    /// character, or `None` if the layout has no such key.
+    resolved: Option<(i32, Modifiers)>,
+}
+
+/// Matches key events against shortcuts, tracking keyboard layout changes.
+///
+/// `TranslateAccelerator` matches virtual key codes, which are layout-dependent in ways
+/// that break shortcuts: a shortcut bound to `VK_Z` is on a different key in a French
+/// layout. Shortcuts here are bound by character or by physical key (see [`ShortcutKey`]);
+/// character shortcuts are resolved to keys for the current layout, and re-resolved on
+/// `WM_INPUTLANGCHANGE`.
+///
+/// [`ShortcutKey`]: enum.ShortcutKey.html
+pub struct ShortcutMatcher<T> {
+    layout: LayoutSnapshot,
+    bindings: Vec<Binding<T>>,
+    suppress_during_ime: bool,
+}
+
+impl<T> ShortcutMatcher<T> {
+    /// Create a matcher for the calling thread's keyboard layout.
+    pub fn new() -> ShortcutMatcher<T> {
+        ShortcutMatcher {
+            layout: LayoutSnapshot::current(),
+            bindings: Vec::new(),
+            suppress_during_ime: true,
+        }
+    }
+
+    /// Whether keys belonging to the IME are kept from matching shortcuts.
+    ///
+    /// The default is `true`: `process_message` matches nothing during an IME composition,
+    /// or for keys the IME processed. See [`is_composing`](fn.is_composing.html).
+    pub fn set_suppress_during_ime(&mut self, suppress: bool) {
+        self.suppress_during_ime = suppress;
+    }
+
+    /// Bind a shortcut to a value, which is returned when it matches.
+    ///
+    /// Earlier bindings take precedence.
+    pub fn bind(&mut self, shortcut: Shortcut, value: T) {
+        let resolved = self.resolve(&shortcut.key);
+        self.bindings.push(Binding {
+            shortcut,
+            value,
+            resolved,
+        });
+    }
+
+    /// Process a message, returning the value of a matching shortcut.
+    ///
+    /// Call this for `WM_KEYDOWN`, `WM_SYSKEYDOWN`, and `WM_INPUTLANGCHANGE` (other messages
+    /// are ignored). If it returns a value, the key message should be considered handled.
+    ///
+    /// # Safety
+    ///
+    /// The `hwnd` argument must be a valid `HWND`, and `lparam` a valid `HKL` in
+    /// `WM_INPUTLANGCHANGE`.
+    pub unsafe fn process_message(
+        &mut self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<&T> {
+        match msg {
+            WM_INPUTLANGCHANGE => {
+                self.set_layout(LayoutSnapshot::new(lparam as HKL));
+                None
+            }
+            WM_KEYDOWN | WM_SYSKEYDOWN => {
+                if self.suppress_during_ime && is_ime_key(hwnd, msg, wparam) {
+                    return None;
+                }
+                let event = translate_key_message(hwnd, msg, wparam, lparam, &self.layout)?;
+                self.match_event(&event)
+            }
+            _ => None,
+        }
+    }
+
+    /// Find the shortcut matching a key down event.
+    pub fn match_event(&self, event: &RawKeyEvent) -> Option<&T> {
+        if event.state != KeyState::Down {
+            return None;
+        }
+        let modifiers = shortcut_modifiers(event.modifiers);
+        // Shortcuts are bound to the generic modifier key codes.
+        let vk = match event.vk {
+            VK_LSHIFT | VK_RSHIFT => VK_SHIFT,
+            VK_LCONTROL | VK_RCONTROL => VK_CONTROL,
+            VK_LMENU | VK_RMENU => VK_MENU,
+            vk => vk,
+        };
+        self.bindings
+            .iter()
+            .find(|binding| match &binding.shortcut.key {
+                ShortcutKey::Char(_) => binding.resolved.is_some_and(|(key_vk, extra)| {
+                    key_vk == vk
+                        && modifiers == shortcut_modifiers(binding.shortcut.modifiers | extra)
+                }),
+                ShortcutKey::Code(code) => {
+                    *code == event.code
+                        && modifiers == shortcut_modifiers(binding.shortcut.modifiers)
+                }
+            })
+            .map(|binding| &binding.value)
+    }
+
+    /// Use a different keyboard layout, re-resolving character shortcuts.
+    pub fn set_layout(&mut self, layout: LayoutSnapshot) {
+        self.layout = layout;
+        for i in 0..self.bindings.len() {
+            self.bindings[i].resolved = self.resolve(&self.bindings[i].shortcut.key);
+        }
+    }
+
+    fn resolve(&self, key: &ShortcutKey) -> Option<(i32, Modifiers)> {
+        let c = match key {
+            // Shortcuts name letters in either case, but uppercase would add Shift.
+            ShortcutKey::Char(c) => c.to_ascii_lowercase(),
+            ShortcutKey::Code(_) => return None,
+        };
+        let wchar: u16 = (c as u32).try_into().ok()?;
+        let scan = unsafe { VkKeyScanExW(wchar, self.layout.hkl) };
+        if scan != -1 {
+            // The high byte is the shift state needed to produce the character.
+            let mut extra = Modifiers::empty();
+            if scan & 0x100 != 0 {
+                extra |= Modifiers::SHIFT;
+            }
+            if scan & 0x600 == 0x600 {
+                extra |= Modifiers::ALT_GRAPH;
+            }
+            return Some(((scan & 0xff) as i32, extra));
+        }
+        // Letter and digit virtual key codes are their uppercase ASCII values.
+        if c.is_ascii_alphanumeric() {
+            Some((c.to_ascii_uppercase() as i32, Modifiers::empty()))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Default for ShortcutMatcher<T> {
+    fn default() -> Self {
+        ShortcutMatcher::new()
+    }
+}
@@ -0,0 +1,178 @@
+//! Kiosk mode: a window locked to cover a monitor.
+//!
+//! Point-of-sale terminals, signage, and exhibition displays run one window covering a
+//! monitor, which the user must not be able to move, resize, minimize, or (usually) close.
+//! [`set_kiosk`] makes a window [fullscreen](fn.set_fullscreen.html) on a chosen monitor
+//! (which removes its caption and frame) and then holds it there: window menu commands that
+//! would move or resize it are refused, any change of its position or size is undone, and
+//! when the display configuration changes, it is moved to cover the monitor again. Keeping
+//! the user from switching to other applications is a matter of system configuration, such as
+//! assigned access, rather than of the window.
+//!
+//! [`set_kiosk`]: fn.set_kiosk.html
+
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::rc::Rc;
+
+use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::{HWND, RECT};
+use winapi::um::winuser::{
+    IsIconic, SC_CLOSE, SC_MAXIMIZE, SC_MINIMIZE, SC_MOVE, SC_RESTORE, SC_SIZE, SWP_NOMOVE,
+    SWP_NOSIZE, WINDOWPOS, WM_DISPLAYCHANGE, WM_MOVING, WM_SIZING, WM_SYSCOMMAND,
+    WM_WINDOWPOSCHANGING,
+};
+
+use crate::error::Error;
+use crate::fullscreen::{set_fullscreen, Fullscreen};
+use crate::geometry::Rect;
+use crate::monitor::Monitor;
+use crate::window::window_state;
+
+/// Kiosk mode settings.
+///
+/// See [`set_kiosk`](fn.set_kiosk.html) and [`WindowBuilder::kiosk`].
+///
+/// [`WindowBuilder::kiosk`]: struct.WindowBuilder.html#method.kiosk
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Kiosk {
+    monitor: Option<Monitor>,
+    allow_close: bool,
+}
+
+impl Kiosk {
+    /// Kiosk mode on the window's current monitor, without closing from the window menu or
+    /// Alt+F4.
+    pub fn new() -> Kiosk {
+        Default::default()
+    }
+
+    /// Cover a particular monitor.
+    ///
+    /// The monitor is remembered by its device name; if it is disconnected, the window moves
+    /// to the primary monitor, and back when it is reconnected.
+    pub fn monitor(mut self, monitor: Monitor) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// Allow the window to be closed with Alt+F4, the window menu, or the taskbar.
+    ///
+    /// Closing the window from the application (with `WM_CLOSE` or `DestroyWindow`) is always
+    /// possible.
+    pub fn allow_close(mut self, allow: bool) -> Self {
+        self.allow_close = allow;
+        self
+    }
+}
+
+/// The kiosk state of a window.
+pub(crate) struct KioskState {
+    allow_close: bool,
+    /// The device name of the chosen monitor, to find it again after display changes.
+    device: Option<OsString>,
+    /// The area covered, in screen coordinates.
+    rect: Cell<Rect>,
+}
+
+impl KioskState {
+    /// The monitor to cover: the chosen one if it is connected, and otherwise the primary.
+    unsafe fn monitor(&self) -> Monitor {
+        let device = match &self.device {
+            Some(device) => device,
+            None => return Monitor::primary(),
+        };
+        Monitor::all()
+            .into_iter()
+            .find(|monitor| monitor.device_name().as_ref() == Some(device))
+            .unwrap_or_else(Monitor::primary)
+    }
+
+    /// Cover the monitor again.
+    unsafe fn refit(&self, hwnd: HWND) -> Result<(), Error> {
+        let monitor = self.monitor();
+        self.rect.set(monitor.rect());
+        set_fullscreen(hwnd, Some(Fullscreen::Monitor(monitor)))
+    }
+
+    /// Enforce kiosk mode on a message, before the window procedure sees it.
+    ///
+    /// A `Some` result means the message was refused, and is the result to return.
+    pub(crate) unsafe fn process_message(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<LRESULT> {
+        match msg {
+            WM_SYSCOMMAND => match wparam & 0xfff0 {
+                SC_MOVE | SC_SIZE | SC_MINIMIZE | SC_MAXIMIZE | SC_RESTORE => Some(0),
+                SC_CLOSE if !self.allow_close => Some(0),
+                _ => None,
+            },
+            WM_MOVING | WM_SIZING => {
+                *(lparam as *mut RECT) = self.rect.get().into();
+                None
+            }
+            WM_WINDOWPOSCHANGING if IsIconic(hwnd) == 0 => {
+                let pos = &mut *(lparam as *mut WINDOWPOS);
+                let rect = self.rect.get();
+                if pos.flags & SWP_NOMOVE == 0 {
+                    pos.x = rect.left;
+                    pos.y = rect.top;
+                }
+                if pos.flags & SWP_NOSIZE == 0 {
+                    pos.cx = rect.width();
+                    pos.cy = rect.height();
+                }
+                None
+            }
+            WM_DISPLAYCHANGE => {
+                let _ = self.refit(hwnd);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Enter or leave kiosk mode.
+///
+/// With `Some`, the window becomes fullscreen on the kiosk's monitor, losing its caption and
+/// frame, and is held there: window menu commands (and their shortcuts, such as Alt+F4) that
+/// would move, resize, minimize, or close it are refused, changes of its position or size are
+/// undone, and on `WM_DISPLAYCHANGE` it is moved to cover the monitor again. This uses the
+/// window's own messages, without global hooks, so the user can still switch to other
+/// windows. With `None`, the window leaves fullscreen, restoring its frame and placement.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference, to a top-level window.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_kiosk(hwnd: HWND, kiosk: Option<Kiosk>) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    match kiosk {
+        Some(kiosk) => {
+            let monitor = kiosk.monitor.unwrap_or_else(|| Monitor::from_window(hwnd));
+            let kiosk = Rc::new(KioskState {
+                allow_close: kiosk.allow_close,
+                device: monitor.device_name(),
+                rect: Cell::new(monitor.rect()),
+            });
+            // Installed before moving into place, so that nothing moves it elsewhere meanwhile.
+            *state.kiosk.borrow_mut() = Some(kiosk.clone());
+            kiosk.refit(hwnd)
+        }
+        None => {
+            // Removed first, as it would undo the move back.
+            *state.kiosk.borrow_mut() = None;
+            set_fullscreen(hwnd, None)
+        }
+    }
+}
@@ -1,14 +1,163 @@
 //! Window creation for Windows.
 
+#[macro_use]
+mod util;
+
+mod advanced_color;
+mod app;
+mod appcommand;
+mod capture;
+mod close;
+mod confirm_close;
+mod cursor;
+#[cfg(feature = "dcomp")]
+mod dcomp;
+mod devices;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
+mod dpi;
+mod dwm;
 mod error;
+mod events;
+mod font;
+mod fullscreen;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod geometry;
+mod hit_test;
+mod hwnd;
+mod icon;
+mod idle;
+mod ime;
+#[cfg(feature = "islands")]
+mod island;
+mod key_hold;
 #[cfg(feature = "kb")]
 mod keyboard;
+mod kiosk;
+mod message;
+pub mod metrics;
+mod monitor;
+mod mouse;
+mod owned;
+mod paint;
+mod pointer;
+mod popup;
+pub mod prelude;
+mod reentrancy;
+mod render_sync;
 mod runloop;
+mod shutdown;
+mod snap;
+mod style;
+mod swapchain;
+mod tear_off;
+mod threads;
+mod touch;
+mod ui_thread;
+mod uipi;
+mod uistate;
+pub mod version;
+mod vsync;
+#[cfg(feature = "webview2")]
+mod webview;
 mod window;
+mod window_data;
+mod wnd_cell;
+mod zorder;
 
+pub use advanced_color::AdvancedColor;
+pub use app::{run, Context, ControlFlow};
+pub use appcommand::{AppCommand, AppCommandDevice, AppCommandEvent, APPCOMMAND_HANDLED};
+pub use capture::{capture_window, Image};
+pub use close::{CloseRequest, CloseToken};
+pub use confirm_close::{confirm_close, CloseConfirmation, CloseDecision, DocumentState};
+pub use cursor::{
+    client_cursor_pos, cursor_pos, set_client_cursor_pos, set_cursor, set_cursor_map,
+    set_cursor_pos, set_window_cursor, BusyCursor, Cursor, CursorIcon, CursorMap,
+};
+#[cfg(feature = "dcomp")]
+pub use dcomp::create_composition_target;
+pub use devices::{
+    input_devices, register_device_notifications, DeviceEvent, DeviceId, DeviceKind, DeviceTracker,
+    InputDevice,
+};
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::{
+    is_proc_reentrant, proc_depth, proc_strong_count, LeakCheck, LeakReport, LiveWindow,
+};
+pub use dpi::{set_process_dpi_awareness, Dpi, DpiAwareness, ScaledCursor, WindowIcons};
+pub use dwm::{
+    cloaked_state, set_backdrop, set_cloaked, set_corner_preference, set_dark_mode_titlebar,
+    set_drop_shadow, AppTheme, Backdrop, Cloaked, CornerPreference,
+};
 pub use error::Error;
-pub use runloop::runloop;
-pub use window::{WindowBuilder, WindowClass, WindowClassBuilder, WindowProc};
+pub use events::{
+    is_minimized, is_visible, window_icc_profile_path, DisplayMode, Event, EventHandler, EventProc,
+    KeyEvent, MouseButton, MouseButtonEvent, SizeState, VisibilityReason,
+};
+pub use font::{set_font_for_children, system_message_font, Font};
+pub use fullscreen::{
+    is_fullscreen, refit_fullscreen, set_fullscreen, toggle_fullscreen, Fullscreen,
+};
+#[cfg(feature = "gamepad")]
+pub use gamepad::{GamepadAxis, GamepadButton, GamepadEvent, GamepadState, Gamepads};
+pub use geometry::{Point, Rect};
+pub use hit_test::{
+    clear_hit_test, set_drag_regions, set_hit_test, set_title_bar, DragRegions, HitTest, TitleBar,
+};
+pub use hwnd::Hwnd;
+pub use icon::{set_icon, set_small_icon, Icon};
+pub use idle::{idle_duration, last_input_time, IdleEvent, IdleScope, IdleTracker};
+pub use ime::is_composing;
+#[cfg(feature = "islands")]
+pub use island::{create_desktop_window_target, XamlIsland};
+pub use key_hold::{KeyHoldEvent, KeyHoldScope, KeyHoldTracker};
+pub use kiosk::{set_kiosk, Kiosk};
+pub use message::{elapsed_ms, message_name, message_pos, message_time, MessageCtx, MessageSource};
+pub use monitor::Monitor;
+pub use mouse::{
+    lparam_to_point, ButtonState, MouseEvent, WheelAxis, WheelEvent, XButton, XButtonEvent,
+};
+pub use owned::{add_owned_window, remove_owned_window};
+pub use paint::{update_rects, Paint};
+pub use pointer::{PenState, PointerEvent, PointerKind, PointerPhase};
+pub use popup::set_light_dismiss;
+pub use reentrancy::{Entered, ReentrancyGuard};
+pub use render_sync::{RenderFrame, RenderHandle, RenderSync};
+pub use runloop::{runloop, set_accelerator, Runloop};
+pub use shutdown::shutdown;
+pub use snap::{set_snap, Snap};
+pub use style::{WindowExStyle, WindowStyle};
+pub use swapchain::{clear_resize_for_swapchain, on_resize_for_swapchain, SwapchainSize};
+pub use tear_off::{begin_caption_drag, detect_drag, tear_off, TabDrag, TabDragState};
+pub use threads::{thread_windows, window_thread_id, Dispatcher, InputAttachment};
+pub use touch::{
+    hide_touch_keyboard, is_touch_keyboard_visible, show_touch_keyboard, InputPaneEvent,
+    InputPaneTracker,
+};
+pub use ui_thread::UiThreadToken;
+pub use uipi::{
+    allow_drop_files_from_lower_integrity, allow_message_from_lower_integrity,
+    disallow_message_from_lower_integrity, reset_message_filter,
+};
+pub use uistate::{change_ui_state, KeyboardCues, UiStateAction, UiStateChange};
+#[cfg(feature = "dxgi")]
+pub use vsync::VblankWaiter;
+pub use vsync::{qpc_to_duration, wait_for_vblank, FrameTiming};
+#[cfg(feature = "webview2")]
+pub use webview::{WebMessage, WebView, WebViewHandler};
+pub use window::{
+    set_window_title, window_title, FnMutProc, OwnedWindowClass, ShowCommand, StateStorage,
+    WindowBuilder, WindowClass, WindowClassBuilder, WindowProc,
+};
+pub use window_data::{get_window_data, remove_window_data, set_window_data};
+pub use wnd_cell::{BorrowConflict, ConflictPolicy, WndCell, WndRef, WndRefMut};
+pub use zorder::{bring_to_front, is_always_on_top, set_always_on_top};
 
 #[cfg(feature = "kb")]
-pub use keyboard::{key_to_vk, KeyboardState};
+pub use keyboard::{
+    caps_lock, key_to_vk, num_lock, scancode_to_key_location, scancode_to_key_name, scancode_to_vk,
+    scroll_lock, set_lock_key, translate_key_message, vk_to_scancode, KeyboardInput, KeyboardState,
+    LayoutSnapshot, LockKey, RawKeyEvent, Shortcut, ShortcutKey, ShortcutMatcher, TextEvent,
+};
@@ -0,0 +1,197 @@
+//! Information about the message being processed.
+//!
+//! Each message retrieved from the queue carries the time it was posted and the cursor
+//! position at that time. These should be used in preference to the current time and cursor
+//! position for anything timing-sensitive (input latency measurement, gesture velocity,
+//! double-click detection), as the message may have waited in the queue for some time
+//! before being processed.
+//!
+//! [`MessageCtx`] collects this information, along with how the message was delivered.
+//!
+//! [`MessageCtx`]: struct.MessageCtx.html
+
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{LRESULT, UINT};
+use winapi::um::winuser::{
+    GetMessagePos, GetMessageTime, InSendMessageEx, ReplyMessage, ISMEX_CALLBACK, ISMEX_NOTIFY,
+    ISMEX_REPLIED, ISMEX_SEND, WM_ACTIVATE, WM_ACTIVATEAPP, WM_APP, WM_CHAR, WM_CLOSE, WM_COMMAND,
+    WM_CREATE, WM_DESTROY, WM_DPICHANGED, WM_ENTERSIZEMOVE, WM_ERASEBKGND, WM_EXITSIZEMOVE,
+    WM_GETMINMAXINFO, WM_KEYDOWN, WM_KEYUP, WM_KILLFOCUS, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_MOUSELEAVE, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOVE, WM_NCACTIVATE, WM_NCCALCSIZE, WM_NCCREATE,
+    WM_NCDESTROY, WM_NCHITTEST, WM_PAINT, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETCURSOR,
+    WM_SETFOCUS, WM_SHOWWINDOW, WM_SIZE, WM_SIZING, WM_SYSCHAR, WM_SYSCOMMAND, WM_SYSKEYDOWN,
+    WM_SYSKEYUP, WM_TIMER, WM_USER, WM_WINDOWPOSCHANGED, WM_WINDOWPOSCHANGING,
+};
+
+use crate::geometry::Point;
+use crate::mouse::lparam_to_point;
+
+/// The time of the message being processed, in milliseconds since system start.
+///
+/// This is the value of [`GetMessageTime`]. It wraps around about every 49.7 days; use
+/// [`elapsed_ms`] to compute differences.
+///
+/// [`GetMessageTime`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getmessagetime
+/// [`elapsed_ms`]: fn.elapsed_ms.html
+pub fn message_time() -> u32 {
+    unsafe { GetMessageTime() as u32 }
+}
+
+/// The cursor position when the message being processed was posted, in screen coordinates.
+///
+/// This is the value of [`GetMessagePos`].
+///
+/// [`GetMessagePos`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getmessagepos
+pub fn message_pos() -> Point {
+    unsafe { lparam_to_point(GetMessagePos() as _) }
+}
+
+/// The number of milliseconds from `earlier` to `later`, both message times.
+///
+/// This accounts for the wraparound of message times.
+pub fn elapsed_ms(earlier: u32, later: u32) -> u32 {
+    later.wrapping_sub(earlier)
+}
+
+/// How the message being processed was delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageSource {
+    /// Posted to the queue, or sent from the same thread.
+    ///
+    /// [`InSendMessageEx`] can't distinguish a message sent from the same thread from a
+    /// posted one; in both cases, no other thread is waiting on the result.
+    ///
+    /// [`InSendMessageEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-insendmessageex
+    PostedOrSameThread,
+    /// Sent from another thread with `SendMessage`, which is blocked until the message is
+    /// processed (or [`reply`] is called).
+    ///
+    /// [`reply`]: struct.MessageCtx.html#method.reply
+    Sent,
+    /// Sent from another thread with `SendNotifyMessage`; the sender is not waiting.
+    Notify,
+    /// Sent from another thread with `SendMessageCallback`; the sender is not waiting.
+    Callback,
+}
+
+/// Context for the message being processed.
+///
+/// Call [`current`] from the window procedure. A handler that may block (for example, by
+/// showing a modal dialog or waiting on another thread) should check [`source`] first: a
+/// message sent from another thread blocks that thread, which risks deadlock, and some
+/// operations (such as COM calls into other apartments) fail with
+/// `RPC_E_CANTCALLOUT_ININPUTSYNCCALL` while handling one.
+///
+/// [`current`]: #method.current
+/// [`source`]: #structfield.source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MessageCtx {
+    /// The message time; see [`message_time`](fn.message_time.html).
+    pub time: u32,
+    /// The cursor position when the message was posted; see
+    /// [`message_pos`](fn.message_pos.html).
+    pub pos: Point,
+    pub source: MessageSource,
+    /// Whether [`reply`](#method.reply) has already been called for this message.
+    pub replied: bool,
+}
+
+impl MessageCtx {
+    /// The context for the message being processed by the calling thread.
+    pub fn current() -> MessageCtx {
+        let flags = unsafe { InSendMessageEx(null_mut()) };
+        let source = if flags & ISMEX_NOTIFY != 0 {
+            MessageSource::Notify
+        } else if flags & ISMEX_CALLBACK != 0 {
+            MessageSource::Callback
+        } else if flags & ISMEX_SEND != 0 {
+            MessageSource::Sent
+        } else {
+            MessageSource::PostedOrSameThread
+        };
+        MessageCtx {
+            time: message_time(),
+            pos: message_pos(),
+            source,
+            replied: flags & ISMEX_REPLIED != 0,
+        }
+    }
+
+    /// Whether another thread is blocked waiting for the result of this message.
+    pub fn sender_is_waiting(&self) -> bool {
+        self.source == MessageSource::Sent && !self.replied
+    }
+
+    /// Reply to a message sent from another thread, unblocking the sender.
+    ///
+    /// This is the value the sender's `SendMessage` returns; the window procedure's return
+    /// value is then ignored. It is a good idea before doing anything lengthy in response to
+    /// a sent message. Returns `false` if the message was not sent from another thread.
+    ///
+    /// See [`ReplyMessage`].
+    ///
+    /// [`ReplyMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-replymessage
+    pub fn reply(&mut self, result: LRESULT) -> bool {
+        let replied = unsafe { ReplyMessage(result) != 0 };
+        if replied {
+            self.replied = true;
+        }
+        replied
+    }
+}
+
+/// The name of a window message, for diagnostics.
+///
+/// This covers the messages most often seen in window procedures. Others are formatted as
+/// hexadecimal, or as an offset from `WM_USER` or `WM_APP`.
+pub fn message_name(msg: UINT) -> String {
+    let name = match msg {
+        WM_ACTIVATE => "WM_ACTIVATE",
+        WM_ACTIVATEAPP => "WM_ACTIVATEAPP",
+        WM_CHAR => "WM_CHAR",
+        WM_CLOSE => "WM_CLOSE",
+        WM_COMMAND => "WM_COMMAND",
+        WM_CREATE => "WM_CREATE",
+        WM_DESTROY => "WM_DESTROY",
+        WM_DPICHANGED => "WM_DPICHANGED",
+        WM_ENTERSIZEMOVE => "WM_ENTERSIZEMOVE",
+        WM_ERASEBKGND => "WM_ERASEBKGND",
+        WM_EXITSIZEMOVE => "WM_EXITSIZEMOVE",
+        WM_GETMINMAXINFO => "WM_GETMINMAXINFO",
+        WM_KEYDOWN => "WM_KEYDOWN",
+        WM_KEYUP => "WM_KEYUP",
+        WM_KILLFOCUS => "WM_KILLFOCUS",
+        WM_LBUTTONDOWN => "WM_LBUTTONDOWN",
+        WM_LBUTTONUP => "WM_LBUTTONUP",
+        WM_MOUSELEAVE => "WM_MOUSELEAVE",
+        WM_MOUSEMOVE => "WM_MOUSEMOVE",
+        WM_MOUSEWHEEL => "WM_MOUSEWHEEL",
+        WM_MOVE => "WM_MOVE",
+        WM_NCACTIVATE => "WM_NCACTIVATE",
+        WM_NCCALCSIZE => "WM_NCCALCSIZE",
+        WM_NCCREATE => "WM_NCCREATE",
+        WM_NCDESTROY => "WM_NCDESTROY",
+        WM_NCHITTEST => "WM_NCHITTEST",
+        WM_PAINT => "WM_PAINT",
+        WM_QUIT => "WM_QUIT",
+        WM_RBUTTONDOWN => "WM_RBUTTONDOWN",
+        WM_RBUTTONUP => "WM_RBUTTONUP",
+        WM_SETCURSOR => "WM_SETCURSOR",
+        WM_SETFOCUS => "WM_SETFOCUS",
+        WM_SHOWWINDOW => "WM_SHOWWINDOW",
+        WM_SIZE => "WM_SIZE",
+        WM_SIZING => "WM_SIZING",
+        WM_SYSCHAR => "WM_SYSCHAR",
+        WM_SYSCOMMAND => "WM_SYSCOMMAND",
+        WM_SYSKEYDOWN => "WM_SYSKEYDOWN",
+        WM_SYSKEYUP => "WM_SYSKEYUP",
+        WM_TIMER => "WM_TIMER",
+        WM_WINDOWPOSCHANGED => "WM_WINDOWPOSCHANGED",
+        WM_WINDOWPOSCHANGING => "WM_WINDOWPOSCHANGING",
+        WM_APP..=0xbfff => return format!("WM_APP+{}", msg - WM_APP),
+        WM_USER..=0x7fff => return format!("WM_USER+{}", msg - WM_USER),
+        _ => return format!("0x{:04x}", msg),
+    };
+    name.to_string()
+}
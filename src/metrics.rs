@@ -0,0 +1,254 @@
+//! System metrics and parameters.
+//!
+//! Sizes of standard window elements and user input settings, from [`GetSystemMetrics`] and
+//! [`SystemParametersInfo`]. Sizes depend on the DPI, so they take a [`Dpi`]; with per-monitor
+//! DPI awareness, pass the window's DPI rather than the system DPI. This uses the `ForDpi`
+//! variants of the functions where available (Windows 10 1607 and later), and otherwise
+//! scales the system DPI values.
+//!
+//! [`GetSystemMetrics`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsystemmetrics
+//! [`SystemParametersInfo`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-systemparametersinfow
+//! [`Dpi`]: ../struct.Dpi.html
+
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{BOOL, LPVOID, UINT};
+use winapi::shared::windef::{HWND, RECT};
+use winapi::um::winbase::MulDiv;
+use winapi::um::wingdi::{
+    CreateFontIndirectW, DeleteObject, GetTextExtentPoint32W, GetTextMetricsW, SelectObject,
+    LOGFONTW,
+};
+use winapi::um::winuser::{
+    GetCaretBlinkTime, GetDC, GetDialogBaseUnits, GetDoubleClickTime, ReleaseDC,
+    SystemParametersInfoW, NONCLIENTMETRICSW, SM_CXBORDER, SM_CXDOUBLECLK, SM_CXDRAG,
+    SM_CXPADDEDBORDER, SM_CXSIZEFRAME, SM_CXVSCROLL, SM_CYBORDER, SM_CYCAPTION, SM_CYDOUBLECLK,
+    SM_CYDRAG, SM_CYHSCROLL, SM_CYSIZEFRAME, SPI_GETMOUSEHOVERTIME, SPI_GETNONCLIENTMETRICS,
+    SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES, SPI_GETWORKAREA, WHEEL_PAGESCROLL,
+};
+
+use crate::dpi::Dpi;
+use crate::error::Error;
+use crate::geometry::Rect;
+
+/// The blink time meaning the caret doesn't blink; this is `INFINITE` in winbase.
+const NO_BLINK: u32 = !0;
+
+/// Get a value with `SystemParametersInfoW`.
+unsafe fn get_parameter<T>(action: UINT) -> Option<T> {
+    let mut value: T = mem::zeroed();
+    if SystemParametersInfoW(action, 0, &mut value as *mut T as LPVOID, 0) == 0 {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// The height of a standard title bar, excluding the window border.
+pub fn caption_height(dpi: Dpi) -> i32 {
+    dpi.system_metric(SM_CYCAPTION)
+}
+
+/// The width and height of the sizing border of a resizable window, including padding.
+///
+/// This is the thickness of the area outside the client area that resizes the window, which
+/// a window with a custom frame should reproduce.
+pub fn frame_size(dpi: Dpi) -> (i32, i32) {
+    let padding = dpi.system_metric(SM_CXPADDEDBORDER);
+    (
+        dpi.system_metric(SM_CXSIZEFRAME) + padding,
+        dpi.system_metric(SM_CYSIZEFRAME) + padding,
+    )
+}
+
+/// The width and height of a thin window border.
+pub fn border_size(dpi: Dpi) -> (i32, i32) {
+    (
+        dpi.system_metric(SM_CXBORDER),
+        dpi.system_metric(SM_CYBORDER),
+    )
+}
+
+/// The width of a vertical scroll bar, and the height of a horizontal one.
+pub fn scrollbar_size(dpi: Dpi) -> (i32, i32) {
+    (
+        dpi.system_metric(SM_CXVSCROLL),
+        dpi.system_metric(SM_CYHSCROLL),
+    )
+}
+
+/// How far the mouse must move with a button down, horizontally and vertically, before a drag
+/// starts.
+pub fn drag_threshold(dpi: Dpi) -> (i32, i32) {
+    (dpi.system_metric(SM_CXDRAG), dpi.system_metric(SM_CYDRAG))
+}
+
+/// The size of the area within which a second click counts as a double-click.
+pub fn double_click_size(dpi: Dpi) -> (i32, i32) {
+    (
+        dpi.system_metric(SM_CXDOUBLECLK),
+        dpi.system_metric(SM_CYDOUBLECLK),
+    )
+}
+
+/// The maximum time between the clicks of a double-click, in milliseconds.
+pub fn double_click_time() -> u32 {
+    unsafe { GetDoubleClickTime() }
+}
+
+/// How long the mouse must stay still to count as hovering, in milliseconds.
+///
+/// This is the default for `TrackMouseEvent` with `TME_HOVER`, and suits tooltips.
+pub fn hover_time() -> u32 {
+    unsafe { get_parameter::<UINT>(SPI_GETMOUSEHOVERTIME).unwrap_or(400) }
+}
+
+/// The caret blink interval, in milliseconds, or `None` if the caret shouldn't blink.
+pub fn caret_blink_time() -> Option<u32> {
+    match unsafe { GetCaretBlinkTime() } {
+        NO_BLINK => None,
+        // Zero means the call failed.
+        0 => Some(530),
+        time => Some(time),
+    }
+}
+
+/// The number of lines to scroll per wheel notch (a delta of `WHEEL_DELTA`), or `None` if
+/// the user chose to scroll a page at a time.
+pub fn wheel_scroll_lines() -> Option<u32> {
+    match unsafe { get_parameter::<UINT>(SPI_GETWHEELSCROLLLINES) } {
+        Some(WHEEL_PAGESCROLL) => None,
+        Some(lines) => Some(lines),
+        None => Some(3),
+    }
+}
+
+/// The number of characters to scroll per horizontal wheel notch.
+pub fn wheel_scroll_chars() -> u32 {
+    unsafe { get_parameter::<UINT>(SPI_GETWHEELSCROLLCHARS).unwrap_or(3) }
+}
+
+/// The work area of the primary monitor: the screen excluding the taskbar and other docked
+/// toolbars.
+///
+/// For other monitors, use [`Monitor::work_area`](../struct.Monitor.html#method.work_area).
+pub fn work_area() -> Rect {
+    unsafe {
+        get_parameter::<RECT>(SPI_GETWORKAREA)
+            .map(Rect::from)
+            .unwrap_or_default()
+    }
+}
+
+/// The font used in message boxes and status bars, which is the usual UI font.
+///
+/// The height of the returned font is for the given DPI.
+pub fn message_font(dpi: Dpi) -> Result<LOGFONTW, Error> {
+    unsafe {
+        let mut metrics: NONCLIENTMETRICSW = mem::zeroed();
+        metrics.cbSize = mem::size_of::<NONCLIENTMETRICSW>() as UINT;
+        let ptr = &mut metrics as *mut NONCLIENTMETRICSW as LPVOID;
+        if let Some(spi_for_dpi) = dynamic_fn!(
+            "user32.dll",
+            SystemParametersInfoForDpi: fn(UINT, UINT, LPVOID, UINT, UINT) -> BOOL
+        ) {
+            if spi_for_dpi(SPI_GETNONCLIENTMETRICS, metrics.cbSize, ptr, 0, dpi.0) == 0 {
+                return Err(Error::last_error());
+            }
+            return Ok(metrics.lfMessageFont);
+        }
+        if SystemParametersInfoW(SPI_GETNONCLIENTMETRICS, metrics.cbSize, ptr, 0) == 0 {
+            return Err(Error::last_error());
+        }
+        let mut font = metrics.lfMessageFont;
+        let system = Dpi::system().0.max(1);
+        font.lfHeight = (font.lfHeight as i64 * dpi.0 as i64 / system as i64) as i32;
+        Ok(font)
+    }
+}
+
+/// The text used to measure the average character width, as `MapDialogRect` does.
+const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The horizontal and vertical base units of a font, in pixels.
+///
+/// These are the average character width and the character height, the units in which
+/// [dialog units] are defined: a horizontal dialog unit is a quarter of the horizontal base
+/// unit, and a vertical one an eighth of the vertical base unit. The average width is
+/// measured as `MapDialogRect` does, which differs from `tmAveCharWidth`.
+///
+/// [dialog units]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getdialogbaseunits
+pub fn font_base_units(font: &LOGFONTW) -> Result<(i32, i32), Error> {
+    unsafe {
+        let hfont = CreateFontIndirectW(font);
+        if hfont.is_null() {
+            return Err(Error::last_error());
+        }
+        let hdc = GetDC(null_mut());
+        if hdc.is_null() {
+            let err = Error::last_error();
+            DeleteObject(hfont as _);
+            return Err(err);
+        }
+        let old_font = SelectObject(hdc, hfont as _);
+        let mut metrics = mem::zeroed();
+        let mut size = mem::zeroed();
+        let alphabet: Vec<u16> = ALPHABET.encode_utf16().collect();
+        let ok = GetTextMetricsW(hdc, &mut metrics) != 0
+            && GetTextExtentPoint32W(hdc, alphabet.as_ptr(), alphabet.len() as i32, &mut size) != 0;
+        let result = if ok {
+            // The rounded average over both cases.
+            Ok(((size.cx / 26 + 1) / 2, metrics.tmHeight))
+        } else {
+            Err(Error::last_error())
+        };
+        SelectObject(hdc, old_font);
+        ReleaseDC(null_mut(), hdc);
+        DeleteObject(hfont as _);
+        result
+    }
+}
+
+/// The dialog base units for the [message font](fn.message_font.html) at the given DPI.
+///
+/// These suit laying out controls in the UI font; see [`font_base_units`].
+///
+/// [`font_base_units`]: fn.font_base_units.html
+pub fn dialog_base_units(dpi: Dpi) -> Result<(i32, i32), Error> {
+    font_base_units(&message_font(dpi)?)
+}
+
+/// The dialog base units of the system font, at the system DPI, from [`GetDialogBaseUnits`].
+///
+/// This is what dialog templates without a font use. The system font is a legacy bitmap font,
+/// so applications should usually use [`dialog_base_units`] instead.
+///
+/// [`GetDialogBaseUnits`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getdialogbaseunits
+/// [`dialog_base_units`]: fn.dialog_base_units.html
+pub fn system_dialog_base_units() -> (i32, i32) {
+    let units = unsafe { GetDialogBaseUnits() };
+    ((units & 0xffff) as i32, ((units >> 16) & 0xffff) as i32)
+}
+
+/// Convert dialog units to pixels, given the base units from [`font_base_units`] or
+/// [`dialog_base_units`].
+///
+/// [`font_base_units`]: fn.font_base_units.html
+/// [`dialog_base_units`]: fn.dialog_base_units.html
+pub fn scale_dialog_units(base_units: (i32, i32), du: (i32, i32)) -> (i32, i32) {
+    unsafe { (MulDiv(du.0, base_units.0, 4), MulDiv(du.1, base_units.1, 8)) }
+}
+
+/// Convert dialog units to pixels for a window, using the message font at the window's DPI.
+///
+/// This lays out controls as a dialog using the message font would, and follows the DPI and
+/// the user's font settings.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn dlu_to_pixels(hwnd: HWND, du: (i32, i32)) -> Result<(i32, i32), Error> {
+    let base_units = dialog_base_units(Dpi::for_window(hwnd))?;
+    Ok(scale_dialog_units(base_units, du))
+}
@@ -0,0 +1,238 @@
+//! Monitor queries.
+
+use std::ffi::OsString;
+use std::mem;
+use std::path::PathBuf;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, MAX_PATH, TRUE, UINT};
+use winapi::shared::windef::{HDC, HMONITOR, HWND, LPRECT, POINT};
+use winapi::um::shellapi::{
+    SHAppBarMessage, ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_GETAUTOHIDEBAREX, APPBARDATA,
+};
+use winapi::um::wingdi::{CreateDCW, DeleteDC, GetICMProfileW, DEVMODEW};
+use winapi::um::winuser::{
+    EnumDisplayMonitors, EnumDisplaySettingsW, GetMonitorInfoW, MonitorFromPoint,
+    MonitorFromWindow, ENUM_CURRENT_SETTINGS, MONITORINFO, MONITORINFOEXW, MONITORINFOF_PRIMARY,
+    MONITOR_DEFAULTTONEAREST, MONITOR_DEFAULTTOPRIMARY,
+};
+
+use wio::wide::FromWide;
+
+use crate::geometry::{Point, Rect};
+
+/// Whether the monitor has an auto-hide app bar (such as the taskbar) on the given edge.
+fn has_auto_hide_bar(monitor: Rect, edge: UINT) -> bool {
+    unsafe {
+        let mut data: APPBARDATA = mem::zeroed();
+        data.cbSize = mem::size_of::<APPBARDATA>() as DWORD;
+        data.uEdge = edge;
+        data.rc = monitor.into();
+        SHAppBarMessage(ABM_GETAUTOHIDEBAREX, &mut data) != 0
+    }
+}
+
+/// A display monitor.
+///
+/// This is a lightweight wrapper around an `HMONITOR`. Monitor handles are not reference
+/// counted; a handle becomes stale when the display configuration changes (see
+/// [`WM_DISPLAYCHANGE`]), in which case queries return `None` or default values.
+///
+/// [`WM_DISPLAYCHANGE`]: https://docs.microsoft.com/en-us/windows/win32/gdi/wm-displaychange
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Monitor(HMONITOR);
+
+impl Monitor {
+    /// The primary monitor.
+    pub fn primary() -> Monitor {
+        unsafe {
+            Monitor(MonitorFromPoint(
+                POINT { x: 0, y: 0 },
+                MONITOR_DEFAULTTOPRIMARY,
+            ))
+        }
+    }
+
+    /// The monitor containing the point, or the nearest one.
+    ///
+    /// The point is in virtual screen coordinates.
+    pub fn from_point(pt: Point) -> Monitor {
+        unsafe { Monitor(MonitorFromPoint(pt.into(), MONITOR_DEFAULTTONEAREST)) }
+    }
+
+    /// The monitor with the largest area of intersection with the window.
+    ///
+    /// See [`MonitorFromWindow`] for details.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`MonitorFromWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-monitorfromwindow
+    pub unsafe fn from_window(hwnd: HWND) -> Monitor {
+        Monitor(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST))
+    }
+
+    /// Create a monitor from a raw handle.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HMONITOR reference.
+    pub unsafe fn from_raw(hmonitor: HMONITOR) -> Monitor {
+        Monitor(hmonitor)
+    }
+
+    /// All monitors attached to the desktop, as reported by [`EnumDisplayMonitors`].
+    ///
+    /// [`EnumDisplayMonitors`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumdisplaymonitors
+    pub fn all() -> Vec<Monitor> {
+        unsafe extern "system" fn enum_proc(
+            hmonitor: HMONITOR,
+            _hdc: HDC,
+            _rect: LPRECT,
+            lparam: LPARAM,
+        ) -> BOOL {
+            let monitors = &mut *(lparam as *mut Vec<Monitor>);
+            monitors.push(Monitor(hmonitor));
+            TRUE
+        }
+        let mut monitors = Vec::new();
+        unsafe {
+            EnumDisplayMonitors(
+                null_mut(),
+                null_mut(),
+                Some(enum_proc),
+                &mut monitors as *mut Vec<Monitor> as LPARAM,
+            );
+        }
+        monitors
+    }
+
+    /// The raw `HMONITOR` handle.
+    pub fn raw(&self) -> HMONITOR {
+        self.0
+    }
+
+    /// The bounds of the monitor, in virtual screen coordinates.
+    pub fn rect(&self) -> Rect {
+        self.info()
+            .map(|info| info.rcMonitor.into())
+            .unwrap_or_default()
+    }
+
+    /// The work area of the monitor, in virtual screen coordinates.
+    ///
+    /// This is the monitor rect minus the taskbar and any docked app bars.
+    pub fn work_area(&self) -> Rect {
+        self.info()
+            .map(|info| info.rcWork.into())
+            .unwrap_or_default()
+    }
+
+    /// The area a maximized window without a frame should cover.
+    ///
+    /// This is the work area, except that it leaves a one-pixel gap along any edge with an
+    /// auto-hide taskbar or app bar. A window covering that edge would stop the bar from
+    /// appearing when the mouse reaches it, as the shell takes such a window to be full screen.
+    pub fn maximized_rect(&self) -> Rect {
+        self.inset_auto_hide_edges(self.work_area())
+    }
+
+    /// Shrink a rectangle by one pixel along each monitor edge with an auto-hide app bar.
+    pub(crate) fn inset_auto_hide_edges(&self, mut rect: Rect) -> Rect {
+        let monitor = self.rect();
+        for &edge in &[ABE_LEFT, ABE_TOP, ABE_RIGHT, ABE_BOTTOM] {
+            if !has_auto_hide_bar(monitor, edge) {
+                continue;
+            }
+            match edge {
+                ABE_LEFT if rect.left <= monitor.left => rect.left = monitor.left + 1,
+                ABE_TOP if rect.top <= monitor.top => rect.top = monitor.top + 1,
+                ABE_RIGHT if rect.right >= monitor.right => rect.right = monitor.right - 1,
+                ABE_BOTTOM if rect.bottom >= monitor.bottom => rect.bottom = monitor.bottom - 1,
+                _ => (),
+            }
+        }
+        rect
+    }
+
+    /// Whether this is the primary monitor.
+    pub fn is_primary(&self) -> bool {
+        self.info()
+            .map(|info| info.dwFlags & MONITORINFOF_PRIMARY != 0)
+            .unwrap_or(false)
+    }
+
+    /// The GDI device name of the monitor, for example `\\.\DISPLAY1`.
+    pub fn device_name(&self) -> Option<OsString> {
+        self.info()
+            .map(|info| OsString::from_wide_null(&info.szDevice))
+    }
+
+    /// The path of the color (ICC) profile associated with the monitor.
+    ///
+    /// Color-managed applications should convert their output to this profile. Returns `None`
+    /// if the monitor has no profile, or it can't be queried. See [`GetICMProfile`].
+    ///
+    /// [`GetICMProfile`]: https://docs.microsoft.com/en-us/windows/win32/api/wingdi/nf-wingdi-geticmprofilew
+    pub fn icc_profile_path(&self) -> Option<PathBuf> {
+        let info = self.info()?;
+        unsafe {
+            let hdc = CreateDCW(
+                info.szDevice.as_ptr(),
+                info.szDevice.as_ptr(),
+                null_mut(),
+                null_mut(),
+            );
+            if hdc.is_null() {
+                return None;
+            }
+            let mut buf = [0u16; MAX_PATH];
+            let mut len = buf.len() as DWORD;
+            let ok = GetICMProfileW(hdc, &mut len, buf.as_mut_ptr());
+            DeleteDC(hdc);
+            if ok == 0 {
+                return None;
+            }
+            Some(OsString::from_wide_null(&buf).into())
+        }
+    }
+
+    /// The refresh rate of the monitor's current display mode, in Hz.
+    ///
+    /// This is the integer rate reported by [`EnumDisplaySettings`]; fractional rates such as
+    /// 59.94Hz are rounded by the driver. For the precise rate as seen by the compositor, use
+    /// [`FrameTiming`](struct.FrameTiming.html).
+    ///
+    /// Returns `None` if the rate is unknown, including the "hardware default" values 0 and 1.
+    ///
+    /// [`EnumDisplaySettings`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumdisplaysettingsw
+    pub fn refresh_rate(&self) -> Option<u32> {
+        let info = self.info()?;
+        unsafe {
+            let mut devmode: DEVMODEW = mem::zeroed();
+            devmode.dmSize = mem::size_of::<DEVMODEW>() as u16;
+            if EnumDisplaySettingsW(info.szDevice.as_ptr(), ENUM_CURRENT_SETTINGS, &mut devmode)
+                == 0
+            {
+                return None;
+            }
+            match devmode.dmDisplayFrequency {
+                0 | 1 => None,
+                rate => Some(rate),
+            }
+        }
+    }
+
+    fn info(&self) -> Option<MONITORINFOEXW> {
+        unsafe {
+            let mut info: MONITORINFOEXW = mem::zeroed();
+            info.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+            if GetMonitorInfoW(self.0, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) == 0 {
+                None
+            } else {
+                Some(info)
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Mouse message decoding.
+//!
+//! This covers the mouse messages that are easy to get subtly wrong when decoding by hand:
+//! the wheel messages, whose delta is a signed value in the high word of `wparam` and whose
+//! position is in screen (not client) coordinates, and the X button messages, which must
+//! return `TRUE` rather than zero when handled.
+
+use winapi::shared::minwindef::{HIWORD, LOWORD, LPARAM, LRESULT, UINT, WORD, WPARAM};
+use winapi::um::winuser::{
+    WHEEL_DELTA, WM_MOUSEHWHEEL, WM_MOUSEWHEEL, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN, WM_XBUTTONUP,
+    XBUTTON1, XBUTTON2,
+};
+
+use crate::geometry::Point;
+use crate::message::message_time;
+
+/// A decoded mouse message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseEvent {
+    Wheel(WheelEvent),
+    XButton(XButtonEvent),
+}
+
+/// The axis of a wheel event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WheelAxis {
+    /// A standard wheel, from [`WM_MOUSEWHEEL`].
+    ///
+    /// [`WM_MOUSEWHEEL`]: https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-mousewheel
+    Vertical,
+    /// A tilt wheel or horizontal touchpad scroll, from [`WM_MOUSEHWHEEL`].
+    ///
+    /// [`WM_MOUSEHWHEEL`]: https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-mousehwheel
+    Horizontal,
+}
+
+/// A mouse wheel event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WheelEvent {
+    pub axis: WheelAxis,
+    /// The wheel delta, in units where one notch is `WHEEL_DELTA` (120).
+    ///
+    /// For the vertical axis, positive is away from the user (scroll up). For the horizontal
+    /// axis, positive is to the right. High-resolution devices report fractions of a notch.
+    pub delta: i16,
+    /// The state of the virtual keys, as a combination of `MK_` flags.
+    pub keys: WORD,
+    /// The cursor position, in screen coordinates.
+    pub screen_pos: Point,
+    /// The message time, in milliseconds; see [`message_time`](fn.message_time.html).
+    pub time: u32,
+}
+
+/// One of the extra mouse buttons, usually mapped to back and forward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XButton {
+    /// The first X button, conventionally "back".
+    X1,
+    /// The second X button, conventionally "forward".
+    X2,
+}
+
+/// The transition reported by a button message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonState {
+    Down,
+    Up,
+    DoubleClick,
+}
+
+/// An X button event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XButtonEvent {
+    pub button: XButton,
+    pub state: ButtonState,
+    /// The state of the virtual keys, as a combination of `MK_` flags.
+    pub keys: WORD,
+    /// The cursor position, in client coordinates.
+    pub pos: Point,
+    /// The message time, in milliseconds; see [`message_time`](fn.message_time.html).
+    pub time: u32,
+}
+
+impl MouseEvent {
+    /// Decode a mouse message.
+    ///
+    /// Handles `WM_MOUSEWHEEL`, `WM_MOUSEHWHEEL`, `WM_XBUTTONDOWN`, `WM_XBUTTONUP`, and
+    /// `WM_XBUTTONDBLCLK`; returns `None` for other messages.
+    ///
+    /// This queries the message time, so it must be called while processing the message.
+    pub fn decode(msg: UINT, wparam: WPARAM, lparam: LPARAM) -> Option<MouseEvent> {
+        let keys = LOWORD(wparam as u32);
+        match msg {
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                let axis = if msg == WM_MOUSEWHEEL {
+                    WheelAxis::Vertical
+                } else {
+                    WheelAxis::Horizontal
+                };
+                Some(MouseEvent::Wheel(WheelEvent {
+                    axis,
+                    delta: HIWORD(wparam as u32) as i16,
+                    keys,
+                    screen_pos: lparam_to_point(lparam),
+                    time: message_time(),
+                }))
+            }
+            WM_XBUTTONDOWN | WM_XBUTTONUP | WM_XBUTTONDBLCLK => {
+                let button = match HIWORD(wparam as u32) {
+                    XBUTTON1 => XButton::X1,
+                    XBUTTON2 => XButton::X2,
+                    _ => return None,
+                };
+                let state = match msg {
+                    WM_XBUTTONDOWN => ButtonState::Down,
+                    WM_XBUTTONUP => ButtonState::Up,
+                    _ => ButtonState::DoubleClick,
+                };
+                Some(MouseEvent::XButton(XButtonEvent {
+                    button,
+                    state,
+                    keys,
+                    pos: lparam_to_point(lparam),
+                    time: message_time(),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value the window procedure should return when it handles this event.
+    ///
+    /// This is zero for wheel messages and `TRUE` for X button messages, as documented. Note
+    /// that some mouse drivers only auto-repeat horizontal tilt when `WM_MOUSEHWHEEL` returns
+    /// `TRUE`; returning that instead is harmless.
+    pub fn handled_result(&self) -> LRESULT {
+        match self {
+            MouseEvent::Wheel(_) => 0,
+            MouseEvent::XButton(_) => 1,
+        }
+    }
+}
+
+impl WheelEvent {
+    /// The delta as a (possibly fractional) number of notches.
+    pub fn notches(&self) -> f64 {
+        self.delta as f64 / WHEEL_DELTA as f64
+    }
+}
+
+/// Extract a signed point from the `lparam` of a mouse message.
+///
+/// This is the equivalent of the `GET_X_LPARAM` and `GET_Y_LPARAM` macros. Using `LOWORD`
+/// and `HIWORD` instead is a classic bug, as coordinates can be negative on multi-monitor
+/// setups.
+pub fn lparam_to_point(lparam: LPARAM) -> Point {
+    Point::new(
+        LOWORD(lparam as u32) as i16 as i32,
+        HIWORD(lparam as u32) as i16 as i32,
+    )
+}
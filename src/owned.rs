@@ -0,0 +1,179 @@
+//! Keeping auxiliary windows with their owner.
+//!
+//! Editors and similar applications float palettes, inspectors, and toolbars over a main
+//! window. Making them [owned] windows keeps them above the owner, but they stay put when the
+//! owner moves, and activating the owner from the taskbar doesn't always bring them back
+//! above other applications' windows. [`add_owned_window`] handles this bookkeeping: the
+//! window is moved along with its owner (on `WM_MOVE`), and raised above it when the owner
+//! is activated (on `WM_ACTIVATE`).
+//!
+//! [owned]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#owned-windows
+//! [`add_owned_window`]: fn.add_owned_window.html
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use winapi::shared::basetsd::{DWORD_PTR, UINT_PTR};
+use winapi::shared::minwindef::{LOWORD, LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::commctrl::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{
+    BeginDeferWindowPos, ClientToScreen, DeferWindowPos, EndDeferWindowPos, GetWindow,
+    GetWindowRect, GetWindowThreadProcessId, IsIconic, IsWindow, SetWindowLongPtrW, SetWindowPos,
+    GWLP_HWNDPARENT, GW_OWNER, HWND_TOP, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER,
+    WA_INACTIVE, WM_ACTIVATE, WM_MOVE, WM_NCDESTROY,
+};
+
+use crate::error::Error;
+use crate::geometry::Point;
+use crate::window_data::{get_window_data, remove_window_data, set_window_data};
+
+/// The window data key for the owner's state.
+const DATA_KEY: &str = "owned";
+
+/// The subclass id; any value unique to this pair with `owner_subclass_proc` will do.
+const OWNER_SUBCLASS_ID: UINT_PTR = 0x7769_6e6f;
+
+struct OwnedWindows {
+    windows: RefCell<Vec<HWND>>,
+    /// The owner's client origin, in screen coordinates, as of the last move.
+    origin: Cell<Point>,
+}
+
+unsafe fn client_origin(hwnd: HWND) -> Point {
+    let mut pt = mem::zeroed();
+    ClientToScreen(hwnd, &mut pt);
+    pt.into()
+}
+
+/// Keep a window with its owner.
+///
+/// The window is made owned by `owner` if it isn't already. From then on, it moves when the
+/// owner moves, and is raised above the owner when the owner is activated. This stops when
+/// either window is destroyed, or on [`remove_owned_window`].
+///
+/// Returns [`Error::ForeignWindow`] if either window belongs to another thread.
+///
+/// # Safety
+///
+/// The arguments must be valid HWND references, to top-level windows.
+///
+/// [`remove_owned_window`]: fn.remove_owned_window.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn add_owned_window(owner: HWND, window: HWND) -> Result<(), Error> {
+    if GetWindowThreadProcessId(window, null_mut()) != GetCurrentThreadId() {
+        return Err(Error::ForeignWindow);
+    }
+    let state = match get_window_data::<OwnedWindows>(owner, DATA_KEY) {
+        Some(state) => state,
+        None => {
+            let state = Rc::new(OwnedWindows {
+                windows: RefCell::new(Vec::new()),
+                origin: Cell::new(client_origin(owner)),
+            });
+            set_window_data(owner, DATA_KEY, state.clone())?;
+            if SetWindowSubclass(owner, Some(owner_subclass_proc), OWNER_SUBCLASS_ID, 0) == 0 {
+                remove_window_data(owner, DATA_KEY);
+                return Err(Error::last_error());
+            }
+            state
+        }
+    };
+    if GetWindow(window, GW_OWNER) != owner {
+        // For a top-level window, this is the owner; SetParent would make it a child.
+        SetWindowLongPtrW(window, GWLP_HWNDPARENT, owner as _);
+    }
+    let mut windows = state.windows.borrow_mut();
+    if !windows.contains(&window) {
+        windows.push(window);
+    }
+    Ok(())
+}
+
+/// Stop keeping a window with its owner.
+///
+/// The window remains owned.
+///
+/// # Safety
+///
+/// The arguments must be valid HWND references.
+pub unsafe fn remove_owned_window(owner: HWND, window: HWND) {
+    if let Some(state) = get_window_data::<OwnedWindows>(owner, DATA_KEY) {
+        state.windows.borrow_mut().retain(|&w| w != window);
+    }
+}
+
+unsafe fn move_windows(state: &OwnedWindows, owner: HWND) {
+    let origin = client_origin(owner);
+    let old = state.origin.replace(origin);
+    let (dx, dy) = (origin.x - old.x, origin.y - old.y);
+    if dx == 0 && dy == 0 {
+        return;
+    }
+    let windows = state.windows.borrow();
+    let mut dwp = BeginDeferWindowPos(windows.len() as i32);
+    for &window in windows.iter() {
+        let mut rect = mem::zeroed();
+        if dwp.is_null() || GetWindowRect(window, &mut rect) == 0 {
+            continue;
+        }
+        dwp = DeferWindowPos(
+            dwp,
+            window,
+            null_mut(),
+            rect.left + dx,
+            rect.top + dy,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+    if !dwp.is_null() {
+        EndDeferWindowPos(dwp);
+    }
+}
+
+unsafe fn raise_windows(state: &OwnedWindows) {
+    for &window in state.windows.borrow().iter() {
+        SetWindowPos(
+            window,
+            HWND_TOP,
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        );
+    }
+}
+
+unsafe extern "system" fn owner_subclass_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    id: UINT_PTR,
+    _ref_data: DWORD_PTR,
+) -> LRESULT {
+    match msg {
+        WM_MOVE | WM_ACTIVATE => {
+            if let Some(state) = get_window_data::<OwnedWindows>(hwnd, DATA_KEY) {
+                // Forget windows destroyed since the last message.
+                state.windows.borrow_mut().retain(|&w| IsWindow(w) != 0);
+                if msg == WM_MOVE && IsIconic(hwnd) == 0 {
+                    move_windows(&state, hwnd);
+                } else if msg == WM_ACTIVATE && LOWORD(wparam as u32) != WA_INACTIVE {
+                    raise_windows(&state);
+                }
+            }
+        }
+        WM_NCDESTROY => {
+            RemoveWindowSubclass(hwnd, Some(owner_subclass_proc), id);
+        }
+        _ => (),
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
@@ -0,0 +1,135 @@
+//! Painting helpers.
+
+use std::mem;
+use std::ptr::null_mut;
+
+use winapi::shared::minwindef::{FALSE, HRGN};
+use winapi::shared::windef::{HDC, HWND, RECT};
+use winapi::um::wingdi::{
+    CreateRectRgn, DeleteObject, GetRegionData, NULLREGION, RGNDATA, RGNDATAHEADER,
+};
+use winapi::um::winuser::{BeginPaint, EndPaint, GetUpdateRgn, PAINTSTRUCT};
+
+use crate::geometry::Rect;
+
+/// A paint session, for handling [`WM_PAINT`].
+///
+/// This wraps [`BeginPaint`] and [`EndPaint`] (called on drop). In addition, it captures the
+/// update region as a list of rectangles before `BeginPaint` validates it, so that software
+/// renderers can redraw only the parts of the window that actually changed, rather than the
+/// bounding box reported in `PAINTSTRUCT`.
+///
+/// [`WM_PAINT`]: https://docs.microsoft.com/en-us/windows/win32/gdi/wm-paint
+/// [`BeginPaint`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-beginpaint
+/// [`EndPaint`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-endpaint
+pub struct Paint {
+    hwnd: HWND,
+    ps: PAINTSTRUCT,
+    dirty_rects: Vec<Rect>,
+}
+
+impl Paint {
+    /// Begin painting.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, and this should only be called while
+    /// handling `WM_PAINT` for that window.
+    pub unsafe fn begin(hwnd: HWND) -> Paint {
+        let mut dirty_rects = update_rects(hwnd);
+        let mut ps: PAINTSTRUCT = mem::zeroed();
+        BeginPaint(hwnd, &mut ps);
+        if dirty_rects.is_empty() {
+            dirty_rects.push(ps.rcPaint.into());
+        }
+        Paint {
+            hwnd,
+            ps,
+            dirty_rects,
+        }
+    }
+
+    /// The device context to paint into.
+    pub fn hdc(&self) -> HDC {
+        self.ps.hdc
+    }
+
+    /// The bounding rectangle of the area to be painted, in client coordinates.
+    pub fn bounds(&self) -> Rect {
+        self.ps.rcPaint.into()
+    }
+
+    /// The rectangles making up the area to be painted, in client coordinates.
+    ///
+    /// These don't overlap, and their union is the update region. When the update region
+    /// couldn't be retrieved, this is the single bounding rectangle.
+    pub fn dirty_rects(&self) -> &[Rect] {
+        &self.dirty_rects
+    }
+
+    /// Whether the background should be erased by the application.
+    ///
+    /// This is the `fErase` field of `PAINTSTRUCT`, which is set when `WM_ERASEBKGND` was not
+    /// handled (or returned zero).
+    pub fn erase(&self) -> bool {
+        self.ps.fErase != 0
+    }
+
+    /// The raw `PAINTSTRUCT`.
+    pub fn paint_struct(&self) -> &PAINTSTRUCT {
+        &self.ps
+    }
+}
+
+impl Drop for Paint {
+    fn drop(&mut self) {
+        unsafe {
+            EndPaint(self.hwnd, &self.ps);
+        }
+    }
+}
+
+/// The update region of a window, as a list of rectangles in client coordinates.
+///
+/// This must be called before [`BeginPaint`] (which validates the region), and is done
+/// automatically by [`Paint::begin`]. It uses [`GetUpdateRgn`] and [`GetRegionData`]. The
+/// result is empty if there is no update region.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`BeginPaint`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-beginpaint
+/// [`Paint::begin`]: struct.Paint.html#method.begin
+/// [`GetUpdateRgn`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getupdatergn
+/// [`GetRegionData`]: https://docs.microsoft.com/en-us/windows/win32/api/wingdi/nf-wingdi-getregiondata
+pub unsafe fn update_rects(hwnd: HWND) -> Vec<Rect> {
+    let hrgn = CreateRectRgn(0, 0, 0, 0);
+    if hrgn.is_null() {
+        return Vec::new();
+    }
+    let result = match GetUpdateRgn(hwnd, hrgn, FALSE) {
+        NULLREGION | 0 => Vec::new(),
+        _ => region_rects(hrgn),
+    };
+    DeleteObject(hrgn as _);
+    result
+}
+
+unsafe fn region_rects(hrgn: HRGN) -> Vec<Rect> {
+    let size = GetRegionData(hrgn, 0, null_mut());
+    if size == 0 {
+        return Vec::new();
+    }
+    // Use a u32 buffer so the header and rectangles are suitably aligned.
+    let mut buf = vec![0u32; (size as usize).div_ceil(4)];
+    let data = buf.as_mut_ptr() as *mut RGNDATA;
+    if GetRegionData(hrgn, size, data) == 0 {
+        return Vec::new();
+    }
+    let header: &RGNDATAHEADER = &(*data).rdh;
+    let rects_ptr = (data as *const u8).add(header.dwSize as usize) as *const RECT;
+    (0..header.nCount as usize)
+        .map(|i| (*rects_ptr.add(i)).into())
+        .collect()
+}
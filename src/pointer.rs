@@ -0,0 +1,199 @@
+//! Pointer input (pen, touch, and touchpad).
+//!
+//! On Windows 8 and later, pen and touch input arrive as `WM_POINTER` messages. This module
+//! decodes them, with particular attention to pen hover: a pen in range of the digitizer but
+//! not touching it produces [`WM_POINTERENTER`], a stream of [`WM_POINTERUPDATE`] messages with
+//! zero pressure, and finally [`WM_POINTERLEAVE`]. Drawing apps use these to show a brush
+//! cursor that tracks the hovering stylus.
+//!
+//! The pointer functions are loaded at runtime, so on older versions of Windows decoding
+//! simply returns `None`.
+//!
+//! [`WM_POINTERENTER`]: https://docs.microsoft.com/en-us/windows/win32/inputmsg/wm-pointerenter
+//! [`WM_POINTERUPDATE`]: https://docs.microsoft.com/en-us/windows/win32/inputmsg/wm-pointerupdate
+//! [`WM_POINTERLEAVE`]: https://docs.microsoft.com/en-us/windows/win32/inputmsg/wm-pointerleave
+
+use std::mem;
+
+use winapi::shared::minwindef::{BOOL, HIWORD, LOWORD, LPARAM, UINT, WPARAM};
+use winapi::um::winuser::{
+    PEN_FLAG_BARREL, PEN_FLAG_ERASER, PEN_FLAG_INVERTED, PEN_MASK_PRESSURE, PEN_MASK_ROTATION,
+    PEN_MASK_TILT_X, PEN_MASK_TILT_Y, POINTER_INPUT_TYPE, POINTER_MESSAGE_FLAG_INCONTACT,
+    POINTER_MESSAGE_FLAG_INRANGE, POINTER_MESSAGE_FLAG_PRIMARY, POINTER_PEN_INFO, PT_MOUSE, PT_PEN,
+    PT_TOUCH, PT_TOUCHPAD, WM_POINTERDOWN, WM_POINTERENTER, WM_POINTERLEAVE, WM_POINTERUP,
+    WM_POINTERUPDATE,
+};
+
+use crate::geometry::Point;
+use crate::message::message_time;
+use crate::mouse::lparam_to_point;
+
+/// The maximum pen pressure reported by the system.
+const MAX_PEN_PRESSURE: f32 = 1024.0;
+
+/// The kind of device producing pointer input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerKind {
+    Touch,
+    Pen,
+    Mouse,
+    Touchpad,
+    Other,
+}
+
+/// The message a pointer event was decoded from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointerPhase {
+    /// The pointer entered detection range or the window (`WM_POINTERENTER`).
+    Enter,
+    /// The pointer moved or changed state (`WM_POINTERUPDATE`).
+    Update,
+    /// The pointer made contact (`WM_POINTERDOWN`).
+    Down,
+    /// The pointer broke contact (`WM_POINTERUP`).
+    Up,
+    /// The pointer left detection range or the window (`WM_POINTERLEAVE`).
+    Leave,
+}
+
+/// Pen-specific state.
+///
+/// Each of the optional values is `None` when the device doesn't report it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PenState {
+    /// Normalized pressure, from 0.0 to 1.0. This is 0.0 while hovering.
+    pub pressure: Option<f32>,
+    /// Clockwise rotation (twist), in degrees from 0 to 359.
+    pub rotation: Option<u32>,
+    /// Tilt along the x axis, in degrees from -90 to 90; positive is to the right.
+    pub tilt_x: Option<i32>,
+    /// Tilt along the y axis, in degrees from -90 to 90; positive is toward the user.
+    pub tilt_y: Option<i32>,
+    /// Whether the barrel button is pressed.
+    pub barrel: bool,
+    /// Whether the pen is inverted.
+    pub inverted: bool,
+    /// Whether the eraser button is pressed.
+    pub eraser: bool,
+}
+
+/// A decoded pointer message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerEvent {
+    /// The pointer identifier, stable for the duration of an interaction.
+    pub id: u32,
+    pub kind: PointerKind,
+    pub phase: PointerPhase,
+    /// The position, in screen coordinates.
+    pub screen_pos: Point,
+    /// Whether the pointer is within detection range of the device.
+    pub in_range: bool,
+    /// Whether the pointer is in contact with the surface.
+    pub in_contact: bool,
+    /// Whether this is the primary pointer.
+    pub primary: bool,
+    /// Pen state, for pen pointers.
+    pub pen: Option<PenState>,
+    /// The message time, in milliseconds; see [`message_time`](fn.message_time.html).
+    pub time: u32,
+}
+
+impl PointerEvent {
+    /// Decode a pointer message.
+    ///
+    /// Handles `WM_POINTERENTER`, `WM_POINTERLEAVE`, `WM_POINTERUPDATE`, `WM_POINTERDOWN`
+    /// and `WM_POINTERUP`. Returns `None` for other messages, or if the pointer API is not
+    /// available.
+    ///
+    /// This queries the pointer state with [`GetPointerType`] and [`GetPointerPenInfo`], so it
+    /// must be called while processing the message.
+    ///
+    /// [`GetPointerType`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getpointertype
+    /// [`GetPointerPenInfo`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getpointerpeninfo
+    pub fn decode(msg: UINT, wparam: WPARAM, lparam: LPARAM) -> Option<PointerEvent> {
+        let phase = match msg {
+            WM_POINTERENTER => PointerPhase::Enter,
+            WM_POINTERUPDATE => PointerPhase::Update,
+            WM_POINTERDOWN => PointerPhase::Down,
+            WM_POINTERUP => PointerPhase::Up,
+            WM_POINTERLEAVE => PointerPhase::Leave,
+            _ => return None,
+        };
+        let id = LOWORD(wparam as u32) as u32;
+        let flags = HIWORD(wparam as u32) as u32;
+        let kind = unsafe { pointer_kind(id)? };
+        let pen = if kind == PointerKind::Pen {
+            unsafe { pen_state(id) }
+        } else {
+            None
+        };
+        Some(PointerEvent {
+            id,
+            kind,
+            phase,
+            screen_pos: lparam_to_point(lparam),
+            in_range: flags & POINTER_MESSAGE_FLAG_INRANGE != 0,
+            in_contact: flags & POINTER_MESSAGE_FLAG_INCONTACT != 0,
+            primary: flags & POINTER_MESSAGE_FLAG_PRIMARY != 0,
+            pen,
+            time: message_time(),
+        })
+    }
+
+    /// Whether the pointer is hovering: in range of the device, but not in contact.
+    pub fn is_hovering(&self) -> bool {
+        self.in_range && !self.in_contact
+    }
+}
+
+unsafe fn pointer_kind(id: u32) -> Option<PointerKind> {
+    let get_pointer_type =
+        dynamic_fn!("user32.dll", GetPointerType: fn(u32, *mut POINTER_INPUT_TYPE) -> BOOL)?;
+    let mut pointer_type = 0;
+    if get_pointer_type(id, &mut pointer_type) == 0 {
+        return None;
+    }
+    Some(match pointer_type {
+        PT_TOUCH => PointerKind::Touch,
+        PT_PEN => PointerKind::Pen,
+        PT_MOUSE => PointerKind::Mouse,
+        PT_TOUCHPAD => PointerKind::Touchpad,
+        _ => PointerKind::Other,
+    })
+}
+
+unsafe fn pen_state(id: u32) -> Option<PenState> {
+    let get_pointer_pen_info =
+        dynamic_fn!("user32.dll", GetPointerPenInfo: fn(u32, *mut POINTER_PEN_INFO) -> BOOL)?;
+    let mut info: POINTER_PEN_INFO = mem::zeroed();
+    if get_pointer_pen_info(id, &mut info) == 0 {
+        return None;
+    }
+    let mask = info.penMask;
+    let flags = info.penFlags;
+    Some(PenState {
+        pressure: if mask & PEN_MASK_PRESSURE != 0 {
+            Some(info.pressure as f32 / MAX_PEN_PRESSURE)
+        } else {
+            None
+        },
+        rotation: if mask & PEN_MASK_ROTATION != 0 {
+            Some(info.rotation)
+        } else {
+            None
+        },
+        tilt_x: if mask & PEN_MASK_TILT_X != 0 {
+            Some(info.tiltX)
+        } else {
+            None
+        },
+        tilt_y: if mask & PEN_MASK_TILT_Y != 0 {
+            Some(info.tiltY)
+        } else {
+            None
+        },
+        barrel: flags & PEN_FLAG_BARREL != 0,
+        inverted: flags & PEN_FLAG_INVERTED != 0,
+        eraser: flags & PEN_FLAG_ERASER != 0,
+    })
+}
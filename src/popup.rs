@@ -0,0 +1,218 @@
+//! Light dismiss for popups.
+//!
+//! Popups such as menus, autocomplete lists, and flyouts are expected to close when the user
+//! clicks anywhere else, or switches away from the application; this is known as "light
+//! dismiss". A popup created with [`WindowBuilder::popup`] doesn't take activation, so it
+//! can't rely on losing activation to notice. Instead, [`set_light_dismiss`] watches clicks
+//! on the thread with a [mouse hook], and deactivation and moves of the owner with a
+//! [subclass], and closes the popup by posting `WM_CLOSE` to it.
+//!
+//! [`WindowBuilder::popup`]: struct.WindowBuilder.html#method.popup
+//! [`set_light_dismiss`]: fn.set_light_dismiss.html
+//! [mouse hook]: https://docs.microsoft.com/en-us/windows/win32/winmsg/mouseproc
+//! [subclass]: https://docs.microsoft.com/en-us/windows/win32/controls/subclassing-overview
+
+use std::cell::{Cell, RefCell};
+use std::ptr::null_mut;
+
+use winapi::ctypes::c_int;
+use winapi::shared::basetsd::{DWORD_PTR, UINT_PTR};
+use winapi::shared::minwindef::{LOWORD, LPARAM, LRESULT, UINT, WPARAM};
+use winapi::shared::windef::{HHOOK, HWND};
+use winapi::um::commctrl::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{
+    CallNextHookEx, GetAncestor, GetWindow, GetWindowThreadProcessId, PostMessageW,
+    SetWindowsHookExW, UnhookWindowsHookEx, GA_ROOT, GW_OWNER, HC_ACTION, MOUSEHOOKSTRUCT,
+    WA_INACTIVE, WH_MOUSE, WM_ACTIVATE, WM_ACTIVATEAPP, WM_CLOSE, WM_ENTERSIZEMOVE, WM_LBUTTONDOWN,
+    WM_MBUTTONDOWN, WM_NCDESTROY, WM_NCLBUTTONDOWN, WM_NCMBUTTONDOWN, WM_NCRBUTTONDOWN,
+    WM_NCXBUTTONDOWN, WM_RBUTTONDOWN, WM_XBUTTONDOWN,
+};
+
+use crate::error::Error;
+
+/// The subclass id for popups; any value unique to this pair with `popup_subclass_proc` will
+/// do. The owner subclass uses the popup's handle as its id.
+const POPUP_SUBCLASS_ID: UINT_PTR = 0x7769_6e70;
+
+thread_local! {
+    /// Popups with light dismiss enabled on this thread.
+    static POPUPS: RefCell<Vec<HWND>> = const { RefCell::new(Vec::new()) };
+    /// The mouse hook, installed while there are any popups.
+    static MOUSE_HOOK: Cell<HHOOK> = const { Cell::new(null_mut()) };
+}
+
+/// Enable or disable light dismiss for a popup.
+///
+/// When enabled, the popup is sent `WM_CLOSE` (by default, destroying it) when the user
+/// clicks outside it (including in windows it owns, such as nested flyouts), when its owner
+/// is deactivated, or when its owner starts moving or resizing. It is then disabled, so the
+/// popup is closed at most once.
+///
+/// Returns [`Error::ForeignWindow`] if the popup belongs to another thread.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_light_dismiss(popup: HWND, enabled: bool) -> Result<(), Error> {
+    if GetWindowThreadProcessId(popup, null_mut()) != GetCurrentThreadId() {
+        return Err(Error::ForeignWindow);
+    }
+    if enabled {
+        register(popup)
+    } else {
+        unregister(popup);
+        Ok(())
+    }
+}
+
+unsafe fn register(popup: HWND) -> Result<(), Error> {
+    if POPUPS.with(|popups| popups.borrow().contains(&popup)) {
+        return Ok(());
+    }
+    if SetWindowSubclass(popup, Some(popup_subclass_proc), POPUP_SUBCLASS_ID, 0) == 0 {
+        return Err(Error::last_error());
+    }
+    let owner = GetWindow(popup, GW_OWNER);
+    if !owner.is_null()
+        && SetWindowSubclass(
+            owner,
+            Some(owner_subclass_proc),
+            popup as UINT_PTR,
+            popup as DWORD_PTR,
+        ) == 0
+    {
+        let err = Error::last_error();
+        RemoveWindowSubclass(popup, Some(popup_subclass_proc), POPUP_SUBCLASS_ID);
+        return Err(err);
+    }
+    if MOUSE_HOOK.with(Cell::get).is_null() {
+        let hook = SetWindowsHookExW(
+            WH_MOUSE,
+            Some(mouse_hook_proc),
+            null_mut(),
+            GetCurrentThreadId(),
+        );
+        if hook.is_null() {
+            let err = Error::last_error();
+            RemoveWindowSubclass(popup, Some(popup_subclass_proc), POPUP_SUBCLASS_ID);
+            if !owner.is_null() {
+                RemoveWindowSubclass(owner, Some(owner_subclass_proc), popup as UINT_PTR);
+            }
+            return Err(err);
+        }
+        MOUSE_HOOK.with(|h| h.set(hook));
+    }
+    POPUPS.with(|popups| popups.borrow_mut().push(popup));
+    Ok(())
+}
+
+unsafe fn unregister(popup: HWND) {
+    let (removed, empty) = POPUPS.with(|popups| {
+        let mut popups = popups.borrow_mut();
+        let len = popups.len();
+        popups.retain(|&p| p != popup);
+        (popups.len() != len, popups.is_empty())
+    });
+    if !removed {
+        return;
+    }
+    RemoveWindowSubclass(popup, Some(popup_subclass_proc), POPUP_SUBCLASS_ID);
+    let owner = GetWindow(popup, GW_OWNER);
+    if !owner.is_null() {
+        RemoveWindowSubclass(owner, Some(owner_subclass_proc), popup as UINT_PTR);
+    }
+    if empty {
+        let hook = MOUSE_HOOK.with(|h| h.replace(null_mut()));
+        if !hook.is_null() {
+            UnhookWindowsHookEx(hook);
+        }
+    }
+}
+
+/// Disable light dismiss for all popups on the calling thread, removing the mouse hook.
+pub(crate) unsafe fn clear_light_dismiss() {
+    for popup in POPUPS.with(|popups| popups.borrow().clone()) {
+        unregister(popup);
+    }
+}
+
+unsafe fn dismiss(popup: HWND) {
+    unregister(popup);
+    PostMessageW(popup, WM_CLOSE, 0, 0);
+}
+
+/// Whether `target` is the popup, a descendant, or a window owned (directly or not) by it.
+unsafe fn is_within(popup: HWND, target: HWND) -> bool {
+    if target.is_null() {
+        return false;
+    }
+    let mut window = GetAncestor(target, GA_ROOT);
+    while !window.is_null() {
+        if window == popup {
+            return true;
+        }
+        window = GetWindow(window, GW_OWNER);
+    }
+    false
+}
+
+unsafe extern "system" fn mouse_hook_proc(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION {
+        match wparam as UINT {
+            WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN
+            | WM_NCLBUTTONDOWN | WM_NCRBUTTONDOWN | WM_NCMBUTTONDOWN | WM_NCXBUTTONDOWN => {
+                let target = (*(lparam as *const MOUSEHOOKSTRUCT)).hwnd;
+                // Dismissing changes the list, so take a copy first.
+                let popups = POPUPS.with(|popups| popups.borrow().clone());
+                for popup in popups {
+                    if !is_within(popup, target) {
+                        dismiss(popup);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    CallNextHookEx(null_mut(), code, wparam, lparam)
+}
+
+unsafe extern "system" fn popup_subclass_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: UINT_PTR,
+    _ref_data: DWORD_PTR,
+) -> LRESULT {
+    if msg == WM_NCDESTROY {
+        unregister(hwnd);
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+unsafe extern "system" fn owner_subclass_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: UINT_PTR,
+    ref_data: DWORD_PTR,
+) -> LRESULT {
+    let popup = ref_data as HWND;
+    match msg {
+        // Activation moving to the popup itself (which shouldn't happen, as it doesn't take
+        // activation) or a window it owns is not a reason to dismiss.
+        WM_ACTIVATE
+            if LOWORD(wparam as u32) == WA_INACTIVE && !is_within(popup, lparam as HWND) =>
+        {
+            dismiss(popup)
+        }
+        WM_ACTIVATEAPP if wparam == 0 => dismiss(popup),
+        WM_ENTERSIZEMOVE => dismiss(popup),
+        _ => (),
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
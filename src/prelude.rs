@@ -0,0 +1,39 @@
+//! Commonly used types and constants.
+//!
+//! This re-exports the builder types along with the winapi types and constants most often
+//! needed to create and show a window, so that simple programs can start with
+//! `use win_win::prelude::*;` rather than depending on winapi directly.
+
+pub use crate::app::{run, Context, ControlFlow};
+pub use crate::dpi::{set_process_dpi_awareness, DpiAwareness};
+pub use crate::error::Error;
+pub use crate::events::{Event, EventHandler, EventProc};
+pub use crate::geometry::{Point, Rect};
+pub use crate::hwnd::Hwnd;
+pub use crate::runloop::{runloop, Runloop};
+pub use crate::style::{WindowExStyle, WindowStyle};
+pub use crate::ui_thread::UiThreadToken;
+pub use crate::window::{
+    FnMutProc, ShowCommand, WindowBuilder, WindowClass, WindowClassBuilder, WindowProc,
+};
+
+pub use winapi::shared::minwindef::{LPARAM, LRESULT, UINT, WPARAM};
+pub use winapi::shared::windef::HWND;
+pub use winapi::um::winuser::{PostQuitMessage, ShowWindow};
+
+pub use winapi::um::winuser::{
+    WS_CAPTION, WS_CHILD, WS_CLIPCHILDREN, WS_CLIPSIBLINGS, WS_MAXIMIZEBOX, WS_MINIMIZEBOX,
+    WS_OVERLAPPED, WS_OVERLAPPEDWINDOW, WS_POPUP, WS_POPUPWINDOW, WS_SYSMENU, WS_THICKFRAME,
+    WS_VISIBLE,
+};
+
+pub use winapi::um::winuser::{
+    WS_EX_APPWINDOW, WS_EX_CLIENTEDGE, WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_NOREDIRECTIONBITMAP,
+    WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT,
+};
+
+pub use winapi::um::winuser::{CS_DBLCLKS, CS_HREDRAW, CS_OWNDC, CS_VREDRAW};
+
+pub use winapi::um::winuser::{
+    SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE, SW_SHOW, SW_SHOWNOACTIVATE, SW_SHOWNORMAL,
+};
@@ -0,0 +1,140 @@
+//! Detecting reentrant messages.
+//!
+//! Many Win32 calls made from a message handler send messages back to the same window before
+//! returning: `SetWindowPos` sends `WM_SIZE` and `WM_WINDOWPOSCHANGED`, `UpdateWindow` sends
+//! `WM_PAINT`, and anything running a modal loop can deliver nearly anything. A handler that
+//! isn't written with this in mind can recurse without bound, or observe its own state half
+//! updated. [`ReentrancyGuard`] tracks which messages are being handled, counts reentrant
+//! calls, and can defer work until the outermost call returns.
+//!
+//! [`ReentrancyGuard`]: struct.ReentrancyGuard.html
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+
+use winapi::shared::minwindef::UINT;
+
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    depth: u32,
+    reentered: u64,
+}
+
+/// Tracks the messages being handled by a window procedure.
+///
+/// Call [`enter`] at the start of handling a message, and keep the returned guard for the
+/// duration of the handler. Work that shouldn't run reentrantly can be passed to [`defer`];
+/// it runs when the outermost guard (for any message) is dropped.
+///
+/// [`enter`]: #method.enter
+/// [`defer`]: #method.defer
+#[derive(Default)]
+pub struct ReentrancyGuard {
+    counts: RefCell<HashMap<UINT, Counts>>,
+    total_depth: Cell<u32>,
+    deferred: RefCell<VecDeque<Box<dyn FnOnce()>>>,
+}
+
+/// The guard returned by [`ReentrancyGuard::enter`](struct.ReentrancyGuard.html#method.enter).
+pub struct Entered<'a> {
+    guard: &'a ReentrancyGuard,
+    msg: UINT,
+    reentrant: bool,
+}
+
+impl ReentrancyGuard {
+    pub fn new() -> ReentrancyGuard {
+        Default::default()
+    }
+
+    /// Record the start of handling a message.
+    ///
+    /// The returned guard records the end of handling when dropped.
+    pub fn enter(&self, msg: UINT) -> Entered<'_> {
+        let reentrant = {
+            let mut counts = self.counts.borrow_mut();
+            let counts = counts.entry(msg).or_default();
+            counts.depth += 1;
+            if counts.depth > 1 {
+                counts.reentered += 1;
+            }
+            counts.depth > 1
+        };
+        self.total_depth.set(self.total_depth.get() + 1);
+        Entered {
+            guard: self,
+            msg,
+            reentrant,
+        }
+    }
+
+    /// The number of calls handling `msg` currently in progress.
+    pub fn depth(&self, msg: UINT) -> u32 {
+        self.counts.borrow().get(&msg).map_or(0, |c| c.depth)
+    }
+
+    /// Whether any message is currently being handled.
+    pub fn is_active(&self) -> bool {
+        self.total_depth.get() > 0
+    }
+
+    /// The number of times `msg` has been entered while already being handled.
+    pub fn reentered_count(&self, msg: UINT) -> u64 {
+        self.counts.borrow().get(&msg).map_or(0, |c| c.reentered)
+    }
+
+    /// Run `f` after the outermost call returns.
+    ///
+    /// If no message is being handled, `f` runs immediately. Deferred work runs in the order
+    /// it was deferred; work deferred while it is running is also run.
+    pub fn defer(&self, f: impl FnOnce() + 'static) {
+        if self.is_active() {
+            self.deferred.borrow_mut().push_back(Box::new(f));
+        } else {
+            f();
+        }
+    }
+
+    fn exit(&self, msg: UINT) {
+        if let Some(counts) = self.counts.borrow_mut().get_mut(&msg) {
+            counts.depth -= 1;
+        }
+        let total_depth = self.total_depth.get() - 1;
+        self.total_depth.set(total_depth);
+        if total_depth == 0 {
+            self.run_deferred();
+        }
+    }
+
+    fn run_deferred(&self) {
+        // Deferred work may itself handle messages, so it runs as if inside a call, and the
+        // queue isn't borrowed while it runs.
+        self.total_depth.set(1);
+        loop {
+            let next = self.deferred.borrow_mut().pop_front();
+            match next {
+                Some(f) => f(),
+                None => break,
+            }
+        }
+        self.total_depth.set(0);
+    }
+}
+
+impl<'a> Entered<'a> {
+    /// Whether this message was already being handled when entered.
+    pub fn is_reentrant(&self) -> bool {
+        self.reentrant
+    }
+
+    /// The message being handled.
+    pub fn msg(&self) -> UINT {
+        self.msg
+    }
+}
+
+impl<'a> Drop for Entered<'a> {
+    fn drop(&mut self) {
+        self.guard.exit(self.msg);
+    }
+}
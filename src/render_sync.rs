@@ -0,0 +1,258 @@
+//! Coordinating a window with a thread that renders into it.
+//!
+//! Rendering from a dedicated thread keeps frame pacing independent of the message loop, but
+//! the render thread needs three things from the window's thread: the current client size,
+//! a chance to draw at a new size before a live resize moves on (otherwise the window shows
+//! stretched or torn frames), and warning before the window is destroyed, so it can release
+//! its swapchain while the `HWND` is still valid.
+//!
+//! [`RenderSync::new`] returns the two ends of this handshake. The window procedure passes its
+//! messages to [`RenderSync::process_message`]; the render thread owns the [`RenderHandle`],
+//! calling [`frame`] before each frame and [`presented`] after presenting it, and dropping the
+//! handle when it is done with the window.
+//!
+//! [`RenderSync::new`]: struct.RenderSync.html#method.new
+//! [`RenderSync::process_message`]: struct.RenderSync.html#method.process_message
+//! [`RenderHandle`]: struct.RenderHandle.html
+//! [`frame`]: struct.RenderHandle.html#method.frame
+//! [`presented`]: struct.RenderHandle.html#method.presented
+
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use winapi::shared::minwindef::{HIWORD, LOWORD, LPARAM, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{GetClientRect, WM_DESTROY, WM_ENTERSIZEMOVE, WM_EXITSIZEMOVE, WM_SIZE};
+
+/// The state shared between the window's thread and the render thread.
+#[derive(Default)]
+struct State {
+    width: u32,
+    height: u32,
+    /// Incremented on each size change.
+    generation: u64,
+    /// The generation of the last frame presented.
+    presented: u64,
+    /// Set on `WM_DESTROY`.
+    destroyed: bool,
+    /// Set when the render thread drops its handle.
+    released: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait until `done` is true or the timeout elapses, returning whether it is done.
+    fn wait_until(&self, timeout: Duration, done: impl Fn(&State) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while !done(&state) {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self
+                .cond
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        true
+    }
+}
+
+/// The window's side of the handshake with a render thread.
+///
+/// This isn't `Send`; it belongs with the window procedure.
+pub struct RenderSync {
+    shared: Arc<Shared>,
+    in_size_move: bool,
+    resize_timeout: Duration,
+    destroy_timeout: Duration,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// The render thread's side of the handshake.
+///
+/// Dropping the handle tells the window's thread that the render thread no longer uses the
+/// window.
+pub struct RenderHandle {
+    shared: Arc<Shared>,
+    /// The window handle, as an integer (window handles are not `Send`).
+    hwnd: usize,
+    /// The generation of the last frame returned.
+    last_generation: u64,
+}
+
+/// The size to render a frame at, from [`RenderHandle::frame`].
+///
+/// [`RenderHandle::frame`]: struct.RenderHandle.html#method.frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderFrame {
+    /// The client width, in pixels.
+    pub width: u32,
+    /// The client height, in pixels.
+    pub height: u32,
+    /// Whether the size changed since the previous frame, so buffers need resizing.
+    pub resized: bool,
+    generation: u64,
+}
+
+impl RenderSync {
+    /// Create both ends of the handshake for a window.
+    ///
+    /// Send the handle to the render thread. The initial size is the window's current client
+    /// size.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, to a window on the calling thread.
+    pub unsafe fn new(hwnd: HWND) -> (RenderSync, RenderHandle) {
+        let mut rect = mem::zeroed();
+        GetClientRect(hwnd, &mut rect);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                width: (rect.right - rect.left).max(0) as u32,
+                height: (rect.bottom - rect.top).max(0) as u32,
+                ..State::default()
+            }),
+            cond: Condvar::new(),
+        });
+        let sync = RenderSync {
+            shared: shared.clone(),
+            in_size_move: false,
+            resize_timeout: Duration::from_millis(100),
+            destroy_timeout: Duration::from_secs(1),
+            _not_send: PhantomData,
+        };
+        let handle = RenderHandle {
+            shared,
+            hwnd: hwnd as usize,
+            last_generation: 0,
+        };
+        (sync, handle)
+    }
+
+    /// Set how long a size change during a live resize waits for a frame at the new size.
+    ///
+    /// The default is 100ms. Zero disables the resize barrier.
+    pub fn set_resize_timeout(&mut self, timeout: Duration) {
+        self.resize_timeout = timeout;
+    }
+
+    /// Set how long `WM_DESTROY` waits for the render thread to drop its handle.
+    ///
+    /// The default is one second.
+    pub fn set_destroy_timeout(&mut self, timeout: Duration) {
+        self.destroy_timeout = timeout;
+    }
+
+    /// Process a window message.
+    ///
+    /// This only observes messages, so they should still be handled as usual. On `WM_SIZE`,
+    /// the new size is published to the render thread; during a live resize (between
+    /// `WM_ENTERSIZEMOVE` and `WM_EXITSIZEMOVE`), this then waits for a frame at that size,
+    /// so the window isn't shown stretched. On `WM_DESTROY`, the render thread is told the
+    /// window is going away, and this waits until it drops its handle.
+    ///
+    /// The waits are bounded by the timeouts, as the render thread can deadlock with the
+    /// window's thread if, while the window's thread is waiting, it sends a message to the
+    /// window (which some `Present` calls do).
+    pub fn process_message(&mut self, msg: UINT, _wparam: WPARAM, lparam: LPARAM) {
+        match msg {
+            WM_ENTERSIZEMOVE => self.in_size_move = true,
+            WM_EXITSIZEMOVE => self.in_size_move = false,
+            WM_SIZE => {
+                let (width, height) = (LOWORD(lparam as u32) as u32, HIWORD(lparam as u32) as u32);
+                let generation = {
+                    let mut state = self.shared.lock();
+                    if (state.width, state.height) == (width, height) {
+                        return;
+                    }
+                    state.width = width;
+                    state.height = height;
+                    state.generation += 1;
+                    state.generation
+                };
+                self.shared.cond.notify_all();
+                // A minimized window has nothing to show.
+                if self.in_size_move && width != 0 && height != 0 {
+                    self.shared.wait_until(self.resize_timeout, |state| {
+                        state.presented >= generation || state.released
+                    });
+                }
+            }
+            WM_DESTROY => {
+                self.shared.lock().destroyed = true;
+                self.shared.cond.notify_all();
+                self.shared
+                    .wait_until(self.destroy_timeout, |state| state.released);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl RenderHandle {
+    /// The window handle.
+    ///
+    /// It is valid until [`frame`](#method.frame) returns `None`.
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd as HWND
+    }
+
+    /// The size for the next frame, or `None` if the window is being destroyed.
+    ///
+    /// On `None`, release any resources tied to the window (such as the swapchain), then
+    /// drop the handle.
+    pub fn frame(&mut self) -> Option<RenderFrame> {
+        let state = self.shared.lock();
+        if state.destroyed {
+            return None;
+        }
+        let resized = state.generation != self.last_generation;
+        self.last_generation = state.generation;
+        Some(RenderFrame {
+            width: state.width,
+            height: state.height,
+            resized,
+            generation: state.generation,
+        })
+    }
+
+    /// Report that a frame has been presented, releasing a live resize waiting for it.
+    pub fn presented(&self, frame: &RenderFrame) {
+        let mut state = self.shared.lock();
+        if frame.generation > state.presented {
+            state.presented = frame.generation;
+            drop(state);
+            self.shared.cond.notify_all();
+        }
+    }
+
+    /// Wait until the size changes or the window is being destroyed, up to a timeout.
+    ///
+    /// This suits a render thread that only draws when needed. Returns whether there was a
+    /// change.
+    pub fn wait_for_change(&self, timeout: Duration) -> bool {
+        let last = self.last_generation;
+        self.shared
+            .wait_until(timeout, |state| state.generation != last || state.destroyed)
+    }
+}
+
+impl Drop for RenderHandle {
+    fn drop(&mut self) {
+        self.shared.lock().released = true;
+        self.shared.cond.notify_all();
+    }
+}
@@ -2,8 +2,16 @@ use std::mem;
 use std::ptr::null_mut;
 
 use winapi::shared::minwindef::BOOL;
-use winapi::shared::windef::HACCEL;
-use winapi::um::winuser::{DispatchMessageW, GetMessageW, TranslateAcceleratorW, TranslateMessage};
+use winapi::shared::windef::{HACCEL, HWND};
+use winapi::um::winuser::{
+    DispatchMessageW, GetAncestor, GetMessageW, TranslateAcceleratorW, TranslateMessage, GA_ROOT,
+    MSG, WM_KEYFIRST, WM_KEYLAST,
+};
+
+use crate::error::Error;
+use crate::ime::is_ime_key;
+use crate::ui_thread::{mark_ui_thread, UiThreadToken};
+use crate::window::window_state;
 
 /// A basic winapi runloop.
 ///
@@ -14,7 +22,13 @@ use winapi::um::winuser::{DispatchMessageW, GetMessageW, TranslateAcceleratorW,
 /// or other events, but these strategies are risky. In particular, the main runloop is not
 /// always in control; when the window is being resized, or a modal dialog is open, then
 /// that runloop takes precedence. For waking the UI thread from another thread,
-/// [`SendMessage`] is probably the best bet.
+/// [`SendMessage`] is probably the best bet, or [`Dispatcher`] to run a closure there.
+///
+/// Each UI thread runs its own loop, which dispatches only the messages of that thread's
+/// windows; see [`Dispatcher`] for running windows on several threads.
+///
+/// This is equivalent to `Runloop::new().accelerator(accel).run()`; see [`Runloop`] for
+/// more options.
 ///
 /// # Safety
 ///
@@ -22,17 +36,201 @@ use winapi::um::winuser::{DispatchMessageW, GetMessageW, TranslateAcceleratorW,
 ///
 /// [`WM_QUIT`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-quit
 /// [`SendMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-sendmessage
+/// [`Runloop`]: struct.Runloop.html
+/// [`Dispatcher`]: struct.Dispatcher.html
 pub unsafe fn runloop(accel: HACCEL) -> BOOL {
-    loop {
-        let mut msg = mem::MaybeUninit::uninit();
-        let res = GetMessageW(msg.as_mut_ptr(), null_mut(), 0, 0);
-        if res <= 0 {
-            return res;
+    Runloop::new().accelerator(accel).run()
+}
+
+type MessageFilter = Box<dyn FnMut(&MSG) -> bool>;
+
+/// A builder for a configurable runloop.
+pub struct Runloop {
+    accel: HACCEL,
+    translate_if: Option<MessageFilter>,
+    pre_translate: Option<MessageFilter>,
+    accelerators_during_ime: bool,
+}
+
+impl Runloop {
+    /// Create a runloop with the default behavior.
+    ///
+    /// This makes the calling thread a UI thread; see [`UiThreadToken`].
+    ///
+    /// [`UiThreadToken`]: struct.UiThreadToken.html
+    pub fn new() -> Runloop {
+        mark_ui_thread();
+        Runloop {
+            accel: null_mut(),
+            translate_if: None,
+            pre_translate: None,
+            accelerators_during_ime: false,
         }
-        let mut msg = msg.assume_init();
-        if accel.is_null() || TranslateAcceleratorW(msg.hwnd, accel, &mut msg) == 0 {
-            TranslateMessage(&msg);
-            DispatchMessageW(&msg);
+    }
+
+    /// A token for the thread that will run the loop, which is the calling thread.
+    pub fn ui_thread(&self) -> UiThreadToken {
+        UiThreadToken::assume()
+    }
+
+    /// Set the accelerator table.
+    ///
+    /// This table is used for messages to windows that don't have their own accelerator
+    /// table (see [`set_accelerator`](fn.set_accelerator.html)).
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HACCEL handle (though `null_mut()` is valid).
+    pub unsafe fn accelerator(mut self, accel: HACCEL) -> Self {
+        self.accel = accel;
+        self
+    }
+
+    /// Translate accelerators even while an IME composition is in progress.
+    ///
+    /// By default, key messages that belong to the IME (see [`is_composing`]) skip
+    /// accelerator translation, so that keys such as Ctrl with a letter reach the IME.
+    ///
+    /// [`is_composing`]: fn.is_composing.html
+    pub fn accelerators_during_ime(mut self) -> Self {
+        self.accelerators_during_ime = true;
+        self
+    }
+
+    /// Choose which messages are passed to [`TranslateMessage`].
+    ///
+    /// `TranslateMessage` generates `WM_CHAR` (and `WM_DEADCHAR`, `WM_SYSCHAR`) messages from
+    /// key down messages. An application that produces its own text input from `WM_KEYDOWN`
+    /// and the keyboard layout will see duplicate input unless it skips that step for the key
+    /// messages it owns. The predicate is called for each message (other than accelerators),
+    /// and the message is translated only if it returns `true`.
+    ///
+    /// Note that the `kb` feature relies on `WM_CHAR` messages, so windows using
+    /// `KeyboardState` should keep translation.
+    ///
+    /// [`TranslateMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-translatemessage
+    pub fn translate_if(mut self, f: impl FnMut(&MSG) -> bool + 'static) -> Self {
+        self.translate_if = Some(Box::new(f));
+        self
+    }
+
+    /// Give a function the first look at each message.
+    ///
+    /// The function is called for each message before accelerators and translation, and
+    /// returns whether it handled the message, in which case the message isn't translated or
+    /// dispatched. This is for hosted content with its own keyboard handling, such as
+    /// [`XamlIsland::pre_translate_message`] (with the `islands` feature), and for modeless
+    /// dialogs, with [`IsDialogMessage`].
+    ///
+    /// [`XamlIsland::pre_translate_message`]: struct.XamlIsland.html#method.pre_translate_message
+    /// [`IsDialogMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-isdialogmessagew
+    pub fn pre_translate(mut self, f: impl FnMut(&MSG) -> bool + 'static) -> Self {
+        self.pre_translate = Some(Box::new(f));
+        self
+    }
+
+    /// Never call `TranslateMessage`.
+    ///
+    /// See [`translate_if`](#method.translate_if).
+    pub fn no_translate(self) -> Self {
+        self.translate_if(|_| false)
+    }
+
+    /// Run the loop until [`WM_QUIT`].
+    ///
+    /// The return value is that of the last call to [`GetMessage`]: zero for `WM_QUIT`, and
+    /// -1 on error.
+    ///
+    /// [`WM_QUIT`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-quit
+    /// [`GetMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getmessagew
+    pub fn run(mut self) -> BOOL {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("runloop").entered();
+        unsafe {
+            loop {
+                let mut msg = mem::MaybeUninit::uninit();
+                let res = GetMessageW(msg.as_mut_ptr(), null_mut(), 0, 0);
+                if res <= 0 {
+                    return res;
+                }
+                let mut msg = msg.assume_init();
+                #[cfg(feature = "tracing")]
+                let _span = tracing::trace_span!(
+                    "runloop_message",
+                    msg = %crate::message::message_name(msg.message),
+                )
+                .entered();
+                if self
+                    .pre_translate
+                    .as_mut()
+                    .map(|f| f(&msg))
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+                // Accelerators only translate key messages, so other messages (such as the
+                // frequent mouse moves) skip the lookup.
+                let mut accel = null_mut();
+                if (WM_KEYFIRST..=WM_KEYLAST).contains(&msg.message) {
+                    accel = window_accelerator(msg.hwnd).unwrap_or(self.accel);
+                    if !accel.is_null()
+                        && !self.accelerators_during_ime
+                        && is_ime_key(msg.hwnd, msg.message, msg.wParam)
+                    {
+                        accel = null_mut();
+                    }
+                }
+                if accel.is_null() || TranslateAcceleratorW(msg.hwnd, accel, &mut msg) == 0 {
+                    if self.translate_if.as_mut().map(|f| f(&msg)).unwrap_or(true) {
+                        TranslateMessage(&msg);
+                    }
+                    DispatchMessageW(&msg);
+                }
+            }
         }
     }
 }
+
+impl Default for Runloop {
+    fn default() -> Self {
+        Runloop::new()
+    }
+}
+
+/// Set or clear the accelerator table of a top-level window.
+///
+/// Multi-window applications often have different shortcuts for different kinds of windows.
+/// When a message is for a window whose root (as found by [`GetAncestor`] with `GA_ROOT`)
+/// has an accelerator table, [`Runloop`] translates it with that table instead of its own.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid HWND reference. The `accel` argument must be a valid
+/// HACCEL handle (or `null_mut()` to clear it), which must outlive its use by the window.
+///
+/// [`GetAncestor`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getancestor
+/// [`Runloop`]: struct.Runloop.html
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_accelerator(hwnd: HWND, accel: HACCEL) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    state.accel.set(accel);
+    Ok(())
+}
+
+/// The accelerator table of the root window of `hwnd`, if any.
+unsafe fn window_accelerator(hwnd: HWND) -> Option<HACCEL> {
+    if hwnd.is_null() {
+        return None;
+    }
+    let root = GetAncestor(hwnd, GA_ROOT);
+    let accel = window_state(root)?.accel.get();
+    if accel.is_null() {
+        None
+    } else {
+        Some(accel)
+    }
+}
@@ -0,0 +1,63 @@
+//! Tearing down the crate's state on a thread.
+//!
+//! Plugins and tests may need to shut down all their windows and start again within one
+//! process. [`shutdown`] undoes what this crate has set up on the calling thread, so that a
+//! later setup starts from a clean state.
+//!
+//! [`shutdown`]: fn.shutdown.html
+
+use std::ptr::null_mut;
+
+use winapi::um::winuser::{
+    DestroyWindow, DispatchMessageW, PeekMessageW, TranslateMessage, UnregisterClassW, MSG,
+    PM_REMOVE, WM_QUIT,
+};
+
+use wio::wide::ToWide;
+
+use crate::popup::clear_light_dismiss;
+use crate::threads::{close_dispatcher, thread_windows, window_destroyed};
+
+/// Destroy this crate's windows and other state on the calling thread.
+///
+/// In order, this:
+///
+/// - disables light dismiss for all popups, removing the mouse hook it installs;
+/// - destroys every window created by [`WindowBuilder`] on the thread that still exists,
+///   newest first, which also drops their window procedures and window data;
+/// - destroys the thread's [`Dispatcher`], dropping any closures that haven't run;
+/// - dispatches the messages left in the thread's queue, discarding any `WM_QUIT`;
+/// - unregisters the window classes registered internally, by [`run`] and [`Dispatcher`].
+///
+/// Classes registered by the application are left alone; drop an [`OwnedWindowClass`] to
+/// unregister it. A class that still has windows on another thread stays registered.
+///
+/// Call this after the message loop returns, not from a window procedure.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Dispatcher`]: struct.Dispatcher.html
+/// [`run`]: fn.run.html
+/// [`OwnedWindowClass`]: struct.OwnedWindowClass.html
+pub fn shutdown() {
+    unsafe {
+        clear_light_dismiss();
+        // Destroying a window also destroys the windows it owns, so take them one at a time.
+        while let Some(&hwnd) = thread_windows().last() {
+            if DestroyWindow(hwnd) == 0 {
+                // Already gone without `WM_NCDESTROY` reaching us; just forget it.
+                window_destroyed(hwnd);
+            }
+        }
+        close_dispatcher();
+        let mut msg: MSG = std::mem::zeroed();
+        while PeekMessageW(&mut msg, null_mut(), 0, 0, PM_REMOVE) != 0 {
+            if msg.message != WM_QUIT {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        for name in &[crate::app::CLASS_NAME, crate::threads::DISPATCHER_CLASS] {
+            UnregisterClassW(name.to_wide_null().as_ptr(), null_mut());
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Magnetic snapping of windows while they are moved and resized.
+//!
+//! While the user drags a window, [`WM_MOVING`] and [`WM_SIZING`] carry the proposed window
+//! rectangle, which the window procedure may change. With [`Snap`] set on a window, edges that
+//! come within the snap distance of a monitor's work area edge, or of another window's edge,
+//! are moved to line up with it. The other windows are those created by [`WindowBuilder`] on
+//! the same thread (see [`thread_windows`]), so the windows of an application snap together,
+//! in the style of Winamp's panels.
+//!
+//! Snapping is computed from each proposed rectangle, which follows the mouse, so a window
+//! pulls free as soon as the mouse moves beyond the snap distance.
+//!
+//! [`WM_MOVING`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-moving
+//! [`WM_SIZING`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-sizing
+//! [`Snap`]: struct.Snap.html
+//! [`WindowBuilder`]: struct.WindowBuilder.html
+//! [`thread_windows`]: fn.thread_windows.html
+
+use std::mem;
+
+use winapi::shared::minwindef::WPARAM;
+use winapi::shared::windef::{HWND, RECT};
+use winapi::um::winuser::{
+    GetWindowLongW, GetWindowRect, IsIconic, IsWindowVisible, GWL_STYLE, WMSZ_BOTTOM,
+    WMSZ_BOTTOMLEFT, WMSZ_BOTTOMRIGHT, WMSZ_LEFT, WMSZ_RIGHT, WMSZ_TOP, WMSZ_TOPLEFT,
+    WMSZ_TOPRIGHT, WM_MOVING, WM_SIZING, WS_CHILD,
+};
+
+use crate::dpi::Dpi;
+use crate::dwm::frame_bounds;
+use crate::error::Error;
+use crate::geometry::Rect;
+use crate::monitor::Monitor;
+use crate::threads::thread_windows;
+use crate::window::window_state;
+
+/// Snapping settings for a window.
+///
+/// See [`set_snap`](fn.set_snap.html) and [`WindowBuilder::snap`].
+///
+/// [`WindowBuilder::snap`]: struct.WindowBuilder.html#method.snap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Snap {
+    distance: i32,
+    work_area: bool,
+    windows: bool,
+}
+
+impl Snap {
+    /// Snap to work area edges and other windows, within 10 pixels (at 96 DPI).
+    pub fn new() -> Snap {
+        Snap {
+            distance: 10,
+            work_area: true,
+            windows: true,
+        }
+    }
+
+    /// Set the snap distance, in pixels at 96 DPI.
+    ///
+    /// It is scaled to the window's DPI.
+    pub fn distance(mut self, distance: i32) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    /// Set whether to snap to the edges of the monitors' work areas.
+    pub fn work_area(mut self, snap: bool) -> Self {
+        self.work_area = snap;
+        self
+    }
+
+    /// Set whether to snap to the other windows of the thread.
+    pub fn windows(mut self, snap: bool) -> Self {
+        self.windows = snap;
+        self
+    }
+
+    /// Snap the proposed rectangle of a `WM_MOVING` or `WM_SIZING` message.
+    pub(crate) unsafe fn process_message(
+        &self,
+        hwnd: HWND,
+        msg: u32,
+        wparam: WPARAM,
+        rect: &mut RECT,
+    ) {
+        let edges = match msg {
+            WM_MOVING => Edges::ALL,
+            WM_SIZING => Edges::sizing(wparam),
+            _ => return,
+        };
+        let distance = Dpi::for_window(hwnd).scale(self.distance);
+        // Snap the visible frame, not the invisible resize border around it.
+        let mut window = mem::zeroed();
+        GetWindowRect(hwnd, &mut window);
+        let frame = frame_bounds(hwnd);
+        let insets = (
+            frame.left - window.left,
+            frame.top - window.top,
+            window.right - frame.right,
+            window.bottom - frame.bottom,
+        );
+        let visible = Rect {
+            left: rect.left + insets.0,
+            top: rect.top + insets.1,
+            right: rect.right - insets.2,
+            bottom: rect.bottom - insets.3,
+        };
+        let targets = self.targets(hwnd);
+        let snap = |value: i32, candidates: fn(&Target) -> Vec<i32>| {
+            targets
+                .iter()
+                .filter(|target| target.near(&visible, distance))
+                .flat_map(candidates)
+                .map(|candidate| candidate - value)
+                .filter(|delta| delta.abs() <= distance)
+                .min_by_key(|delta| delta.abs())
+        };
+        let left = snap(visible.left, Target::left_candidates);
+        let right = snap(visible.right, Target::right_candidates);
+        let top = snap(visible.top, Target::top_candidates);
+        let bottom = snap(visible.bottom, Target::bottom_candidates);
+        if edges == Edges::ALL {
+            let dx = closest(left, right);
+            let dy = closest(top, bottom);
+            rect.left += dx;
+            rect.right += dx;
+            rect.top += dy;
+            rect.bottom += dy;
+            return;
+        }
+        if edges.left {
+            rect.left += left.unwrap_or(0);
+        }
+        if edges.right {
+            rect.right += right.unwrap_or(0);
+        }
+        if edges.top {
+            rect.top += top.unwrap_or(0);
+        }
+        if edges.bottom {
+            rect.bottom += bottom.unwrap_or(0);
+        }
+    }
+
+    unsafe fn targets(&self, hwnd: HWND) -> Vec<Target> {
+        let mut targets = Vec::new();
+        if self.work_area {
+            targets.extend(
+                Monitor::all()
+                    .iter()
+                    .map(|monitor| Target::WorkArea(monitor.work_area())),
+            );
+        }
+        if self.windows {
+            targets.extend(
+                thread_windows()
+                    .into_iter()
+                    .filter(|&other| {
+                        other != hwnd
+                            && IsWindowVisible(other) != 0
+                            && IsIconic(other) == 0
+                            && GetWindowLongW(other, GWL_STYLE) as u32 & WS_CHILD == 0
+                    })
+                    .map(|other| Target::Window(frame_bounds(other))),
+            );
+        }
+        targets
+    }
+}
+
+impl Default for Snap {
+    fn default() -> Self {
+        Snap::new()
+    }
+}
+
+/// The smaller of two optional adjustments, or zero.
+fn closest(a: Option<i32>, b: Option<i32>) -> i32 {
+    match (a, b) {
+        (Some(a), Some(b)) if b.abs() < a.abs() => b,
+        (Some(a), _) => a,
+        (None, Some(b)) => b,
+        (None, None) => 0,
+    }
+}
+
+/// The edges being dragged.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Edges {
+    left: bool,
+    top: bool,
+    right: bool,
+    bottom: bool,
+}
+
+impl Edges {
+    const ALL: Edges = Edges {
+        left: true,
+        top: true,
+        right: true,
+        bottom: true,
+    };
+
+    /// The edges being dragged, from the `WMSZ` value of `WM_SIZING`.
+    fn sizing(wparam: WPARAM) -> Edges {
+        let edge = wparam as u32;
+        Edges {
+            left: matches!(edge, WMSZ_LEFT | WMSZ_TOPLEFT | WMSZ_BOTTOMLEFT),
+            top: matches!(edge, WMSZ_TOP | WMSZ_TOPLEFT | WMSZ_TOPRIGHT),
+            right: matches!(edge, WMSZ_RIGHT | WMSZ_TOPRIGHT | WMSZ_BOTTOMRIGHT),
+            bottom: matches!(edge, WMSZ_BOTTOM | WMSZ_BOTTOMLEFT | WMSZ_BOTTOMRIGHT),
+        }
+    }
+}
+
+/// Something to snap to.
+enum Target {
+    /// A work area, whose edges are snapped to from inside.
+    WorkArea(Rect),
+    /// Another window, whose edges are snapped to from either side.
+    Window(Rect),
+}
+
+impl Target {
+    /// Whether the target is close enough to a rectangle to snap to.
+    ///
+    /// A window's edges only attract a rectangle beside it; a window far above doesn't
+    /// align the sides of another.
+    fn near(&self, rect: &Rect, distance: i32) -> bool {
+        match self {
+            Target::WorkArea(_) => true,
+            Target::Window(other) => {
+                rect.left - distance < other.right
+                    && other.left < rect.right + distance
+                    && rect.top - distance < other.bottom
+                    && other.top < rect.bottom + distance
+            }
+        }
+    }
+
+    fn left_candidates(&self) -> Vec<i32> {
+        match self {
+            Target::WorkArea(area) => vec![area.left],
+            Target::Window(other) => vec![other.left, other.right],
+        }
+    }
+
+    fn right_candidates(&self) -> Vec<i32> {
+        match self {
+            Target::WorkArea(area) => vec![area.right],
+            Target::Window(other) => vec![other.left, other.right],
+        }
+    }
+
+    fn top_candidates(&self) -> Vec<i32> {
+        match self {
+            Target::WorkArea(area) => vec![area.top],
+            Target::Window(other) => vec![other.top, other.bottom],
+        }
+    }
+
+    fn bottom_candidates(&self) -> Vec<i32> {
+        match self {
+            Target::WorkArea(area) => vec![area.bottom],
+            Target::Window(other) => vec![other.top, other.bottom],
+        }
+    }
+}
+
+/// Set or clear the snapping of a window.
+///
+/// Snapping applies to `WM_MOVING` and `WM_SIZING`, before the window procedure sees them,
+/// so a window procedure that also adjusts the rectangle (for example, to keep an aspect
+/// ratio) sees the snapped one.
+///
+/// The window must have been created by [`WindowBuilder`] on the calling thread; otherwise,
+/// this returns [`Error::ForeignWindow`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_snap(hwnd: HWND, snap: Option<Snap>) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    state.snap.set(snap);
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Typed window styles.
+//!
+//! [`WindowStyle`] and [`WindowExStyle`] wrap the `WS_` and `WS_EX_` flags passed to
+//! [`CreateWindowEx`], so that a style can't be passed as an extended style or the reverse,
+//! and the common flags are available without importing winapi. They combine with `|`, and
+//! convert from raw `DWORD` values for flags that have no named constant here.
+//!
+//! [`WindowStyle`]: struct.WindowStyle.html
+//! [`WindowExStyle`]: struct.WindowExStyle.html
+//! [`CreateWindowEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createwindowexw
+
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+use winapi::shared::minwindef::DWORD;
+use winapi::um::winuser::{
+    WS_BORDER, WS_CAPTION, WS_CHILD, WS_CLIPCHILDREN, WS_CLIPSIBLINGS, WS_DISABLED, WS_DLGFRAME,
+    WS_EX_ACCEPTFILES, WS_EX_APPWINDOW, WS_EX_CLIENTEDGE, WS_EX_COMPOSITED, WS_EX_CONTROLPARENT,
+    WS_EX_DLGMODALFRAME, WS_EX_LAYERED, WS_EX_LAYOUTRTL, WS_EX_NOACTIVATE,
+    WS_EX_NOREDIRECTIONBITMAP, WS_EX_OVERLAPPEDWINDOW, WS_EX_STATICEDGE, WS_EX_TOOLWINDOW,
+    WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_EX_WINDOWEDGE, WS_HSCROLL, WS_MAXIMIZE, WS_MAXIMIZEBOX,
+    WS_MINIMIZE, WS_MINIMIZEBOX, WS_OVERLAPPED, WS_OVERLAPPEDWINDOW, WS_POPUP, WS_POPUPWINDOW,
+    WS_SYSMENU, WS_THICKFRAME, WS_VISIBLE, WS_VSCROLL,
+};
+
+/// Define a style type: a newtype over the raw flags, with set operations.
+macro_rules! style_type {
+    ($(#[$attr:meta])* $name:ident { $($(#[$const_attr:meta])* $const:ident = $value:expr,)* }) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        pub struct $name(DWORD);
+
+        impl $name {
+            $($(#[$const_attr])* pub const $const: $name = $name($value);)*
+
+            /// The raw flags.
+            pub fn bits(self) -> DWORD {
+                self.0
+            }
+
+            /// Whether all the flags in `other` are set.
+            pub fn contains(self, other: $name) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Set or clear the flags in `other`.
+            pub fn set(&mut self, other: $name, value: bool) {
+                if value {
+                    self.0 |= other.0;
+                } else {
+                    self.0 &= !other.0;
+                }
+            }
+        }
+
+        impl From<DWORD> for $name {
+            fn from(bits: DWORD) -> $name {
+                $name(bits)
+            }
+        }
+
+        impl From<$name> for DWORD {
+            fn from(style: $name) -> DWORD {
+                style.0
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+            fn bitor(self, rhs: $name) -> $name {
+                $name(self.0 | rhs.0)
+            }
+        }
+
+        impl BitOrAssign for $name {
+            fn bitor_assign(&mut self, rhs: $name) {
+                self.0 |= rhs.0;
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+            fn bitand(self, rhs: $name) -> $name {
+                $name(self.0 & rhs.0)
+            }
+        }
+
+        impl Not for $name {
+            type Output = $name;
+            fn not(self) -> $name {
+                $name(!self.0)
+            }
+        }
+    };
+}
+
+style_type! {
+    /// A window style, from the [Window Styles] enumeration.
+    ///
+    /// [Window Styles]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-styles
+    WindowStyle {
+        OVERLAPPED = WS_OVERLAPPED,
+        POPUP = WS_POPUP,
+        CHILD = WS_CHILD,
+        MINIMIZE = WS_MINIMIZE,
+        VISIBLE = WS_VISIBLE,
+        DISABLED = WS_DISABLED,
+        CLIP_SIBLINGS = WS_CLIPSIBLINGS,
+        CLIP_CHILDREN = WS_CLIPCHILDREN,
+        MAXIMIZE = WS_MAXIMIZE,
+        /// A title bar, which includes `BORDER`.
+        CAPTION = WS_CAPTION,
+        BORDER = WS_BORDER,
+        DLG_FRAME = WS_DLGFRAME,
+        VSCROLL = WS_VSCROLL,
+        HSCROLL = WS_HSCROLL,
+        /// The window menu; with `CAPTION`, this also shows the close button.
+        SYSMENU = WS_SYSMENU,
+        /// A sizing border.
+        THICK_FRAME = WS_THICKFRAME,
+        MINIMIZE_BOX = WS_MINIMIZEBOX,
+        MAXIMIZE_BOX = WS_MAXIMIZEBOX,
+        /// A standard resizable top-level window, with caption and all buttons.
+        OVERLAPPED_WINDOW = WS_OVERLAPPEDWINDOW,
+        POPUP_WINDOW = WS_POPUPWINDOW,
+    }
+}
+
+style_type! {
+    /// An extended window style, from the [Extended Window Styles] enumeration.
+    ///
+    /// [Extended Window Styles]: https://docs.microsoft.com/en-us/windows/win32/winmsg/extended-window-styles
+    WindowExStyle {
+        DLG_MODAL_FRAME = WS_EX_DLGMODALFRAME,
+        TOPMOST = WS_EX_TOPMOST,
+        ACCEPT_FILES = WS_EX_ACCEPTFILES,
+        TRANSPARENT = WS_EX_TRANSPARENT,
+        TOOL_WINDOW = WS_EX_TOOLWINDOW,
+        WINDOW_EDGE = WS_EX_WINDOWEDGE,
+        CLIENT_EDGE = WS_EX_CLIENTEDGE,
+        CONTROL_PARENT = WS_EX_CONTROLPARENT,
+        STATIC_EDGE = WS_EX_STATICEDGE,
+        /// Show a taskbar button even for an owned window.
+        APP_WINDOW = WS_EX_APPWINDOW,
+        OVERLAPPED_WINDOW = WS_EX_OVERLAPPEDWINDOW,
+        LAYERED = WS_EX_LAYERED,
+        /// Mirror the window layout, for right-to-left languages.
+        LAYOUT_RTL = WS_EX_LAYOUTRTL,
+        COMPOSITED = WS_EX_COMPOSITED,
+        /// No redirection bitmap; see [`WindowBuilder::ex_style`].
+        ///
+        /// [`WindowBuilder::ex_style`]: struct.WindowBuilder.html#method.ex_style
+        NO_REDIRECTION_BITMAP = WS_EX_NOREDIRECTIONBITMAP,
+        NO_ACTIVATE = WS_EX_NOACTIVATE,
+    }
+}
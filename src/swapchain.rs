@@ -0,0 +1,178 @@
+//! Resizing swapchains at the right time.
+//!
+//! A DXGI swapchain's buffers must be resized (with `ResizeBuffers`) when the window's client
+//! area changes size, and the timing is easy to get wrong: a minimized window reports a size
+//! of zero, which `ResizeBuffers` rejects or turns into a default size; the swapchain must
+//! not be resized from within a call that itself sends `WM_SIZE` (as `ResizeBuffers` does for
+//! fullscreen swapchains); and resizing on every `WM_SIZE` of a live resize may be too slow
+//! for some renderers. [`on_resize_for_swapchain`] installs a callback that is called
+//! with each new size, with these cases sorted out.
+//!
+//! [`on_resize_for_swapchain`]: fn.on_resize_for_swapchain.html
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{GetClientRect, IsIconic, WM_ENTERSIZEMOVE, WM_EXITSIZEMOVE, WM_SIZE};
+
+use crate::error::Error;
+use crate::window::window_state;
+
+/// A new client size, passed to the callback of [`on_resize_for_swapchain`].
+///
+/// [`on_resize_for_swapchain`]: fn.on_resize_for_swapchain.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapchainSize {
+    /// The client width, in pixels; zero when minimized.
+    pub width: u32,
+    /// The client height, in pixels; zero when minimized.
+    pub height: u32,
+    /// Whether the window is minimized, or otherwise has an empty client area.
+    ///
+    /// Don't resize the buffers to this size; stop presenting until the next call.
+    pub minimized: bool,
+    /// Whether the user is resizing the window.
+    ///
+    /// A renderer for which `ResizeBuffers` is slow can skip resizing while this is true, as
+    /// there is always a call with `live` false when the resize ends.
+    pub live: bool,
+}
+
+pub(crate) type SwapchainResizeFn = Box<dyn FnMut(SwapchainSize)>;
+
+/// The per-window state of the resize callback.
+#[derive(Default)]
+pub(crate) struct SwapchainResize {
+    callback: RefCell<Option<SwapchainResizeFn>>,
+    /// Set when the callback is replaced or removed, so that a callback taken out for a call
+    /// isn't put back.
+    replaced: Cell<bool>,
+    /// Whether the callback is running.
+    in_call: Cell<bool>,
+    /// Whether the size changed while the callback was running.
+    pending: Cell<bool>,
+    /// The size last passed to the callback.
+    last: Cell<Option<(u32, u32, bool)>>,
+    in_size_move: Cell<bool>,
+    resized_in_move: Cell<bool>,
+}
+
+impl SwapchainResize {
+    /// Observe a window message, calling the callback if the size changed.
+    pub(crate) unsafe fn process_message(&self, hwnd: HWND, msg: u32) {
+        match msg {
+            WM_ENTERSIZEMOVE => self.in_size_move.set(true),
+            WM_EXITSIZEMOVE => {
+                self.in_size_move.set(false);
+                if self.resized_in_move.replace(false) {
+                    self.notify(hwnd, true);
+                }
+            }
+            WM_SIZE => {
+                if self.in_size_move.get() {
+                    self.resized_in_move.set(true);
+                }
+                self.notify(hwnd, false);
+            }
+            _ => (),
+        }
+    }
+
+    unsafe fn notify(&self, hwnd: HWND, mut force: bool) {
+        // A size change from within the callback is delivered after it returns.
+        if self.in_call.get() {
+            self.pending.set(true);
+            return;
+        }
+        if self.callback.borrow().is_none() {
+            return;
+        }
+        loop {
+            let size = current_size(hwnd, self.in_size_move.get());
+            let key = (size.width, size.height, size.minimized);
+            if force || self.last.get() != Some(key) {
+                self.last.set(Some(key));
+                let mut callback = match self.callback.borrow_mut().take() {
+                    Some(callback) => callback,
+                    None => return,
+                };
+                self.replaced.set(false);
+                self.in_call.set(true);
+                callback(size);
+                self.in_call.set(false);
+                if !self.replaced.get() {
+                    *self.callback.borrow_mut() = Some(callback);
+                }
+            }
+            if !self.pending.replace(false) {
+                return;
+            }
+            force = false;
+        }
+    }
+
+    unsafe fn set(&self, hwnd: HWND, callback: Option<SwapchainResizeFn>) {
+        let size = current_size(hwnd, false);
+        *self.callback.borrow_mut() = callback;
+        self.replaced.set(true);
+        self.last
+            .set(Some((size.width, size.height, size.minimized)));
+    }
+}
+
+unsafe fn current_size(hwnd: HWND, live: bool) -> SwapchainSize {
+    let mut rect = mem::zeroed();
+    GetClientRect(hwnd, &mut rect);
+    let width = (rect.right - rect.left).max(0) as u32;
+    let height = (rect.bottom - rect.top).max(0) as u32;
+    let minimized = IsIconic(hwnd) != 0 || width == 0 || height == 0;
+    SwapchainSize {
+        width: if minimized { 0 } else { width },
+        height: if minimized { 0 } else { height },
+        minimized,
+        live,
+    }
+}
+
+/// Call a function whenever a window's client area changes size, to resize a swapchain.
+///
+/// The function is called synchronously, during `WM_SIZE` (before the window procedure sees
+/// it) or at the end of a live resize, in `WM_EXITSIZEMOVE`. It's called only when the size
+/// actually changed, and once when the window is minimized, with
+/// [`minimized`](struct.SwapchainSize.html#structfield.minimized) set. If the size changes
+/// again while it runs (for example because `ResizeBuffers` sent `WM_SIZE`), it isn't called
+/// reentrantly, but again after it returns. The function may call this to replace itself.
+///
+/// The function isn't called for the current size; create the swapchain at that size.
+///
+/// Returns [`Error::ForeignWindow`] if the window wasn't created by [`WindowBuilder`] on this
+/// thread.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+pub unsafe fn on_resize_for_swapchain(
+    hwnd: HWND,
+    f: impl FnMut(SwapchainSize) + 'static,
+) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    state.swapchain_resize.set(hwnd, Some(Box::new(f)));
+    Ok(())
+}
+
+/// Remove the function installed by [`on_resize_for_swapchain`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`on_resize_for_swapchain`]: fn.on_resize_for_swapchain.html
+pub unsafe fn clear_resize_for_swapchain(hwnd: HWND) -> Result<(), Error> {
+    let state = window_state(hwnd).ok_or(Error::ForeignWindow)?;
+    state.swapchain_resize.set(hwnd, None);
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! Tearing tabs off into new windows.
+//!
+//! In a browser-style tab strip, dragging a tab along the strip reorders it, and dragging it
+//! away from the strip tears it off into a new window, which then follows the mouse until the
+//! button is released, as if dragged by its caption. Drawing and reordering the tabs is up to
+//! the application; this module has the windowing half:
+//!
+//! * [`TabDrag`] classifies the mouse moves of a tab drag, with the system drag thresholds.
+//! * [`tear_off`] creates the new window under the cursor and hands the drag over to it.
+//! * [`begin_caption_drag`] starts a caption drag of an existing window, for example to move
+//!   the whole window when its last tab is dragged.
+//!
+//! The drop target is decided when the move ends: on `WM_EXITSIZEMOVE`, the torn-off window
+//! can check whether it was released over another window's tab strip, and merge into it.
+//!
+//! [`TabDrag`]: struct.TabDrag.html
+//! [`tear_off`]: fn.tear_off.html
+//! [`begin_caption_drag`]: fn.begin_caption_drag.html
+
+use std::mem;
+
+use winapi::shared::minwindef::{LPARAM, MAKELONG, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    ClientToScreen, DragDetect, GetCursorPos, PostMessageW, ReleaseCapture, SetForegroundWindow,
+    HTCAPTION, SM_CXDRAG, SM_CYDRAG, WM_NCLBUTTONDOWN,
+};
+
+use crate::dpi::Dpi;
+use crate::error::Error;
+use crate::geometry::{Point, Rect};
+use crate::hwnd::Hwnd;
+use crate::window::WindowBuilder;
+
+/// What a tab drag has become, from [`TabDrag::update`].
+///
+/// [`TabDrag::update`]: struct.TabDrag.html#method.update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TabDragState {
+    /// The mouse hasn't moved past the drag threshold; this may still be a click.
+    Pending,
+    /// The tab is being dragged along the strip, to reorder it.
+    Reorder,
+    /// The tab was dragged away from the strip, and should be torn off.
+    TearOff,
+}
+
+/// The state of a tab drag, started by a button press on a tab.
+///
+/// Feed it the mouse moves while the button is down (with the mouse captured). Once it
+/// returns `TearOff`, remove the tab from the strip and call [`tear_off`](fn.tear_off.html).
+#[derive(Clone, Debug)]
+pub struct TabDrag {
+    start: Point,
+    strip: Rect,
+    threshold: (i32, i32),
+    detach: i32,
+    state: TabDragState,
+}
+
+impl TabDrag {
+    /// Start tracking a drag from a point on a tab, in client coordinates.
+    ///
+    /// The strip is the area of the tab strip, in the same coordinates. The thresholds are
+    /// the system drag thresholds for the window's DPI, and a tab tears off when dragged more
+    /// than three times the vertical threshold above or below the strip.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn new(hwnd: HWND, start: Point, strip: Rect) -> TabDrag {
+        let dpi = Dpi::for_window(hwnd);
+        let threshold = (dpi.system_metric(SM_CXDRAG), dpi.system_metric(SM_CYDRAG));
+        TabDrag {
+            start,
+            strip,
+            threshold,
+            detach: threshold.1 * 3,
+            state: TabDragState::Pending,
+        }
+    }
+
+    /// Set how far outside the strip, vertically, the tab tears off, in pixels.
+    pub fn detach_distance(mut self, distance: i32) -> Self {
+        self.detach = distance;
+        self
+    }
+
+    /// Update with a mouse position, in client coordinates.
+    ///
+    /// Once the drag becomes `TearOff`, it stays so.
+    pub fn update(&mut self, pt: Point) -> TabDragState {
+        if self.state == TabDragState::Pending
+            && ((pt.x - self.start.x).abs() > self.threshold.0
+                || (pt.y - self.start.y).abs() > self.threshold.1)
+        {
+            self.state = TabDragState::Reorder;
+        }
+        if self.state == TabDragState::Reorder
+            && (pt.y < self.strip.top - self.detach || pt.y >= self.strip.bottom + self.detach)
+        {
+            self.state = TabDragState::TearOff;
+        }
+        self.state
+    }
+
+    /// The current state.
+    pub fn state(&self) -> TabDragState {
+        self.state
+    }
+}
+
+/// Wait for the mouse to move past the drag threshold, or the button to be released.
+///
+/// This wraps [`DragDetect`], which captures the mouse and runs its own message loop until
+/// either happens, and returns `true` for a drag. The point is where the button was pressed,
+/// in client coordinates. It's a simpler alternative to [`TabDrag`] for telling a click from
+/// the start of a drag.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`DragDetect`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-dragdetect
+/// [`TabDrag`]: struct.TabDrag.html
+pub unsafe fn detect_drag(hwnd: HWND, pt: Point) -> bool {
+    let mut pt = pt.into();
+    ClientToScreen(hwnd, &mut pt);
+    DragDetect(hwnd, pt) != 0
+}
+
+/// Start moving a window with the mouse, as if it were dragged by its caption.
+///
+/// The left button must be down; the move follows the mouse until it is released. Any mouse
+/// capture (such as that of a tab drag in another window) is released first. The move runs
+/// in the system's modal loop, starting once the posted message is dispatched, and sends
+/// `WM_ENTERSIZEMOVE` and `WM_EXITSIZEMOVE` as usual.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn begin_caption_drag(hwnd: HWND) -> Result<(), Error> {
+    let mut pt = mem::zeroed();
+    if GetCursorPos(&mut pt) == 0 {
+        return Err(Error::last_error());
+    }
+    ReleaseCapture();
+    let lparam = MAKELONG(pt.x as u16, pt.y as u16) as LPARAM;
+    if PostMessageW(hwnd, WM_NCLBUTTONDOWN, HTCAPTION as WPARAM, lparam) == 0 {
+        return Err(Error::last_error());
+    }
+    Ok(())
+}
+
+/// Tear a tab off into a new window, which follows the mouse.
+///
+/// The window is built from `builder`, which is usually configured like the window the tab
+/// came from (same class, style, and size), and placed so that the cursor is at `grab`,
+/// relative to the top left corner of the window: where the tab would be in the new window's
+/// tab strip, plus where within the tab it was grabbed. A position set on the builder is
+/// overridden, so it shouldn't be [centered](struct.WindowBuilder.html#method.centered).
+/// The window is then activated and a [caption drag](fn.begin_caption_drag.html) started,
+/// taking the mouse over from the window the tab came from.
+///
+/// Call this while the left button is still down, from the tab drag's mouse handling.
+pub fn tear_off(builder: WindowBuilder, grab: Point) -> Result<Hwnd, Error> {
+    unsafe {
+        let mut pt = mem::zeroed();
+        if GetCursorPos(&mut pt) == 0 {
+            return Err(Error::last_error());
+        }
+        let hwnd = builder.position(pt.x - grab.x, pt.y - grab.y).build()?;
+        SetForegroundWindow(hwnd.raw());
+        begin_caption_drag(hwnd.raw())?;
+        Ok(hwnd)
+    }
+}
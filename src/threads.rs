@@ -0,0 +1,292 @@
+//! Running windows on more than one UI thread.
+//!
+//! Each window belongs to the thread that created it, and its messages are dispatched by that
+//! thread's message loop. A window whose handling is slow (a render-intensive tool window,
+//! say, or a preview that decodes large images) stalls every other window on its thread. The
+//! remedy is to give it a thread of its own:
+//!
+//! 1. Spawn a thread, and on it register any classes not yet registered, create the window
+//!    with [`WindowBuilder`], and run a [`Runloop`]. Window classes are shared by the whole
+//!    process, so a class registered on the main thread can be used here too.
+//! 2. Talk to the window from other threads with a [`Dispatcher`], which runs closures on
+//!    the window's thread. Get one with [`Dispatcher::current`] on that thread and send a
+//!    clone back, for example over a channel.
+//! 3. End the thread by calling `PostQuitMessage` on it (from a dispatched closure, or when
+//!    its last window is destroyed), which makes the runloop return.
+//!
+//! [`thread_windows`] lists the windows created by this crate on the calling thread, so each
+//! thread can find and close its own windows.
+//!
+//! Input is separate per thread: each thread has its own focus, active window, and keyboard
+//! state. When a window on one thread owns a window on another (or is its parent), the system
+//! attaches the two threads' input, which synchronizes their input processing, so a stall on
+//! either thread stalls input on both. Prefer unowned top-level windows for independence.
+//! [`InputAttachment`] attaches input explicitly, for short operations such as moving focus
+//! to a window on another thread.
+//!
+//! [`WindowBuilder`]: struct.WindowBuilder.html
+//! [`Runloop`]: struct.Runloop.html
+//! [`Dispatcher`]: struct.Dispatcher.html
+//! [`Dispatcher::current`]: struct.Dispatcher.html#method.current
+//! [`thread_windows`]: fn.thread_windows.html
+//! [`InputAttachment`]: struct.InputAttachment.html
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use winapi::shared::minwindef::{DWORD, FALSE, TRUE};
+use winapi::shared::windef::HWND;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{AttachThreadInput, GetWindowThreadProcessId, PostMessageW, WM_USER};
+
+use crate::error::Error;
+use crate::hwnd::Hwnd;
+use crate::window::{FnMutProc, WindowBuilder, WindowClass};
+
+pub(crate) const DISPATCHER_CLASS: &str = "win-win.dispatcher";
+
+/// The message that wakes the dispatcher window to run queued closures.
+const WM_DISPATCH: u32 = WM_USER;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+thread_local! {
+    /// Windows created by `WindowBuilder` on this thread and not yet destroyed.
+    static WINDOWS: RefCell<Vec<HWND>> = const { RefCell::new(Vec::new()) };
+    /// This thread's dispatcher, created on first use.
+    static DISPATCHER: RefCell<Option<DispatcherWindow>> = const { RefCell::new(None) };
+}
+
+/// The windows created by this crate on the calling thread that haven't been destroyed,
+/// in order of creation.
+///
+/// The hidden window used by [`Dispatcher`](struct.Dispatcher.html) isn't included.
+pub fn thread_windows() -> Vec<HWND> {
+    WINDOWS
+        .try_with(|windows| windows.borrow().clone())
+        .unwrap_or_default()
+}
+
+// These use `try_with`, as windows may be destroyed by the destructors of other thread-locals
+// (such as the dispatcher's) when the thread exits, after `WINDOWS` is gone; panicking in a
+// window procedure would abort.
+
+pub(crate) fn window_created(hwnd: HWND) {
+    let _ = WINDOWS.try_with(|windows| windows.borrow_mut().push(hwnd));
+}
+
+pub(crate) fn window_destroyed(hwnd: HWND) {
+    let _ = WINDOWS.try_with(|windows| windows.borrow_mut().retain(|&w| w != hwnd));
+}
+
+/// Destroy the calling thread's dispatcher, if it has one.
+pub(crate) fn close_dispatcher() {
+    // Taken out first, as dropping it destroys the window, which may reenter.
+    let window = DISPATCHER.with(|slot| slot.borrow_mut().take());
+    drop(window);
+}
+
+struct Queue {
+    tasks: Mutex<VecDeque<Task>>,
+    /// The dispatcher window, as an integer (window handles are not `Send`); 0 once the
+    /// thread has shut down its dispatcher.
+    hwnd: AtomicUsize,
+    thread_id: DWORD,
+}
+
+/// Runs closures on a UI thread, from any thread.
+///
+/// Each UI thread has one dispatcher, a hidden [message-only window], created by the first
+/// call to [`current`] on the thread. The dispatcher can be cloned and sent to other
+/// threads; closures passed to [`dispatch`] are run in order by the UI thread's message loop
+/// (including modal loops such as those of dialogs and window resizing). The dispatcher
+/// window is destroyed when the thread exits, after which dispatching fails.
+///
+/// [message-only window]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#message-only-windows
+/// [`current`]: #method.current
+/// [`dispatch`]: #method.dispatch
+#[derive(Clone)]
+pub struct Dispatcher {
+    queue: Arc<Queue>,
+}
+
+/// The thread-local owner of a dispatcher window.
+struct DispatcherWindow {
+    hwnd: Hwnd,
+    dispatcher: Dispatcher,
+}
+
+impl Dispatcher {
+    /// The dispatcher for the calling thread, creating it if needed.
+    pub fn current() -> Result<Dispatcher, Error> {
+        DISPATCHER.with(|slot| {
+            let mut slot = slot.borrow_mut();
+            if let Some(window) = slot.as_ref() {
+                return Ok(window.dispatcher.clone());
+            }
+            let window = DispatcherWindow::new()?;
+            let dispatcher = window.dispatcher.clone();
+            *slot = Some(window);
+            Ok(dispatcher)
+        })
+    }
+
+    /// Run a closure on the dispatcher's thread.
+    ///
+    /// This returns immediately; the closure runs when the thread next processes messages.
+    /// If called on the dispatcher's own thread, the closure still waits for the message
+    /// loop. Returns [`Error::WindowDestroyed`] if the thread has exited.
+    ///
+    /// [`Error::WindowDestroyed`]: enum.Error.html#variant.WindowDestroyed
+    pub fn dispatch(&self, f: impl FnOnce() + Send + 'static) -> Result<(), Error> {
+        let mut tasks = self.queue.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let hwnd = self.queue.hwnd.load(Ordering::Acquire) as HWND;
+        if hwnd.is_null() {
+            return Err(Error::WindowDestroyed);
+        }
+        tasks.push_back(Box::new(f));
+        // A wake message is already pending if there were queued tasks.
+        if tasks.len() == 1 && unsafe { PostMessageW(hwnd, WM_DISPATCH, 0, 0) } == 0 {
+            let err = Error::last_error();
+            tasks.pop_back();
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// The id of the dispatcher's thread.
+    pub fn thread_id(&self) -> u32 {
+        self.queue.thread_id
+    }
+
+    /// Whether the calling thread is the dispatcher's thread.
+    pub fn is_current(&self) -> bool {
+        unsafe { GetCurrentThreadId() == self.queue.thread_id }
+    }
+}
+
+impl fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("thread_id", &self.queue.thread_id)
+            .finish()
+    }
+}
+
+impl DispatcherWindow {
+    fn new() -> Result<DispatcherWindow, Error> {
+        let queue = Arc::new(Queue {
+            tasks: Mutex::new(VecDeque::new()),
+            hwnd: AtomicUsize::new(0),
+            thread_id: unsafe { GetCurrentThreadId() },
+        });
+        let class = match WindowClass::builder(DISPATCHER_CLASS).build() {
+            Ok(class) => class,
+            // Already registered by another thread.
+            Err(_) => WindowClass::from_name(DISPATCHER_CLASS),
+        };
+        let proc_queue = queue.clone();
+        let proc = FnMutProc::new(move |_hwnd, msg, _wparam, _lparam| {
+            if msg != WM_DISPATCH {
+                return None;
+            }
+            loop {
+                // Popped into a local, so the lock is released before the task runs, and
+                // tasks can dispatch more.
+                let task = {
+                    let mut tasks = proc_queue.tasks.lock().unwrap_or_else(|e| e.into_inner());
+                    tasks.pop_front()
+                };
+                match task {
+                    Some(task) => task(),
+                    None => return Some(0),
+                }
+            }
+        });
+        let hwnd = WindowBuilder::new(proc, &class).message_only().build()?;
+        window_destroyed(hwnd.raw());
+        queue.hwnd.store(hwnd.raw() as usize, Ordering::Release);
+        Ok(DispatcherWindow {
+            hwnd,
+            dispatcher: Dispatcher { queue },
+        })
+    }
+}
+
+impl Drop for DispatcherWindow {
+    fn drop(&mut self) {
+        let queue = &self.dispatcher.queue;
+        let tasks = {
+            let mut tasks = queue.tasks.lock().unwrap_or_else(|e| e.into_inner());
+            queue.hwnd.store(0, Ordering::Release);
+            mem::take(&mut *tasks)
+        };
+        // Closures that never ran are dropped here, outside the lock.
+        drop(tasks);
+        let _ = self.hwnd.destroy(self.hwnd.ui_thread());
+    }
+}
+
+/// The id of the thread that created a window.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn window_thread_id(hwnd: HWND) -> u32 {
+    GetWindowThreadProcessId(hwnd, null_mut())
+}
+
+/// The calling thread's input, attached to another thread's, until dropped.
+///
+/// While attached, the two threads share focus, the active window, and keyboard state, so
+/// that (for example) [`SetFocus`] can move the focus to a window on the other thread. As
+/// the threads' input processing is also synchronized, keep the attachment short. See
+/// [`AttachThreadInput`].
+///
+/// [`SetFocus`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setfocus
+/// [`AttachThreadInput`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-attachthreadinput
+#[derive(Debug)]
+pub struct InputAttachment {
+    from: DWORD,
+    to: DWORD,
+}
+
+impl InputAttachment {
+    /// Attach the calling thread's input to that of another thread.
+    ///
+    /// This fails for the calling thread itself, and for threads without a message queue.
+    pub fn new(thread_id: u32) -> Result<InputAttachment, Error> {
+        unsafe {
+            let from = GetCurrentThreadId();
+            if AttachThreadInput(from, thread_id, TRUE) == 0 {
+                return Err(Error::last_error());
+            }
+            Ok(InputAttachment {
+                from,
+                to: thread_id,
+            })
+        }
+    }
+
+    /// Attach the calling thread's input to that of a window's thread.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn to_window(hwnd: HWND) -> Result<InputAttachment, Error> {
+        InputAttachment::new(window_thread_id(hwnd))
+    }
+}
+
+impl Drop for InputAttachment {
+    fn drop(&mut self) {
+        unsafe {
+            AttachThreadInput(self.from, self.to, FALSE);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! The touch keyboard and input pane.
+//!
+//! On tablets (and in tablet mode), the touch keyboard covers part of the screen when it
+//! appears. Apps that want to keep the focused text field visible need to know where it is;
+//! [`InputPaneTracker`] reports that through the [`IFrameworkInputPane`] interface.
+//!
+//! Classic Win32 apps also don't get the touch keyboard automatically when a custom-drawn text
+//! field gains focus; [`show_touch_keyboard`] and [`hide_touch_keyboard`] invoke it
+//! explicitly.
+//!
+//! All of these require COM to be initialized on the calling thread, and are only available
+//! on Windows 8 and later.
+//!
+//! [`InputPaneTracker`]: struct.InputPaneTracker.html
+//! [`IFrameworkInputPane`]: https://docs.microsoft.com/en-us/windows/win32/api/shobjidl_core/nn-shobjidl_core-iframeworkinputpane
+//! [`show_touch_keyboard`]: fn.show_touch_keyboard.html
+//! [`hide_touch_keyboard`]: fn.hide_touch_keyboard.html
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ptr::{null, null_mut};
+
+use winapi::shared::guiddef::{IsEqualGUID, GUID, REFIID};
+use winapi::shared::minwindef::{BOOL, DWORD, ULONG};
+use winapi::shared::windef::{HWND, RECT};
+use winapi::shared::winerror::{E_NOINTERFACE, E_POINTER, HRESULT, SUCCEEDED, S_OK};
+use winapi::shared::wtypesbase::{CLSCTX_INPROC_SERVER, CLSCTX_LOCAL_SERVER};
+use winapi::um::combaseapi::CoCreateInstance;
+use winapi::um::processenv::ExpandEnvironmentStringsW;
+use winapi::um::shellapi::ShellExecuteW;
+use winapi::um::shobjidl_core::FrameworkInputPane;
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::LPWSTR;
+use winapi::um::winuser::{GetDesktopWindow, SW_SHOWNOACTIVATE};
+use winapi::{Class, Interface};
+
+use wio::com::ComPtr;
+use wio::wide::ToWide;
+
+use crate::error::Error;
+use crate::geometry::Rect;
+
+use self::interfaces::{
+    IFrameworkInputPane, IFrameworkInputPaneHandler, IFrameworkInputPaneHandlerVtbl, ITipInvocation,
+};
+
+// These interfaces are not defined in winapi.
+#[allow(non_snake_case)]
+mod interfaces {
+    use winapi::shared::minwindef::{BOOL, DWORD};
+    use winapi::shared::windef::{HWND, RECT};
+    use winapi::shared::winerror::HRESULT;
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::RIDL;
+
+    RIDL! {#[uuid(0x226c537b, 0x1e76, 0x4d9e, 0xa0, 0xef, 0xe9, 0xbf, 0x5e, 0x3b, 0x5f, 0x0a)]
+    interface IFrameworkInputPaneHandler(IFrameworkInputPaneHandlerVtbl): IUnknown(IUnknownVtbl) {
+        fn Showing(
+            prcInputPaneScreenLocation: *mut RECT,
+            fEnsureFocusedElementInView: BOOL,
+        ) -> HRESULT,
+        fn Hiding(
+            fEnsureFocusedElementInView: BOOL,
+        ) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x5752238b, 0x24f0, 0x495a, 0x82, 0xf1, 0x2f, 0xd5, 0x93, 0x05, 0x67, 0x96)]
+    interface IFrameworkInputPane(IFrameworkInputPaneVtbl): IUnknown(IUnknownVtbl) {
+        fn Advise(
+            pWindow: *mut IUnknown,
+            pHandler: *mut IFrameworkInputPaneHandler,
+            pdwCookie: *mut DWORD,
+        ) -> HRESULT,
+        fn AdviseWithHWND(
+            hwnd: HWND,
+            pHandler: *mut IFrameworkInputPaneHandler,
+            pdwCookie: *mut DWORD,
+        ) -> HRESULT,
+        fn Unadvise(
+            dwCookie: DWORD,
+        ) -> HRESULT,
+        fn Location(
+            prcInputPaneScreenLocation: *mut RECT,
+        ) -> HRESULT,
+    }}
+
+    // This interface is undocumented, but is the only way to invoke the touch keyboard from a
+    // desktop app. It is used by (among others) Chromium and Firefox.
+    RIDL! {#[uuid(0x37c994e7, 0x432b, 0x4834, 0xa2, 0xf7, 0xdc, 0xe1, 0xf1, 0x3b, 0x83, 0x4b)]
+    interface ITipInvocation(ITipInvocationVtbl): IUnknown(IUnknownVtbl) {
+        fn Toggle(
+            wnd: HWND,
+        ) -> HRESULT,
+    }}
+}
+
+const CLSID_UIHOSTNOLAUNCH: GUID = GUID {
+    Data1: 0x4ce576fa,
+    Data2: 0x83dc,
+    Data3: 0x4f88,
+    Data4: [0x95, 0x1c, 0x9d, 0x07, 0x82, 0xb4, 0xe3, 0x76],
+};
+
+/// A change in the input pane (touch keyboard) state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputPaneEvent {
+    /// The input pane is appearing.
+    Showing {
+        /// The area occluded by the input pane, in screen coordinates.
+        rect: Rect,
+        /// Whether the app should scroll the focused element into view.
+        ensure_focused_in_view: bool,
+    },
+    /// The input pane is disappearing.
+    Hiding {
+        /// Whether the app should scroll the focused element into view.
+        ensure_focused_in_view: bool,
+    },
+}
+
+/// Tracks the input pane for a window.
+///
+/// The callback is invoked (on the window's thread) when the touch keyboard appears or
+/// disappears. Tracking stops when this is dropped.
+pub struct InputPaneTracker {
+    pane: ComPtr<IFrameworkInputPane>,
+    cookie: DWORD,
+}
+
+#[repr(C)]
+struct PaneHandler {
+    vtbl: *const IFrameworkInputPaneHandlerVtbl,
+    refcount: Cell<ULONG>,
+    callback: RefCell<Box<dyn FnMut(InputPaneEvent)>>,
+}
+
+static PANE_HANDLER_VTBL: IFrameworkInputPaneHandlerVtbl = IFrameworkInputPaneHandlerVtbl {
+    parent: IUnknownVtbl {
+        QueryInterface: handler_query_interface,
+        AddRef: handler_add_ref,
+        Release: handler_release,
+    },
+    Showing: handler_showing,
+    Hiding: handler_hiding,
+};
+
+unsafe extern "system" fn handler_query_interface(
+    this: *mut IUnknown,
+    riid: REFIID,
+    ppv: *mut *mut winapi::ctypes::c_void,
+) -> HRESULT {
+    if ppv.is_null() {
+        return E_POINTER;
+    }
+    if IsEqualGUID(&*riid, &IUnknown::uuidof())
+        || IsEqualGUID(&*riid, &IFrameworkInputPaneHandler::uuidof())
+    {
+        handler_add_ref(this);
+        *ppv = this as *mut _;
+        S_OK
+    } else {
+        *ppv = null_mut();
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn handler_add_ref(this: *mut IUnknown) -> ULONG {
+    let handler = &*(this as *const PaneHandler);
+    let count = handler.refcount.get() + 1;
+    handler.refcount.set(count);
+    count
+}
+
+unsafe extern "system" fn handler_release(this: *mut IUnknown) -> ULONG {
+    let count = {
+        let handler = &*(this as *const PaneHandler);
+        let count = handler.refcount.get() - 1;
+        handler.refcount.set(count);
+        count
+    };
+    if count == 0 {
+        mem::drop(Box::from_raw(this as *mut PaneHandler));
+    }
+    count
+}
+
+unsafe extern "system" fn handler_showing(
+    this: *mut IFrameworkInputPaneHandler,
+    rect: *mut RECT,
+    ensure_focused_in_view: BOOL,
+) -> HRESULT {
+    let handler = &*(this as *const PaneHandler);
+    let rect = if rect.is_null() {
+        Rect::default()
+    } else {
+        (*rect).into()
+    };
+    let event = InputPaneEvent::Showing {
+        rect,
+        ensure_focused_in_view: ensure_focused_in_view != 0,
+    };
+    if let Ok(mut callback) = handler.callback.try_borrow_mut() {
+        callback(event);
+    }
+    S_OK
+}
+
+unsafe extern "system" fn handler_hiding(
+    this: *mut IFrameworkInputPaneHandler,
+    ensure_focused_in_view: BOOL,
+) -> HRESULT {
+    let handler = &*(this as *const PaneHandler);
+    let event = InputPaneEvent::Hiding {
+        ensure_focused_in_view: ensure_focused_in_view != 0,
+    };
+    if let Ok(mut callback) = handler.callback.try_borrow_mut() {
+        callback(event);
+    }
+    S_OK
+}
+
+unsafe fn input_pane() -> Result<ComPtr<IFrameworkInputPane>, Error> {
+    let mut pane = null_mut();
+    let hr = CoCreateInstance(
+        &FrameworkInputPane::uuidof(),
+        null_mut(),
+        CLSCTX_INPROC_SERVER,
+        &IFrameworkInputPane::uuidof(),
+        &mut pane,
+    );
+    if SUCCEEDED(hr) {
+        Ok(ComPtr::from_raw(pane as *mut IFrameworkInputPane))
+    } else {
+        Err(Error::Hresult(hr))
+    }
+}
+
+impl InputPaneTracker {
+    /// Start tracking the input pane for the window.
+    ///
+    /// # Safety
+    ///
+    /// The `hwnd` argument must be a valid HWND reference, and COM must be initialized on the
+    /// calling thread.
+    pub unsafe fn new(
+        hwnd: HWND,
+        callback: impl FnMut(InputPaneEvent) + 'static,
+    ) -> Result<InputPaneTracker, Error> {
+        let pane = input_pane()?;
+        let handler = Box::into_raw(Box::new(PaneHandler {
+            vtbl: &PANE_HANDLER_VTBL,
+            refcount: Cell::new(1),
+            callback: RefCell::new(Box::new(callback)),
+        }));
+        let mut cookie = 0;
+        let hr = pane.AdviseWithHWND(
+            hwnd,
+            handler as *mut IFrameworkInputPaneHandler,
+            &mut cookie,
+        );
+        // The input pane holds its own reference if the call succeeded.
+        handler_release(handler as *mut IUnknown);
+        if SUCCEEDED(hr) {
+            Ok(InputPaneTracker { pane, cookie })
+        } else {
+            Err(Error::Hresult(hr))
+        }
+    }
+
+    /// The area currently occluded by the input pane, in screen coordinates.
+    ///
+    /// The rectangle is empty when the input pane is hidden.
+    pub fn location(&self) -> Result<Rect, Error> {
+        unsafe { pane_location(&self.pane) }
+    }
+}
+
+impl Drop for InputPaneTracker {
+    fn drop(&mut self) {
+        unsafe {
+            self.pane.Unadvise(self.cookie);
+        }
+    }
+}
+
+unsafe fn pane_location(pane: &IFrameworkInputPane) -> Result<Rect, Error> {
+    let mut rect = mem::zeroed();
+    let hr = pane.Location(&mut rect);
+    if SUCCEEDED(hr) {
+        Ok(rect.into())
+    } else {
+        Err(Error::Hresult(hr))
+    }
+}
+
+/// Whether the touch keyboard is currently showing.
+///
+/// # Safety
+///
+/// COM must be initialized on the calling thread.
+pub unsafe fn is_touch_keyboard_visible() -> Result<bool, Error> {
+    let rect = pane_location(&*input_pane()?)?;
+    Ok(rect.width() > 0 && rect.height() > 0)
+}
+
+/// Show the touch keyboard, if it is not already showing.
+///
+/// This starts the touch keyboard process (`TabTip.exe`) if needed. Call it when a
+/// custom-drawn text field gains focus from touch or pen input.
+///
+/// # Safety
+///
+/// COM must be initialized on the calling thread.
+pub unsafe fn show_touch_keyboard() -> Result<(), Error> {
+    if !is_touch_keyboard_visible()? {
+        toggle_touch_keyboard()?;
+    }
+    Ok(())
+}
+
+/// Hide the touch keyboard, if it is showing.
+///
+/// # Safety
+///
+/// COM must be initialized on the calling thread.
+pub unsafe fn hide_touch_keyboard() -> Result<(), Error> {
+    if is_touch_keyboard_visible()? {
+        toggle_touch_keyboard()?;
+    }
+    Ok(())
+}
+
+unsafe fn toggle_touch_keyboard() -> Result<(), Error> {
+    let mut tip = null_mut();
+    let mut hr = CoCreateInstance(
+        &CLSID_UIHOSTNOLAUNCH,
+        null_mut(),
+        CLSCTX_INPROC_SERVER | CLSCTX_LOCAL_SERVER,
+        &ITipInvocation::uuidof(),
+        &mut tip,
+    );
+    if !SUCCEEDED(hr) {
+        // The host isn't running; starting it shows the keyboard.
+        return launch_tabtip();
+    }
+    let tip = ComPtr::from_raw(tip as *mut ITipInvocation);
+    hr = tip.Toggle(GetDesktopWindow());
+    if SUCCEEDED(hr) {
+        Ok(())
+    } else {
+        Err(Error::Hresult(hr))
+    }
+}
+
+unsafe fn launch_tabtip() -> Result<(), Error> {
+    let path = r"%CommonProgramFiles%\microsoft shared\ink\TabTip.exe".to_wide_null();
+    let mut expanded = [0u16; 260];
+    let len = ExpandEnvironmentStringsW(
+        path.as_ptr(),
+        expanded.as_mut_ptr() as LPWSTR,
+        expanded.len() as DWORD,
+    );
+    if len == 0 || len as usize > expanded.len() {
+        return Err(Error::last_error());
+    }
+    let open = "open".to_wide_null();
+    let result = ShellExecuteW(
+        null_mut(),
+        open.as_ptr(),
+        expanded.as_ptr(),
+        null(),
+        null(),
+        SW_SHOWNOACTIVATE,
+    );
+    // ShellExecute returns a value greater than 32 on success.
+    if result as usize > 32 {
+        Ok(())
+    } else {
+        Err(Error::Win32(result as DWORD))
+    }
+}
@@ -0,0 +1,163 @@
+//! A token for calls that must be made on the UI thread.
+//!
+//! Windows belong to the thread that created them, and many functions fail (or quietly do
+//! nothing) when called from another thread: `DestroyWindow` fails with access denied, and
+//! timers set on another thread's window never fire where expected. [`UiThreadToken`] makes
+//! the requirement visible in the types. It isn't `Send`, so a token obtained on the UI thread
+//! can't be moved into a worker thread or a closure run there, and the functions that take one
+//! check that the window belongs to the calling thread, returning
+//! [`Error::ForeignWindow`] up front rather than an obscure failure later.
+//!
+//! A thread becomes a UI thread when it registers a window class, creates a window, or
+//! creates a [`Runloop`] with this crate, and these hand out tokens. [`UiThreadToken::new`]
+//! makes one for other threads, and is `unsafe`, as the token proves only what its caller
+//! asserts.
+//!
+//! [`UiThreadToken`]: struct.UiThreadToken.html
+//! [`UiThreadToken::new`]: struct.UiThreadToken.html#method.new
+//! [`Runloop`]: struct.Runloop.html
+//! [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+
+use winapi::shared::basetsd::UINT_PTR;
+use winapi::shared::windef::HWND;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winuser::{DestroyWindow, GetWindowThreadProcessId, KillTimer, SetTimer};
+
+use crate::error::Error;
+
+thread_local! {
+    /// Whether this thread has registered a class, created a window or runloop, or asked for
+    /// a token.
+    static IS_UI_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Proof that the code holding it runs on a UI thread.
+///
+/// The token is zero-sized and `Copy`, but not `Send` or `Sync`. Get one from
+/// [`Hwnd::ui_thread`], [`OwnedWindowClass::ui_thread`], [`Runloop::ui_thread`], or
+/// [`Context::ui_thread`], or with [`current`](#method.current) on a thread that has made
+/// any of these.
+///
+/// [`Hwnd::ui_thread`]: struct.Hwnd.html#method.ui_thread
+/// [`OwnedWindowClass::ui_thread`]: struct.OwnedWindowClass.html#method.ui_thread
+/// [`Runloop::ui_thread`]: struct.Runloop.html#method.ui_thread
+/// [`Context::ui_thread`]: struct.Context.html#method.ui_thread
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UiThreadToken {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl UiThreadToken {
+    /// Make the calling thread a UI thread, and return a token for it.
+    ///
+    /// Tokens are normally obtained from the window, class, or runloop of the thread; this is
+    /// for code that must be handed a token before any of those exist.
+    ///
+    /// # Safety
+    ///
+    /// The calling thread must be the one that creates the windows the token is used with, and
+    /// must run a message loop for them.
+    pub unsafe fn new() -> UiThreadToken {
+        mark_ui_thread();
+        UiThreadToken::assume()
+    }
+
+    /// A token for the calling thread, if it is a UI thread.
+    ///
+    /// Returns `None` on a thread that hasn't registered a class, created a window or
+    /// runloop, or called [`new`](#method.new), which is usually a worker thread.
+    pub fn current() -> Option<UiThreadToken> {
+        if IS_UI_THREAD.with(Cell::get) {
+            Some(UiThreadToken::assume())
+        } else {
+            None
+        }
+    }
+
+    /// A token for the calling thread, which is known to own windows.
+    pub(crate) fn assume() -> UiThreadToken {
+        UiThreadToken {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Whether the window belongs to the calling thread.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn owns(self, hwnd: HWND) -> bool {
+        GetWindowThreadProcessId(hwnd, null_mut()) == GetCurrentThreadId()
+    }
+
+    /// [`Error::ForeignWindow`](enum.Error.html#variant.ForeignWindow) unless the window
+    /// belongs to the calling thread.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn check(self, hwnd: HWND) -> Result<(), Error> {
+        if self.owns(hwnd) {
+            Ok(())
+        } else {
+            Err(Error::ForeignWindow)
+        }
+    }
+
+    /// Destroy a window belonging to this thread.
+    ///
+    /// See [`DestroyWindow`]. To close a window from another thread, post it `WM_CLOSE`.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`DestroyWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-destroywindow
+    pub unsafe fn destroy_window(self, hwnd: HWND) -> Result<(), Error> {
+        self.check(hwnd)?;
+        if DestroyWindow(hwnd) == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(())
+    }
+
+    /// Start (or reset) a timer on a window belonging to this thread.
+    ///
+    /// The window receives `WM_TIMER` with `id` as `wParam` every `elapse_ms` milliseconds.
+    /// See [`SetTimer`].
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`SetTimer`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-settimer
+    pub unsafe fn set_timer(self, hwnd: HWND, id: UINT_PTR, elapse_ms: u32) -> Result<(), Error> {
+        self.check(hwnd)?;
+        if SetTimer(hwnd, id, elapse_ms, None) == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(())
+    }
+
+    /// Stop a timer started with [`set_timer`](#method.set_timer).
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn kill_timer(self, hwnd: HWND, id: UINT_PTR) -> Result<(), Error> {
+        self.check(hwnd)?;
+        if KillTimer(hwnd, id) == 0 {
+            return Err(Error::last_error());
+        }
+        Ok(())
+    }
+}
+
+/// Record that the calling thread owns windows or classes, or runs a message loop.
+pub(crate) fn mark_ui_thread() {
+    IS_UI_THREAD.with(|is_ui| is_ui.set(true));
+}
@@ -0,0 +1,93 @@
+//! User Interface Privilege Isolation (UIPI) message filtering.
+//!
+//! When a process runs elevated (as administrator), UIPI blocks most messages sent to its
+//! windows from processes at a lower integrity level. Among other things, this breaks
+//! drag-and-drop from Explorer and `WM_COPYDATA` based IPC. The functions in this module relax
+//! the filter for specific messages on specific windows, using [`ChangeWindowMessageFilterEx`].
+//!
+//! Only relax the filter for messages the window procedure validates carefully, as their
+//! contents come from a less trusted process.
+//!
+//! [`ChangeWindowMessageFilterEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-changewindowmessagefilterex
+
+use std::mem;
+
+use winapi::shared::minwindef::{DWORD, UINT};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    ChangeWindowMessageFilterEx, CHANGEFILTERSTRUCT, WM_COPYDATA, WM_DROPFILES,
+};
+
+use crate::error::Error;
+
+const MSGFLT_RESET: DWORD = 0;
+const MSGFLT_ALLOW: DWORD = 1;
+const MSGFLT_DISALLOW: DWORD = 2;
+
+/// An undocumented message used internally by the shell for `WM_DROPFILES`.
+const WM_COPYGLOBALDATA: UINT = 0x0049;
+
+/// Allow a message to reach the window from lower-integrity processes.
+///
+/// This is typically used with `WM_COPYDATA` and with custom messages obtained from
+/// [`RegisterWindowMessage`].
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid HWND reference.
+///
+/// [`RegisterWindowMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-registerwindowmessagew
+pub unsafe fn allow_message_from_lower_integrity(hwnd: HWND, msg: UINT) -> Result<(), Error> {
+    change_filter(hwnd, msg, MSGFLT_ALLOW)
+}
+
+/// Block a message from lower-integrity processes, even if allowed process-wide.
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid HWND reference.
+pub unsafe fn disallow_message_from_lower_integrity(hwnd: HWND, msg: UINT) -> Result<(), Error> {
+    change_filter(hwnd, msg, MSGFLT_DISALLOW)
+}
+
+/// Reset the filter for a message on this window to the default.
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid HWND reference.
+pub unsafe fn reset_message_filter(hwnd: HWND, msg: UINT) -> Result<(), Error> {
+    change_filter(hwnd, msg, MSGFLT_RESET)
+}
+
+/// Allow files to be dropped on the window from lower-integrity processes.
+///
+/// This allows `WM_DROPFILES` along with `WM_COPYDATA` and the undocumented
+/// `WM_COPYGLOBALDATA` message which the shell uses to transfer the file list. The window
+/// must also have called [`DragAcceptFiles`] (or have the `WS_EX_ACCEPTFILES` style).
+///
+/// Note that OLE drag and drop (`RegisterDragDrop`) cannot be made to work across integrity
+/// levels at all.
+///
+/// # Safety
+///
+/// The `hwnd` argument must be a valid HWND reference.
+///
+/// [`DragAcceptFiles`]: https://docs.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-dragacceptfiles
+pub unsafe fn allow_drop_files_from_lower_integrity(hwnd: HWND) -> Result<(), Error> {
+    for &msg in &[WM_DROPFILES, WM_COPYDATA, WM_COPYGLOBALDATA] {
+        change_filter(hwnd, msg, MSGFLT_ALLOW)?;
+    }
+    Ok(())
+}
+
+unsafe fn change_filter(hwnd: HWND, msg: UINT, action: DWORD) -> Result<(), Error> {
+    let mut filter = CHANGEFILTERSTRUCT {
+        cbSize: mem::size_of::<CHANGEFILTERSTRUCT>() as DWORD,
+        ExtStatus: 0,
+    };
+    if ChangeWindowMessageFilterEx(hwnd, msg, action, &mut filter) == 0 {
+        Err(Error::last_error())
+    } else {
+        Ok(())
+    }
+}
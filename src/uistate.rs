@@ -0,0 +1,165 @@
+//! Keyboard cue state.
+//!
+//! Windows hides focus rectangles and accelerator underlines until the user starts using the
+//! keyboard (pressing Alt or Tab), then shows them for the rest of the window's lifetime. This
+//! state is tracked per window by `DefWindowProc` and communicated with the
+//! [`WM_CHANGEUISTATE`], [`WM_UPDATEUISTATE`] and [`WM_QUERYUISTATE`] messages. Custom-drawn
+//! controls need to respect it to match native behavior.
+//!
+//! The usual pattern is to let `DefWindowProc` handle `WM_CHANGEUISTATE` (it propagates the
+//! request up to the top-level window, which then broadcasts `WM_UPDATEUISTATE` down to all
+//! descendants), and on `WM_UPDATEUISTATE` to compute the new state with
+//! [`KeyboardCues::updated`], store it, invalidate, and also return `None` so that
+//! `DefWindowProc` updates its own copy.
+//!
+//! [`WM_CHANGEUISTATE`]: https://docs.microsoft.com/en-us/windows/win32/menurc/wm-changeuistate
+//! [`WM_UPDATEUISTATE`]: https://docs.microsoft.com/en-us/windows/win32/menurc/wm-updateuistate
+//! [`WM_QUERYUISTATE`]: https://docs.microsoft.com/en-us/windows/win32/menurc/wm-queryuistate
+//! [`KeyboardCues::updated`]: struct.KeyboardCues.html#method.updated
+
+use winapi::shared::minwindef::{HIWORD, LOWORD, MAKELONG, UINT, WORD, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    SendMessageW, UISF_ACTIVE, UISF_HIDEACCEL, UISF_HIDEFOCUS, UIS_CLEAR, UIS_INITIALIZE, UIS_SET,
+    WM_CHANGEUISTATE, WM_QUERYUISTATE, WM_UPDATEUISTATE,
+};
+
+/// Which keyboard cues should currently be drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyboardCues {
+    /// Whether focus rectangles should be drawn.
+    pub focus_rects: bool,
+    /// Whether accelerator (mnemonic) underlines should be drawn.
+    pub accelerators: bool,
+    /// Whether controls should be drawn in the style used for active controls.
+    pub active: bool,
+}
+
+/// The action requested by a UI state message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiStateAction {
+    /// Set the given hide flags, hiding the cues.
+    Set,
+    /// Clear the given hide flags, showing the cues.
+    Clear,
+    /// Initialize the state based on the last input event.
+    Initialize,
+}
+
+/// A decoded `WM_CHANGEUISTATE` or `WM_UPDATEUISTATE` message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UiStateChange {
+    pub action: UiStateAction,
+    /// Whether the change affects focus rectangles.
+    pub focus_rects: bool,
+    /// Whether the change affects accelerator underlines.
+    pub accelerators: bool,
+    /// Whether the change affects the active state.
+    pub active: bool,
+}
+
+impl UiStateChange {
+    /// Decode a UI state message.
+    ///
+    /// Returns `None` if the message is not `WM_CHANGEUISTATE` or `WM_UPDATEUISTATE`, or the
+    /// action is unknown.
+    pub fn decode(msg: UINT, wparam: WPARAM) -> Option<UiStateChange> {
+        if msg != WM_CHANGEUISTATE && msg != WM_UPDATEUISTATE {
+            return None;
+        }
+        let action = match LOWORD(wparam as u32) {
+            UIS_SET => UiStateAction::Set,
+            UIS_CLEAR => UiStateAction::Clear,
+            UIS_INITIALIZE => UiStateAction::Initialize,
+            _ => return None,
+        };
+        let flags = HIWORD(wparam as u32);
+        Some(UiStateChange {
+            action,
+            focus_rects: flags & UISF_HIDEFOCUS != 0,
+            accelerators: flags & UISF_HIDEACCEL != 0,
+            active: flags & UISF_ACTIVE != 0,
+        })
+    }
+
+    fn flags(&self) -> WORD {
+        let mut flags = 0;
+        if self.focus_rects {
+            flags |= UISF_HIDEFOCUS;
+        }
+        if self.accelerators {
+            flags |= UISF_HIDEACCEL;
+        }
+        if self.active {
+            flags |= UISF_ACTIVE;
+        }
+        flags
+    }
+
+    fn to_wparam(self) -> WPARAM {
+        let action = match self.action {
+            UiStateAction::Set => UIS_SET,
+            UiStateAction::Clear => UIS_CLEAR,
+            UiStateAction::Initialize => UIS_INITIALIZE,
+        };
+        MAKELONG(action, self.flags()) as WPARAM
+    }
+}
+
+impl KeyboardCues {
+    /// Query the current state of a window, using `WM_QUERYUISTATE`.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    pub unsafe fn query(hwnd: HWND) -> KeyboardCues {
+        let flags = SendMessageW(hwnd, WM_QUERYUISTATE, 0, 0) as WORD;
+        KeyboardCues {
+            focus_rects: flags & UISF_HIDEFOCUS == 0,
+            accelerators: flags & UISF_HIDEACCEL == 0,
+            active: flags & UISF_ACTIVE != 0,
+        }
+    }
+
+    /// The state after applying a `WM_UPDATEUISTATE` message.
+    ///
+    /// The `Initialize` action depends on the last input event, so it can't be resolved here;
+    /// in that case the state is left unchanged, and should be re-queried (for example, by
+    /// posting a message to self) after `DefWindowProc` has processed the update.
+    pub fn updated(self, wparam: WPARAM) -> KeyboardCues {
+        let mut result = self;
+        if let Some(change) = UiStateChange::decode(WM_UPDATEUISTATE, wparam) {
+            let hide = match change.action {
+                UiStateAction::Set => true,
+                UiStateAction::Clear => false,
+                UiStateAction::Initialize => return result,
+            };
+            if change.focus_rects {
+                result.focus_rects = !hide;
+            }
+            if change.accelerators {
+                result.accelerators = !hide;
+            }
+            if change.active {
+                // Note that for the active flag, "set" means active rather than hidden.
+                result.active = hide;
+            }
+        }
+        result
+    }
+}
+
+/// Request a change to the keyboard cue state.
+///
+/// This sends `WM_CHANGEUISTATE` to the window; `DefWindowProc` propagates it to the top-level
+/// window, which broadcasts the resulting `WM_UPDATEUISTATE` to all its descendants.
+///
+/// Sending `UiStateAction::Initialize` with focus rectangles and accelerators is what dialog
+/// boxes do on creation, and is appropriate for top-level windows that want native behavior.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn change_ui_state(hwnd: HWND, change: UiStateChange) {
+    SendMessageW(hwnd, WM_CHANGEUISTATE, change.to_wparam(), 0);
+}
@@ -0,0 +1,45 @@
+//! Internal utilities.
+
+use std::ffi::CString;
+
+use winapi::um::libloaderapi::{GetProcAddress, LoadLibraryW};
+
+use wio::wide::ToWide;
+
+/// Look up a function in a system DLL at runtime, returning its address or 0.
+///
+/// This is used for functions that don't exist on all supported versions of Windows;
+/// linking them statically would prevent the program from starting on older versions.
+pub(crate) fn load_proc(dll: &str, name: &str) -> usize {
+    unsafe {
+        let module = LoadLibraryW(dll.to_wide_null().as_ptr());
+        if module.is_null() {
+            return 0;
+        }
+        let name = match CString::new(name) {
+            Ok(name) => name,
+            Err(_) => return 0,
+        };
+        GetProcAddress(module, name.as_ptr()) as usize
+    }
+}
+
+/// Get a dynamically loaded function, with the given signature.
+///
+/// Evaluates to an `Option` of the function pointer. The lookup is done once and cached.
+/// Must be used in an unsafe context, as the signature is not checked.
+macro_rules! dynamic_fn {
+    ($dll:expr, $name:ident: fn($($arg:ty),*) -> $ret:ty) => {{
+        static PROC: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(1);
+        let mut addr = PROC.load(std::sync::atomic::Ordering::Relaxed);
+        if addr == 1 {
+            addr = $crate::util::load_proc($dll, stringify!($name));
+            PROC.store(addr, std::sync::atomic::Ordering::Relaxed);
+        }
+        if addr == 0 {
+            None
+        } else {
+            Some(std::mem::transmute::<usize, unsafe extern "system" fn($($arg),*) -> $ret>(addr))
+        }
+    }};
+}
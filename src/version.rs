@@ -0,0 +1,84 @@
+//! Windows version detection.
+//!
+//! `GetVersionEx` reports the version the application is manifested for, not the version it
+//! is running on, so an unmanifested program sees Windows 8. The functions here use
+//! [`RtlGetVersion`], which reports the real version, including the build number, which is
+//! the only way to tell Windows 11 apart from Windows 10.
+//!
+//! Prefer testing for a function's presence (as this crate does for dynamically loaded
+//! functions) where possible; version checks are for behavior that changed without a new
+//! API, such as DWM attributes that are accepted but ignored on older builds.
+//!
+//! [`RtlGetVersion`]: https://docs.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-rtlgetversion
+
+use std::mem;
+
+use winapi::shared::ntdef::NTSTATUS;
+use winapi::um::winnt::OSVERSIONINFOW;
+
+/// A Windows version.
+///
+/// Versions compare by major, minor, then build number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WindowsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+}
+
+impl WindowsVersion {
+    /// Windows 10, version 1507 (the first release).
+    pub const WINDOWS_10: WindowsVersion = WindowsVersion::new(10, 0, 10240);
+    /// Windows 11 (the first release).
+    pub const WINDOWS_11: WindowsVersion = WindowsVersion::new(10, 0, 22000);
+
+    pub const fn new(major: u32, minor: u32, build: u32) -> WindowsVersion {
+        WindowsVersion {
+            major,
+            minor,
+            build,
+        }
+    }
+
+    /// The version of Windows the program is running on.
+    ///
+    /// In the unlikely event that `RtlGetVersion` fails, this returns 0.0.0.
+    pub fn current() -> WindowsVersion {
+        unsafe {
+            let mut info: OSVERSIONINFOW = mem::zeroed();
+            info.dwOSVersionInfoSize = mem::size_of::<OSVERSIONINFOW>() as u32;
+            let status =
+                dynamic_fn!("ntdll.dll", RtlGetVersion: fn(*mut OSVERSIONINFOW) -> NTSTATUS)
+                    .map(|f| f(&mut info));
+            if status != Some(0) {
+                return WindowsVersion::new(0, 0, 0);
+            }
+            WindowsVersion::new(info.dwMajorVersion, info.dwMinorVersion, info.dwBuildNumber)
+        }
+    }
+
+    /// Whether the running version is at least `self`.
+    pub fn is_running(self) -> bool {
+        WindowsVersion::current() >= self
+    }
+}
+
+/// Whether the program is running on Windows 10 or later.
+pub fn is_windows10() -> bool {
+    WindowsVersion::WINDOWS_10.is_running()
+}
+
+/// Whether the program is running on Windows 11 or later.
+///
+/// Windows 11 reports itself as version 10.0, so this checks the build number.
+pub fn is_windows11() -> bool {
+    WindowsVersion::WINDOWS_11.is_running()
+}
+
+/// Whether the program is running on at least the given build of Windows 10 or later.
+///
+/// Windows 10 feature updates are identified by build number; for example, dark mode title
+/// bars arrived in build 17763 (version 1809).
+pub fn is_build_at_least(build: u32) -> bool {
+    WindowsVersion::new(10, 0, build).is_running()
+}
@@ -0,0 +1,236 @@
+//! Waiting for vertical blank.
+//!
+//! A render loop that presents faster than the display refreshes wastes power and gains
+//! nothing, as the compositor only picks up one frame per refresh. These helpers let a loop
+//! block until the next vertical blank without requiring a full graphics API.
+
+use std::mem;
+use std::ptr::null_mut;
+use std::time::Duration;
+
+use winapi::um::dwmapi::{DwmFlush, DwmGetCompositionTimingInfo, DWM_TIMING_INFO};
+use winapi::um::profileapi::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+use crate::error::Error;
+
+#[cfg(feature = "dxgi")]
+pub use self::dxgi::VblankWaiter;
+
+/// Wait for the next vertical blank, as seen by the compositor.
+///
+/// This calls [`DwmFlush`], which blocks until the next composition pass. It is the simplest
+/// way to pace a render loop to the display, but it is tied to the compositor clock, which
+/// follows the primary monitor; for windows on a secondary monitor with a different refresh
+/// rate, prefer [`VblankWaiter`] (requires the `dxgi` feature).
+///
+/// Fails when desktop composition is disabled, which is only possible before Windows 8.
+///
+/// [`DwmFlush`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmflush
+/// [`VblankWaiter`]: struct.VblankWaiter.html
+pub fn wait_for_vblank() -> Result<(), Error> {
+    unsafe {
+        let hr = DwmFlush();
+        if hr < 0 {
+            Err(Error::Hresult(hr))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Composition timing statistics.
+///
+/// This is a typed subset of [`DWM_TIMING_INFO`], as reported by
+/// [`DwmGetCompositionTimingInfo`]. Times are in query performance counter (QPC) units, the same
+/// clock as [`QueryPerformanceCounter`] and `IDXGISwapChain::GetFrameStatistics`, so they can be
+/// compared directly against timestamps taken by the application.
+///
+/// Since Windows 8.1, only global (not per-window) timing is available, so the frame counts
+/// reflect the compositor as a whole. Missed-frame detection therefore works by comparing
+/// [`refresh_count`](#structfield.refresh_count) across successive queries: if it advanced by
+/// more than one between two presents, the application missed at least one refresh.
+///
+/// [`DWM_TIMING_INFO`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/ns-dwmapi-dwm_timing_info
+/// [`DwmGetCompositionTimingInfo`]: https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmgetcompositiontiminginfo
+/// [`QueryPerformanceCounter`]: https://docs.microsoft.com/en-us/windows/win32/api/profileapi/nf-profileapi-queryperformancecounter
+#[derive(Clone, Copy, Debug)]
+pub struct FrameTiming {
+    /// The monitor refresh rate, as a (numerator, denominator) ratio in Hz.
+    pub refresh_rate: (u32, u32),
+    /// The duration of one refresh, in QPC units.
+    pub refresh_period: u64,
+    /// The composition rate, as a (numerator, denominator) ratio in Hz.
+    pub compose_rate: (u32, u32),
+    /// The time of the most recent vertical blank, in QPC units.
+    pub last_vblank: u64,
+    /// The number of refreshes since the compositor started.
+    pub refresh_count: u64,
+    /// The time of the most recent composition, in QPC units.
+    pub last_compose: u64,
+    /// The number of composed frames.
+    pub frame_count: u64,
+    /// The number of frames that were presented late.
+    pub frames_late: u64,
+    /// The number of refreshes on which a new frame was expected but not available.
+    pub frames_missed: u64,
+    /// The number of frames dropped by the compositor.
+    pub frames_dropped: u64,
+}
+
+impl FrameTiming {
+    /// Query the current composition timing.
+    pub fn query() -> Result<FrameTiming, Error> {
+        unsafe {
+            let mut info: DWM_TIMING_INFO = mem::zeroed();
+            info.cbSize = mem::size_of::<DWM_TIMING_INFO>() as u32;
+            let hr = DwmGetCompositionTimingInfo(null_mut(), &mut info);
+            if hr < 0 {
+                return Err(Error::Hresult(hr));
+            }
+            Ok(FrameTiming {
+                refresh_rate: (info.rateRefresh.uiNumerator, info.rateRefresh.uiDenominator),
+                refresh_period: info.qpcRefreshPeriod,
+                compose_rate: (info.rateCompose.uiNumerator, info.rateCompose.uiDenominator),
+                last_vblank: info.qpcVBlank,
+                refresh_count: info.cRefresh,
+                last_compose: info.qpcCompose,
+                frame_count: info.cFrame,
+                frames_late: info.cFramesLate,
+                frames_missed: info.cFramesMissed,
+                frames_dropped: info.cFramesDropped,
+            })
+        }
+    }
+
+    /// The refresh rate in Hz, as a float.
+    pub fn refresh_rate_hz(&self) -> f64 {
+        let (num, den) = self.refresh_rate;
+        if den == 0 {
+            0.0
+        } else {
+            num as f64 / den as f64
+        }
+    }
+
+    /// The predicted time of the next vertical blank, in QPC units.
+    ///
+    /// This is the deadline by which a frame must be presented to be picked up on the next
+    /// refresh; in practice, leave some margin for the compositor's own work.
+    pub fn next_vblank(&self) -> u64 {
+        let now = qpc_now();
+        if self.refresh_period == 0 || now < self.last_vblank {
+            return self.last_vblank;
+        }
+        let elapsed = (now - self.last_vblank) / self.refresh_period;
+        self.last_vblank + (elapsed + 1) * self.refresh_period
+    }
+
+    /// The time remaining until the next vertical blank.
+    pub fn time_to_next_vblank(&self) -> Duration {
+        qpc_to_duration(self.next_vblank().saturating_sub(qpc_now()))
+    }
+}
+
+/// The current value of the performance counter.
+fn qpc_now() -> u64 {
+    unsafe {
+        let mut count = mem::zeroed();
+        QueryPerformanceCounter(&mut count);
+        *count.QuadPart() as u64
+    }
+}
+
+/// Convert a span in QPC units to a `Duration`.
+pub fn qpc_to_duration(qpc: u64) -> Duration {
+    let freq = unsafe {
+        let mut freq = mem::zeroed();
+        QueryPerformanceFrequency(&mut freq);
+        *freq.QuadPart() as u64
+    };
+    if freq == 0 {
+        return Duration::default();
+    }
+    let secs = qpc / freq;
+    let nanos = (qpc % freq) * 1_000_000_000 / freq;
+    Duration::new(secs, nanos as u32)
+}
+
+#[cfg(feature = "dxgi")]
+mod dxgi {
+    use std::mem;
+    use std::ptr::null_mut;
+
+    use winapi::shared::dxgi::{
+        CreateDXGIFactory1, IDXGIAdapter, IDXGIFactory1, IDXGIOutput, DXGI_OUTPUT_DESC,
+    };
+    use winapi::shared::winerror::{DXGI_ERROR_NOT_FOUND, SUCCEEDED};
+    use winapi::Interface;
+
+    use wio::com::ComPtr;
+
+    use crate::error::Error;
+    use crate::monitor::Monitor;
+
+    /// Waits for vertical blank on a specific monitor.
+    ///
+    /// This finds the DXGI output corresponding to the monitor and calls
+    /// [`IDXGIOutput::WaitForVBlank`] on it. Finding the output involves enumerating adapters,
+    /// so create one of these up front and reuse it, recreating it when the window moves to
+    /// another monitor or the display configuration changes.
+    ///
+    /// [`IDXGIOutput::WaitForVBlank`]: https://docs.microsoft.com/en-us/windows/win32/api/dxgi/nf-dxgi-idxgioutput-waitforvblank
+    pub struct VblankWaiter {
+        output: ComPtr<IDXGIOutput>,
+    }
+
+    impl VblankWaiter {
+        /// Create a waiter for the given monitor.
+        ///
+        /// Returns `Error::Hresult(DXGI_ERROR_NOT_FOUND)` if no adapter drives the monitor.
+        pub fn new(monitor: Monitor) -> Result<VblankWaiter, Error> {
+            unsafe {
+                let mut factory = null_mut();
+                let hr = CreateDXGIFactory1(&IDXGIFactory1::uuidof(), &mut factory);
+                if !SUCCEEDED(hr) {
+                    return Err(Error::Hresult(hr));
+                }
+                let factory = ComPtr::from_raw(factory as *mut IDXGIFactory1);
+                let mut adapter_ix = 0;
+                loop {
+                    let mut adapter: *mut IDXGIAdapter = null_mut();
+                    if !SUCCEEDED(factory.EnumAdapters(adapter_ix, &mut adapter)) {
+                        break;
+                    }
+                    let adapter = ComPtr::from_raw(adapter);
+                    let mut output_ix = 0;
+                    loop {
+                        let mut output = null_mut();
+                        if !SUCCEEDED(adapter.EnumOutputs(output_ix, &mut output)) {
+                            break;
+                        }
+                        let output = ComPtr::from_raw(output);
+                        let mut desc: DXGI_OUTPUT_DESC = mem::zeroed();
+                        if SUCCEEDED(output.GetDesc(&mut desc)) && desc.Monitor == monitor.raw() {
+                            return Ok(VblankWaiter { output });
+                        }
+                        output_ix += 1;
+                    }
+                    adapter_ix += 1;
+                }
+                Err(Error::Hresult(DXGI_ERROR_NOT_FOUND))
+            }
+        }
+
+        /// Block until the next vertical blank on the monitor.
+        pub fn wait(&self) -> Result<(), Error> {
+            unsafe {
+                let hr = self.output.WaitForVBlank();
+                if SUCCEEDED(hr) {
+                    Ok(())
+                } else {
+                    Err(Error::Hresult(hr))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,873 @@
+//! Hosting a WebView2 browser control in a window.
+//!
+//! [WebView2] embeds Microsoft Edge (Chromium) in a window. Its API is asynchronous COM:
+//! an environment is created, then a controller bound to a parent window, and the
+//! controller's bounds, visibility, and focus must be kept in step with the parent window.
+//! [`WebView::create`] does the first part, and [`WebView::process_message`] the rest, given
+//! the parent window's messages. Navigation, script, and web messages are wrapped; other
+//! APIs can be reached through [`WebView::core_webview2`].
+//!
+//! This requires the WebView2 runtime, which is part of Windows 11 and can be installed on
+//! earlier versions, and `WebView2Loader.dll` (from the WebView2 SDK) next to the executable,
+//! as the loader is loaded at runtime. COM must be initialized as a single-threaded apartment
+//! on the window's thread, and the thread must run a message loop, through which the
+//! callbacks are delivered.
+//!
+//! [WebView2]: https://docs.microsoft.com/en-us/microsoft-edge/webview2/
+//! [`WebView::create`]: struct.WebView.html#method.create
+//! [`WebView::process_message`]: struct.WebView.html#method.process_message
+//! [`WebView::core_webview2`]: struct.WebView.html#method.core_webview2
+
+use std::cell::{Cell, RefCell};
+use std::ffi::OsString;
+use std::mem;
+use std::path::Path;
+use std::ptr::{null, null_mut};
+use std::rc::Rc;
+
+use winapi::ctypes::c_void;
+use winapi::shared::guiddef::{IsEqualGUID, GUID, REFIID};
+use winapi::shared::minwindef::{BOOL, FALSE, LPARAM, TRUE, UINT, ULONG, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::shared::winerror::{
+    ERROR_MOD_NOT_FOUND, E_NOINTERFACE, E_POINTER, HRESULT, SUCCEEDED, S_OK,
+};
+use winapi::um::combaseapi::CoTaskMemFree;
+use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+use winapi::um::winnt::{LPCWSTR, LPWSTR};
+use winapi::um::winuser::{
+    GetClientRect, SIZE_MINIMIZED, WM_MOVE, WM_MOVING, WM_SETFOCUS, WM_SHOWWINDOW, WM_SIZE,
+};
+use winapi::Interface;
+
+use wio::com::ComPtr;
+use wio::wide::{FromWide, ToWide};
+
+use crate::error::Error;
+use crate::geometry::Rect;
+
+use self::interfaces::{
+    EventRegistrationToken, ICoreWebView2, ICoreWebView2Controller, ICoreWebView2Environment,
+    ICoreWebView2NavigationCompletedEventArgs, ICoreWebView2WebMessageReceivedEventArgs,
+};
+
+// These interfaces are from the WebView2 SDK, and not defined in winapi. Only the methods up
+// to the last one used are declared.
+#[allow(non_snake_case)]
+mod interfaces {
+    use winapi::shared::minwindef::BOOL;
+    use winapi::shared::windef::{HWND, RECT};
+    use winapi::shared::winerror::HRESULT;
+    use winapi::um::unknwnbase::{IUnknown, IUnknownVtbl};
+    use winapi::um::winnt::{LPCWSTR, LPWSTR};
+    use winapi::RIDL;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    pub struct EventRegistrationToken {
+        pub value: i64,
+    }
+
+    RIDL! {#[uuid(0xb96d755e, 0x0319, 0x4e92, 0xa2, 0x96, 0x23, 0x43, 0x6f, 0x46, 0xa1, 0xfc)]
+    interface ICoreWebView2Environment(ICoreWebView2EnvironmentVtbl): IUnknown(IUnknownVtbl) {
+        fn CreateCoreWebView2Controller(
+            parentWindow: HWND,
+            handler: *mut IUnknown,
+        ) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x4d00c0d1, 0x9434, 0x4eb6, 0x80, 0x78, 0x86, 0x97, 0xa5, 0x60, 0x33, 0x4f)]
+    interface ICoreWebView2Controller(ICoreWebView2ControllerVtbl): IUnknown(IUnknownVtbl) {
+        fn get_IsVisible(
+            isVisible: *mut BOOL,
+        ) -> HRESULT,
+        fn put_IsVisible(
+            isVisible: BOOL,
+        ) -> HRESULT,
+        fn get_Bounds(
+            bounds: *mut RECT,
+        ) -> HRESULT,
+        fn put_Bounds(
+            bounds: RECT,
+        ) -> HRESULT,
+        fn get_ZoomFactor(
+            zoomFactor: *mut f64,
+        ) -> HRESULT,
+        fn put_ZoomFactor(
+            zoomFactor: f64,
+        ) -> HRESULT,
+        fn add_ZoomFactorChanged(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_ZoomFactorChanged(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn SetBoundsAndZoomFactor(
+            bounds: RECT,
+            zoomFactor: f64,
+        ) -> HRESULT,
+        fn MoveFocus(
+            reason: i32,
+        ) -> HRESULT,
+        fn add_MoveFocusRequested(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_MoveFocusRequested(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_GotFocus(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_GotFocus(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_LostFocus(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_LostFocus(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_AcceleratorKeyPressed(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_AcceleratorKeyPressed(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn get_ParentWindow(
+            parentWindow: *mut HWND,
+        ) -> HRESULT,
+        fn put_ParentWindow(
+            parentWindow: HWND,
+        ) -> HRESULT,
+        fn NotifyParentWindowPositionChanged() -> HRESULT,
+        fn Close() -> HRESULT,
+        fn get_CoreWebView2(
+            coreWebView2: *mut *mut ICoreWebView2,
+        ) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x76eceacb, 0x0462, 0x4d94, 0xac, 0x83, 0x42, 0x3a, 0x67, 0x93, 0x77, 0x5e)]
+    interface ICoreWebView2(ICoreWebView2Vtbl): IUnknown(IUnknownVtbl) {
+        fn get_Settings(
+            settings: *mut *mut IUnknown,
+        ) -> HRESULT,
+        fn get_Source(
+            uri: *mut LPWSTR,
+        ) -> HRESULT,
+        fn Navigate(
+            uri: LPCWSTR,
+        ) -> HRESULT,
+        fn NavigateToString(
+            htmlContent: LPCWSTR,
+        ) -> HRESULT,
+        fn add_NavigationStarting(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_NavigationStarting(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_ContentLoading(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_ContentLoading(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_SourceChanged(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_SourceChanged(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_HistoryChanged(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_HistoryChanged(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_NavigationCompleted(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_NavigationCompleted(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_FrameNavigationStarting(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_FrameNavigationStarting(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_FrameNavigationCompleted(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_FrameNavigationCompleted(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_ScriptDialogOpening(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_ScriptDialogOpening(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_PermissionRequested(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_PermissionRequested(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn add_ProcessFailed(
+            eventHandler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_ProcessFailed(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn AddScriptToExecuteOnDocumentCreated(
+            javaScript: LPCWSTR,
+            handler: *mut IUnknown,
+        ) -> HRESULT,
+        fn RemoveScriptToExecuteOnDocumentCreated(
+            id: LPCWSTR,
+        ) -> HRESULT,
+        fn ExecuteScript(
+            javaScript: LPCWSTR,
+            handler: *mut IUnknown,
+        ) -> HRESULT,
+        fn CapturePreview(
+            imageFormat: i32,
+            imageStream: *mut IUnknown,
+            handler: *mut IUnknown,
+        ) -> HRESULT,
+        fn Reload() -> HRESULT,
+        fn PostWebMessageAsJson(
+            webMessageAsJson: LPCWSTR,
+        ) -> HRESULT,
+        fn PostWebMessageAsString(
+            webMessageAsString: LPCWSTR,
+        ) -> HRESULT,
+        fn add_WebMessageReceived(
+            handler: *mut IUnknown,
+            token: *mut EventRegistrationToken,
+        ) -> HRESULT,
+        fn remove_WebMessageReceived(
+            token: EventRegistrationToken,
+        ) -> HRESULT,
+        fn CallDevToolsProtocolMethod(
+            methodName: LPCWSTR,
+            parametersAsJson: LPCWSTR,
+            handler: *mut IUnknown,
+        ) -> HRESULT,
+        fn get_BrowserProcessId(
+            value: *mut u32,
+        ) -> HRESULT,
+        fn get_CanGoBack(
+            canGoBack: *mut BOOL,
+        ) -> HRESULT,
+        fn get_CanGoForward(
+            canGoForward: *mut BOOL,
+        ) -> HRESULT,
+        fn GoBack() -> HRESULT,
+        fn GoForward() -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x30d68b7d, 0x20d9, 0x4752, 0xa9, 0xca, 0xec, 0x84, 0x48, 0xfb, 0xb5, 0xc1)]
+    interface ICoreWebView2NavigationCompletedEventArgs(ICoreWebView2NavigationCompletedEventArgsVtbl):
+        IUnknown(IUnknownVtbl) {
+        fn get_IsSuccess(
+            isSuccess: *mut BOOL,
+        ) -> HRESULT,
+        fn get_WebErrorStatus(
+            webErrorStatus: *mut i32,
+        ) -> HRESULT,
+        fn get_NavigationId(
+            navigationId: *mut u64,
+        ) -> HRESULT,
+    }}
+
+    RIDL! {#[uuid(0x0f99a40c, 0xe962, 0x4207, 0x9e, 0x92, 0xe3, 0xd5, 0x42, 0xef, 0xf8, 0x49)]
+    interface ICoreWebView2WebMessageReceivedEventArgs(ICoreWebView2WebMessageReceivedEventArgsVtbl):
+        IUnknown(IUnknownVtbl) {
+        fn get_Source(
+            source: *mut LPWSTR,
+        ) -> HRESULT,
+        fn get_WebMessageAsJson(
+            webMessageAsJson: *mut LPWSTR,
+        ) -> HRESULT,
+        fn TryGetWebMessageAsString(
+            webMessageAsString: *mut LPWSTR,
+        ) -> HRESULT,
+    }}
+}
+
+const fn guid(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> GUID {
+    GUID {
+        Data1: data1,
+        Data2: data2,
+        Data3: data3,
+        Data4: data4,
+    }
+}
+
+// The interface ids of the handlers implemented here.
+const IID_ENVIRONMENT_COMPLETED_HANDLER: GUID = guid(
+    0x4e8a3389,
+    0xc9d8,
+    0x4bd2,
+    [0xb6, 0xb5, 0x12, 0x4f, 0xee, 0x6c, 0xc1, 0x4d],
+);
+const IID_CONTROLLER_COMPLETED_HANDLER: GUID = guid(
+    0x6c4819f3,
+    0xc9b7,
+    0x4260,
+    [0x81, 0x27, 0xc9, 0xf5, 0xbd, 0xe7, 0xf6, 0x8c],
+);
+const IID_NAVIGATION_COMPLETED_HANDLER: GUID = guid(
+    0xd33a35bf,
+    0x1c49,
+    0x4f98,
+    [0x93, 0xab, 0x00, 0x6e, 0x05, 0x33, 0xfe, 0x1c],
+);
+const IID_WEB_MESSAGE_RECEIVED_HANDLER: GUID = guid(
+    0x57213f19,
+    0x00e6,
+    0x49fa,
+    [0x8e, 0x07, 0x89, 0x8e, 0xa0, 0x1e, 0xcb, 0xd2],
+);
+const IID_EXECUTE_SCRIPT_COMPLETED_HANDLER: GUID = guid(
+    0x49511172,
+    0xcc67,
+    0x4bca,
+    [0x99, 0x23, 0x13, 0x71, 0x12, 0xf4, 0xc4, 0xcc],
+);
+
+/// `COREWEBVIEW2_MOVE_FOCUS_REASON_PROGRAMMATIC`.
+const MOVE_FOCUS_PROGRAMMATIC: i32 = 0;
+
+/// A handler interface: `IUnknown` and an `Invoke` method with two arguments, which is the
+/// shape of all the WebView2 completion and event handlers.
+#[repr(C)]
+struct HandlerVtbl<A, B> {
+    parent: IUnknownVtbl,
+    invoke: unsafe extern "system" fn(*mut Handler<A, B>, A, B) -> HRESULT,
+}
+
+#[repr(C)]
+struct Handler<A, B> {
+    vtbl: *const HandlerVtbl<A, B>,
+    refcount: Cell<ULONG>,
+    iid: GUID,
+    callback: RefCell<Box<dyn FnMut(A, B) -> HRESULT>>,
+    /// The vtable, pointed to by `vtbl`; generic statics aren't possible.
+    vtbl_storage: HandlerVtbl<A, B>,
+}
+
+impl<A: 'static, B: 'static> Handler<A, B> {
+    /// Create a handler implementing the interface `iid`.
+    fn create(iid: GUID, callback: impl FnMut(A, B) -> HRESULT + 'static) -> ComPtr<IUnknown> {
+        let mut handler = Box::new(Handler {
+            vtbl: null(),
+            refcount: Cell::new(1),
+            iid,
+            callback: RefCell::new(Box::new(callback)),
+            vtbl_storage: HandlerVtbl {
+                parent: IUnknownVtbl {
+                    QueryInterface: handler_query_interface::<A, B>,
+                    AddRef: handler_add_ref::<A, B>,
+                    Release: handler_release::<A, B>,
+                },
+                invoke: handler_invoke::<A, B>,
+            },
+        });
+        handler.vtbl = &handler.vtbl_storage;
+        unsafe { ComPtr::from_raw(Box::into_raw(handler) as *mut IUnknown) }
+    }
+}
+
+unsafe extern "system" fn handler_query_interface<A, B>(
+    this: *mut IUnknown,
+    riid: REFIID,
+    ppv: *mut *mut c_void,
+) -> HRESULT {
+    if ppv.is_null() {
+        return E_POINTER;
+    }
+    let handler = &*(this as *const Handler<A, B>);
+    if IsEqualGUID(&*riid, &IUnknown::uuidof()) || IsEqualGUID(&*riid, &handler.iid) {
+        handler_add_ref::<A, B>(this);
+        *ppv = this as *mut _;
+        S_OK
+    } else {
+        *ppv = null_mut();
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn handler_add_ref<A, B>(this: *mut IUnknown) -> ULONG {
+    let handler = &*(this as *const Handler<A, B>);
+    let count = handler.refcount.get() + 1;
+    handler.refcount.set(count);
+    count
+}
+
+unsafe extern "system" fn handler_release<A, B>(this: *mut IUnknown) -> ULONG {
+    let count = {
+        let handler = &*(this as *const Handler<A, B>);
+        let count = handler.refcount.get() - 1;
+        handler.refcount.set(count);
+        count
+    };
+    if count == 0 {
+        mem::drop(Box::from_raw(this as *mut Handler<A, B>));
+    }
+    count
+}
+
+unsafe extern "system" fn handler_invoke<A, B>(this: *mut Handler<A, B>, a: A, b: B) -> HRESULT {
+    let handler = &*this;
+    match handler.callback.try_borrow_mut() {
+        Ok(mut callback) => callback(a, b),
+        Err(_) => S_OK,
+    }
+}
+
+fn check(hr: HRESULT) -> Result<(), Error> {
+    if SUCCEEDED(hr) {
+        Ok(())
+    } else {
+        Err(Error::Hresult(hr))
+    }
+}
+
+/// Take a string allocated by WebView2, freeing it.
+unsafe fn take_string(ptr: LPWSTR) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    let s = OsString::from_wide_ptr_null(ptr);
+    CoTaskMemFree(ptr as *mut c_void);
+    s.to_string_lossy().into_owned()
+}
+
+/// A message posted by the page with `window.chrome.webview.postMessage`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebMessage {
+    /// The message, serialized as JSON.
+    pub json: String,
+    /// The message, if it was a string.
+    pub text: Option<String>,
+    /// The URI of the document that posted the message.
+    pub source: String,
+}
+
+/// A WebView2 control in a window.
+///
+/// The control is closed when this is dropped, so keep it with the window's state.
+pub struct WebView {
+    controller: ComPtr<ICoreWebView2Controller>,
+    webview: ComPtr<ICoreWebView2>,
+    hwnd: HWND,
+    fill_window: Cell<bool>,
+    /// Whether the control was hidden because the window was minimized.
+    hidden_for_minimize: Cell<bool>,
+}
+
+impl WebView {
+    /// Create a WebView2 control in a window.
+    ///
+    /// Creation is asynchronous; `f` is called with the control (or the error) once it is
+    /// ready, from the thread's message loop. The control fills the window's client area
+    /// until [`set_bounds`](#method.set_bounds) is called.
+    ///
+    /// The user data folder holds the browser profile (cookies, cache, and so on); the
+    /// default, with `None`, is a folder next to the executable, which isn't writable for
+    /// applications installed in Program Files, so installed applications should pass a
+    /// folder in the user's local application data.
+    ///
+    /// Returns a `Win32` error of `ERROR_MOD_NOT_FOUND` if `WebView2Loader.dll` can't be
+    /// loaded; errors after that, such as a missing WebView2 runtime, are passed to `f`.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, to a window on the calling thread, and
+    /// COM must be initialized as a single-threaded apartment on the calling thread.
+    pub unsafe fn create(
+        hwnd: HWND,
+        user_data_folder: Option<&Path>,
+        f: impl FnOnce(Result<WebView, Error>) + 'static,
+    ) -> Result<(), Error> {
+        let create_environment = dynamic_fn!(
+            "WebView2Loader.dll",
+            CreateCoreWebView2EnvironmentWithOptions: fn(
+                LPCWSTR,
+                LPCWSTR,
+                *mut IUnknown,
+                *mut IUnknown
+            ) -> HRESULT
+        )
+        .ok_or(Error::Win32(ERROR_MOD_NOT_FOUND))?;
+        // Shared by both handlers, so that either can report an error.
+        let f = Rc::new(RefCell::new(Some(f)));
+        let handler = Handler::create(
+            IID_ENVIRONMENT_COMPLETED_HANDLER,
+            move |hr: HRESULT, environment: *mut ICoreWebView2Environment| {
+                if !SUCCEEDED(hr) || environment.is_null() {
+                    let f = f.borrow_mut().take();
+                    if let Some(f) = f {
+                        f(Err(Error::Hresult(hr)));
+                    }
+                    return S_OK;
+                }
+                let controller_f = f.clone();
+                let handler = Handler::create(
+                    IID_CONTROLLER_COMPLETED_HANDLER,
+                    move |hr: HRESULT, controller: *mut ICoreWebView2Controller| {
+                        let f = controller_f.borrow_mut().take();
+                        if let Some(f) = f {
+                            f(WebView::from_controller(hwnd, hr, controller));
+                        }
+                        S_OK
+                    },
+                );
+                let hr = (*environment).CreateCoreWebView2Controller(hwnd, handler.as_raw());
+                if !SUCCEEDED(hr) {
+                    let f = f.borrow_mut().take();
+                    if let Some(f) = f {
+                        f(Err(Error::Hresult(hr)));
+                    }
+                }
+                S_OK
+            },
+        );
+        let folder = user_data_folder.map(|folder| folder.to_wide_null());
+        let folder_ptr = folder.as_ref().map_or(null(), |folder| folder.as_ptr());
+        check(create_environment(
+            null(),
+            folder_ptr,
+            null_mut(),
+            handler.as_raw(),
+        ))
+    }
+
+    unsafe fn from_controller(
+        hwnd: HWND,
+        hr: HRESULT,
+        controller: *mut ICoreWebView2Controller,
+    ) -> Result<WebView, Error> {
+        if !SUCCEEDED(hr) || controller.is_null() {
+            return Err(Error::Hresult(hr));
+        }
+        // The controller is only borrowed for the duration of the callback.
+        (*controller).AddRef();
+        let controller = ComPtr::from_raw(controller);
+        let mut webview = null_mut();
+        check(controller.get_CoreWebView2(&mut webview))?;
+        let webview = WebView {
+            controller,
+            webview: ComPtr::from_raw(webview),
+            hwnd,
+            fill_window: Cell::new(true),
+            hidden_for_minimize: Cell::new(false),
+        };
+        webview.fit_to_window()?;
+        Ok(webview)
+    }
+
+    /// Process a message of the parent window, keeping the control in step with it.
+    ///
+    /// On `WM_SIZE`, the control is resized to fill the client area (unless bounds were set),
+    /// and hidden while the window is minimized, which lets the browser throttle the page.
+    /// On `WM_MOVE`, the control is told the window moved, so that popups such as dropdowns
+    /// are placed correctly. On `WM_SETFOCUS`, the focus is passed to the control, and on
+    /// `WM_SHOWWINDOW`, the control is shown or hidden with the window. Messages are only
+    /// observed, and should still be handled as usual.
+    pub fn process_message(&self, msg: UINT, wparam: WPARAM, _lparam: LPARAM) {
+        unsafe {
+            match msg {
+                WM_SIZE => {
+                    if wparam == SIZE_MINIMIZED {
+                        self.controller.put_IsVisible(FALSE);
+                        self.hidden_for_minimize.set(true);
+                    } else {
+                        if self.hidden_for_minimize.replace(false) {
+                            self.controller.put_IsVisible(TRUE);
+                        }
+                        let _ = self.fit_to_window();
+                    }
+                }
+                WM_MOVE | WM_MOVING => {
+                    self.controller.NotifyParentWindowPositionChanged();
+                }
+                WM_SETFOCUS => {
+                    self.controller.MoveFocus(MOVE_FOCUS_PROGRAMMATIC);
+                }
+                WM_SHOWWINDOW => {
+                    self.controller.put_IsVisible((wparam != 0) as BOOL);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn fit_to_window(&self) -> Result<(), Error> {
+        if !self.fill_window.get() {
+            return Ok(());
+        }
+        unsafe {
+            let mut rect = mem::zeroed();
+            GetClientRect(self.hwnd, &mut rect);
+            check(self.controller.put_Bounds(rect))
+        }
+    }
+
+    /// Set the area of the control, in the parent window's client coordinates.
+    ///
+    /// After this, the control no longer follows the size of the window.
+    pub fn set_bounds(&self, bounds: Rect) -> Result<(), Error> {
+        self.fill_window.set(false);
+        unsafe { check(self.controller.put_Bounds(bounds.into())) }
+    }
+
+    /// Make the control fill the window's client area, following its size.
+    ///
+    /// This is the default, until [`set_bounds`](#method.set_bounds) is called.
+    pub fn fill_window(&self) -> Result<(), Error> {
+        self.fill_window.set(true);
+        self.fit_to_window()
+    }
+
+    /// Show or hide the control.
+    pub fn set_visible(&self, visible: bool) -> Result<(), Error> {
+        unsafe { check(self.controller.put_IsVisible(visible as BOOL)) }
+    }
+
+    /// Give the keyboard focus to the control.
+    pub fn focus(&self) -> Result<(), Error> {
+        unsafe { check(self.controller.MoveFocus(MOVE_FOCUS_PROGRAMMATIC)) }
+    }
+
+    /// Navigate to a URI.
+    pub fn navigate(&self, uri: &str) -> Result<(), Error> {
+        unsafe { check(self.webview.Navigate(uri.to_wide_null().as_ptr())) }
+    }
+
+    /// Show an HTML document, given as a string.
+    pub fn navigate_to_string(&self, html: &str) -> Result<(), Error> {
+        unsafe { check(self.webview.NavigateToString(html.to_wide_null().as_ptr())) }
+    }
+
+    /// Reload the current page.
+    pub fn reload(&self) -> Result<(), Error> {
+        unsafe { check(self.webview.Reload()) }
+    }
+
+    /// Go back in the history, if possible.
+    pub fn go_back(&self) -> Result<(), Error> {
+        unsafe { check(self.webview.GoBack()) }
+    }
+
+    /// Go forward in the history, if possible.
+    pub fn go_forward(&self) -> Result<(), Error> {
+        unsafe { check(self.webview.GoForward()) }
+    }
+
+    /// The URI of the current page.
+    pub fn source(&self) -> Result<String, Error> {
+        unsafe {
+            let mut uri = null_mut();
+            check(self.webview.get_Source(&mut uri))?;
+            Ok(take_string(uri))
+        }
+    }
+
+    /// Run a script in the current page.
+    ///
+    /// The script runs asynchronously; `f` is called with its result, serialized as JSON,
+    /// or the error.
+    pub fn execute_script(
+        &self,
+        script: &str,
+        f: impl FnOnce(Result<String, Error>) + 'static,
+    ) -> Result<(), Error> {
+        let mut f = Some(f);
+        let handler = Handler::create(
+            IID_EXECUTE_SCRIPT_COMPLETED_HANDLER,
+            move |hr: HRESULT, result: LPCWSTR| {
+                if let Some(f) = f.take() {
+                    if SUCCEEDED(hr) {
+                        let result = if result.is_null() {
+                            String::new()
+                        } else {
+                            unsafe { OsString::from_wide_ptr_null(result) }
+                                .to_string_lossy()
+                                .into_owned()
+                        };
+                        f(Ok(result));
+                    } else {
+                        f(Err(Error::Hresult(hr)));
+                    }
+                }
+                S_OK
+            },
+        );
+        unsafe {
+            check(
+                self.webview
+                    .ExecuteScript(script.to_wide_null().as_ptr(), handler.as_raw()),
+            )
+        }
+    }
+
+    /// Post a message to the page, which receives it as a `message` event on
+    /// `window.chrome.webview`, with the parsed JSON as its data.
+    pub fn post_message_json(&self, json: &str) -> Result<(), Error> {
+        unsafe {
+            check(
+                self.webview
+                    .PostWebMessageAsJson(json.to_wide_null().as_ptr()),
+            )
+        }
+    }
+
+    /// Post a string message to the page.
+    pub fn post_message_string(&self, message: &str) -> Result<(), Error> {
+        unsafe {
+            check(
+                self.webview
+                    .PostWebMessageAsString(message.to_wide_null().as_ptr()),
+            )
+        }
+    }
+
+    /// Call a function for each message the page posts with
+    /// `window.chrome.webview.postMessage`.
+    ///
+    /// The function is called until the returned handler is dropped.
+    pub fn on_web_message(
+        &self,
+        mut f: impl FnMut(WebMessage) + 'static,
+    ) -> Result<WebViewHandler, Error> {
+        let handler = Handler::create(
+            IID_WEB_MESSAGE_RECEIVED_HANDLER,
+            move |_sender: *mut ICoreWebView2,
+                  args: *mut ICoreWebView2WebMessageReceivedEventArgs| {
+                if args.is_null() {
+                    return S_OK;
+                }
+                unsafe {
+                    let args = &*args;
+                    let mut ptr = null_mut();
+                    args.get_WebMessageAsJson(&mut ptr);
+                    let json = take_string(ptr);
+                    let mut ptr = null_mut();
+                    let text = if SUCCEEDED(args.TryGetWebMessageAsString(&mut ptr)) {
+                        Some(take_string(ptr))
+                    } else {
+                        None
+                    };
+                    let mut ptr = null_mut();
+                    args.get_Source(&mut ptr);
+                    let source = take_string(ptr);
+                    f(WebMessage { json, text, source });
+                }
+                S_OK
+            },
+        );
+        let mut token = EventRegistrationToken::default();
+        unsafe {
+            check(
+                self.webview
+                    .add_WebMessageReceived(handler.as_raw(), &mut token),
+            )?;
+        }
+        Ok(WebViewHandler {
+            webview: self.webview.clone(),
+            token,
+            remove: ICoreWebView2::remove_WebMessageReceived,
+        })
+    }
+
+    /// Call a function when each navigation completes, with whether it succeeded.
+    ///
+    /// The function is called until the returned handler is dropped.
+    pub fn on_navigation_completed(
+        &self,
+        mut f: impl FnMut(bool) + 'static,
+    ) -> Result<WebViewHandler, Error> {
+        let handler = Handler::create(
+            IID_NAVIGATION_COMPLETED_HANDLER,
+            move |_sender: *mut ICoreWebView2,
+                  args: *mut ICoreWebView2NavigationCompletedEventArgs| {
+                let mut success = FALSE;
+                if !args.is_null() {
+                    unsafe { (*args).get_IsSuccess(&mut success) };
+                }
+                f(success != 0);
+                S_OK
+            },
+        );
+        let mut token = EventRegistrationToken::default();
+        unsafe {
+            check(
+                self.webview
+                    .add_NavigationCompleted(handler.as_raw(), &mut token),
+            )?;
+        }
+        Ok(WebViewHandler {
+            webview: self.webview.clone(),
+            token,
+            remove: ICoreWebView2::remove_NavigationCompleted,
+        })
+    }
+
+    /// The `ICoreWebView2` interface, for APIs not wrapped here.
+    ///
+    /// The pointer is valid as long as this is; query it for later versions of the interface.
+    pub fn core_webview2(&self) -> *mut IUnknown {
+        self.webview.as_raw() as *mut IUnknown
+    }
+
+    /// The `ICoreWebView2Controller` interface, for APIs not wrapped here.
+    pub fn controller(&self) -> *mut IUnknown {
+        self.controller.as_raw() as *mut IUnknown
+    }
+}
+
+impl Drop for WebView {
+    fn drop(&mut self) {
+        unsafe {
+            self.controller.Close();
+        }
+    }
+}
+
+/// An event handler added to a [`WebView`](struct.WebView.html).
+///
+/// The handler is removed when this is dropped. Dropping it after the control is closed is
+/// harmless; closing the control removes all its handlers.
+#[must_use = "the handler is removed when this is dropped"]
+pub struct WebViewHandler {
+    webview: ComPtr<ICoreWebView2>,
+    token: EventRegistrationToken,
+    remove: unsafe fn(&ICoreWebView2, EventRegistrationToken) -> HRESULT,
+}
+
+impl Drop for WebViewHandler {
+    fn drop(&mut self) {
+        unsafe {
+            (self.remove)(&self.webview, self.token);
+        }
+    }
+}
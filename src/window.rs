@@ -1,22 +1,67 @@
 #![allow(non_snake_case)]
 
-use std::ffi::OsStr;
+use std::cell::{Cell, RefCell};
+use std::ffi::{OsStr, OsString};
 use std::mem;
 use std::ptr::{null, null_mut};
 use std::rc::Rc;
 
 use winapi::ctypes::c_int;
-use winapi::shared::minwindef::{ATOM, DWORD, HINSTANCE, LPARAM, LPVOID, LRESULT, UINT, WPARAM};
-use winapi::shared::windef::{HBRUSH, HCURSOR, HICON, HMENU, HWND};
-use winapi::um::winnt::LPCWSTR;
+use winapi::shared::minwindef::{
+    ATOM, BOOL, DWORD, HINSTANCE, LOWORD, LPARAM, LPVOID, LRESULT, UINT, WPARAM,
+};
+use winapi::shared::windef::{HACCEL, HBRUSH, HCURSOR, HICON, HMENU, HWND, RECT};
+use winapi::shared::winerror::ERROR_CLASS_HAS_WINDOWS;
+use winapi::um::errhandlingapi::{GetLastError, SetLastError};
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winnt::{HANDLE, LPCWSTR};
 use winapi::um::winuser::{
-    CreateWindowExW, DefWindowProcW, GetWindowLongPtrW, RegisterClassExW, SetWindowLongPtrW,
-    CREATESTRUCTW, CW_USEDEFAULT, GWLP_USERDATA, WM_CREATE, WM_NCDESTROY, WNDCLASSEXW,
+    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyMenu, GetClassInfoExW,
+    GetClassLongPtrW, GetClientRect, GetCursorPos, GetMenu, GetPropW, GetWindowLongPtrW,
+    GetWindowRect, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsMenu,
+    IsZoomed, LoadMenuW, RegisterClassExW, RemovePropW, ScreenToClient, SetCursor,
+    SetForegroundWindow, SetPropW, SetWindowLongPtrW, SetWindowPos, SetWindowTextW, ShowWindow,
+    UnregisterClassW, ValidateRect, CREATESTRUCTW, CS_CLASSDC, CS_DROPSHADOW, CS_HREDRAW, CS_OWNDC,
+    CS_PARENTDC, CS_VREDRAW, CW_USEDEFAULT, GCLP_WNDPROC, GWLP_USERDATA, HTCAPTION, HTCLIENT,
+    HWND_MESSAGE, MAKEINTRESOURCEW, MINMAXINFO, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE,
+    SWP_NOZORDER, SW_SHOWMAXIMIZED, SW_SHOWMINIMIZED, SW_SHOWNOACTIVATE, SW_SHOWNORMAL, WM_CREATE,
+    WM_DISPLAYCHANGE, WM_ENTERSIZEMOVE, WM_ERASEBKGND, WM_EXITSIZEMOVE, WM_GETMINMAXINFO,
+    WM_MOVING, WM_NCDESTROY, WM_NCHITTEST, WM_NCLBUTTONDBLCLK, WM_NCLBUTTONDOWN, WM_NCLBUTTONUP,
+    WM_NCMOUSELEAVE, WM_NCMOUSEMOVE, WM_PAINT, WM_SETCURSOR, WM_SIZE, WM_SIZING, WM_SYSCOMMAND,
+    WM_WINDOWPOSCHANGING, WNDCLASSEXW, WNDPROC, WS_CAPTION, WS_CHILD, WS_EX_COMPOSITED,
+    WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_NOREDIRECTIONBITMAP, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    WS_EX_TRANSPARENT, WS_MAXIMIZEBOX, WS_MINIMIZEBOX, WS_POPUP, WS_SYSMENU, WS_THICKFRAME,
 };
 
-use wio::wide::ToWide;
+#[cfg(feature = "dcomp")]
+use winapi::um::{dcomp::IDCompositionTarget, unknwnbase::IUnknown};
+
+#[cfg(feature = "dcomp")]
+use wio::com::ComPtr;
+use wio::wide::{FromWide, ToWide};
 
+use crate::cursor::{Cursor, CursorMap};
+#[cfg(feature = "dcomp")]
+use crate::dcomp::create_composition_target;
+use crate::dpi::Dpi;
+use crate::dwm::{
+    set_backdrop, set_cloaked, set_corner_preference, set_dark_mode_titlebar, Backdrop,
+    CornerPreference,
+};
 use crate::error::Error;
+use crate::geometry::{Point, Rect};
+use crate::hit_test::{DragRegions, HitTest, HitTestFn, TitleBar};
+use crate::hwnd::Hwnd;
+use crate::icon::Icon;
+use crate::kiosk::{set_kiosk, Kiosk, KioskState};
+use crate::monitor::Monitor;
+use crate::mouse::lparam_to_point;
+use crate::snap::Snap;
+use crate::style::{WindowExStyle, WindowStyle};
+use crate::swapchain::SwapchainResize;
+use crate::ui_thread::{mark_ui_thread, UiThreadToken};
+use crate::wnd_cell::{ConflictPolicy, WndCell};
 
 /// A Rust wrapper for the winapi "window procedure".
 ///
@@ -35,11 +80,15 @@ use crate::error::Error;
 ///
 /// * Calling a synchronous dialog, including a file dialog.
 ///
+/// Information about the message beyond its parameters (its time, and whether it was sent
+/// from another thread) is available from [`MessageCtx::current`].
+///
 /// [Window Procedures]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-procedures
 /// [`DestroyWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-destroywindow
 /// [`SendMessage`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-sendmessage
 /// [reentrantly]: https://www-user.tu-chemnitz.de/~heha/viewchm.php/hs/petzold.chm/petzoldi/ch03c.htm
 /// [`WM_NCDESTROY`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-ncdestroy
+/// [`MessageCtx::current`]: struct.MessageCtx.html#method.current
 pub trait WindowProc {
     /// The Rust-side implementation of the window procedure.
     ///
@@ -48,6 +97,95 @@ pub trait WindowProc {
     /// [`DefWindowProc`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-defwindowprocw
     fn window_proc(&self, hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM)
         -> Option<LRESULT>;
+
+    /// Messages to dispatch with as little overhead as possible.
+    ///
+    /// This is intended for high-frequency messages such as `WM_MOUSEMOVE`, `WM_INPUT`, and
+    /// `WM_TIMER`. For these, the window state is kept alive with a call count rather than a
+    /// reference, and the window procedure may still destroy the window while handling them.
+    /// It is queried once, when the window is created; `WM_CREATE` and `WM_NCDESTROY` are never hot, and
+    /// neither are the messages this crate handles before the window procedure, such as
+    /// `WM_SIZE`.
+    fn hot_messages(&self) -> &[UINT] {
+        &[]
+    }
+}
+
+impl<F> WindowProc for F
+where
+    F: Fn(HWND, UINT, WPARAM, LPARAM) -> Option<LRESULT>,
+{
+    fn window_proc(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<LRESULT> {
+        self(hwnd, msg, wparam, lparam)
+    }
+}
+
+/// A window procedure from an `FnMut` closure.
+///
+/// The closure is held in a [`WndCell`]. A reentrant call (a message sent to the window while
+/// the closure is already running, for example by `DestroyWindow`) can't borrow it, and what
+/// happens then follows the [`ConflictPolicy`]: by default, the message is passed to
+/// `DefWindowProc` (and with the `tracing` feature, the conflict is logged with both
+/// messages); with `ConflictPolicy::Panic`, the panic names both messages. Implement
+/// [`WindowProc`] directly for control over reentrant messages.
+///
+/// [`WndCell`]: struct.WndCell.html
+/// [`ConflictPolicy`]: enum.ConflictPolicy.html
+/// [`WindowProc`]: trait.WindowProc.html
+pub struct FnMutProc<F> {
+    f: WndCell<F>,
+    policy: ConflictPolicy,
+}
+
+impl<F> FnMutProc<F>
+where
+    F: FnMut(HWND, UINT, WPARAM, LPARAM) -> Option<LRESULT>,
+{
+    /// Create a window procedure that passes reentrant messages to `DefWindowProc`.
+    pub fn new(f: F) -> FnMutProc<F> {
+        FnMutProc::with_policy(f, ConflictPolicy::Skip)
+    }
+
+    /// Create a window procedure with the given policy for reentrant messages.
+    pub fn with_policy(f: F, policy: ConflictPolicy) -> FnMutProc<F> {
+        FnMutProc {
+            f: WndCell::with_policy(f, policy),
+            policy,
+        }
+    }
+}
+
+impl<F> WindowProc for FnMutProc<F>
+where
+    F: FnMut(HWND, UINT, WPARAM, LPARAM) -> Option<LRESULT>,
+{
+    fn window_proc(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<LRESULT> {
+        match self.f.try_borrow_mut(msg) {
+            Ok(mut f) => f(hwnd, msg, wparam, lparam),
+            Err(conflict) => {
+                if self.policy == ConflictPolicy::Panic {
+                    panic!("FnMutProc: {}", conflict);
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(%conflict, "passed to DefWindowProc");
+                #[cfg(not(feature = "tracing"))]
+                let _ = conflict;
+                None
+            }
+        }
+    }
 }
 
 /// A window class.
@@ -65,13 +203,94 @@ pub struct WindowClassBuilder {
     hCursor: HCURSOR,
     hbrBackground: HBRUSH,
     menu_name: Vec<u16>,
+    menu_id: Option<u16>,
     class_name: Vec<u16>,
     hIconSm: HICON,
+    no_flicker: bool,
+    storage: StateStorage,
+}
+
+/// How the window relates to its `hWndParent`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Relation {
+    /// Set with `parent_hwnd`, and interpreted according to the style.
+    Unspecified,
+    /// A top-level window owned by another.
+    Owned,
+    /// A child window, with `WS_CHILD`.
+    Child,
+}
+
+/// How [`WindowBuilder::build`] shows the window it creates.
+///
+/// [`WindowBuilder::build`]: struct.WindowBuilder.html#method.build
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShowCommand {
+    /// Show and activate the window at its normal size and position (`SW_SHOWNORMAL`).
+    Normal,
+    /// Show and activate the window maximized (`SW_SHOWMAXIMIZED`).
+    Maximized,
+    /// Show the window minimized (`SW_SHOWMINIMIZED`).
+    Minimized,
+    /// Leave the window hidden, to be shown later (for example, once its content is ready).
+    Hidden,
+}
+
+impl ShowCommand {
+    /// The `SW_` value for `ShowWindow`, or `None` for `Hidden`.
+    fn sw(self) -> Option<c_int> {
+        match self {
+            ShowCommand::Normal => Some(SW_SHOWNORMAL),
+            ShowCommand::Maximized => Some(SW_SHOWMAXIMIZED),
+            ShowCommand::Minimized => Some(SW_SHOWMINIMIZED),
+            ShowCommand::Hidden => None,
+        }
+    }
+}
+
+/// Where to place a window, in place of its position.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    /// At the position set with `position`, or the default.
+    Position,
+    /// Centered on the work area of its monitor.
+    Centered,
+    /// Centered on another window.
+    CenteredOn(HWND),
+}
+
+/// The minimum and maximum window size, in pixels at 96 DPI.
+#[derive(Clone, Copy, Default)]
+struct SizeLimits {
+    min: Option<(c_int, c_int)>,
+    max: Option<(c_int, c_int)>,
+}
+
+impl SizeLimits {
+    /// Apply the limits, scaled to the window's DPI, to a `WM_GETMINMAXINFO` request.
+    unsafe fn apply(&self, hwnd: HWND, info: &mut MINMAXINFO) {
+        if self.min.is_none() && self.max.is_none() {
+            return;
+        }
+        // The DPI is already the new one when `WM_DPICHANGED` is sent, so the limits follow.
+        let dpi = Dpi::for_window(hwnd);
+        if let Some((width, height)) = self.min {
+            info.ptMinTrackSize.x = dpi.scale(width);
+            info.ptMinTrackSize.y = dpi.scale(height);
+        }
+        if let Some((width, height)) = self.max {
+            info.ptMaxTrackSize.x = dpi.scale(width);
+            info.ptMaxTrackSize.y = dpi.scale(height);
+            // A maximized window is also limited, rather than covering the monitor.
+            info.ptMaxSize.x = info.ptMaxSize.x.min(info.ptMaxTrackSize.x);
+            info.ptMaxSize.y = info.ptMaxSize.y.min(info.ptMaxTrackSize.y);
+        }
+    }
 }
 
 /// A builder for creating new windows.
 pub struct WindowBuilder<'a> {
-    window_proc: Rc<Box<dyn WindowProc>>,
+    window_proc: Box<dyn WindowProc>,
     dwExStyle: DWORD,
     window_class: &'a WindowClass,
     window_name: Vec<u16>,
@@ -81,8 +300,59 @@ pub struct WindowBuilder<'a> {
     nWidth: c_int,
     nHeight: c_int,
     hWndParent: HWND,
+    relation: Relation,
     hMenu: HMENU,
+    menu_resource: Option<u16>,
     hInstance: HINSTANCE,
+    no_flicker: bool,
+    cursor_map: Option<CursorMap>,
+    drag_regions: Option<DragRegions>,
+    hit_test: Option<HitTestFn>,
+    title_bar: Option<TitleBar>,
+    snap: Option<Snap>,
+    kiosk: Option<Kiosk>,
+    maximize_to_work_area: bool,
+    accel: HACCEL,
+    first_paint: Option<FirstPaint>,
+    message_only: bool,
+    show_command: ShowCommand,
+    placement: Placement,
+    client_size: Option<(c_int, c_int)>,
+    dark_mode_titlebar: Option<bool>,
+    backdrop: Option<Backdrop>,
+    corner_preference: Option<CornerPreference>,
+    size_limits: SizeLimits,
+}
+
+/// The state owned by a window, dropped on [`WM_NCDESTROY`].
+///
+/// [`WM_NCDESTROY`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-ncdestroy
+pub(crate) struct WindowState {
+    window_proc: Box<dyn WindowProc>,
+    /// The window procedure's hot messages, sorted.
+    hot_messages: Box<[UINT]>,
+    no_flicker: bool,
+    pub(crate) cursor_map: RefCell<Option<CursorMap>>,
+    pub(crate) drag_regions: RefCell<Option<DragRegions>>,
+    pub(crate) hit_test: RefCell<Option<HitTestFn>>,
+    pub(crate) title_bar: RefCell<Option<TitleBar>>,
+    pub(crate) snap: Cell<Option<Snap>>,
+    pub(crate) kiosk: RefCell<Option<Rc<KioskState>>>,
+    maximize_to_work_area: bool,
+    size_limits: SizeLimits,
+    pub(crate) swapchain_resize: SwapchainResize,
+    pub(crate) accel: Cell<HACCEL>,
+    /// The callback for the first paint, while the window is cloaked awaiting it.
+    first_paint: RefCell<Option<FirstPaint>>,
+    /// Shared with the window's `Hwnd` handles; cleared on `WM_NCDESTROY`.
+    alive: Rc<Cell<bool>>,
+    /// The number of hot message calls in progress, which don't hold a reference.
+    hot_depth: Cell<u32>,
+    /// Set when `WM_NCDESTROY` left the window's reference for the last hot call to release.
+    release_after_hot: Cell<bool>,
+    /// The number of calls to the window procedure in progress.
+    #[cfg(feature = "diagnostics")]
+    pub(crate) depth: Cell<u32>,
 }
 
 impl<'a> WindowBuilder<'a> {
@@ -97,7 +367,7 @@ impl<'a> WindowBuilder<'a> {
         window_class: &WindowClass,
     ) -> WindowBuilder {
         WindowBuilder {
-            window_proc: Rc::new(Box::new(window_proc)),
+            window_proc: Box::new(window_proc),
             dwExStyle: 0,
             window_class,
             window_name: Vec::new(),
@@ -107,40 +377,195 @@ impl<'a> WindowBuilder<'a> {
             nWidth: CW_USEDEFAULT,
             nHeight: CW_USEDEFAULT,
             hWndParent: null_mut(),
+            relation: Relation::Unspecified,
             hMenu: null_mut(),
+            menu_resource: None,
             hInstance: null_mut(),
+            no_flicker: false,
+            cursor_map: None,
+            drag_regions: None,
+            hit_test: None,
+            title_bar: None,
+            snap: None,
+            kiosk: None,
+            maximize_to_work_area: false,
+            accel: null_mut(),
+            first_paint: None,
+            message_only: false,
+            show_command: ShowCommand::Hidden,
+            placement: Placement::Position,
+            client_size: None,
+            dark_mode_titlebar: None,
+            backdrop: None,
+            corner_preference: None,
+            size_limits: SizeLimits::default(),
         }
     }
 
     /// Build a window.
     ///
-    /// The return value is the HWND for the window, or 0 on error.
+    /// The return value is a handle for the window, which tracks whether it still exists;
+    /// use [`Hwnd::raw`] for the raw `HWND`. On failure, the error is
+    /// [`Error::CreateWindowFailed`], with the reason reported by the system.
     ///
     /// The lifetime of the window is until `WM_NCDESTROY` is called,
     /// at which point the window procedure is dropped.
     ///
     /// [`WM_NCDESTROY`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-ncdestroy
-    pub fn build(self) -> HWND {
+    /// [`Error::CreateWindowFailed`]: enum.Error.html#variant.CreateWindowFailed
+    /// [`Hwnd::raw`]: struct.Hwnd.html#method.raw
+    pub fn build(mut self) -> Result<Hwnd, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("build_window").entered();
+        if !self.message_only {
+            match self.relation {
+                Relation::Owned if self.dwStyle & WS_CHILD != 0 => {
+                    return Err(Error::InvalidStyle("an owned window can't have WS_CHILD"));
+                }
+                Relation::Child if self.dwStyle & WS_POPUP != 0 => {
+                    return Err(Error::InvalidStyle("a child window can't have WS_POPUP"));
+                }
+                _ => (),
+            }
+        }
+        if self.relation == Relation::Child {
+            self.dwStyle |= WS_CHILD;
+        }
+        if self.dwExStyle & WS_EX_COMPOSITED != 0 {
+            if self.dwExStyle & WS_EX_NOREDIRECTIONBITMAP != 0 {
+                return Err(Error::InvalidStyle(
+                    "WS_EX_COMPOSITED is incompatible with WS_EX_NOREDIRECTIONBITMAP",
+                ));
+            }
+            let class_style = unsafe { self.window_class.style(self.hInstance) };
+            if class_style.is_some_and(|style| style & (CS_OWNDC | CS_CLASSDC | CS_PARENTDC) != 0) {
+                return Err(Error::InvalidStyle(
+                    "WS_EX_COMPOSITED is incompatible with CS_OWNDC, CS_CLASSDC, and CS_PARENTDC",
+                ));
+            }
+        }
         unsafe {
-            let wnd_proc_ptr = Rc::into_raw(self.window_proc) as LPVOID;
+            let loaded_menu = match self.menu_resource {
+                Some(id) if !self.message_only => {
+                    let instance = if self.hInstance.is_null() {
+                        GetModuleHandleW(null())
+                    } else {
+                        self.hInstance
+                    };
+                    let menu = LoadMenuW(instance, MAKEINTRESOURCEW(id));
+                    if menu.is_null() {
+                        return Err(Error::last_error());
+                    }
+                    self.hMenu = menu;
+                    Some(menu)
+                }
+                _ => None,
+            };
+            let mut hot_messages = self.window_proc.hot_messages().to_vec();
+            hot_messages.retain(|&msg| {
+                msg != WM_CREATE && msg != WM_NCDESTROY && !HOOKED_MESSAGES.contains(&msg)
+            });
+            hot_messages.sort_unstable();
+            let show_after_first_paint = self.first_paint.is_some() && !self.message_only;
+            let alive = Rc::new(Cell::new(true));
+            let state = WindowState {
+                window_proc: self.window_proc,
+                hot_messages: hot_messages.into(),
+                no_flicker: self.no_flicker,
+                cursor_map: RefCell::new(self.cursor_map),
+                drag_regions: RefCell::new(self.drag_regions),
+                hit_test: RefCell::new(self.hit_test),
+                title_bar: RefCell::new(self.title_bar),
+                snap: Cell::new(self.snap),
+                kiosk: RefCell::new(None),
+                maximize_to_work_area: self.maximize_to_work_area,
+                size_limits: self.size_limits,
+                swapchain_resize: SwapchainResize::default(),
+                accel: Cell::new(self.accel),
+                first_paint: RefCell::new(self.first_paint),
+                alive: alive.clone(),
+                hot_depth: Cell::new(0),
+                release_after_hot: Cell::new(false),
+                #[cfg(feature = "diagnostics")]
+                depth: Cell::new(0),
+            };
+            let wnd_proc_ptr = Rc::into_raw(Rc::new(state)) as LPVOID;
+            // A message-only window has no use for the parameters of a visible window.
+            let (ex_style, style, parent, menu) = if self.message_only {
+                (0, 0, HWND_MESSAGE, null_mut())
+            } else {
+                (self.dwExStyle, self.dwStyle, self.hWndParent, self.hMenu)
+            };
             let hwnd = CreateWindowExW(
-                self.dwExStyle,
+                ex_style,
                 self.window_class.as_lpcwstr(),
                 pointer_or_null(&self.window_name),
-                self.dwStyle,
+                style,
                 self.x,
                 self.y,
                 self.nWidth,
                 self.nHeight,
-                self.hWndParent,
-                self.hMenu,
+                parent,
+                menu,
                 self.hInstance,
                 wnd_proc_ptr,
             );
             if hwnd.is_null() {
-                std::mem::drop(Rc::from_raw(wnd_proc_ptr));
+                // Capture the error before dropping the state, which may make system calls.
+                let err = Error::create_window_failed();
+                std::mem::drop(Rc::from_raw(wnd_proc_ptr as *const WindowState));
+                // The menu is destroyed with the window if it got as far as being attached.
+                if let Some(menu) = loaded_menu.filter(|&menu| IsMenu(menu) != 0) {
+                    DestroyMenu(menu);
+                }
+                return Err(err);
+            }
+            #[cfg(feature = "diagnostics")]
+            crate::diagnostics::window_created(
+                wnd_proc_ptr as usize,
+                hwnd,
+                winapi::um::winuser::GetClassWord(hwnd, winapi::um::winuser::GCW_ATOM),
+            );
+            if !self.message_only {
+                if let Some(size) = self.client_size {
+                    set_client_size(hwnd, size, style, ex_style);
+                }
+                if style & WS_CHILD == 0 {
+                    if let Some(dark) = self.dark_mode_titlebar {
+                        let _ = set_dark_mode_titlebar(hwnd, dark);
+                    }
+                    if let Some(backdrop) = self.backdrop {
+                        let _ = set_backdrop(hwnd, backdrop);
+                    }
+                    if let Some(preference) = self.corner_preference {
+                        let _ = set_corner_preference(hwnd, preference);
+                    }
+                    match self.placement {
+                        Placement::Position => (),
+                        Placement::Centered => center_window(hwnd, None),
+                        Placement::CenteredOn(target) => center_window(hwnd, Some(target)),
+                    }
+                    if let Some(kiosk) = self.kiosk {
+                        let _ = set_kiosk(hwnd, Some(kiosk));
+                    }
+                }
             }
-            hwnd
+            if show_after_first_paint {
+                // Without cloaking (before Windows 8), the window is just shown.
+                let _ = set_cloaked(hwnd, true);
+                let cmd = match self.show_command {
+                    ShowCommand::Maximized => SW_SHOWMAXIMIZED,
+                    _ => SW_SHOWNOACTIVATE,
+                };
+                ShowWindow(hwnd, cmd);
+            } else if !self.message_only {
+                if let Some(cmd) = self.show_command.sw() {
+                    ShowWindow(hwnd, cmd);
+                }
+            }
+            mark_ui_thread();
+            crate::threads::window_created(hwnd);
+            Ok(Hwnd::new(hwnd, alive))
         }
     }
 
@@ -156,32 +581,63 @@ impl<'a> WindowBuilder<'a> {
 
     /// Set the window style.
     ///
-    /// The argument is the bitwise OR of a number of `WS_` values from the [Window Styles] enumeration.
-    /// It becomes the `dwStyle` parameter to [`CreateWindowEx`].
+    /// The argument is a [`WindowStyle`], or the bitwise OR of a number of raw `WS_` values
+    /// from the [Window Styles] enumeration. It becomes the `dwStyle` parameter to
+    /// [`CreateWindowEx`].
     ///
+    /// [`WindowStyle`]: struct.WindowStyle.html
     /// [`CreateWindowEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createwindowexw
     /// [Window Styles]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-styles
-    pub fn style(mut self, style: DWORD) -> Self {
-        self.dwStyle = style;
+    pub fn style(mut self, style: impl Into<WindowStyle>) -> Self {
+        self.dwStyle = style.into().bits();
+        self
+    }
+
+    /// Whether the window can be resized by the user.
+    ///
+    /// This sets or clears the sizing border and maximize box in the style, so call it after
+    /// [`style`](#method.style).
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        let mut style = WindowStyle::from(self.dwStyle);
+        style.set(
+            WindowStyle::THICK_FRAME | WindowStyle::MAXIMIZE_BOX,
+            resizable,
+        );
+        self.dwStyle = style.bits();
+        self
+    }
+
+    /// Whether the window has a minimize box.
+    ///
+    /// This sets or clears the minimize box in the style, so call it after
+    /// [`style`](#method.style).
+    pub fn minimizable(mut self, minimizable: bool) -> Self {
+        let mut style = WindowStyle::from(self.dwStyle);
+        style.set(WindowStyle::MINIMIZE_BOX, minimizable);
+        self.dwStyle = style.bits();
         self
     }
 
     /// Set the extended window style.
     ///
-    /// The argument is the bitwise OR of a number of `WS_EX` values from the [Extended Window Styles] enumeration.
-    /// It becomes the `dwExStyle` parameter to [`CreateWindowEx`].
+    /// The argument is a [`WindowExStyle`], or the bitwise OR of a number of raw `WS_EX` values
+    /// from the [Extended Window Styles] enumeration. It becomes the `dwExStyle` parameter to
+    /// [`CreateWindowEx`].
     ///
     /// An interesting parameter is `WS_EX_NOREDIRECTIONBITMAP`, which disables the redirection bitmap.
     /// It is useful to set when the window will contain a swapchain and no GDI content (in particular, no
     /// menus). There is a particular source of artifacting on window resize that is reduced when the
     /// redirection bitmap is disabled. It should almost always be set when using DirectComposition,
-    /// see this [article by Kenny Kerr].
+    /// see this [article by Kenny Kerr]. [`for_composition`] sets it, along with the other settings
+    /// for such windows.
     ///
+    /// [`for_composition`]: #method.for_composition
+    /// [`WindowExStyle`]: struct.WindowExStyle.html
     /// [`CreateWindowEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createwindowexw
     /// [Extended Window Styles]: https://docs.microsoft.com/en-us/windows/win32/winmsg/extended-window-styles
     /// [article by Kenny Kerr]: https://docs.microsoft.com/en-us/archive/msdn-magazine/2014/june/windows-with-c-high-performance-window-layering-using-the-windows-composition-engine
-    pub fn ex_style(mut self, style: DWORD) -> Self {
-        self.dwExStyle = style;
+    pub fn ex_style(mut self, style: impl Into<WindowExStyle>) -> Self {
+        self.dwExStyle = style.into().bits();
         self
     }
 
@@ -201,6 +657,71 @@ impl<'a> WindowBuilder<'a> {
         self
     }
 
+    /// Set the size of the client area, in pixels.
+    ///
+    /// Unlike [`size`](#method.size), which sets the outer size of the window, this sets the
+    /// drawable area. The outer size is computed with [`AdjustWindowRectExForDpi`] (or
+    /// `AdjustWindowRectEx` before Windows 10 1607) from the builder's styles and menu, at the
+    /// window's DPI, once the window is created and before it is shown. This overrides `size`.
+    ///
+    /// [`AdjustWindowRectExForDpi`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-adjustwindowrectexfordpi
+    pub fn client_size(mut self, width: c_int, height: c_int) -> Self {
+        self.client_size = Some((width, height));
+        self
+    }
+
+    /// Give the window a dark or light title bar, before it is shown.
+    ///
+    /// See [`set_dark_mode_titlebar`](fn.set_dark_mode_titlebar.html), which can also change
+    /// it later. This has no effect on child windows.
+    pub fn dark_mode_titlebar(mut self, dark: bool) -> Self {
+        self.dark_mode_titlebar = Some(dark);
+        self
+    }
+
+    /// Give the window a system backdrop, such as Mica, before it is shown.
+    ///
+    /// See [`set_backdrop`](fn.set_backdrop.html), which can also change it later, and
+    /// describes how the window must draw. This has no effect on child windows, or before
+    /// Windows 11.
+    pub fn backdrop(mut self, backdrop: Backdrop) -> Self {
+        self.backdrop = Some(backdrop);
+        self
+    }
+
+    /// Choose how the window's corners are rounded, before it is shown.
+    ///
+    /// See [`set_corner_preference`](fn.set_corner_preference.html), which can also change it
+    /// later. This has no effect on child windows, or before Windows 11.
+    pub fn corner_preference(mut self, preference: CornerPreference) -> Self {
+        self.corner_preference = Some(preference);
+        self
+    }
+
+    /// Center the window on the work area of its monitor.
+    ///
+    /// The position is computed when the window is built, from its actual size, and before
+    /// it is shown. The monitor is the one the window was created on, which is the primary
+    /// monitor unless a position is also set. This has no effect on child windows.
+    pub fn centered(mut self) -> Self {
+        self.placement = Placement::Centered;
+        self
+    }
+
+    /// Center the window on another window, such as its owner.
+    ///
+    /// As with [`centered`](#method.centered), the position is computed when the window is
+    /// built. It is adjusted to keep the window within the work area of the monitor of
+    /// `hwnd`.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, which must still be valid at build time.
+    pub unsafe fn centered_on(mut self, hwnd: HWND) -> Self {
+        self.placement = Placement::CenteredOn(hwnd);
+        self
+    }
+
     /// Set the window size.
     ///
     /// The arguments become the `nWidth` and `nHeight` parameters to [`CreateWindowEx`]. To set one but not
@@ -215,7 +736,9 @@ impl<'a> WindowBuilder<'a> {
 
     /// Set the parent window.
     ///
-    /// The argument becomes the `hWndParent` parameter to [`CreateWindowEx`].
+    /// The argument becomes the `hWndParent` parameter to [`CreateWindowEx`]. Its meaning
+    /// depends on the style: with `WS_CHILD` it is the parent, and otherwise the owner. Prefer
+    /// [`owner`] or [`child_of`], which say which is meant and check the style.
     ///
     /// # Safety
     ///
@@ -224,6 +747,62 @@ impl<'a> WindowBuilder<'a> {
     /// [`CreateWindowEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createwindowexw
     pub unsafe fn parent_hwnd(mut self, parent: HWND) -> Self {
         self.hWndParent = parent;
+        self.relation = Relation::Unspecified;
+        self
+    }
+
+    /// Make the window a top-level window [owned] by `owner`.
+    ///
+    /// An owned window stays above its owner, is hidden when the owner is minimized, and is
+    /// destroyed with it, but isn't clipped to it and can be positioned anywhere on screen.
+    /// This suits dialogs, palettes, and popups. [`build`] fails with `Error::InvalidStyle`
+    /// if the style includes `WS_CHILD`.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, to a top-level window.
+    ///
+    /// [owned]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#owned-windows
+    /// [`build`]: #method.build
+    pub unsafe fn owner(mut self, owner: HWND) -> Self {
+        self.hWndParent = owner;
+        self.relation = Relation::Owned;
+        self
+    }
+
+    /// Make the window a [child window] of `parent`.
+    ///
+    /// A child window is positioned in the parent's client coordinates, clipped to its client
+    /// area, and moves with it; it has no taskbar button and doesn't activate separately.
+    /// `WS_CHILD` is added to the style when the window is built, and [`build`] fails with
+    /// `Error::InvalidStyle` if the style includes `WS_POPUP`.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [child window]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#child-windows
+    /// [`build`]: #method.build
+    pub unsafe fn child_of(mut self, parent: HWND) -> Self {
+        self.hWndParent = parent;
+        self.relation = Relation::Child;
+        self
+    }
+
+    /// Create a [message-only window], which is never shown and just receives messages.
+    ///
+    /// This suits windows that exist only as a target for timers, posted messages, clipboard
+    /// listeners, or device notifications. The window is created with `HWND_MESSAGE` as its
+    /// parent, and the style, extended style, parent, menu, and
+    /// [`show_after_first_paint`](#method.show_after_first_paint) settings are ignored.
+    ///
+    /// A message-only window can't be shown and gets no input. It is not enumerated, and
+    /// doesn't receive broadcast messages such as `WM_SETTINGCHANGE` and `WM_DISPLAYCHANGE`,
+    /// so it can't be used to listen for those; use a hidden top-level window instead.
+    ///
+    /// [message-only window]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#message-only-windows
+    pub fn message_only(mut self) -> Self {
+        self.message_only = true;
         self
     }
 
@@ -238,6 +817,21 @@ impl<'a> WindowBuilder<'a> {
     /// [`CreateWindowEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createwindowexw
     pub unsafe fn menu(mut self, menu: HMENU) -> Self {
         self.hMenu = menu;
+        self.menu_resource = None;
+        self
+    }
+
+    /// Set the menu, from a menu resource with an integer ID.
+    ///
+    /// The menu is loaded with [`LoadMenu`] from the module given by [`instance`], or the
+    /// executable if that isn't set, when the window is built, and is destroyed with the
+    /// window. This replaces a menu set with [`menu`], and overrides the class menu.
+    ///
+    /// [`LoadMenu`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-loadmenuw
+    /// [`instance`]: #method.instance
+    /// [`menu`]: #method.menu
+    pub fn menu_resource(mut self, id: u16) -> Self {
+        self.menu_resource = Some(id);
         self
     }
 
@@ -261,25 +855,581 @@ impl<'a> WindowBuilder<'a> {
         self.hInstance = instance;
         self
     }
+
+    /// Whether to exclude the area of child windows when the window paints.
+    ///
+    /// This sets or clears `WS_CLIPCHILDREN` in the style, so call it after
+    /// [`style`](#method.style). Without it, the window's painting (including background
+    /// erasing) draws over its children, which then repaint, and the children flicker. Set
+    /// it on any window with child windows that it doesn't draw under on purpose.
+    pub fn clip_children(mut self, clip: bool) -> Self {
+        let mut style = WindowStyle::from(self.dwStyle);
+        style.set(WindowStyle::CLIP_CHILDREN, clip);
+        self.dwStyle = style.bits();
+        self
+    }
+
+    /// Whether to exclude the area of overlapping sibling windows when a child window paints.
+    ///
+    /// This sets or clears `WS_CLIPSIBLINGS` in the style, so call it after
+    /// [`style`](#method.style). Without it, overlapping child windows paint over each other
+    /// in an order that depends on which was invalidated. Top-level windows always clip their
+    /// siblings.
+    pub fn clip_siblings(mut self, clip: bool) -> Self {
+        let mut style = WindowStyle::from(self.dwStyle);
+        style.set(WindowStyle::CLIP_SIBLINGS, clip);
+        self.dwStyle = style.bits();
+        self
+    }
+
+    /// Whether to paint the window and its descendants together, double-buffered.
+    ///
+    /// This sets or clears `WS_EX_COMPOSITED` in the extended style, so call it after
+    /// [`ex_style`](#method.ex_style). The window and all its descendants are painted
+    /// bottom to top into one buffer, which removes flicker between a parent and its
+    /// GDI-drawn children, at the cost of repainting more. It only works with GDI painting:
+    /// [`build`] fails with `Error::InvalidStyle` if it is combined with
+    /// `WS_EX_NOREDIRECTIONBITMAP`, or with a class style of `CS_OWNDC`, `CS_CLASSDC`, or
+    /// `CS_PARENTDC`. Don't use it for windows rendered with DirectX.
+    ///
+    /// [`build`]: #method.build
+    pub fn composited(mut self, composited: bool) -> Self {
+        let mut ex_style = WindowExStyle::from(self.dwExStyle);
+        ex_style.set(WindowExStyle::COMPOSITED, composited);
+        self.dwExStyle = ex_style.bits();
+        self
+    }
+
+    /// Suppress background erasing, to reduce flicker.
+    ///
+    /// When set, [`WM_ERASEBKGND`] is answered with a nonzero value (meaning "erased") if the
+    /// window procedure doesn't handle it, so the background brush is never painted. This
+    /// avoids the flash of background color that otherwise precedes each paint, most visibly
+    /// during resize, and is appropriate whenever the window procedure paints its entire client
+    /// area (in particular, when rendering with a GPU).
+    ///
+    /// This is best combined with [`no_flicker`](struct.WindowClassBuilder.html#method.no_flicker)
+    /// on the window class.
+    ///
+    /// [`WM_ERASEBKGND`]: https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-erasebkgnd
+    pub fn no_flicker(mut self) -> Self {
+        self.no_flicker = true;
+        self
+    }
+
+    /// Configure the window for content drawn only with DirectComposition (or a swapchain).
+    ///
+    /// This adds `WS_EX_NOREDIRECTIONBITMAP` (see [`ex_style`](#method.ex_style)), so the
+    /// window has no GDI surface to cover or lag behind the composition content, and sets
+    /// [`no_flicker`](#method.no_flicker), so the class background brush is never painted.
+    /// The window can't then show GDI content, including a menu bar.
+    ///
+    /// With the `dcomp` feature, [`build_for_composition`](#method.build_for_composition)
+    /// also creates the composition target.
+    pub fn for_composition(mut self) -> Self {
+        self.dwExStyle |= WS_EX_NOREDIRECTIONBITMAP;
+        self.no_flicker = true;
+        self
+    }
+
+    /// Build the window, and create a DirectComposition target for it.
+    ///
+    /// This applies [`for_composition`](#method.for_composition) before building. See
+    /// [`create_composition_target`](fn.create_composition_target.html) for the arguments; if
+    /// the target can't be created, the window is destroyed and the error returned.
+    ///
+    /// # Safety
+    ///
+    /// The `device` argument must be a valid COM object.
+    #[cfg(feature = "dcomp")]
+    pub unsafe fn build_for_composition(
+        self,
+        device: *mut IUnknown,
+        topmost: bool,
+    ) -> Result<(Hwnd, ComPtr<IDCompositionTarget>), Error> {
+        let hwnd = self.for_composition().build()?;
+        match create_composition_target(hwnd.raw(), device, topmost) {
+            Ok(target) => Ok((hwnd, target)),
+            Err(err) => {
+                let _ = hwnd.destroy(hwnd.ui_thread());
+                Err(err)
+            }
+        }
+    }
+
+    /// Set the cursor map.
+    ///
+    /// See [`set_cursor_map`](fn.set_cursor_map.html), which can also change it after the
+    /// window is created.
+    pub fn cursor_map(mut self, map: CursorMap) -> Self {
+        self.cursor_map = Some(map);
+        self
+    }
+
+    /// Set the drag regions, for a custom-drawn title bar.
+    ///
+    /// See [`set_drag_regions`](fn.set_drag_regions.html), which can also change them after
+    /// the window is created.
+    pub fn drag_regions(mut self, regions: DragRegions) -> Self {
+        self.drag_regions = Some(regions);
+        self
+    }
+
+    /// Set the hit test function.
+    ///
+    /// See [`set_hit_test`](fn.set_hit_test.html), which can also change it after the window
+    /// is created.
+    pub fn hit_test(mut self, f: impl Fn(Point) -> Option<HitTest> + 'static) -> Self {
+        self.hit_test = Some(Box::new(f));
+        self
+    }
+
+    /// Put the window in kiosk mode, locked to cover a monitor, before it is shown.
+    ///
+    /// See [`set_kiosk`](fn.set_kiosk.html), which can also change it later. This has no
+    /// effect on child windows.
+    pub fn kiosk(mut self, kiosk: Kiosk) -> Self {
+        self.kiosk = Some(kiosk);
+        self
+    }
+
+    /// Snap the window to work area edges and other windows while it is moved or resized.
+    ///
+    /// See [`set_snap`](fn.set_snap.html), which can also change it after the window is
+    /// created.
+    pub fn snap(mut self, snap: Snap) -> Self {
+        self.snap = Some(snap);
+        self
+    }
+
+    /// Set the title bar layout, for a custom-drawn frame.
+    ///
+    /// See [`set_title_bar`](fn.set_title_bar.html), which can also change it after the window
+    /// is created.
+    pub fn title_bar(mut self, title_bar: TitleBar) -> Self {
+        self.title_bar = Some(title_bar);
+        self
+    }
+
+    /// Configure the window as a tool window (a floating palette) owned by `owner`.
+    ///
+    /// This sets a resizable popup style with a caption and close button, and adds
+    /// `WS_EX_TOOLWINDOW`, which gives the window a small caption and keeps it out of the
+    /// taskbar and Alt+Tab. Being [owned], the window stays above its owner (but not above
+    /// other applications), is hidden when the owner is minimized, and is destroyed with it.
+    /// Use [`topmost`] to keep it above all windows instead, and [`no_activate`] so that
+    /// clicking it doesn't take activation from the owner.
+    ///
+    /// This replaces the style set by [`style`], and adds to the extended style, so call
+    /// [`ex_style`] first if both are used.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference, or null for an unowned window.
+    ///
+    /// [owned]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-features#owned-windows
+    /// [`topmost`]: #method.topmost
+    /// [`no_activate`]: #method.no_activate
+    /// [`style`]: #method.style
+    /// [`ex_style`]: #method.ex_style
+    pub unsafe fn tool_window(mut self, owner: HWND) -> Self {
+        self.dwStyle = WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_THICKFRAME;
+        self.dwExStyle |= WS_EX_TOOLWINDOW;
+        self.owner(owner)
+    }
+
+    /// Configure the window as a popup (such as an autocomplete list or flyout) owned by
+    /// `owner`.
+    ///
+    /// This sets the `WS_POPUP` style, with no caption or border, and adds `WS_EX_TOOLWINDOW`
+    /// and `WS_EX_NOACTIVATE`, so the popup stays out of the taskbar and doesn't take
+    /// activation from the owner. Show it with `SW_SHOWNOACTIVATE`. For a shadow, register
+    /// its class with [`drop_shadow`]; to close it when the user clicks elsewhere, use
+    /// [`set_light_dismiss`].
+    ///
+    /// This replaces the style set by [`style`], and adds to the extended style, so call
+    /// [`ex_style`] first if both are used.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`drop_shadow`]: struct.WindowClassBuilder.html#method.drop_shadow
+    /// [`set_light_dismiss`]: fn.set_light_dismiss.html
+    /// [`style`]: #method.style
+    /// [`ex_style`]: #method.ex_style
+    pub unsafe fn popup(mut self, owner: HWND) -> Self {
+        self.dwStyle = WS_POPUP;
+        self.dwExStyle |= WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE;
+        self.owner(owner)
+    }
+
+    /// Configure the window as an overlay: a child of `parent` that is drawn above its
+    /// siblings, but lets all input through to whatever is below it.
+    ///
+    /// This suits guides, selection rectangles, and other decorations drawn over embedded
+    /// child windows (such as a WebView2 control) without taking their input. The window is
+    /// a [child](#method.child_of) with `WS_EX_LAYERED`, `WS_EX_TRANSPARENT`, and
+    /// `WS_EX_NOACTIVATE`, and its [hit test](#method.hit_test) returns `HTTRANSPARENT`.
+    /// `HTTRANSPARENT` only passes input to windows of the same thread; the layered,
+    /// transparent extended style is what passes it to windows of other threads and
+    /// processes.
+    ///
+    /// Layered child windows require Windows 8, and the application manifest must declare
+    /// Windows 8 (or later) as a supported OS. A layered window isn't drawn until its content
+    /// is set, with [`UpdateLayeredWindow`] (with per-pixel alpha) or
+    /// `SetLayeredWindowAttributes`; alternatively, add `WS_EX_NOREDIRECTIONBITMAP` and draw
+    /// with DirectComposition. The overlay starts above its existing siblings, but siblings
+    /// created later go above it, so raise it with `SetWindowPos` and `HWND_TOP` if needed.
+    ///
+    /// This adds to the style and extended style.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HWND reference.
+    ///
+    /// [`UpdateLayeredWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-updatelayeredwindow
+    pub unsafe fn input_transparent_overlay(mut self, parent: HWND) -> Self {
+        self.dwExStyle |= WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_NOACTIVATE;
+        self.hit_test = Some(Box::new(|_| Some(HitTest::Transparent)));
+        self.child_of(parent)
+    }
+
+    /// Configure the window as a top-level window without a frame or title bar.
+    ///
+    /// This sets the `WS_POPUP` style with the minimize and maximize boxes and window menu,
+    /// so that the taskbar button and keyboard shortcuts can still minimize, maximize, and
+    /// close it, and enables [`maximize_to_work_area`]. The window has no system drag area or
+    /// resize border; supply them with [`drag_regions`] or [`hit_test`].
+    ///
+    /// This replaces the style set by [`style`].
+    ///
+    /// [`maximize_to_work_area`]: #method.maximize_to_work_area
+    /// [`drag_regions`]: #method.drag_regions
+    /// [`hit_test`]: #method.hit_test
+    /// [`style`]: #method.style
+    pub fn borderless(mut self) -> Self {
+        self.dwStyle = WS_POPUP | WS_SYSMENU | WS_MINIMIZEBOX | WS_MAXIMIZEBOX;
+        self.maximize_to_work_area()
+    }
+
+    /// Set the minimum size the user can resize the window to.
+    ///
+    /// The size is of the whole window, in pixels at 96 DPI, and is scaled to the window's
+    /// DPI, so it keeps the same physical size when the window moves to a monitor with a
+    /// different scale. The limit is applied to `WM_GETMINMAXINFO` before the window
+    /// procedure sees it, so the window procedure can still adjust it.
+    pub fn min_size(mut self, width: c_int, height: c_int) -> Self {
+        self.size_limits.min = Some((width, height));
+        self
+    }
+
+    /// Set the maximum size the user can resize the window to.
+    ///
+    /// As with [`min_size`](#method.min_size), the size is of the whole window, in pixels at
+    /// 96 DPI. A maximized window is also limited to this size.
+    pub fn max_size(mut self, width: c_int, height: c_int) -> Self {
+        self.size_limits.max = Some((width, height));
+        self
+    }
+
+    /// Maximize the window to the work area of its monitor, rather than the whole monitor.
+    ///
+    /// A window without a caption (such as a `WS_POPUP` window) maximizes over the taskbar, as
+    /// if it were full screen. With this option, `WM_GETMINMAXINFO` messages that the window
+    /// procedure doesn't handle are answered with the work area of the window's monitor, so
+    /// maximizing behaves as for a normal window. Where the taskbar is set to auto-hide, a
+    /// one-pixel gap is left along its edge, so it can still be revealed (see
+    /// [`Monitor::maximized_rect`](struct.Monitor.html#method.maximized_rect)).
+    pub fn maximize_to_work_area(mut self) -> Self {
+        self.maximize_to_work_area = true;
+        self
+    }
+
+    /// Prevent the window from being activated when clicked.
+    ///
+    /// This adds `WS_EX_NOACTIVATE` to the extended style. Keyboard focus stays with the
+    /// previously active window, which is usually what a palette or on-screen keyboard wants.
+    pub fn no_activate(mut self) -> Self {
+        self.dwExStyle |= WS_EX_NOACTIVATE;
+        self
+    }
+
+    /// Keep the window above all non-topmost windows, including those of other applications.
+    ///
+    /// This adds `WS_EX_TOPMOST` to the extended style. To change it later, use
+    /// [`set_always_on_top`](fn.set_always_on_top.html).
+    pub fn topmost(mut self) -> Self {
+        self.dwExStyle |= WS_EX_TOPMOST;
+        self
+    }
+
+    /// Show the window once its first frame has been rendered.
+    ///
+    /// A newly shown window is painted with its background (or left black, without a
+    /// background brush) until the application renders its first frame, which for GPU
+    /// rendering can take a noticeable time. With this option, the window is created
+    /// [cloaked] and shown (without activation), so it is laid out and composed but invisible.
+    /// On the first `WM_PAINT`, `render` is called instead of the window procedure; when it
+    /// returns `true`, the window is validated, uncloaked, and activated. When it returns
+    /// `false` (for example, because the swapchain isn't ready), the message goes to the
+    /// window procedure as usual, and `render` is called again on the next `WM_PAINT`.
+    ///
+    /// Don't call `ShowWindow` on the window after building it. Cloaking requires Windows 8;
+    /// on earlier versions, the window is simply shown.
+    ///
+    /// [cloaked]: fn.set_cloaked.html
+    pub fn show_after_first_paint(mut self, render: impl FnMut(HWND) -> bool + 'static) -> Self {
+        self.first_paint = Some(Box::new(render));
+        self
+    }
+
+    /// Show the window when it is built.
+    ///
+    /// The default is [`ShowCommand::Hidden`], which leaves the window to be shown later, for
+    /// example once a swapchain has been set up for it. A window with `WS_VISIBLE` in its
+    /// style is shown regardless. With
+    /// [`show_after_first_paint`](#method.show_after_first_paint), the window is revealed by
+    /// its first frame, and only `Maximized` has an effect; it is ignored for message-only
+    /// windows.
+    ///
+    /// [`ShowCommand::Hidden`]: enum.ShowCommand.html#variant.Hidden
+    pub fn show_command(mut self, cmd: ShowCommand) -> Self {
+        self.show_command = cmd;
+        self
+    }
+
+    /// Set the accelerator table for the window.
+    ///
+    /// [`Runloop`](struct.Runloop.html) uses this table for messages to the window and its
+    /// descendants, in preference to its own. See [`set_accelerator`](fn.set_accelerator.html),
+    /// which can also change it after the window is created.
+    ///
+    /// # Safety
+    ///
+    /// The argument must be a valid HACCEL handle, which must outlive the window.
+    pub unsafe fn accelerator(mut self, accel: HACCEL) -> Self {
+        self.accel = accel;
+        self
+    }
+}
+
+type FirstPaint = Box<dyn FnMut(HWND) -> bool>;
+
+/// Resize a window so its client area has the given size.
+unsafe fn set_client_size(hwnd: HWND, size: (c_int, c_int), style: DWORD, ex_style: DWORD) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: size.0,
+        bottom: size.1,
+    };
+    // Child windows can't have menus.
+    let has_menu = (style & WS_CHILD == 0 && !GetMenu(hwnd).is_null()) as BOOL;
+    let adjusted = match dynamic_fn!(
+        "user32.dll",
+        AdjustWindowRectExForDpi: fn(*mut RECT, DWORD, BOOL, DWORD, UINT) -> BOOL
+    ) {
+        Some(adjust_for_dpi) => adjust_for_dpi(
+            &mut rect,
+            style,
+            has_menu,
+            ex_style,
+            Dpi::for_window(hwnd).0,
+        ),
+        None => AdjustWindowRectEx(&mut rect, style, has_menu, ex_style),
+    };
+    if adjusted == 0 {
+        return;
+    }
+    SetWindowPos(
+        hwnd,
+        null_mut(),
+        0,
+        0,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+        SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+    );
 }
 
+/// Center a window on the work area of its monitor, or on another window.
+///
+/// Either way, the window is kept within the work area of the monitor it is centered on.
+unsafe fn center_window(hwnd: HWND, target: Option<HWND>) {
+    let mut rect = mem::zeroed();
+    GetWindowRect(hwnd, &mut rect);
+    let rect = Rect::from(rect);
+    let monitor = Monitor::from_window(target.unwrap_or(hwnd));
+    let work_area = monitor.work_area();
+    let area = match target {
+        Some(target) => {
+            let mut target_rect = mem::zeroed();
+            GetWindowRect(target, &mut target_rect);
+            Rect::from(target_rect)
+        }
+        None => work_area,
+    };
+    let x = area.left + (area.width() - rect.width()) / 2;
+    let y = area.top + (area.height() - rect.height()) / 2;
+    // Too large a window is aligned with the top left, keeping the title bar in reach.
+    let x = x.min(work_area.right - rect.width()).max(work_area.left);
+    let y = y.min(work_area.bottom - rect.height()).max(work_area.top);
+    SetWindowPos(
+        hwnd,
+        null_mut(),
+        x,
+        y,
+        0,
+        0,
+        SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+    );
+}
+
+/// The messages handled by `WindowState::pre_proc`.
+const HOOKED_MESSAGES: &[UINT] = &[
+    WM_GETMINMAXINFO,
+    WM_MOVING,
+    WM_SIZING,
+    WM_SYSCOMMAND,
+    WM_WINDOWPOSCHANGING,
+    WM_DISPLAYCHANGE,
+    WM_SIZE,
+    WM_ENTERSIZEMOVE,
+    WM_EXITSIZEMOVE,
+];
+
 #[cfg(target_arch = "x86_64")]
 type WindowLongPtr = winapi::shared::basetsd::LONG_PTR;
 #[cfg(target_arch = "x86")]
 type WindowLongPtr = winapi::shared::ntdef::LONG;
 
+/// Where a window's state pointer is stored.
+///
+/// The state pointer is normally stored in `GWLP_USERDATA`. Some hosts and libraries
+/// assume that slot is theirs to use on any window, which breaks windows created by this
+/// crate; storing the pointer in a [window property] avoids the conflict, at a small cost
+/// per message.
+///
+/// [window property]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-properties
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateStorage {
+    /// Store the state in `GWLP_USERDATA` (the default).
+    UserData,
+    /// Store the state in a window property, leaving `GWLP_USERDATA` free.
+    Property,
+}
+
+/// The property name for `StateStorage::Property`: "win-win.state", null terminated.
+const STATE_PROP: &[u16] = &[
+    b'w' as u16,
+    b'i' as u16,
+    b'n' as u16,
+    b'-' as u16,
+    b'w' as u16,
+    b'i' as u16,
+    b'n' as u16,
+    b'.' as u16,
+    b's' as u16,
+    b't' as u16,
+    b'a' as u16,
+    b't' as u16,
+    b'e' as u16,
+    0,
+];
+
+impl StateStorage {
+    /// The storage used by a window, or `None` if it's not one of ours.
+    unsafe fn of_window(hwnd: HWND) -> Option<StateStorage> {
+        let wndproc = GetClassLongPtrW(hwnd, GCLP_WNDPROC) as usize;
+        if wndproc == raw_window_proc as *const () as usize {
+            Some(StateStorage::UserData)
+        } else if wndproc == raw_window_proc_prop as *const () as usize {
+            Some(StateStorage::Property)
+        } else {
+            None
+        }
+    }
+
+    fn wndproc(self) -> WNDPROC {
+        match self {
+            StateStorage::UserData => Some(raw_window_proc),
+            StateStorage::Property => Some(raw_window_proc_prop),
+        }
+    }
+
+    unsafe fn get(self, hwnd: HWND) -> *const WindowState {
+        match self {
+            StateStorage::UserData => GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const WindowState,
+            StateStorage::Property => GetPropW(hwnd, STATE_PROP.as_ptr()) as *const WindowState,
+        }
+    }
+
+    unsafe fn set(self, hwnd: HWND, ptr: *const WindowState) {
+        match self {
+            StateStorage::UserData => {
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, ptr as WindowLongPtr);
+            }
+            StateStorage::Property if ptr.is_null() => {
+                RemovePropW(hwnd, STATE_PROP.as_ptr());
+            }
+            StateStorage::Property => {
+                SetPropW(hwnd, STATE_PROP.as_ptr(), ptr as HANDLE);
+            }
+        }
+    }
+}
+
 unsafe extern "system" fn raw_window_proc(
     hwnd: HWND,
     msg: UINT,
     wparam: WPARAM,
     lparam: LPARAM,
+) -> LRESULT {
+    window_proc_impl(StateStorage::UserData, hwnd, msg, wparam, lparam)
+}
+
+unsafe extern "system" fn raw_window_proc_prop(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    window_proc_impl(StateStorage::Property, hwnd, msg, wparam, lparam)
+}
+
+unsafe fn window_proc_impl(
+    storage: StateStorage,
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
 ) -> LRESULT {
     if msg == WM_CREATE {
         let create_struct = &*(lparam as *const CREATESTRUCTW);
         let window_state_ptr = create_struct.lpCreateParams;
-        SetWindowLongPtrW(hwnd, GWLP_USERDATA, window_state_ptr as WindowLongPtr);
+        storage.set(hwnd, window_state_ptr as *const WindowState);
+    }
+    let window_proc_ptr = storage.get(hwnd);
+    if !window_proc_ptr.is_null() {
+        let state = &*window_proc_ptr;
+        if state.hot_messages.binary_search(&msg).is_ok() {
+            // A count rather than a reference, which is cheaper; if the window is destroyed
+            // during the call, the state is released when the count drops to zero.
+            state.hot_depth.set(state.hot_depth.get() + 1);
+            let result = state
+                .window_proc
+                .window_proc(hwnd, msg, wparam, lparam)
+                .or_else(|| state.default_proc(hwnd, msg, wparam, lparam))
+                .unwrap_or_else(|| DefWindowProcW(hwnd, msg, wparam, lparam));
+            let depth = state.hot_depth.get() - 1;
+            state.hot_depth.set(depth);
+            if depth == 0 && state.release_after_hot.get() {
+                mem::drop(Rc::from_raw(window_proc_ptr));
+            }
+            return result;
+        }
     }
-    let window_proc_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const Box<dyn WindowProc>;
     let result = {
         if window_proc_ptr.is_null() {
             None
@@ -289,17 +1439,219 @@ unsafe extern "system" fn raw_window_proc(
             // procedure called DestroyWindow).
             let reference = Rc::from_raw(window_proc_ptr);
             mem::forget(reference.clone());
-            (*window_proc_ptr).window_proc(hwnd, msg, wparam, lparam)
+            let state = &*window_proc_ptr;
+            #[cfg(feature = "diagnostics")]
+            state.depth.set(state.depth.get() + 1);
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!(
+                "window_proc",
+                msg = %crate::message::message_name(msg),
+            )
+            .entered();
+            let pre_result = state.pre_proc(hwnd, msg, wparam, lparam);
+            let result = if pre_result.is_some() {
+                pre_result
+            } else if msg == WM_PAINT && state.first_paint(hwnd) {
+                Some(0)
+            } else {
+                state
+                    .window_proc
+                    .window_proc(hwnd, msg, wparam, lparam)
+                    .or_else(|| state.default_proc(hwnd, msg, wparam, lparam))
+            };
+            #[cfg(feature = "diagnostics")]
+            state.depth.set(state.depth.get() - 1);
+            result
         }
     };
 
     if msg == WM_NCDESTROY && !window_proc_ptr.is_null() {
-        SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
-        mem::drop(Rc::from_raw(window_proc_ptr));
+        let state = Rc::from_raw(window_proc_ptr);
+        // Calls in progress each hold a reference; anything beyond those and the window's
+        // own reference keeps the state alive after the window is gone.
+        #[cfg(feature = "diagnostics")]
+        crate::diagnostics::window_destroyed(
+            window_proc_ptr as usize,
+            Rc::strong_count(&state) - 1 - state.depth.get() as usize,
+        );
+        storage.set(hwnd, null());
+        state.alive.set(false);
+        crate::threads::window_destroyed(hwnd);
+        if state.hot_depth.get() == 0 {
+            mem::drop(state);
+        } else {
+            // A hot message call in progress releases it.
+            state.release_after_hot.set(true);
+            mem::forget(state);
+        }
     }
     result.unwrap_or_else(|| DefWindowProcW(hwnd, msg, wparam, lparam))
 }
 
+impl WindowState {
+    /// Handling for messages before the window procedure sees them.
+    ///
+    /// This runs with the state held, as callbacks may destroy the window. Its messages are
+    /// in `HOOKED_MESSAGES`, so that they are never hot. A `Some` result is returned without
+    /// calling the window procedure.
+    unsafe fn pre_proc(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<LRESULT> {
+        match msg {
+            WM_GETMINMAXINFO => {
+                self.size_limits
+                    .apply(hwnd, &mut *(lparam as *mut MINMAXINFO));
+            }
+            WM_MOVING | WM_SIZING => {
+                if let Some(snap) = self.snap.get() {
+                    snap.process_message(hwnd, msg, wparam, &mut *(lparam as *mut RECT));
+                }
+            }
+            WM_SIZE | WM_ENTERSIZEMOVE | WM_EXITSIZEMOVE => {
+                self.swapchain_resize.process_message(hwnd, msg)
+            }
+            _ => (),
+        }
+        // After snapping, which kiosk mode overrides.
+        match msg {
+            WM_MOVING | WM_SIZING | WM_SYSCOMMAND | WM_WINDOWPOSCHANGING | WM_DISPLAYCHANGE => {
+                // Cloned, as refitting sends messages that come back here.
+                let kiosk = self.kiosk.borrow().clone();
+                kiosk.and_then(|kiosk| kiosk.process_message(hwnd, msg, wparam, lparam))
+            }
+            _ => None,
+        }
+    }
+
+    /// Render the first frame, if the window is waiting for it, returning `true` on success.
+    ///
+    /// On success, the window is revealed; see `WindowBuilder::show_after_first_paint`.
+    unsafe fn first_paint(&self, hwnd: HWND) -> bool {
+        // Not borrowed during the call, as rendering may cause reentrant messages.
+        let mut render = match self.first_paint.borrow_mut().take() {
+            Some(render) => render,
+            None => return false,
+        };
+        if render(hwnd) {
+            ValidateRect(hwnd, null());
+            let _ = set_cloaked(hwnd, false);
+            SetForegroundWindow(hwnd);
+            true
+        } else {
+            *self.first_paint.borrow_mut() = Some(render);
+            false
+        }
+    }
+
+    /// Handling for messages not handled by the window procedure.
+    ///
+    /// A `None` result falls through to `DefWindowProc`.
+    unsafe fn default_proc(
+        &self,
+        hwnd: HWND,
+        msg: UINT,
+        wparam: WPARAM,
+        lparam: LPARAM,
+    ) -> Option<LRESULT> {
+        match msg {
+            WM_ERASEBKGND if self.no_flicker => Some(1),
+            WM_GETMINMAXINFO if self.maximize_to_work_area => {
+                DefWindowProcW(hwnd, msg, wparam, lparam);
+                let info = &mut *(lparam as *mut MINMAXINFO);
+                let monitor = Monitor::from_window(hwnd);
+                let (rect, work) = (monitor.rect(), monitor.maximized_rect());
+                // The position is relative to the monitor.
+                info.ptMaxPosition.x = work.left - rect.left;
+                info.ptMaxPosition.y = work.top - rect.top;
+                info.ptMaxSize.x = work.width();
+                info.ptMaxSize.y = work.height();
+                // Applied again, so a maximum size still limits the maximized size.
+                self.size_limits.apply(hwnd, info);
+                Some(0)
+            }
+            // Only for the client area of this window; children choose their own cursors.
+            WM_SETCURSOR if wparam as HWND == hwnd && LOWORD(lparam as u32) == HTCLIENT as u16 => {
+                let cursor_map = self.cursor_map.borrow();
+                let map = cursor_map.as_ref()?;
+                let mut pt = mem::zeroed();
+                if GetCursorPos(&mut pt) == 0 || ScreenToClient(hwnd, &mut pt) == 0 {
+                    return None;
+                }
+                SetCursor(map.cursor_at(pt.into()).handle());
+                Some(1)
+            }
+            WM_NCHITTEST => {
+                if let Some(hit_test) = self.hit_test.borrow().as_ref() {
+                    let mut pt = lparam_to_point(lparam).into();
+                    if ScreenToClient(hwnd, &mut pt) != 0 {
+                        if let Some(hit) = hit_test(pt.into()) {
+                            return Some(hit.to_lresult());
+                        }
+                    }
+                }
+                if let Some(title_bar) = self.title_bar.borrow().as_ref() {
+                    let mut pt = lparam_to_point(lparam).into();
+                    let mut rect = mem::zeroed();
+                    if ScreenToClient(hwnd, &mut pt) != 0 && GetClientRect(hwnd, &mut rect) != 0 {
+                        let maximized = IsZoomed(hwnd) != 0;
+                        let hit = title_bar.hit_test(pt.into(), rect.right, rect.bottom, maximized);
+                        return Some(hit.to_lresult());
+                    }
+                }
+                let drag_regions = self.drag_regions.borrow();
+                let regions = drag_regions.as_ref()?;
+                let hit = DefWindowProcW(hwnd, msg, wparam, lparam);
+                if hit != HTCLIENT as LRESULT {
+                    return Some(hit);
+                }
+                let mut pt = lparam_to_point(lparam).into();
+                if ScreenToClient(hwnd, &mut pt) != 0 && regions.is_caption(pt.into()) {
+                    Some(HTCAPTION as LRESULT)
+                } else {
+                    Some(hit)
+                }
+            }
+            WM_NCMOUSEMOVE | WM_NCMOUSELEAVE | WM_NCLBUTTONDOWN | WM_NCLBUTTONDBLCLK
+            | WM_NCLBUTTONUP => {
+                let (result, notify) = self
+                    .title_bar
+                    .borrow()
+                    .as_ref()?
+                    .process_message(hwnd, msg, wparam);
+                if let Some((f, button, pressed)) = notify {
+                    f(button, pressed);
+                }
+                result
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Get the state of a window created by this crate.
+///
+/// Returns `None` if the window was not created by [`WindowBuilder`], or belongs to another
+/// thread (in which case its state must not be touched).
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub(crate) unsafe fn window_state(hwnd: HWND) -> Option<Rc<WindowState>> {
+    if GetWindowThreadProcessId(hwnd, null_mut()) != GetCurrentThreadId() {
+        return None;
+    }
+    let ptr = StateStorage::of_window(hwnd)?.get(hwnd);
+    if ptr.is_null() {
+        return None;
+    }
+    Rc::increment_strong_count(ptr);
+    Some(Rc::from_raw(ptr))
+}
+
 impl WindowClass {
     /// A builder for creating a new window class.
     ///
@@ -314,7 +1666,10 @@ impl WindowClass {
             hCursor: null_mut(),
             hbrBackground: null_mut(),
             menu_name: Vec::new(),
+            menu_id: None,
             hIconSm: null_mut(),
+            no_flicker: false,
+            storage: StateStorage::UserData,
         }
     }
 
@@ -332,6 +1687,74 @@ impl WindowClass {
             WindowClass::Name(name) => name.as_ptr(),
         }
     }
+
+    /// The class style, if the class can be found for the instance or the executable.
+    unsafe fn style(&self, instance: HINSTANCE) -> Option<UINT> {
+        let mut wnd: WNDCLASSEXW = mem::zeroed();
+        wnd.cbSize = mem::size_of::<WNDCLASSEXW>() as u32;
+        let instances = [instance, GetModuleHandleW(null())];
+        instances
+            .iter()
+            .any(|&instance| GetClassInfoExW(instance, self.as_lpcwstr(), &mut wnd) != 0)
+            .then_some(wnd.style)
+    }
+}
+
+/// A window class that is unregistered when dropped.
+///
+/// This is for code that may be unloaded, such as a DLL or plugin, which must unregister its
+/// classes before the code of their window procedure goes away. It dereferences to a
+/// [`WindowClass`] for use with [`WindowBuilder`]. All windows of the class must be destroyed
+/// before it is dropped; otherwise the class stays registered, and debug builds panic.
+///
+/// [`WindowClass`]: enum.WindowClass.html
+/// [`WindowBuilder`]: struct.WindowBuilder.html
+pub struct OwnedWindowClass {
+    class: WindowClass,
+    instance: HINSTANCE,
+}
+
+impl OwnedWindowClass {
+    /// A token for the thread that registered the class, which is the calling thread.
+    pub fn ui_thread(&self) -> UiThreadToken {
+        UiThreadToken::assume()
+    }
+
+    /// Keep the class registered for the rest of the process, as [`WindowClassBuilder::build`]
+    /// does.
+    ///
+    /// [`WindowClassBuilder::build`]: struct.WindowClassBuilder.html#method.build
+    pub fn leak(self) -> WindowClass {
+        let this = mem::ManuallyDrop::new(self);
+        // The class is moved out of a value that is never dropped.
+        unsafe { std::ptr::read(&this.class) }
+    }
+}
+
+impl std::ops::Deref for OwnedWindowClass {
+    type Target = WindowClass;
+
+    fn deref(&self) -> &WindowClass {
+        &self.class
+    }
+}
+
+impl Drop for OwnedWindowClass {
+    fn drop(&mut self) {
+        unsafe {
+            if UnregisterClassW(self.class.as_lpcwstr(), self.instance) == 0 {
+                debug_assert!(
+                    GetLastError() != ERROR_CLASS_HAS_WINDOWS,
+                    "window class dropped while windows of the class still exist"
+                );
+            } else {
+                #[cfg(feature = "diagnostics")]
+                if let WindowClass::Atom(atom) = self.class {
+                    crate::diagnostics::class_unregistered(atom);
+                }
+            }
+        }
+    }
 }
 
 impl WindowClassBuilder {
@@ -343,18 +1766,44 @@ impl WindowClassBuilder {
     ///
     /// [`UnregisterClass`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-unregisterclassw
     pub fn build(self) -> Result<WindowClass, Error> {
+        self.build_owned().map(OwnedWindowClass::leak)
+    }
+
+    /// Create the window class, unregistering it when the result is dropped.
+    ///
+    /// See [`OwnedWindowClass`](struct.OwnedWindowClass.html).
+    pub fn build_owned(self) -> Result<OwnedWindowClass, Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "register_class",
+            class = %String::from_utf16_lossy(&self.class_name[..self.class_name.len() - 1]),
+        )
+        .entered();
+        if self.no_flicker && self.style & (CS_HREDRAW | CS_VREDRAW) != 0 {
+            return Err(Error::InvalidStyle(
+                "CS_HREDRAW and CS_VREDRAW are incompatible with no_flicker",
+            ));
+        }
+        if self.style & CS_PARENTDC != 0 && self.style & (CS_OWNDC | CS_CLASSDC) != 0 {
+            return Err(Error::InvalidStyle(
+                "CS_PARENTDC is incompatible with CS_OWNDC and CS_CLASSDC",
+            ));
+        }
         unsafe {
             let wnd = WNDCLASSEXW {
                 cbSize: mem::size_of::<WNDCLASSEXW>() as u32,
                 style: self.style,
-                lpfnWndProc: Some(raw_window_proc),
+                lpfnWndProc: self.storage.wndproc(),
                 cbClsExtra: 0,
                 cbWndExtra: 0,
                 hInstance: self.hInstance,
                 hIcon: self.hIcon,
                 hCursor: self.hCursor,
                 hbrBackground: self.hbrBackground,
-                lpszMenuName: pointer_or_null(&self.menu_name),
+                lpszMenuName: match self.menu_id {
+                    Some(id) => MAKEINTRESOURCEW(id),
+                    None => pointer_or_null(&self.menu_name),
+                },
                 lpszClassName: self.class_name.as_ptr(),
                 hIconSm: self.hIconSm,
             };
@@ -364,7 +1813,16 @@ impl WindowClassBuilder {
                 // This should probably be GetLastError.
                 Err(Error::RegisterClassFailed)
             } else {
-                Ok(WindowClass::Atom(class_atom))
+                #[cfg(feature = "diagnostics")]
+                crate::diagnostics::class_registered(
+                    class_atom,
+                    String::from_utf16_lossy(&self.class_name[..self.class_name.len() - 1]),
+                );
+                mark_ui_thread();
+                Ok(OwnedWindowClass {
+                    class: WindowClass::Atom(class_atom),
+                    instance: self.hInstance,
+                })
             }
         }
     }
@@ -453,6 +1911,23 @@ impl WindowClassBuilder {
         self
     }
 
+    /// Set the icon from an [`Icon`](struct.Icon.html), such as one created from pixel data.
+    ///
+    /// The class takes ownership of the icon, which is never destroyed, as the system uses it
+    /// for as long as the class is registered.
+    pub fn icon_image(mut self, icon: Icon) -> Self {
+        self.hIcon = icon.into_raw();
+        self
+    }
+
+    /// Set the small icon from an [`Icon`](struct.Icon.html).
+    ///
+    /// As with [`icon_image`](#method.icon_image), the class takes ownership of the icon.
+    pub fn small_icon_image(mut self, icon: Icon) -> Self {
+        self.hIconSm = icon.into_raw();
+        self
+    }
+
     /// Set the cursor.
     ///
     /// The argument becomes the `hCursor` field in the [`WNDCLASSEX`] passed to [`RegisterClassEx`].
@@ -483,6 +1958,16 @@ impl WindowClassBuilder {
         self
     }
 
+    /// Set the cursor from a [`Cursor`](struct.Cursor.html), such as one created from pixel
+    /// data.
+    ///
+    /// See [`cursor`](#method.cursor). As with [`icon_image`](#method.icon_image), the class
+    /// takes ownership of the cursor, which is never destroyed.
+    pub fn cursor_image(mut self, cursor: Cursor) -> Self {
+        self.hCursor = cursor.into_raw();
+        self
+    }
+
     /// Set the background brush.
     ///
     /// The argument becomes the `hBrBackground` field in the [`WNDCLASSEX`] passed to [`RegisterClassEx`].
@@ -500,15 +1985,88 @@ impl WindowClassBuilder {
 
     /// Set the default menu.
     ///
-    /// The argument becomes the `lpszClassName` field in the [`WNDCLASSEX`] passed to [`RegisterClassEx`].
+    /// The argument becomes the `lpszMenuName` field in the [`WNDCLASSEX`] passed to [`RegisterClassEx`].
     ///
-    /// The string references the resource name of the class menu. There is no mechanism to support
-    /// the MAKEINTRESOURCE macro.
+    /// The string references the resource name of the class menu. For a menu resource with an
+    /// integer ID, use [`menu_id`](#method.menu_id).
     ///
     /// [`RegisterClassEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-registerclassexw
     /// [`WNDCLASSEX`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-wndclassexw
     pub fn menu_name(mut self, menu_name: impl AsRef<OsStr>) -> Self {
         self.menu_name = menu_name.to_wide_null();
+        self.menu_id = None;
+        self
+    }
+
+    /// Set the default menu, by the integer ID of its resource.
+    ///
+    /// This is the equivalent of passing `MAKEINTRESOURCE(id)` as the `lpszMenuName` field in
+    /// the [`WNDCLASSEX`] passed to [`RegisterClassEx`], and replaces any name set with
+    /// [`menu_name`](#method.menu_name).
+    ///
+    /// [`RegisterClassEx`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-registerclassexw
+    /// [`WNDCLASSEX`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-wndclassexw
+    pub fn menu_id(mut self, id: u16) -> Self {
+        self.menu_id = Some(id);
+        self.menu_name = Vec::new();
+        self
+    }
+
+    /// Configure the class for flicker-free rendering.
+    ///
+    /// This clears the background brush, so that the system never paints the client area with
+    /// a solid color before the application gets a chance to draw. It is the first thing to
+    /// reach for when resizing a window with custom (especially GPU) rendering shows flicker.
+    ///
+    /// It is an error (reported by [`build`](#method.build)) to combine this with the
+    /// `CS_HREDRAW` or `CS_VREDRAW` class styles, as those invalidate the entire client area on
+    /// every resize, which defeats the purpose. See [Class Styles] for the details of those
+    /// styles.
+    ///
+    /// This is best combined with [`no_flicker`](struct.WindowBuilder.html#method.no_flicker)
+    /// on each window, which also answers `WM_ERASEBKGND`.
+    ///
+    /// [Class Styles]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-class-styles
+    pub fn no_flicker(mut self) -> Self {
+        self.hbrBackground = null_mut();
+        self.no_flicker = true;
+        self
+    }
+
+    /// Have child windows of this class draw with their parent's device context.
+    ///
+    /// This adds `CS_PARENTDC` to the class style. The child window then paints with the
+    /// clipping region of its parent, so it can draw outside its own bounds, which is cheaper
+    /// for simple controls but leaves them responsible for staying within their area. It has
+    /// no effect on top-level windows, and is incompatible with `CS_OWNDC` and `CS_CLASSDC`,
+    /// and with [`WindowBuilder::composited`] windows.
+    ///
+    /// [`WindowBuilder::composited`]: struct.WindowBuilder.html#method.composited
+    pub fn parent_dc(mut self) -> Self {
+        self.style |= CS_PARENTDC;
+        self
+    }
+
+    /// Give windows of this class a drop shadow.
+    ///
+    /// This adds `CS_DROPSHADOW` to the class style, which is intended for short-lived popups
+    /// such as menus and flyouts (see [`WindowBuilder::popup`]). It adds to the style set by
+    /// [`class_style`](#method.class_style), so call that first if both are used. To change
+    /// it later, or to give other windows a shadow, use [`set_drop_shadow`].
+    ///
+    /// [`WindowBuilder::popup`]: struct.WindowBuilder.html#method.popup
+    /// [`set_drop_shadow`]: fn.set_drop_shadow.html
+    pub fn drop_shadow(mut self) -> Self {
+        self.style |= CS_DROPSHADOW;
+        self
+    }
+
+    /// Choose where windows of this class store their state.
+    ///
+    /// The default is `StateStorage::UserData`. Use `StateStorage::Property` when the
+    /// windows need to coexist with code that uses `GWLP_USERDATA` itself.
+    pub fn state_storage(mut self, storage: StateStorage) -> Self {
+        self.storage = storage;
         self
     }
 
@@ -521,6 +2079,50 @@ impl WindowClassBuilder {
     pub const DLGWINDOWEXTRA: c_int = 30;
 }
 
+/// Set the title of a window, or the text of a control. See [`SetWindowTextW`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`SetWindowTextW`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwindowtextw
+pub unsafe fn set_window_title(hwnd: HWND, title: impl AsRef<OsStr>) -> Result<(), Error> {
+    if SetWindowTextW(hwnd, title.to_wide_null().as_ptr()) == 0 {
+        return Err(Error::last_error());
+    }
+    Ok(())
+}
+
+/// The title of a window, or the text of a control. See [`GetWindowTextW`].
+///
+/// For a window of the calling process, this sends `WM_GETTEXT`. If the window belongs to
+/// another thread, the call blocks until that thread processes the message, so it hangs while
+/// that thread is busy, and deadlocks if that thread is waiting on the caller. Only for a
+/// window of another process does it skip `WM_GETTEXT` and read the text the system stored
+/// (the caption, not the text of a control).
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`GetWindowTextW`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getwindowtextw
+pub unsafe fn window_title(hwnd: HWND) -> Result<OsString, Error> {
+    // Zero means either an empty title or failure.
+    SetLastError(0);
+    let len = GetWindowTextLengthW(hwnd);
+    if len == 0 {
+        return match GetLastError() {
+            0 => Ok(OsString::new()),
+            code => Err(Error::Win32(code)),
+        };
+    }
+    // The length may be an overestimate, but not an underestimate.
+    let mut buf = vec![0u16; len as usize + 1];
+    let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as c_int);
+    buf.truncate(len.max(0) as usize);
+    Ok(OsString::from_wide(&buf))
+}
+
 /// A convenience function for an optional string, on which an empty slice
 /// returns a null pointer.
 fn pointer_or_null(slice: &[u16]) -> *const u16 {
@@ -0,0 +1,163 @@
+//! Associating data with windows.
+//!
+//! Subsystems such as timers, drag and drop, or theming often need per-window state for
+//! windows whose window procedure they don't control. The functions here store reference
+//! counted values in [window properties], keyed by name, and drop them automatically when
+//! the window is destroyed.
+//!
+//! Cleanup is done by a [subclass] of the window, installed when the first value is set. As
+//! subclassing only works within a thread, and the values are not `Send`, all of these
+//! functions must be called on the window's thread.
+//!
+//! [window properties]: https://docs.microsoft.com/en-us/windows/win32/winmsg/window-properties
+//! [subclass]: https://docs.microsoft.com/en-us/windows/win32/controls/subclassing-overview
+
+use std::any::Any;
+use std::mem;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use winapi::shared::basetsd::{DWORD_PTR, UINT_PTR, ULONG_PTR};
+use winapi::shared::minwindef::{BOOL, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+use winapi::shared::windef::HWND;
+use winapi::um::commctrl::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use winapi::um::processthreadsapi::GetCurrentThreadId;
+use winapi::um::winnt::{HANDLE, LPWSTR};
+use winapi::um::winuser::{
+    EnumPropsExW, GetPropW, GetWindowThreadProcessId, RemovePropW, SetPropW, PROPENUMPROCEXW,
+    PROPENUMPROCW, WM_NCDESTROY,
+};
+
+use wio::wide::ToWide;
+
+use crate::error::Error;
+
+/// The prefix for property names, so our properties can be found for cleanup.
+const PROP_PREFIX: &str = "win-win.data.";
+
+/// The subclass id; any value unique to this pair with `cleanup_subclass_proc` will do.
+const CLEANUP_SUBCLASS_ID: UINT_PTR = 0x7769_6e77;
+
+type Data = Rc<dyn Any>;
+
+fn prop_name(key: &str) -> Vec<u16> {
+    format!("{}{}", PROP_PREFIX, key).to_wide_null()
+}
+
+unsafe fn check_thread(hwnd: HWND) -> Result<(), Error> {
+    if GetWindowThreadProcessId(hwnd, null_mut()) == GetCurrentThreadId() {
+        Ok(())
+    } else {
+        Err(Error::ForeignWindow)
+    }
+}
+
+/// Associate a value with a window.
+///
+/// Any previous value with the same key is replaced (and dropped). The value is dropped when
+/// the window is destroyed, or on [`remove_window_data`].
+///
+/// Returns [`Error::ForeignWindow`] if the window belongs to another thread.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`remove_window_data`]: fn.remove_window_data.html
+/// [`Error::ForeignWindow`]: enum.Error.html#variant.ForeignWindow
+pub unsafe fn set_window_data(hwnd: HWND, key: &str, data: Rc<dyn Any>) -> Result<(), Error> {
+    check_thread(hwnd)?;
+    // Setting the same subclass again just updates it, so this is idempotent.
+    if SetWindowSubclass(hwnd, Some(cleanup_subclass_proc), CLEANUP_SUBCLASS_ID, 0) == 0 {
+        return Err(Error::last_error());
+    }
+    let name = prop_name(key);
+    let ptr = Box::into_raw(Box::new(data));
+    let old = GetPropW(hwnd, name.as_ptr()) as *mut Data;
+    if SetPropW(hwnd, name.as_ptr(), ptr as HANDLE) == 0 {
+        drop(Box::from_raw(ptr));
+        return Err(Error::last_error());
+    }
+    if !old.is_null() {
+        drop(Box::from_raw(old));
+    }
+    Ok(())
+}
+
+/// Get a value associated with a window.
+///
+/// Returns `None` if there is no value with the key, or it is not of type `T`.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn get_window_data<T: Any>(hwnd: HWND, key: &str) -> Option<Rc<T>> {
+    check_thread(hwnd).ok()?;
+    let ptr = GetPropW(hwnd, prop_name(key).as_ptr()) as *const Data;
+    if ptr.is_null() {
+        return None;
+    }
+    (*ptr).clone().downcast().ok()
+}
+
+/// Remove a value associated with a window, returning it.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn remove_window_data(hwnd: HWND, key: &str) -> Option<Rc<dyn Any>> {
+    check_thread(hwnd).ok()?;
+    let ptr = RemovePropW(hwnd, prop_name(key).as_ptr()) as *mut Data;
+    if ptr.is_null() {
+        None
+    } else {
+        Some(*Box::from_raw(ptr))
+    }
+}
+
+unsafe extern "system" fn cleanup_subclass_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    id: UINT_PTR,
+    _ref_data: DWORD_PTR,
+) -> LRESULT {
+    if msg == WM_NCDESTROY {
+        // winapi declares EnumPropsExW as taking a PROPENUMPROCW, rather than the
+        // PROPENUMPROCEXW it actually calls.
+        let enum_proc: PROPENUMPROCEXW = Some(remove_data_prop);
+        let mut removed: Vec<*mut Data> = Vec::new();
+        EnumPropsExW(
+            hwnd,
+            mem::transmute::<PROPENUMPROCEXW, PROPENUMPROCW>(enum_proc),
+            &mut removed as *mut _ as LPARAM,
+        );
+        RemoveWindowSubclass(hwnd, Some(cleanup_subclass_proc), id);
+        // Drop after enumeration, as drop code might itself touch window properties.
+        for ptr in removed {
+            drop(Box::from_raw(ptr));
+        }
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+unsafe extern "system" fn remove_data_prop(
+    hwnd: HWND,
+    name: LPWSTR,
+    data: HANDLE,
+    removed: ULONG_PTR,
+) -> BOOL {
+    // Properties named by atom have a zero high word; none of ours are.
+    if name as usize >> 16 == 0 {
+        return TRUE;
+    }
+    let len = (0..).take_while(|&i| *name.add(i) != 0).count();
+    let name_slice = std::slice::from_raw_parts(name, len);
+    if String::from_utf16_lossy(name_slice).starts_with(PROP_PREFIX) {
+        // The callback may remove the property it was passed.
+        RemovePropW(hwnd, name);
+        (*(removed as *mut Vec<*mut Data>)).push(data as *mut Data);
+    }
+    TRUE
+}
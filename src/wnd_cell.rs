@@ -0,0 +1,185 @@
+//! A cell for window procedure state.
+//!
+//! Window procedures take `&self`, so mutable state needs interior mutability, and as window
+//! procedures are routinely called reentrantly, a `RefCell` borrowed for the duration of a
+//! message handler will sooner or later panic with a double borrow. The panic message doesn't
+//! say which messages were involved, which is what is needed to fix it. [`WndCell`] records
+//! the message that holds the borrow, and reports both messages on a conflict.
+//!
+//! [`WndCell`]: struct.WndCell.html
+
+use std::cell::{Cell, Ref, RefCell, RefMut};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use winapi::shared::minwindef::UINT;
+
+use crate::message::message_name;
+
+/// What to do on a conflicting borrow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Panic, with a message naming both messages.
+    Panic,
+    /// Return `None`, so the reentrant message can be passed to `DefWindowProc`.
+    Skip,
+}
+
+/// A borrow that conflicted with an existing one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BorrowConflict {
+    /// The message whose handler holds the existing borrow.
+    pub outer: UINT,
+    /// The message whose handler attempted the new borrow.
+    pub inner: UINT,
+}
+
+impl fmt::Display for BorrowConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "reentrant {} while state is borrowed by {}",
+            message_name(self.inner),
+            message_name(self.outer)
+        )
+    }
+}
+
+impl std::error::Error for BorrowConflict {}
+
+/// A `RefCell` that records which message holds the borrow.
+///
+/// Each borrow takes the message being handled. On a conflict, [`borrow`] and
+/// [`borrow_mut`] follow the cell's [`ConflictPolicy`], while the `try_` variants return
+/// the [`BorrowConflict`].
+///
+/// [`borrow`]: #method.borrow
+/// [`borrow_mut`]: #method.borrow_mut
+/// [`ConflictPolicy`]: enum.ConflictPolicy.html
+/// [`BorrowConflict`]: struct.BorrowConflict.html
+pub struct WndCell<T> {
+    value: RefCell<T>,
+    borrowed_by: Cell<Option<UINT>>,
+    policy: ConflictPolicy,
+}
+
+/// A shared borrow of a [`WndCell`](struct.WndCell.html).
+pub struct WndRef<'a, T> {
+    value: Ref<'a, T>,
+    borrowed_by: &'a Cell<Option<UINT>>,
+    prev: Option<UINT>,
+}
+
+/// A mutable borrow of a [`WndCell`](struct.WndCell.html).
+pub struct WndRefMut<'a, T> {
+    value: RefMut<'a, T>,
+    borrowed_by: &'a Cell<Option<UINT>>,
+    prev: Option<UINT>,
+}
+
+impl<T> WndCell<T> {
+    /// Create a cell that panics on conflicting borrows.
+    pub fn new(value: T) -> WndCell<T> {
+        WndCell::with_policy(value, ConflictPolicy::Panic)
+    }
+
+    /// Create a cell with the given conflict policy.
+    pub fn with_policy(value: T, policy: ConflictPolicy) -> WndCell<T> {
+        WndCell {
+            value: RefCell::new(value),
+            borrowed_by: Cell::new(None),
+            policy,
+        }
+    }
+
+    /// Borrow the value while handling `msg`.
+    pub fn try_borrow(&self, msg: UINT) -> Result<WndRef<'_, T>, BorrowConflict> {
+        let value = self.value.try_borrow().map_err(|_| self.conflict(msg))?;
+        let prev = self.borrowed_by.replace(Some(msg));
+        Ok(WndRef {
+            value,
+            borrowed_by: &self.borrowed_by,
+            prev,
+        })
+    }
+
+    /// Mutably borrow the value while handling `msg`.
+    pub fn try_borrow_mut(&self, msg: UINT) -> Result<WndRefMut<'_, T>, BorrowConflict> {
+        let value = self
+            .value
+            .try_borrow_mut()
+            .map_err(|_| self.conflict(msg))?;
+        let prev = self.borrowed_by.replace(Some(msg));
+        Ok(WndRefMut {
+            value,
+            borrowed_by: &self.borrowed_by,
+            prev,
+        })
+    }
+
+    /// Borrow the value while handling `msg`, following the conflict policy.
+    pub fn borrow(&self, msg: UINT) -> Option<WndRef<'_, T>> {
+        self.apply_policy(self.try_borrow(msg))
+    }
+
+    /// Mutably borrow the value while handling `msg`, following the conflict policy.
+    pub fn borrow_mut(&self, msg: UINT) -> Option<WndRefMut<'_, T>> {
+        self.apply_policy(self.try_borrow_mut(msg))
+    }
+
+    /// Consume the cell, returning the value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    fn conflict(&self, msg: UINT) -> BorrowConflict {
+        BorrowConflict {
+            outer: self.borrowed_by.get().unwrap_or(0),
+            inner: msg,
+        }
+    }
+
+    fn apply_policy<R>(&self, result: Result<R, BorrowConflict>) -> Option<R> {
+        match result {
+            Ok(r) => Some(r),
+            Err(conflict) => match self.policy {
+                ConflictPolicy::Panic => panic!("WndCell: {}", conflict),
+                ConflictPolicy::Skip => None,
+            },
+        }
+    }
+}
+
+impl<'a, T> Deref for WndRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<'a, T> Drop for WndRef<'a, T> {
+    fn drop(&mut self) {
+        self.borrowed_by.set(self.prev);
+    }
+}
+
+impl<'a, T> Deref for WndRefMut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<'a, T> DerefMut for WndRefMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<'a, T> Drop for WndRefMut<'a, T> {
+    fn drop(&mut self) {
+        self.borrowed_by.set(self.prev);
+    }
+}
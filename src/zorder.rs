@@ -0,0 +1,64 @@
+//! The z-order of top-level windows.
+//!
+//! Windows are stacked in two bands: topmost windows (with `WS_EX_TOPMOST`) above all others.
+//! Both are changed with [`SetWindowPos`], whose flags must be chosen so that the call
+//! doesn't also move, resize, or activate the window.
+//!
+//! [`SetWindowPos`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwindowpos
+
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::{
+    GetWindowLongW, SetWindowPos, GWL_EXSTYLE, HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST,
+    SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, WS_EX_TOPMOST,
+};
+
+use crate::error::Error;
+
+const ZORDER_ONLY: u32 = SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE;
+
+/// Move a window into or out of the topmost band.
+///
+/// A topmost window stays above all other windows, including those of other applications,
+/// which suits overlays and floating tool windows; its owned windows become topmost too.
+/// Leaving the band puts the window at the top of the other windows. To create a window
+/// topmost, use [`WindowBuilder::topmost`].
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`WindowBuilder::topmost`]: struct.WindowBuilder.html#method.topmost
+pub unsafe fn set_always_on_top(hwnd: HWND, on_top: bool) -> Result<(), Error> {
+    let after = if on_top { HWND_TOPMOST } else { HWND_NOTOPMOST };
+    if SetWindowPos(hwnd, after, 0, 0, 0, 0, ZORDER_ONLY) == 0 {
+        return Err(Error::last_error());
+    }
+    Ok(())
+}
+
+/// Whether a window is in the topmost band.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+pub unsafe fn is_always_on_top(hwnd: HWND) -> bool {
+    GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOPMOST != 0
+}
+
+/// Move a window to the top of its band, without activating it.
+///
+/// A topmost window stays in the topmost band. This doesn't take the keyboard focus, so it
+/// isn't subject to the foreground restrictions of [`SetForegroundWindow`], which activating
+/// the window would need.
+///
+/// # Safety
+///
+/// The argument must be a valid HWND reference.
+///
+/// [`SetForegroundWindow`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setforegroundwindow
+pub unsafe fn bring_to_front(hwnd: HWND) -> Result<(), Error> {
+    if SetWindowPos(hwnd, HWND_TOP, 0, 0, 0, 0, ZORDER_ONLY) == 0 {
+        return Err(Error::last_error());
+    }
+    Ok(())
+}